@@ -0,0 +1,350 @@
+//! ANSI escape sequences in task output
+//!
+//! Lines are replayed the way a terminal would show them: SGR sequences set
+//! colors and text attributes, carriage returns and backspaces move the
+//! cursor back so progress bars end up showing their last state, and erase
+//! or cursor movement sequences within the line are applied. Everything else
+//! (OSC titles and hyperlinks, cursor movement between lines, ...) is dropped.
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// How escape sequences are shown in the log viewer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnsiMode {
+    /// Colors and attributes applied
+    #[default]
+    Rendered,
+    /// Plain text without escape sequences
+    Stripped,
+    /// Escape sequences shown as text, e.g. `^[[32m`
+    Raw,
+}
+
+impl AnsiMode {
+    /// The next mode when cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            AnsiMode::Rendered => AnsiMode::Stripped,
+            AnsiMode::Stripped => AnsiMode::Raw,
+            AnsiMode::Raw => AnsiMode::Rendered,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AnsiMode::Rendered => "rendered",
+            AnsiMode::Stripped => "stripped",
+            AnsiMode::Raw => "raw",
+        }
+    }
+}
+
+/// A character of a replayed line and the style it was written with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StyledChar {
+    pub ch: char,
+    pub style: Style,
+}
+
+/// Remove escape sequences from text, applying carriage returns and
+/// backspaces so that only what a terminal would end up showing is kept
+pub fn strip(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let (content, newline) = match line.strip_suffix('\n') {
+            Some(content) => (content.strip_suffix('\r').unwrap_or(content), "\n"),
+            None => (line, ""),
+        };
+        stripped.extend(parse_line(content).into_iter().map(|c| c.ch));
+        stripped.push_str(newline);
+    }
+    stripped
+}
+
+/// The characters of a line as shown in the given mode
+pub fn display_line(line: &str, mode: AnsiMode) -> Vec<StyledChar> {
+    match mode {
+        AnsiMode::Rendered => parse_line(line),
+        AnsiMode::Stripped => parse_line(line)
+            .into_iter()
+            .map(|c| StyledChar {
+                ch: c.ch,
+                style: Style::default(),
+            })
+            .collect(),
+        AnsiMode::Raw => escape_controls(line)
+            .chars()
+            .map(|ch| StyledChar {
+                ch,
+                style: Style::default(),
+            })
+            .collect(),
+    }
+}
+
+/// The text of a line as shown in the given mode
+pub fn display_text(line: &str, mode: AnsiMode) -> String {
+    match mode {
+        AnsiMode::Rendered | AnsiMode::Stripped => {
+            parse_line(line).into_iter().map(|c| c.ch).collect()
+        }
+        AnsiMode::Raw => escape_controls(line),
+    }
+}
+
+/// Show control characters in caret notation, e.g. ESC as `^[`
+pub fn escape_controls(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    for ch in line.chars() {
+        match ch {
+            '\t' => escaped.push(ch),
+            '\x00'..='\x1f' | '\x7f' => {
+                escaped.push('^');
+                escaped.push((ch as u8 ^ 0x40) as char);
+            }
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Replay a single line (without its newline)
+pub fn parse_line(line: &str) -> Vec<StyledChar> {
+    let mut cells: Vec<StyledChar> = Vec::with_capacity(line.len());
+    let mut cursor = 0;
+    let mut style = Style::default();
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut command = None;
+                    for c in chars.by_ref() {
+                        match c {
+                            '\x40'..='\x7e' => {
+                                command = Some(c);
+                                break;
+                            }
+                            _ => params.push(c),
+                        }
+                    }
+                    match command {
+                        Some('m') => style = apply_sgr(style, &params),
+                        Some('K') => erase_in_line(&mut cells, cursor, &params),
+                        Some('C') => cursor += count(&params),
+                        Some('D') => cursor = cursor.saturating_sub(count(&params)),
+                        Some('G') => cursor = count(&params) - 1,
+                        _ => {}
+                    }
+                }
+                // OSC, e.g. window titles and hyperlinks, ends with BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Character set designations take one more character
+                Some('(' | ')' | '*' | '+') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\r' => cursor = 0,
+            '\x08' => cursor = cursor.saturating_sub(1),
+            '\t' => put(&mut cells, &mut cursor, ch, style),
+            _ if ch.is_control() => {}
+            _ => put(&mut cells, &mut cursor, ch, style),
+        }
+    }
+    cells
+}
+
+fn put(cells: &mut Vec<StyledChar>, cursor: &mut usize, ch: char, style: Style) {
+    let cell = StyledChar { ch, style };
+    if *cursor < cells.len() {
+        cells[*cursor] = cell;
+    } else {
+        // The cursor moved past the end, e.g. with ESC[5C
+        cells.resize(
+            *cursor,
+            StyledChar {
+                ch: ' ',
+                style: Style::default(),
+            },
+        );
+        cells.push(cell);
+    }
+    *cursor += 1;
+}
+
+/// A count parameter such as the 5 in ESC[5C; missing or 0 means 1
+fn count(params: &str) -> usize {
+    params.parse().ok().filter(|&n| n > 0).unwrap_or(1)
+}
+
+fn erase_in_line(cells: &mut Vec<StyledChar>, cursor: usize, params: &str) {
+    let blank = StyledChar {
+        ch: ' ',
+        style: Style::default(),
+    };
+    match params {
+        "" | "0" => cells.truncate(cursor),
+        "1" => {
+            let end = (cursor + 1).min(cells.len());
+            cells[..end].fill(blank);
+        }
+        "2" => cells.fill(blank),
+        _ => {}
+    }
+}
+
+const COLORS: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+];
+
+const BRIGHT_COLORS: [Color; 8] = [
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Apply the parameters of an SGR (ESC[...m) sequence to a style
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params
+        .split([';', ':'])
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+    let mut codes = codes.into_iter();
+
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 | 6 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(COLORS[(code - 30) as usize]),
+            40..=47 => style.bg(COLORS[(code - 40) as usize]),
+            90..=97 => style.fg(BRIGHT_COLORS[(code - 90) as usize]),
+            100..=107 => style.bg(BRIGHT_COLORS[(code - 100) as usize]),
+            38 | 48 => match extended_color(&mut codes) {
+                Some(color) if code == 38 => style.fg(color),
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            39 => Style { fg: None, ..style },
+            49 => Style { bg: None, ..style },
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Read the rest of a 38/48 sequence: 5;N for the 256-color palette or 2;R;G;B
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let byte = |code: Option<u16>| code.and_then(|code| u8::try_from(code).ok());
+    match codes.next()? {
+        5 => byte(codes.next()).map(Color::Indexed),
+        2 => Some(Color::Rgb(
+            byte(codes.next())?,
+            byte(codes.next())?,
+            byte(codes.next())?,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(cells: &[StyledChar]) -> String {
+        cells.iter().map(|c| c.ch).collect()
+    }
+
+    #[test]
+    fn test_sgr_colors_and_attributes() {
+        let cells = parse_line("\x1b[1;32mok\x1b[0m \x1b[38;5;208mx\x1b[48;2;1;2;3my\x1b[39mz");
+        assert_eq!(text(&cells), "ok xyz");
+        assert_eq!(
+            cells[0].style,
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(cells[2].style, Style::default());
+        assert_eq!(cells[3].style, Style::default().fg(Color::Indexed(208)));
+        assert_eq!(
+            cells[4].style,
+            Style::default()
+                .fg(Color::Indexed(208))
+                .bg(Color::Rgb(1, 2, 3))
+        );
+        assert_eq!(cells[5].style, Style::default().bg(Color::Rgb(1, 2, 3)));
+    }
+
+    #[test]
+    fn test_progress_bars_show_their_last_state() {
+        assert_eq!(text(&parse_line("10%\r20%\r100%")), "100%");
+        assert_eq!(text(&parse_line("downloading...\r\x1b[Kdone")), "done");
+        assert_eq!(text(&parse_line("long line\rshort")), "shortline");
+        assert_eq!(text(&parse_line("abc\x08\x08X")), "aXc");
+        assert_eq!(text(&parse_line("a\x1b[3Cb")), "a   b");
+    }
+
+    #[test]
+    fn test_other_sequences_are_dropped() {
+        assert_eq!(
+            text(&parse_line(
+                "\x1b]0;title\x07\x1b]8;;http://x\x1b\\link\x1b]8;;\x1b\\\x1b(B\x1b[2A!"
+            )),
+            "link!"
+        );
+    }
+
+    #[test]
+    fn test_strip_keeps_lines() {
+        assert_eq!(
+            strip("\x1b[31merror\x1b[0m\r\n50%\r100%\nlast"),
+            "error\n100%\nlast"
+        );
+    }
+
+    #[test]
+    fn test_raw_mode_shows_escapes() {
+        assert_eq!(display_text("\x1b[32mok\r", AnsiMode::Raw), "^[[32mok^M");
+        assert_eq!(display_text("\x1b[32mok", AnsiMode::Stripped), "ok");
+        assert!(
+            display_line("\x1b[32mok", AnsiMode::Stripped)
+                .iter()
+                .all(|c| c.style == Style::default())
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Tasks that start after another task has finished
+//!
+//! A step waits with status `waiting` until the task it runs after has
+//! finished, and then either starts or is cancelled, depending on whether that
+//! task succeeded. Steps are started by `queue::promote`, so like queued tasks
+//! they need no daemon. Success is judged by the exit code, which is only
+//! known for tasks that ran under a supervisor; every step does.
+
+use std::path::PathBuf;
+
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use crate::app::config;
+use crate::app::error::{GhostError, Result};
+use crate::app::queue;
+use crate::app::run_options::RunOptions;
+use crate::app::storage::{self, AfterCondition, Task, TaskStatus};
+
+/// Add a task that starts once `after` has finished as required
+///
+/// Without `after` the step starts right away, under a supervisor.
+pub fn add_step(
+    conn: &Connection,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+    after: Option<(&str, AfterCondition)>,
+) -> Result<Task> {
+    if let Some((task_id, _)) = after {
+        check_previous(&storage::get_task(conn, task_id)?)?;
+    }
+
+    let task_id = Uuid::new_v4().to_string();
+    let log_path = config::get_log_dir().join(format!("{task_id}.log"));
+    let cwd = cwd.or_else(|| std::env::current_dir().ok());
+    let env = config::env::inherit_env(env_vars);
+
+    storage::insert_waiting_task(
+        conn,
+        &task_id,
+        &command,
+        Some(&env),
+        cwd.as_deref(),
+        &log_path,
+        after,
+        options,
+    )?;
+
+    storage::get_task(conn, &task_id)
+}
+
+/// Add commands as a chain in which each step runs after the previous one succeeded
+///
+/// The first step starts right away; a failing step cancels the rest.
+pub fn add_chain(
+    conn: &Connection,
+    commands: Vec<Vec<String>>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<Vec<Task>> {
+    let mut steps: Vec<Task> = Vec::new();
+    for command in commands {
+        let after = steps
+            .last()
+            .map(|previous| (previous.id.as_str(), AfterCondition::Success));
+        let step = add_step(conn, command, cwd.clone(), env_vars.clone(), options, after)?;
+        steps.push(step);
+    }
+
+    queue::promote(conn)?;
+
+    steps
+        .iter()
+        .map(|step| storage::get_task(conn, &step.id))
+        .collect()
+}
+
+/// Make sure it will be known whether a task succeeded
+fn check_previous(task: &Task) -> Result<()> {
+    let unknown = match task.status {
+        TaskStatus::Running => task.supervisor_pid.is_none(),
+        TaskStatus::Exited => task.exit_code.is_none(),
+        TaskStatus::Unknown => true,
+        // Claimed tasks are about to be started under a supervisor
+        TaskStatus::Queued
+        | TaskStatus::Waiting
+        | TaskStatus::Starting
+        | TaskStatus::Killed
+        | TaskStatus::Cancelled => false,
+    };
+
+    if unknown {
+        return Err(GhostError::TaskOperation {
+            task_id: task.id.clone(),
+            message: "Task did not run under a supervisor, so its exit code is unknown; \
+                      start it with `ghost chain` to run other tasks after it"
+                .to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        storage::database::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn add(conn: &Connection, after: Option<(&str, AfterCondition)>) -> Task {
+        add_step(
+            conn,
+            vec!["true".to_string()],
+            None,
+            vec![],
+            &RunOptions::default(),
+            after,
+        )
+        .unwrap()
+    }
+
+    fn finish(conn: &Connection, task_id: &str, exit_code: i32) {
+        conn.execute(
+            "UPDATE tasks SET pid = 1, status = 'running' WHERE id = ?1",
+            [task_id],
+        )
+        .unwrap();
+        storage::record_task_exit(conn, task_id, Some(exit_code), TaskStatus::Exited, None)
+            .unwrap();
+    }
+
+    fn claimed_ids(conn: &Connection) -> Vec<String> {
+        storage::claim_ready_tasks(conn)
+            .unwrap()
+            .into_iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_steps_start_one_after_another() {
+        let conn = setup_test_db();
+        let first = add(&conn, None);
+        let second = add(&conn, Some((&first.id, AfterCondition::Success)));
+
+        assert_eq!(second.status, TaskStatus::Waiting);
+        assert_eq!(second.after_task.as_deref(), Some(first.id.as_str()));
+        assert_eq!(second.after_condition, Some(AfterCondition::Success));
+
+        assert_eq!(claimed_ids(&conn), vec![first.id.clone()]);
+        assert!(claimed_ids(&conn).is_empty());
+
+        finish(&conn, &first.id, 0);
+        assert_eq!(claimed_ids(&conn), vec![second.id]);
+    }
+
+    #[test]
+    fn test_failure_cancels_the_rest_of_the_chain() {
+        let conn = setup_test_db();
+        let first = add(&conn, None);
+        let second = add(&conn, Some((&first.id, AfterCondition::Success)));
+        let third = add(&conn, Some((&second.id, AfterCondition::Success)));
+        let on_failure = add(&conn, Some((&first.id, AfterCondition::Failure)));
+
+        claimed_ids(&conn);
+        finish(&conn, &first.id, 1);
+
+        assert_eq!(claimed_ids(&conn), vec![on_failure.id]);
+        for step in [&second, &third] {
+            let step = storage::get_task(&conn, &step.id).unwrap();
+            assert_eq!(step.status, TaskStatus::Cancelled);
+            assert!(step.finished_at.is_some());
+        }
+        assert_eq!(
+            storage::get_task(&conn, &third.id)
+                .unwrap()
+                .exit_reason
+                .as_deref(),
+            Some("previous step was cancelled")
+        );
+    }
+
+    #[test]
+    fn test_get_chain_from_any_step() {
+        let conn = setup_test_db();
+        let first = add(&conn, None);
+        let second = add(&conn, Some((&first.id, AfterCondition::Success)));
+        let third = add(&conn, Some((&second.id, AfterCondition::Success)));
+        let on_failure = add(&conn, Some((&first.id, AfterCondition::Failure)));
+
+        let chain: Vec<(usize, String)> = storage::get_chain(&conn, &third.id)
+            .unwrap()
+            .into_iter()
+            .map(|(depth, task)| (depth, task.id))
+            .collect();
+        assert_eq!(
+            chain,
+            vec![
+                (0, first.id),
+                (1, second.id),
+                (2, third.id),
+                (1, on_failure.id)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupervised_task_cannot_be_waited_for() {
+        let conn = setup_test_db();
+        storage::insert_task(
+            &conn,
+            "plain",
+            std::process::id(),
+            None,
+            &["sleep".to_string()],
+            None,
+            None,
+            std::path::Path::new("/tmp/plain.log"),
+        )
+        .unwrap();
+
+        let result = add_step(
+            &conn,
+            vec!["true".to_string()],
+            None,
+            vec![],
+            &RunOptions::default(),
+            Some(("plain", AfterCondition::Success)),
+        );
+        assert!(matches!(result, Err(GhostError::TaskOperation { .. })));
+    }
+}
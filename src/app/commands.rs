@@ -1,6 +1,14 @@
-use std::path::PathBuf;
-
-use crate::app::{config, display, error, error::Result, helpers, process, storage};
+use std::path::{Path, PathBuf};
+
+use crate::app::log_follow::{self, FollowTargets, LogFollower};
+use crate::app::log_grep::{self, GrepEvent, GrepMode, LogFilter};
+use crate::app::log_level::LevelCounter;
+use crate::app::log_reader::{self, LogQuery, ReadSummary};
+use crate::app::{
+    chain, config, display, error, error::Result, helpers, limits, process, process_tree, queue,
+    run_options::RunOptions, scheduler, search, stats, storage, supervisor, template, watch,
+};
+use nix::sys::signal::Signal;
 use rusqlite::Connection;
 
 /// Run a command in the background
@@ -10,6 +18,18 @@ pub fn spawn(
     cwd: Option<PathBuf>,
     env: Vec<String>,
     show_output: bool,
+) -> Result<process::ProcessInfo> {
+    spawn_with_options(conn, command, cwd, env, &RunOptions::default(), show_output)
+}
+
+/// Run a command in the background with additional run options
+pub fn spawn_with_options(
+    conn: &Connection,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    show_output: bool,
 ) -> Result<process::ProcessInfo> {
     if command.is_empty() {
         return Err(error::GhostError::InvalidArgument {
@@ -17,7 +37,8 @@ pub fn spawn(
         });
     }
     let env_vars = config::env::parse_env_vars(&env)?;
-    let (process_info, _) = spawn_and_register_process(command, cwd, env_vars, conn)?;
+    let (process_info, _) =
+        spawn_and_register_process_with_options(command, cwd, env_vars, options, conn)?;
 
     if show_output {
         display::print_process_started(&process_info.id, process_info.pid, &process_info.log_path);
@@ -32,6 +53,21 @@ pub fn spawn_and_register_process(
     cwd: Option<PathBuf>,
     env_vars: Vec<(String, String)>,
     conn: &Connection,
+) -> Result<(process::ProcessInfo, std::process::Child)> {
+    spawn_and_register_process_with_options(command, cwd, env_vars, &RunOptions::default(), conn)
+}
+
+/// Spawn process with run options and register it in the database
+///
+/// Options that must be enforced after ghost returns (such as stopping an idle
+/// task) start the task under a supervisor; the returned Child is then the
+/// supervisor process rather than the task itself.
+pub fn spawn_and_register_process_with_options(
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+    conn: &Connection,
 ) -> Result<(process::ProcessInfo, std::process::Child)> {
     // If no cwd is specified, use the current directory
     let effective_cwd = match cwd {
@@ -39,11 +75,43 @@ pub fn spawn_and_register_process(
         None => std::env::current_dir().ok(),
     };
 
-    let (process_info, child) = process::spawn_background_process_with_env(
+    // Hooks in the config file apply to every task, and are run by its supervisor
+    if options.needs_supervisor() || !config::FileConfig::current().hooks.is_empty() {
+        return supervisor::launch(&supervisor::SupervisorSpec {
+            command,
+            cwd: effective_cwd,
+            env: env_vars,
+            options: options.clone(),
+            task_id: None,
+        });
+    }
+
+    register_process(
+        conn,
+        process::new_task_id(),
+        command,
+        effective_cwd,
+        env_vars,
+        options,
+    )
+}
+
+/// Spawn the process directly and insert its task into the database
+pub(crate) fn register_process(
+    conn: &Connection,
+    task_id: String,
+    command: Vec<String>,
+    effective_cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<(process::ProcessInfo, std::process::Child)> {
+    let (process_info, child) = process::spawn_background_process_with_id(
+        task_id,
         command.clone(),
         effective_cwd.clone(),
         None,
         env_vars,
+        &options.limits,
     )?;
 
     // Save to database with the actual environment variables from the process
@@ -63,6 +131,13 @@ pub fn spawn_and_register_process(
         &process_info.log_path,
     )?;
 
+    if !options.is_empty() {
+        storage::set_task_options(conn, &process_info.id, options)?;
+    }
+    if let Some(cgroup) = &process_info.cgroup {
+        storage::set_task_cgroup(conn, &process_info.id, cgroup)?;
+    }
+
     Ok((process_info, child))
 }
 
@@ -76,53 +151,270 @@ pub fn list(
 
     if show_output {
         display::print_task_list(&tasks);
+
+        // Schedules have no status, so they are only listed without a filter
+        if status_filter.is_none() {
+            let schedules = storage::get_schedules(conn)?;
+            if !schedules.is_empty() {
+                println!();
+                display::print_schedules(&schedules);
+            }
+        }
     }
 
     Ok(tasks)
 }
 
 /// Show logs for a process
-pub async fn log(
+///
+/// `task_id` may also be a task name, which shows the newest task with it.
+/// Only the lines selected by `query` are read, so tailing or paging through a
+/// huge log stays fast. With `query.strip_ansi`, ANSI escape sequences are
+/// stripped and carriage return redraws collapsed to what a terminal would end
+/// up showing. Returns where reading stopped.
+pub fn log(
     conn: &Connection,
     task_id: &str,
-    follow: bool,
+    query: &LogQuery,
     show_output: bool,
-) -> Result<String> {
-    let task = storage::get_task(conn, task_id)?;
+) -> Result<ReadSummary> {
+    use std::io::Write;
+
+    let mut tasks = log_follow::find_tasks(conn, task_id)?;
+    let task = tasks.pop().expect("find_tasks returns at least one task");
     let log_path = PathBuf::from(&task.log_path);
 
-    let content =
-        std::fs::read_to_string(&log_path).map_err(|e| error::GhostError::InvalidArgument {
-            message: format!("Failed to read log file: {e}"),
-        })?;
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    let summary = log_reader::read_log(&log_path, query, |line| {
+        if show_output {
+            // A closed pipe, e.g. `ghost log | head`, is not an error
+            let _ = stdout.write_all(line.as_bytes());
+        }
+    })?;
+    let _ = stdout.flush();
+    if show_output && !summary.eof {
+        display::print_log_continuation(&summary);
+    }
 
-    if show_output {
-        if follow {
-            display::print_log_follow_header(task_id, &task.log_path);
-            helpers::follow_log_file(&log_path).await?;
-        } else {
-            print!("{content}");
+    Ok(summary)
+}
+
+/// Show the logs of several tasks, each line prefixed with its task's name
+///
+/// With `follow`, lines are printed as they are written and tasks that start
+/// while following join in when they match `targets`. Returns once every
+/// followed task has finished, or on Ctrl+C.
+pub async fn log_follow(
+    conn: &Connection,
+    targets: &FollowTargets,
+    query: &LogQuery,
+    follow: bool,
+    show_output: bool,
+) -> Result<()> {
+    use std::io::{IsTerminal, Write};
+
+    let started_at = helpers::now_timestamp();
+    let tasks = targets.resolve(conn)?;
+    if tasks.is_empty() {
+        if show_output {
+            println!("No running tasks to follow.");
+        }
+        return Ok(());
+    }
+
+    let always_prefix = tasks.len() > 1 || matches!(targets, FollowTargets::AllRunning);
+    let colors = !query.strip_ansi && std::io::stdout().is_terminal();
+    let mut follower = LogFollower::new(query, always_prefix, colors);
+    let mut out: Box<dyn Write> = if show_output {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(std::io::sink())
+    };
+
+    if show_output && follow {
+        match tasks.as_slice() {
+            [task] => display::print_log_follow_header(&task.id, &task.log_path),
+            _ => display::print_logs_follow_header(tasks.len()),
         }
     }
+    for task in &tasks {
+        follower.add(task, query, &mut out)?;
+    }
+    out.flush()?;
+    if !follow {
+        return Ok(());
+    }
 
-    Ok(content)
+    // Tasks joining later are followed from the start of their logs
+    let joining_query = LogQuery {
+        strip_ansi: query.strip_ansi,
+        grep: query.grep.clone(),
+        ..Default::default()
+    };
+    // New output wakes the loop right away; tasks are checked on a timer
+    let mut watcher = log_follow::LogWatcher::new()?;
+    for task in &tasks {
+        watcher.watch(Path::new(&task.log_path))?;
+    }
+    let mut task_check = tokio::time::interval(log_follow::TASK_CHECK_INTERVAL);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            _ = watcher.changed() => {
+                follower.poll(&mut out)?;
+            }
+            _ = task_check.tick() => {
+                follower.poll(&mut out)?;
+                for task in targets.joining(conn, started_at)? {
+                    if !follower.is_following(&task.id) {
+                        watcher.watch(Path::new(&task.log_path))?;
+                        follower.add(&task, &joining_query, &mut out)?;
+                    }
+                }
+                for task_id in follower.unfinished() {
+                    let task = storage::update_task_status_by_process_check(conn, &task_id)?;
+                    if task.status.is_finished() {
+                        follower.finish(&task_id, &mut out)?;
+                    }
+                }
+                if follower.all_finished() {
+                    out.flush()?;
+                    if show_output {
+                        display::print_logs_follow_finished();
+                    }
+                    return Ok(());
+                }
+            }
+            _ = &mut ctrl_c => {
+                if show_output {
+                    println!("\nLog following stopped.");
+                }
+                return Ok(());
+            }
+        }
+        out.flush()?;
+    }
+}
+
+/// Search the logs of the tasks selected by `filter` for `pattern`
+///
+/// Prints `task:line: text` for each matching line as it is found, or with
+/// `GrepMode::Count` the number of matching lines per task, or with
+/// `GrepMode::FilesWithMatches` the log files that match. Returns the number
+/// of matching lines, or of matching logs for the last mode.
+pub fn grep(
+    conn: &Connection,
+    pattern: &regex::Regex,
+    filter: &LogFilter,
+    mode: GrepMode,
+    show_output: bool,
+) -> Result<usize> {
+    let tasks = filter.tasks(conn)?;
+    let mut total = 0;
+    log_grep::search_logs(&tasks, pattern, mode, |event| {
+        match event {
+            GrepEvent::Match(found) => {
+                if show_output {
+                    display::print_log_match(&found);
+                }
+            }
+            GrepEvent::Searched { task, count } if count > 0 => {
+                total += count;
+                if show_output {
+                    display::print_log_search_result(&tasks[task], count, mode);
+                }
+            }
+            GrepEvent::Searched { .. } => {}
+        }
+        true
+    });
+    Ok(total)
 }
 
 /// Stop a background process
-pub fn stop(conn: &Connection, task_id: &str, force: bool, show_output: bool) -> Result<()> {
+///
+/// Every process of the task is signalled, including descendants that left
+/// its process group. Returns the processes still alive shortly afterwards.
+pub fn stop(
+    conn: &Connection,
+    task_id: &str,
+    force: bool,
+    show_output: bool,
+) -> Result<Vec<process_tree::ProcessEntry>> {
     let task = storage::get_task(conn, task_id)?;
 
+    // A queued or waiting task has no process yet; stopping it cancels it
+    if matches!(
+        task.status,
+        storage::TaskStatus::Queued | storage::TaskStatus::Waiting
+    ) {
+        storage::cancel_waiting_task(conn, task_id, "stopped before it started")?;
+        // Cancel the chain steps waiting for it right away
+        queue::promote(conn)?;
+        if show_output {
+            println!("Task {task_id} has been cancelled before it started");
+        }
+        return Ok(Vec::new());
+    }
+
+    // A watched task's supervisor would start it again on the next change, even
+    // after it exited, so it is stopped first
+    let watching = watch::is_watching(&task);
+    if watching && let Some(supervisor_pid) = task.supervisor_pid {
+        process::kill(supervisor_pid, false)?;
+        storage::clear_supervisor_pid(conn, task_id)?;
+    }
+    if watching && task.status != storage::TaskStatus::Running {
+        if show_output {
+            println!("Task {task_id} is no longer watched");
+        }
+        return Ok(Vec::new());
+    }
+
+    // A claimed task has no process to signal yet; a claim that never gets one
+    // is given up on after `CLAIM_TIMEOUT_SECS`
+    if task.status == storage::TaskStatus::Starting {
+        return Err(error::GhostError::TaskOperation {
+            task_id: task_id.to_string(),
+            message: "Task is starting; try again in a moment".to_string(),
+        });
+    }
+
     helpers::validate_task_running(&task)?;
 
-    // Kill the process group if available, otherwise kill individual process
-    if let Some(pgid) = task.pgid {
-        process::kill_group(pgid, force)?;
+    // Without --force the configured stop signal is sent, SIGTERM by default
+    let settings = &config::FileConfig::current().stop;
+    let signal = if force {
+        Signal::SIGKILL
     } else {
-        process::kill(task.pid, force)?;
+        settings.signal()
+    };
+
+    // Signal the process group if available, otherwise the individual process
+    let leader_result = if let Some(pgid) = task.pgid {
+        process::signal_group(pgid, signal)
+    } else {
+        process::send_signal(task.pid, signal)
+    };
+
+    // Then everything else the task spawned; the leader being gone is only an
+    // error when nothing else was left to stop either
+    let processes = process_tree::TaskProcesses::of(&task);
+    let signalled = processes.send(signal);
+    if signalled.is_empty() {
+        leader_result?;
+    }
+
+    let survivors = processes.wait_for_exit(settings.timeout());
+    if survivors.is_empty()
+        && let Some(cgroup) = &task.cgroup
+    {
+        limits::TaskCgroup::open(cgroup).remove();
     }
 
     // Update status in database
-    let status = if force {
+    let status = if signal == Signal::SIGKILL {
         storage::TaskStatus::Killed
     } else {
         storage::TaskStatus::Exited
@@ -132,9 +424,10 @@ pub fn stop(conn: &Connection, task_id: &str, force: bool, show_output: bool) ->
     if show_output {
         let pid = task.pid;
         println!("Process {task_id} ({pid}) has been {status}");
+        display::print_stop_survivors(&survivors);
     }
 
-    Ok(())
+    Ok(survivors)
 }
 
 /// Check status of a background process
@@ -144,11 +437,124 @@ pub fn status(conn: &Connection, task_id: &str, show_output: bool) -> Result<sto
 
     if show_output {
         display::print_task_details(&task);
+        let mut levels = LevelCounter::new(&task.log_path);
+        if levels.update(u64::MAX).is_ok() {
+            display::print_log_levels(levels.counts(), levels.format());
+        }
+        display::print_task_events(&storage::get_task_events(conn, task_id)?);
+
+        let chain = storage::get_chain(conn, task_id)?;
+        if chain.len() > 1 {
+            println!();
+            println!("Chain:");
+            display::print_chain(&chain, task_id);
+        }
     }
 
     Ok(task)
 }
 
+/// How long `ghost stats` measures CPU usage
+const STATS_SAMPLE_WINDOW: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Show CPU, memory, thread and I/O usage of a running task
+pub fn stats(conn: &Connection, task_id: &str, show_output: bool) -> Result<stats::TaskStats> {
+    let task = storage::update_task_status_by_process_check(conn, task_id)?;
+    helpers::validate_task_running(&task)?;
+
+    let task_stats = stats::sample_task(&task, STATS_SAMPLE_WINDOW).ok_or_else(|| {
+        error::GhostError::TaskOperation {
+            task_id: task_id.to_string(),
+            message: "No processes found in the task's process group".to_string(),
+        }
+    })?;
+
+    if show_output {
+        display::print_task_stats(&task, &task_stats);
+    }
+
+    Ok(task_stats)
+}
+
+/// Show the processes a running task has spawned
+pub fn tree(
+    conn: &Connection,
+    task_id: &str,
+    show_output: bool,
+) -> Result<Vec<process_tree::ProcessNode>> {
+    let task = storage::update_task_status_by_process_check(conn, task_id)?;
+    helpers::validate_task_running(&task)?;
+
+    let nodes = process_tree::task_tree(&task);
+    if nodes.is_empty() {
+        return Err(error::GhostError::TaskOperation {
+            task_id: task_id.to_string(),
+            message: "No processes found for the task".to_string(),
+        });
+    }
+
+    if show_output {
+        display::print_process_tree(&task, &nodes);
+    }
+
+    Ok(nodes)
+}
+
+/// Continuously show resource usage of running tasks (Ctrl+C to stop)
+pub async fn top(conn: &Connection, interval: std::time::Duration) -> Result<()> {
+    let mut sampler = stats::StatsSampler::new();
+
+    loop {
+        let tasks = storage::get_tasks_with_process_check(conn, Some("running"))?;
+        sampler.sample(&tasks);
+        display::print_top(&tasks, &sampler);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// How long `doctor_orphans` waits for killed orphans to exit before removing cgroups
+const ORPHAN_EXIT_WAIT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Find, and optionally kill, processes left behind by finished or deleted tasks
+pub fn doctor_orphans(
+    conn: &Connection,
+    kill: bool,
+    show_output: bool,
+) -> Result<Vec<process_tree::Orphan>> {
+    let tasks = storage::get_tasks_with_process_check(conn, None)?;
+    let orphans = process_tree::find_orphans(&tasks);
+
+    if kill {
+        for orphan in &orphans {
+            let _ = process::kill(orphan.process.pid, true);
+        }
+
+        let deadline = std::time::Instant::now() + ORPHAN_EXIT_WAIT;
+        while std::time::Instant::now() < deadline
+            && orphans
+                .iter()
+                .any(|orphan| process::exists(orphan.process.pid))
+        {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        // Cgroups of finished tasks can be removed once they are empty
+        for task in &tasks {
+            if task.status != storage::TaskStatus::Running
+                && let Some(cgroup) = &task.cgroup
+            {
+                limits::TaskCgroup::open(cgroup).remove();
+            }
+        }
+    }
+
+    if show_output {
+        display::print_orphans(&orphans, kill);
+    }
+
+    Ok(orphans)
+}
+
 /// Clean up old finished tasks
 pub fn cleanup(
     conn: &Connection,
@@ -163,10 +569,19 @@ pub fn cleanup(
     // Determine days filter - None if --all is specified
     let days_filter = if all { None } else { Some(days) };
 
+    let stale_cgroups = stale_cgroups(conn);
+
     if dry_run {
         // Show what would be deleted
         let candidates = storage::get_cleanup_candidates(conn, days_filter, &status_filter)?;
 
+        if !stale_cgroups.is_empty() {
+            println!(
+                "{} leftover task cgroup(s) would be removed.",
+                stale_cgroups.len()
+            );
+        }
+
         if candidates.is_empty() {
             println!("No tasks found matching cleanup criteria.");
             return Ok(());
@@ -186,6 +601,23 @@ pub fn cleanup(
             println!("\nNote: Only tasks older than {days} days would be deleted.");
         }
     } else {
+        let removed = stale_cgroups
+            .iter()
+            .filter(|cgroup| {
+                cgroup.remove();
+                !cgroup.path().exists()
+            })
+            .count();
+        if removed > 0 {
+            println!("Removed {removed} leftover task cgroup(s).");
+        }
+
+        // Cgroups of the deleted tasks could not be found again afterwards
+        let candidates = storage::get_cleanup_candidates(conn, days_filter, &status_filter)?;
+        for cgroup in candidates.iter().filter_map(|task| task.cgroup.as_deref()) {
+            limits::TaskCgroup::open(cgroup).remove();
+        }
+
         // Actually delete tasks
         let deleted_count = storage::cleanup_tasks_by_criteria(conn, days_filter, &status_filter)?;
 
@@ -209,6 +641,25 @@ pub fn cleanup(
     Ok(())
 }
 
+/// Cgroups younger than this may belong to a task that is still being spawned
+const CGROUP_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Empty cgroups of tasks that are not running, e.g. ones left behind by tasks
+/// that exited on their own without a supervisor
+fn stale_cgroups(conn: &Connection) -> Vec<limits::TaskCgroup> {
+    limits::task_cgroups()
+        .into_iter()
+        .filter(|(task_id, cgroup)| {
+            let active =
+                storage::get_task(conn, task_id).is_ok_and(|task| !task.status.is_finished());
+            !active
+                && cgroup.pids().is_empty()
+                && cgroup.age().is_some_and(|age| age >= CGROUP_GRACE_PERIOD)
+        })
+        .map(|(_, cgroup)| cgroup)
+        .collect()
+}
+
 /// Parse status filter string into TaskStatus enum list
 fn parse_status_filter(status: Option<&str>) -> Result<Vec<storage::TaskStatus>> {
     match status {
@@ -217,6 +668,7 @@ fn parse_status_filter(status: Option<&str>) -> Result<Vec<storage::TaskStatus>>
             Ok(vec![
                 storage::TaskStatus::Exited,
                 storage::TaskStatus::Killed,
+                storage::TaskStatus::Cancelled,
                 storage::TaskStatus::Unknown,
             ])
         }
@@ -227,13 +679,14 @@ fn parse_status_filter(status: Option<&str>) -> Result<Vec<storage::TaskStatus>>
                 .map(|s| match s {
                     "exited" => Ok(storage::TaskStatus::Exited),
                     "killed" => Ok(storage::TaskStatus::Killed),
+                    "cancelled" => Ok(storage::TaskStatus::Cancelled),
                     "unknown" => Ok(storage::TaskStatus::Unknown),
                     "running" => Err(error::GhostError::InvalidArgument {
                         message: "Cannot cleanup running tasks".to_string(),
                     }),
                     _ => Err(error::GhostError::InvalidArgument {
                         message: format!(
-                            "Invalid status: {s}. Valid options: exited, killed, unknown, all"
+                            "Invalid status: {s}. Valid options: exited, killed, cancelled, unknown, all"
                         ),
                     }),
                 })
@@ -241,10 +694,11 @@ fn parse_status_filter(status: Option<&str>) -> Result<Vec<storage::TaskStatus>>
             statuses
         }
         None => {
-            // Default: exited and killed only
+            // Default: finished tasks, but not those in an unknown state
             Ok(vec![
                 storage::TaskStatus::Exited,
                 storage::TaskStatus::Killed,
+                storage::TaskStatus::Cancelled,
             ])
         }
     }
@@ -259,6 +713,446 @@ fn format_status_list(statuses: &[storage::TaskStatus]) -> String {
         .join(", ")
 }
 
+/// Run a command once another task has finished with the given outcome
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_after(
+    conn: &Connection,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    after_task: &str,
+    condition: storage::AfterCondition,
+    show_output: bool,
+) -> Result<storage::task::Task> {
+    if command.is_empty() {
+        return Err(error::GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
+    let env_vars = config::env::parse_env_vars(&env)?;
+    let task = chain::add_step(
+        conn,
+        command,
+        cwd,
+        env_vars,
+        options,
+        Some((after_task, condition)),
+    )?;
+
+    queue::promote(conn)?;
+    let task = storage::get_task(conn, &task.id)?;
+
+    if show_output {
+        display::print_task_waiting(&task);
+    }
+
+    Ok(task)
+}
+
+/// Run commands one after another, each only once the previous one succeeded
+///
+/// Each command string is parsed like a command of `ghost run "cmd1" "cmd2"`.
+pub fn chain(
+    conn: &Connection,
+    command_strs: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    show_output: bool,
+) -> Result<Vec<storage::task::Task>> {
+    if command_strs.is_empty() {
+        return Err(error::GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
+    let commands = command_strs
+        .iter()
+        .map(|command_str| helpers::parse_command(command_str))
+        .collect::<Result<Vec<_>>>()?;
+    let env_vars = config::env::parse_env_vars(&env)?;
+
+    let steps = chain::add_chain(conn, commands, cwd, env_vars, options)?;
+
+    if show_output {
+        let chain = storage::get_chain(conn, &steps[0].id)?;
+        println!("Started a chain of {} step(s):", steps.len());
+        display::print_chain(&chain, &steps[0].id);
+    }
+
+    Ok(steps)
+}
+
+/// Run a command once, at a later time
+pub fn run_at(
+    conn: &Connection,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    at: chrono::DateTime<chrono::Local>,
+    show_output: bool,
+) -> Result<storage::Schedule> {
+    if command.is_empty() {
+        return Err(error::GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
+    let env_vars = config::env::parse_env_vars(&env)?;
+    let schedule = scheduler::schedule_once(conn, at, command, cwd, env_vars, options)?;
+
+    if show_output {
+        display::print_schedule_added(&schedule);
+    }
+
+    Ok(schedule)
+}
+
+/// Run a command every time a cron expression matches
+pub fn schedule_add(
+    conn: &Connection,
+    cron: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    show_output: bool,
+) -> Result<storage::Schedule> {
+    if command.is_empty() {
+        return Err(error::GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
+    let env_vars = config::env::parse_env_vars(&env)?;
+    let schedule = scheduler::schedule_cron(conn, cron, command, cwd, env_vars, options)?;
+
+    if show_output {
+        display::print_schedule_added(&schedule);
+    }
+
+    Ok(schedule)
+}
+
+/// List delayed starts and recurring schedules, the next to run first
+pub fn schedule_list(conn: &Connection, show_output: bool) -> Result<Vec<storage::Schedule>> {
+    let schedules = storage::get_schedules(conn)?;
+
+    if show_output {
+        display::print_schedules(&schedules);
+    }
+
+    Ok(schedules)
+}
+
+/// Remove a schedule; tasks it already started keep running
+pub fn schedule_remove(conn: &Connection, schedule_id: &str, show_output: bool) -> Result<()> {
+    storage::delete_schedule(conn, schedule_id)?;
+
+    if show_output {
+        println!("Schedule {schedule_id} has been removed");
+    }
+
+    Ok(())
+}
+
+/// List tasks whose files are being watched
+pub fn watch_list(conn: &Connection, show_output: bool) -> Result<Vec<storage::task::Task>> {
+    let tasks = watch::active_watchers(conn)?;
+
+    if show_output {
+        display::print_watchers(&tasks);
+    }
+
+    Ok(tasks)
+}
+
+/// Save a command as a template for `ghost start`
+pub fn template_save(
+    conn: &Connection,
+    name: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    show_output: bool,
+) -> Result<storage::Template> {
+    let env_vars = config::env::parse_env_vars(&env)?;
+    let replaced = storage::get_template(conn, name).is_ok();
+    let saved = template::save(conn, name, command, cwd, env_vars, options)?;
+
+    if show_output {
+        display::print_template_saved(&saved, replaced);
+    }
+
+    Ok(saved)
+}
+
+/// List saved templates
+pub fn template_list(conn: &Connection, show_output: bool) -> Result<Vec<storage::Template>> {
+    let templates = storage::get_templates(conn)?;
+
+    if show_output {
+        display::print_templates(&templates);
+    }
+
+    Ok(templates)
+}
+
+/// Remove a saved template
+pub fn template_remove(conn: &Connection, name: &str, show_output: bool) -> Result<()> {
+    storage::delete_template(conn, name)?;
+
+    if show_output {
+        println!("Template {name} has been removed");
+    }
+
+    Ok(())
+}
+
+/// Save a search preset for the TUI task list
+pub fn filter_save(
+    conn: &Connection,
+    name: &str,
+    query: &str,
+    show_output: bool,
+) -> Result<storage::FilterPreset> {
+    let preset = search::save_preset(conn, name, query)?;
+
+    if show_output {
+        println!("Saved filter '{}': {}", preset.name, preset.query);
+        println!("Use it in the TUI search with: @{}", preset.name);
+    }
+
+    Ok(preset)
+}
+
+/// List saved search presets
+pub fn filter_list(conn: &Connection, show_output: bool) -> Result<Vec<storage::FilterPreset>> {
+    let presets = storage::get_filter_presets(conn)?;
+
+    if show_output {
+        display::print_filter_presets(&presets);
+    }
+
+    Ok(presets)
+}
+
+/// Remove a saved search preset
+pub fn filter_remove(conn: &Connection, name: &str, show_output: bool) -> Result<()> {
+    storage::delete_filter_preset(conn, name)?;
+
+    if show_output {
+        println!("Filter {name} has been removed");
+    }
+
+    Ok(())
+}
+
+/// Print the value a config key has; nothing when it is unset
+pub fn config_get(key: &str) -> Result<()> {
+    config::find_key(key)?;
+    let values = config::config_values()?;
+    if let Some(value) = values
+        .into_iter()
+        .find(|setting| setting.key.name == key)
+        .and_then(|setting| setting.value)
+    {
+        // Strings are printed without quotes, for use in scripts
+        match value {
+            toml::Value::String(text) => println!("{text}"),
+            value => println!("{value}"),
+        }
+    }
+    Ok(())
+}
+
+/// Set a config key in the user config file, or the project one
+pub fn config_set(key: &str, value: &str, project: bool) -> Result<()> {
+    let path = if project {
+        config::check_project_key(key)?;
+        match config::find_project_config() {
+            Some(path) => path,
+            None => std::env::current_dir()?.join(config::PROJECT_CONFIG_FILE),
+        }
+    } else {
+        config::get_config_path()
+    };
+    let value = config::set_value(&path, key, value)?;
+    println!("Set {key} = {value} in {}", path.display());
+    Ok(())
+}
+
+/// List every config key with its value and where it comes from
+pub fn config_list() -> Result<()> {
+    display::print_config_values(&config::config_values()?);
+    Ok(())
+}
+
+/// Print the paths of the config files
+pub fn config_path() -> Result<()> {
+    println!("{}", config::get_config_path().display());
+    if let Some(path) = config::find_project_config() {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// Start a saved template with its placeholders filled from `params` (NAME=VALUE)
+pub fn start_template(
+    conn: &Connection,
+    name: &str,
+    params: &[String],
+    extra_args: Vec<String>,
+    show_output: bool,
+) -> Result<process::ProcessInfo> {
+    let saved = storage::get_template(conn, name)?;
+    let params = template::parse_params(params)?;
+    let launch = template::prepare(&saved, &params, &extra_args)?;
+
+    let (process_info, _) = spawn_and_register_process_with_options(
+        launch.command,
+        launch.cwd,
+        launch.env,
+        &launch.options,
+        conn,
+    )?;
+
+    if show_output {
+        display::print_process_started(&process_info.id, process_info.pid, &process_info.log_path);
+    }
+
+    Ok(process_info)
+}
+
+/// Add a command to a queue; it starts as soon as the queue has a free slot
+pub fn queue_add(
+    conn: &Connection,
+    queue_name: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    show_output: bool,
+) -> Result<storage::task::Task> {
+    if command.is_empty() {
+        return Err(error::GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
+    let env_vars = config::env::parse_env_vars(&env)?;
+    let task = queue::enqueue(conn, queue_name, command, cwd, env_vars, options)?;
+
+    queue::promote(conn)?;
+    let task = storage::get_task(conn, &task.id)?;
+
+    if show_output {
+        display::print_task_queued(&task);
+    }
+
+    Ok(task)
+}
+
+/// List one queue, or all queues, with their waiting tasks
+pub fn queue_list(
+    conn: &Connection,
+    queue_name: Option<&str>,
+    show_output: bool,
+) -> Result<Vec<queue::QueueSummary>> {
+    let summaries = queue::summaries(conn, queue_name)?;
+
+    if show_output {
+        display::print_queues(&summaries);
+    }
+
+    Ok(summaries)
+}
+
+/// Stop starting new tasks from a queue; running tasks keep running
+pub fn queue_pause(conn: &Connection, queue_name: &str, show_output: bool) -> Result<()> {
+    storage::set_queue_paused(conn, queue_name, true)?;
+
+    if show_output {
+        println!("Queue '{queue_name}' has been paused");
+    }
+
+    Ok(())
+}
+
+/// Resume a paused queue and start its waiting tasks
+pub fn queue_resume(
+    conn: &Connection,
+    queue_name: &str,
+    show_output: bool,
+) -> Result<Vec<storage::task::Task>> {
+    storage::set_queue_paused(conn, queue_name, false)?;
+    let started = queue::promote(conn)?;
+
+    if show_output {
+        println!(
+            "Queue '{queue_name}' has been resumed; started {} task(s)",
+            started.len()
+        );
+    }
+
+    Ok(started)
+}
+
+/// Remove the waiting tasks of a queue
+pub fn queue_clear(conn: &Connection, queue_name: &str, show_output: bool) -> Result<usize> {
+    storage::get_queue(conn, queue_name)?;
+    let removed = storage::delete_queued_tasks(conn, queue_name)?;
+
+    if show_output {
+        println!("Removed {removed} waiting task(s) from queue '{queue_name}'");
+    }
+
+    Ok(removed)
+}
+
+/// Move a waiting task to a 1-based position within its queue
+pub fn queue_move(
+    conn: &Connection,
+    task_id: &str,
+    position: usize,
+    show_output: bool,
+) -> Result<storage::task::Task> {
+    if position == 0 {
+        return Err(error::GhostError::InvalidArgument {
+            message: "Queue positions start at 1".to_string(),
+        });
+    }
+    storage::move_queued_task(conn, task_id, position)?;
+    let task = storage::get_task(conn, task_id)?;
+
+    if show_output {
+        display::print_task_queued(&task);
+    }
+
+    Ok(task)
+}
+
+/// Set how many tasks of a queue may run at once
+pub fn queue_parallel(
+    conn: &Connection,
+    queue_name: &str,
+    parallel: u32,
+    show_output: bool,
+) -> Result<()> {
+    if parallel == 0 {
+        return Err(error::GhostError::InvalidArgument {
+            message: "A queue must allow at least 1 running task".to_string(),
+        });
+    }
+    storage::set_queue_parallel(conn, queue_name, parallel)?;
+    queue::promote(conn)?;
+
+    if show_output {
+        println!("Queue '{queue_name}' now runs up to {parallel} task(s) at once");
+    }
+
+    Ok(())
+}
+
 /// Spawn result for a single command in multi-command execution
 #[derive(Debug)]
 pub struct SpawnResult {
@@ -278,6 +1172,25 @@ pub fn spawn_multi(
     cwd: Option<PathBuf>,
     env: Vec<String>,
     show_output: bool,
+) -> Vec<SpawnResult> {
+    spawn_multi_with_options(
+        conn,
+        command_strs,
+        cwd,
+        env,
+        &RunOptions::default(),
+        show_output,
+    )
+}
+
+/// Run multiple commands in parallel, applying the same run options to each
+pub fn spawn_multi_with_options(
+    conn: &Connection,
+    command_strs: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+    show_output: bool,
 ) -> Vec<SpawnResult> {
     let env_vars = match config::env::parse_env_vars(&env) {
         Ok(vars) => vars,
@@ -299,7 +1212,8 @@ pub fn spawn_multi(
     command_strs
         .into_iter()
         .map(|command_str| {
-            let result = spawn_single_command(&command_str, cwd.clone(), env_vars.clone(), conn);
+            let result =
+                spawn_single_command(&command_str, cwd.clone(), env_vars.clone(), options, conn);
 
             if show_output {
                 match &result {
@@ -325,13 +1239,15 @@ fn spawn_single_command(
     command_str: &str,
     cwd: Option<PathBuf>,
     env_vars: Vec<(String, String)>,
+    options: &RunOptions,
     conn: &Connection,
 ) -> Result<process::ProcessInfo> {
     // Parse the command string into command and arguments
     let command = helpers::parse_command(command_str)?;
 
     // Spawn and register the process
-    let (process_info, _) = spawn_and_register_process(command, cwd, env_vars, conn)?;
+    let (process_info, _) =
+        spawn_and_register_process_with_options(command, cwd, env_vars, options, conn)?;
 
     Ok(process_info)
 }
@@ -345,11 +1261,14 @@ pub async fn tui() -> Result<()> {
     };
     use futures::StreamExt;
     use ratatui::{Terminal, backend::CrosstermBackend};
-    use std::io;
+    use std::io::{self, Write as _};
     use tokio::time::{Duration, interval};
 
     use crate::app::tui::app::TuiApp;
 
+    let settings = config::FileConfig::current();
+    let terminal_alert = settings.notifications.terminal;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -360,15 +1279,30 @@ pub async fn tui() -> Result<()> {
     // Create app
     let mut app = TuiApp::new()?;
     app.refresh_tasks()?;
+    app.sample_stats();
 
     // Setup refresh interval and event stream
-    let mut refresh_interval = interval(Duration::from_secs(1));
+    let mut refresh_interval = interval(settings.tui.refresh_interval());
     let mut event_stream = EventStream::new();
+    // The log viewer is drawn again as soon as its log changes
+    let mut log_watcher = log_follow::LogWatcher::new()?;
+    let mut watched_log: Option<String> = None;
 
     let result = loop {
         // Draw the UI
         terminal.draw(|f| app.render(f))?;
 
+        let viewed_log = app.viewed_log().map(str::to_string);
+        if viewed_log != watched_log {
+            log_watcher.clear();
+            if let Some(path) = &viewed_log
+                && let Err(e) = log_watcher.watch(Path::new(path))
+            {
+                tracing::warn!("Failed to watch log file: {e}");
+            }
+            watched_log = viewed_log;
+        }
+
         // Handle input and refresh
         tokio::select! {
             // Handle keyboard events from async stream
@@ -389,11 +1323,31 @@ pub async fn tui() -> Result<()> {
                 }
             }
 
+            // Keep indexing or searching a large log a part at a time
+            _ = tokio::time::sleep(Duration::from_millis(50)), if app.is_loading_log() => {}
+
+            // The viewed log grew, shrank or was replaced
+            _ = log_watcher.changed(), if watched_log.is_some() => {}
+
             // Refresh tasks periodically
             _ = refresh_interval.tick() => {
                 if let Err(e) = app.refresh_tasks() {
                     break Err(e);
                 }
+                app.sample_stats();
+                app.refresh_process_tree();
+                app.refresh_chain();
+                app.refresh_events();
+
+                for notification in app.take_unsent_notifications() {
+                    if let Some(alert) = terminal_alert {
+                        let escape = alert.escape(&notification.title, &notification.message);
+                        let backend = terminal.backend_mut();
+                        if let Err(e) = backend.write_all(escape.as_bytes()).and_then(|()| backend.flush()) {
+                            tracing::warn!("Failed to notify the terminal: {e}");
+                        }
+                    }
+                }
             }
         }
     };
@@ -491,4 +1445,56 @@ mod tests {
             }
         }
     }
+
+    fn is_alive(pid: u32) -> bool {
+        process_tree::list_processes()
+            .iter()
+            .any(|process| process.pid == pid && !process.is_zombie())
+    }
+
+    fn spawn_shell(conn: &Connection, script: &str) -> process::ProcessInfo {
+        let command = vec!["sh".to_string(), "-c".to_string(), script.to_string()];
+        let info = spawn(conn, command, None, vec![], false).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        info
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stop_kills_descendants_outside_process_group() {
+        let conn = setup_test_db();
+        let info = spawn_shell(&conn, "setsid sleep 30 & wait");
+
+        let escaped = process_tree::list_processes()
+            .into_iter()
+            .find(|process| process.ppid == info.pid)
+            .expect("setsid child should be running");
+        assert_ne!(escaped.pgid, info.pgid);
+
+        let survivors = stop(&conn, &info.id, false, false).unwrap();
+
+        assert!(survivors.is_empty());
+        assert!(!is_alive(escaped.pid));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stop_reports_survivors_found_by_doctor() {
+        let conn = setup_test_db();
+        let info = spawn_shell(&conn, "sh -c 'trap \"\" TERM; sleep 30' & wait");
+
+        let survivors = stop(&conn, &info.id, false, false).unwrap();
+        assert!(!survivors.is_empty());
+
+        // Only look, don't kill: processes of tasks from other tests count as orphans here
+        let orphans = doctor_orphans(&conn, false, false).unwrap();
+        for survivor in &survivors {
+            let orphan = orphans
+                .iter()
+                .find(|orphan| orphan.process.pid == survivor.pid)
+                .expect("survivor should be reported as an orphan");
+            assert_eq!(orphan.task_id, info.id);
+            let _ = process::kill(survivor.pid, true);
+        }
+    }
 }
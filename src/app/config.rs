@@ -1,4 +1,18 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use nix::sys::signal::Signal;
+use serde::{Deserialize, Serialize};
+
+use crate::app::error::{GhostError, Result};
+use crate::app::hooks::Hooks;
+use crate::app::notify::TerminalAlert;
+use crate::app::process;
+use crate::app::storage::task::Task;
+use crate::app::tui::keymap::{self, KeyMap, KeyPreset};
+use crate::app::tui::theme::{self, Theme, ThemePreset};
+use crate::app::watch::wildcard_match;
 
 /// Configuration for Ghost application
 #[derive(Debug, Clone)]
@@ -10,14 +24,10 @@ pub struct Config {
 
 impl Default for Config {
     fn default() -> Self {
-        let data_dir = get_data_dir();
-        let log_dir = data_dir.join("logs");
-        let db_path = data_dir.join("tasks.db");
-
         Config {
-            data_dir,
-            log_dir,
-            db_path,
+            data_dir: get_data_dir(),
+            log_dir: get_log_dir(),
+            db_path: get_db_path(),
         }
     }
 }
@@ -53,6 +63,8 @@ pub fn get_data_dir() -> PathBuf {
     // Check if GHOST_DATA_DIR environment variable is set
     if let Ok(data_dir) = std::env::var("GHOST_DATA_DIR") {
         PathBuf::from(data_dir)
+    } else if let Some(data_dir) = &FileConfig::current().paths.data_dir {
+        expand_home(data_dir)
     } else {
         dirs::data_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -62,6 +74,12 @@ pub fn get_data_dir() -> PathBuf {
 
 /// Get the default log directory
 pub fn get_log_dir() -> PathBuf {
+    // GHOST_DATA_DIR moves the logs along with everything else
+    if std::env::var_os("GHOST_DATA_DIR").is_none()
+        && let Some(log_dir) = &FileConfig::current().paths.log_dir
+    {
+        return expand_home(log_dir);
+    }
     get_data_dir().join("logs")
 }
 
@@ -70,6 +88,861 @@ pub fn get_db_path() -> PathBuf {
     get_data_dir().join("tasks.db")
 }
 
+/// Get the delegated cgroup under which per-task cgroups are created, if configured
+pub fn get_cgroup_root() -> Option<PathBuf> {
+    std::env::var_os("GHOST_CGROUP_ROOT").map(PathBuf::from)
+}
+
+/// Get the path of the config file
+pub fn get_config_path() -> PathBuf {
+    // Check if GHOST_CONFIG environment variable is set
+    if let Ok(path) = std::env::var("GHOST_CONFIG") {
+        PathBuf::from(path)
+    } else {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ghost")
+            .join("config.toml")
+    }
+}
+
+/// Name of the project config file, looked for in the current directory and its parents
+pub const PROJECT_CONFIG_FILE: &str = ".ghost.toml";
+
+/// Sections only the user config file may set: they run commands, widen what
+/// MCP clients may do or move ghost's own files, which a checked out
+/// repository must not decide
+pub const USER_ONLY_SECTIONS: &[&str] = &["hooks", "mcp", "paths"];
+
+/// Check that a key like "hooks.on_exit" may be set in a project config file
+pub fn check_project_key(name: &str) -> Result<()> {
+    let section = name.split_once('.').map_or(name, |(section, _)| section);
+    if USER_ONLY_SECTIONS.contains(&section) {
+        return Err(GhostError::InvalidArgument {
+            message: format!(
+                "`{name}` can only be set in the user config file ({})",
+                get_config_path().display()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Get the path of the project config file that applies to the current directory, if any
+pub fn find_project_config() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Replace a leading `~` with the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Days `ghost cleanup` keeps finished tasks by default
+const DEFAULT_CLEANUP_DAYS: u64 = 30;
+/// Seconds `ghost stop` waits for a task's processes to exit by default
+const DEFAULT_STOP_TIMEOUT: u64 = 1;
+/// Milliseconds between refreshes of the TUI by default
+const DEFAULT_REFRESH_INTERVAL: u64 = 1000;
+/// Shortest refresh interval of the TUI, so it does not hog the database
+const MIN_REFRESH_INTERVAL: u64 = 100;
+/// Lines ghost_log returns by default
+const DEFAULT_LOG_LIMIT: u64 = 1000;
+/// Matching lines ghost_search_logs returns by default
+const DEFAULT_SEARCH_LIMIT: u64 = 100;
+/// Environment variables whose values are hidden by default
+const DEFAULT_REDACT_PATTERNS: &[&str] = &[
+    "*TOKEN*",
+    "*SECRET*",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*API_KEY*",
+    "*PRIVATE_KEY*",
+];
+
+/// Settings read from the config files
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct FileConfig {
+    #[serde(default)]
+    pub paths: PathsConfig,
+    #[serde(default)]
+    pub cleanup: CleanupConfig,
+    #[serde(default)]
+    pub stop: StopConfig,
+    #[serde(default)]
+    pub env: EnvConfig,
+    #[serde(default)]
+    pub tui: TuiConfig,
+    #[serde(default)]
+    pub logs: LogsConfig,
+    #[serde(default)]
+    pub mcp: McpConfig,
+    /// Hooks run for every task, before the task's own
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+}
+
+/// Where tasks, logs and the database are kept; GHOST_DATA_DIR overrides both
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PathsConfig {
+    pub data_dir: Option<PathBuf>,
+    /// Defaults to `logs` in the data directory
+    pub log_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CleanupConfig {
+    /// Finished tasks older than this many days are removed by `ghost cleanup`
+    pub days: Option<u64>,
+}
+
+impl CleanupConfig {
+    pub fn days(&self) -> u64 {
+        self.days.unwrap_or(DEFAULT_CLEANUP_DAYS)
+    }
+}
+
+/// How `ghost stop` stops a task without `--force`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct StopConfig {
+    /// Signal sent to the task's processes, e.g. "INT" or "SIGHUP"
+    pub signal: Option<String>,
+    /// Seconds to wait for the processes to exit before reporting survivors
+    pub timeout: Option<u64>,
+}
+
+impl StopConfig {
+    pub fn signal(&self) -> Signal {
+        self.signal
+            .as_deref()
+            .and_then(|name| process::parse_signal(name).ok())
+            .unwrap_or(Signal::SIGTERM)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_STOP_TIMEOUT))
+    }
+}
+
+/// Environment variables whose values are hidden in the TUI and MCP results
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct EnvConfig {
+    /// Name patterns with `*` and `?`, matched ignoring case; an empty list hides nothing
+    pub redact: Option<Vec<String>>,
+}
+
+/// What a redacted value is replaced with
+pub const REDACTED: &str = "***";
+
+impl EnvConfig {
+    /// Whether the value of the variable `name` is hidden
+    pub fn is_redacted(&self, name: &str) -> bool {
+        let name = name.to_uppercase();
+        let matches = |pattern: &str| wildcard_match(&pattern.to_uppercase(), &name);
+        match &self.redact {
+            Some(patterns) => patterns.iter().any(|pattern| matches(pattern)),
+            None => DEFAULT_REDACT_PATTERNS
+                .iter()
+                .any(|pattern| matches(pattern)),
+        }
+    }
+
+    /// Hide the values of the task's redacted environment variables
+    pub fn redact_task(&self, task: &mut Task) {
+        let Some(env) = &task.env else {
+            return;
+        };
+        let Ok(mut vars) = serde_json::from_str::<Vec<(String, String)>>(env) else {
+            return;
+        };
+        for (name, value) in &mut vars {
+            if self.is_redacted(name) {
+                *value = REDACTED.to_string();
+            }
+        }
+        task.env = serde_json::to_string(&vars).ok();
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TuiConfig {
+    /// Milliseconds between refreshes of the task list
+    pub refresh_interval: Option<u64>,
+    /// Key map the keys are applied over: default, emacs or arrows
+    pub keymap: Option<String>,
+    /// Keys bound to actions in every view, and in `[tui.keys.<view>]` tables
+    pub keys: Option<toml::Table>,
+    /// Theme the colors are applied over: dark, light or high-contrast
+    pub theme: Option<String>,
+    pub colors: Option<toml::Table>,
+}
+
+impl TuiConfig {
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_millis(
+            self.refresh_interval
+                .unwrap_or(DEFAULT_REFRESH_INTERVAL)
+                .max(MIN_REFRESH_INTERVAL),
+        )
+    }
+
+    /// The keys of the TUI; fails when a key is bound to two actions of a view
+    pub fn key_map(&self) -> Result<KeyMap> {
+        let preset: KeyPreset = self
+            .keymap
+            .as_deref()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or_default();
+        KeyMap::new(preset, self.keys.as_ref()).map_err(|e| GhostError::Config {
+            message: format!("Invalid config: tui.keys: {e}"),
+        })
+    }
+
+    pub fn theme(&self) -> Theme {
+        let preset: ThemePreset = self
+            .theme
+            .as_deref()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or_default();
+        // Invalid colors were reported when the config files were read
+        Theme::new(preset, self.colors.as_ref()).unwrap_or_default()
+    }
+}
+
+/// How much of the logs the MCP server returns when the caller sets no limit
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct LogsConfig {
+    /// Lines ghost_log returns
+    pub limit: Option<u64>,
+    /// Matching lines ghost_search_logs returns
+    pub search_limit: Option<u64>,
+}
+
+impl LogsConfig {
+    pub fn limit(&self) -> u64 {
+        self.limit.unwrap_or(DEFAULT_LOG_LIMIT)
+    }
+
+    pub fn search_limit(&self) -> u64 {
+        self.search_limit.unwrap_or(DEFAULT_SEARCH_LIMIT)
+    }
+}
+
+/// What the MCP server lets its clients do
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct McpConfig {
+    /// Refuse the tools that start or stop tasks
+    pub read_only: Option<bool>,
+    /// Patterns with `*` and `?` the commands of ghost_run must match; unset allows any
+    pub allowed_commands: Option<Vec<String>>,
+}
+
+impl McpConfig {
+    pub fn read_only(&self) -> bool {
+        self.read_only.unwrap_or(false)
+    }
+
+    /// Whether ghost_run may start `command`
+    pub fn allows_command(&self, command: &str) -> bool {
+        let command = command.trim();
+        self.allowed_commands.as_ref().is_none_or(|patterns| {
+            patterns
+                .iter()
+                .any(|pattern| wildcard_match(pattern.trim(), command))
+        })
+    }
+
+    /// Check the working directory and environment given to ghost_run
+    ///
+    /// With `allowed_commands` set, neither may be given: a variable such as
+    /// `RUSTC_WRAPPER` or `LD_PRELOAD`, or a directory with its own tool
+    /// config, would make an allowed command run anything.
+    pub fn check_run_overrides(
+        &self,
+        cwd: Option<&str>,
+        env: &[String],
+    ) -> std::result::Result<(), String> {
+        if self.allowed_commands.is_none() {
+            return Ok(());
+        }
+        if cwd.is_some() {
+            return Err("cwd cannot be set while mcp.allowed_commands is set".to_string());
+        }
+        if !env.is_empty() {
+            return Err("env cannot be set while mcp.allowed_commands is set".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How the TUI tells about tasks that ended or restarted, besides its toasts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Escape sequence written to the terminal for each notification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal: Option<TerminalAlert>,
+}
+
+impl NotificationConfig {
+    pub fn is_empty(&self) -> bool {
+        self.terminal.is_none()
+    }
+}
+
+impl FileConfig {
+    /// Read the user config file and the project one over it; without them,
+    /// everything has its default
+    pub fn load() -> Result<Self> {
+        Self::from_files(&config_files()?)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let files: Vec<ConfigFile> = ConfigFile::read(path, ConfigSource::User)?
+            .into_iter()
+            .collect();
+        Self::from_files(&files)
+    }
+
+    /// The settings of this process, read once; a broken config file was
+    /// already reported by `ghost` itself, so it counts as missing here
+    pub fn current() -> &'static Self {
+        static CURRENT: OnceLock<FileConfig> = OnceLock::new();
+        CURRENT.get_or_init(|| Self::load().unwrap_or_default())
+    }
+
+    fn from_files(files: &[ConfigFile]) -> Result<Self> {
+        let mut merged = toml::Table::new();
+        for file in files {
+            merge_tables(&mut merged, file.table.clone());
+        }
+        let config: Self = merged.try_into().map_err(|e| GhostError::Config {
+            message: format!("Invalid config: {e}"),
+        })?;
+        // Keys set in one file may clash with those of the other or of the key map
+        config.tui.key_map()?;
+        Ok(config)
+    }
+}
+
+/// Which file a setting comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    /// The user's config file, see `get_config_path`
+    User,
+    /// A `.ghost.toml` in the current directory or a parent
+    Project,
+}
+
+impl ConfigSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::User => "user",
+            ConfigSource::Project => "project",
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A config file that exists, with its checked settings
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub source: ConfigSource,
+    table: toml::Table,
+}
+
+impl ConfigFile {
+    /// Read and check a config file; None when it does not exist
+    fn read(path: &Path, source: ConfigSource) -> Result<Option<Self>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let table = parse_table(path, &text)?;
+        if source == ConfigSource::Project
+            && let Some(section) = USER_ONLY_SECTIONS
+                .iter()
+                .find(|section| table.contains_key(**section))
+        {
+            return Err(GhostError::Config {
+                message: format!(
+                    "Invalid config file {}: `[{section}]` can only be set in the user config file ({})",
+                    path.display(),
+                    get_config_path().display()
+                ),
+            });
+        }
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            source,
+            table,
+        }))
+    }
+
+    /// The value set for a key like "stop.signal"
+    fn get(&self, key: &str) -> Option<&toml::Value> {
+        let (section, field) = key.split_once('.')?;
+        self.table.get(section)?.get(field)
+    }
+}
+
+/// The user config file and the project one, those that exist, in the order they apply
+pub fn config_files() -> Result<Vec<ConfigFile>> {
+    let mut files: Vec<ConfigFile> = ConfigFile::read(&get_config_path(), ConfigSource::User)?
+        .into_iter()
+        .collect();
+    if let Some(path) = find_project_config() {
+        files.extend(ConfigFile::read(&path, ConfigSource::Project)?);
+    }
+    Ok(files)
+}
+
+/// Merge `over` into `base`, the values of `over` winning
+fn merge_tables(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => merge_tables(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Parse a config file and check every key and value in it
+fn parse_table(path: &Path, text: &str) -> Result<toml::Table> {
+    let invalid = |message: String| GhostError::Config {
+        message: format!("Invalid config file {}: {message}", path.display()),
+    };
+    let table: toml::Table = text.parse().map_err(|e| invalid(format!("{e}")))?;
+
+    for (section, entries) in &table {
+        let keys: Vec<&ConfigKey> = CONFIG_KEYS
+            .iter()
+            .filter(|key| key.section() == section)
+            .collect();
+        if keys.is_empty() {
+            return Err(invalid(format!(
+                "unknown section `{section}`; valid sections: {}",
+                sections().join(", ")
+            )));
+        }
+        let Some(entries) = entries.as_table() else {
+            return Err(invalid(format!("`{section}` must be a [{section}] table")));
+        };
+        for (field, value) in entries {
+            let name = format!("{section}.{field}");
+            let Some(key) = keys.iter().find(|key| key.name == name) else {
+                let valid: Vec<&str> = keys.iter().map(|key| key.name).collect();
+                return Err(invalid(format!(
+                    "unknown key `{name}`; valid keys: {}",
+                    valid.join(", ")
+                )));
+            };
+            (key.check)(value).map_err(|e| invalid(format!("invalid value for `{name}`: {e}")))?;
+        }
+    }
+    Ok(table)
+}
+
+/// A setting of the config file
+pub struct ConfigKey {
+    /// Section and field, e.g. "stop.signal"
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Value used when no file sets the key, in TOML
+    pub default: Option<&'static str>,
+    check: fn(&toml::Value) -> std::result::Result<(), String>,
+}
+
+impl ConfigKey {
+    /// The `[section]` the key is written in
+    pub fn section(&self) -> &'static str {
+        self.name
+            .split_once('.')
+            .map_or(self.name, |(section, _)| section)
+    }
+
+    fn field(&self) -> &'static str {
+        self.name
+            .split_once('.')
+            .map_or(self.name, |(_, field)| field)
+    }
+
+    pub fn default_value(&self) -> Option<toml::Value> {
+        self.default.and_then(parse_value)
+    }
+}
+
+/// Every setting of the config file
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        name: "paths.data_dir",
+        description: "Directory of the task database and logs",
+        default: None,
+        check: check_string,
+    },
+    ConfigKey {
+        name: "paths.log_dir",
+        description: "Directory of the task logs (defaults to logs in the data directory)",
+        default: None,
+        check: check_string,
+    },
+    ConfigKey {
+        name: "cleanup.days",
+        description: "Days `ghost cleanup` keeps finished tasks",
+        default: Some("30"),
+        check: check_positive,
+    },
+    ConfigKey {
+        name: "stop.signal",
+        description: "Signal `ghost stop` sends without --force",
+        default: Some("\"TERM\""),
+        check: check_signal,
+    },
+    ConfigKey {
+        name: "stop.timeout",
+        description: "Seconds `ghost stop` waits for the processes to exit",
+        default: Some("1"),
+        check: check_positive,
+    },
+    ConfigKey {
+        name: "env.redact",
+        description: "Environment variables hidden in the TUI and MCP results (* and ? patterns)",
+        default: Some(
+            "[\"*TOKEN*\", \"*SECRET*\", \"*PASSWORD*\", \"*PASSWD*\", \"*API_KEY*\", \"*PRIVATE_KEY*\"]",
+        ),
+        check: check_string_list,
+    },
+    ConfigKey {
+        name: "tui.refresh_interval",
+        description: "Milliseconds between refreshes of the TUI (at least 100)",
+        default: Some("1000"),
+        check: check_refresh_interval,
+    },
+    ConfigKey {
+        name: "tui.keymap",
+        description: "Key map of the TUI the keys are applied over (default, emacs, arrows)",
+        default: Some("\"default\""),
+        check: check_keymap,
+    },
+    ConfigKey {
+        name: "tui.keys",
+        description: "Keys of TUI actions, e.g. { quit = \"Q\" }; [tui.keys.<view>] for one view",
+        default: None,
+        check: keymap::check_keys,
+    },
+    ConfigKey {
+        name: "tui.theme",
+        description: "Colors of the TUI (dark, light, high-contrast)",
+        default: Some("\"dark\""),
+        check: check_theme,
+    },
+    ConfigKey {
+        name: "tui.colors",
+        description: "Colors overriding the theme, e.g. { running = \"light-green\" }",
+        default: None,
+        check: theme::check_colors,
+    },
+    ConfigKey {
+        name: "logs.limit",
+        description: "Lines ghost_log returns when no limit is given",
+        default: Some("1000"),
+        check: check_positive,
+    },
+    ConfigKey {
+        name: "logs.search_limit",
+        description: "Matching lines ghost_search_logs returns when no limit is given",
+        default: Some("100"),
+        check: check_positive,
+    },
+    ConfigKey {
+        name: "mcp.read_only",
+        description: "Refuse the MCP tools that start or stop tasks",
+        default: Some("false"),
+        check: check_bool,
+    },
+    ConfigKey {
+        name: "mcp.allowed_commands",
+        description: "Commands ghost_run may start (* and ? patterns; unset allows any)",
+        default: None,
+        check: check_string_list,
+    },
+    ConfigKey {
+        name: "hooks.pre_start",
+        description: "Command run before every task starts",
+        default: None,
+        check: check_string,
+    },
+    ConfigKey {
+        name: "hooks.post_start",
+        description: "Command run after every task started",
+        default: None,
+        check: check_string,
+    },
+    ConfigKey {
+        name: "hooks.on_exit",
+        description: "Command run when a task exits",
+        default: None,
+        check: check_string,
+    },
+    ConfigKey {
+        name: "hooks.on_failure",
+        description: "Command run when a task fails",
+        default: None,
+        check: check_string,
+    },
+    ConfigKey {
+        name: "hooks.timeout",
+        description: "Seconds a hook may run before it is killed",
+        default: Some("60"),
+        check: check_positive,
+    },
+    ConfigKey {
+        name: "notifications.terminal",
+        description: "Terminal alert of the TUI for ended tasks (bell, osc9, osc777)",
+        default: None,
+        check: check_terminal_alert,
+    },
+];
+
+/// The sections of the config file, in order
+fn sections() -> Vec<&'static str> {
+    let mut sections: Vec<&str> = CONFIG_KEYS.iter().map(ConfigKey::section).collect();
+    sections.dedup();
+    sections
+}
+
+/// Find a setting by its name, e.g. "stop.signal"
+pub fn find_key(name: &str) -> Result<&'static ConfigKey> {
+    CONFIG_KEYS
+        .iter()
+        .find(|key| key.name == name)
+        .ok_or_else(|| GhostError::InvalidArgument {
+            message: format!(
+                "Unknown config key `{name}`. Run `ghost config list` to see all keys"
+            ),
+        })
+}
+
+/// A setting and the value it has, after the files are layered
+pub struct ConfigValue {
+    pub key: &'static ConfigKey,
+    /// None when neither a file nor a default sets it
+    pub value: Option<toml::Value>,
+    pub source: ConfigSource,
+}
+
+/// The value of every setting and where it comes from
+pub fn config_values() -> Result<Vec<ConfigValue>> {
+    let files = config_files()?;
+    Ok(CONFIG_KEYS
+        .iter()
+        .map(|key| {
+            match files
+                .iter()
+                .rev()
+                .find_map(|file| file.get(key.name).map(|value| (value, file.source)))
+            {
+                Some((value, source)) => ConfigValue {
+                    key,
+                    value: Some(value.clone()),
+                    source,
+                },
+                None => ConfigValue {
+                    key,
+                    value: key.default_value(),
+                    source: ConfigSource::Default,
+                },
+            }
+        })
+        .collect())
+}
+
+/// Set a key in a config file, keeping the rest of the file as written
+///
+/// `raw` is read as a TOML value, e.g. `5` or `["*TOKEN*"]`, and else as a
+/// plain string. Returns the value written.
+pub fn set_value(path: &Path, name: &str, raw: &str) -> Result<toml::Value> {
+    let key = find_key(name)?;
+    let value = match parse_value(raw) {
+        Some(value) if (key.check)(&value).is_ok() => value,
+        parsed => {
+            let string = toml::Value::String(raw.to_string());
+            match (key.check)(&string) {
+                Ok(()) => string,
+                Err(message) => {
+                    // Report what the value was most likely meant to be
+                    let message = match parsed.map(|value| (key.check)(&value)) {
+                        Some(Err(message)) => message,
+                        _ => message,
+                    };
+                    return Err(GhostError::InvalidArgument {
+                        message: format!("Invalid value for `{name}`: {message}"),
+                    });
+                }
+            }
+        }
+    };
+
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut expected = parse_table(path, &text)?;
+    merge_tables(
+        &mut expected,
+        toml::Table::from_iter([(
+            key.section().to_string(),
+            toml::Value::Table(toml::Table::from_iter([(
+                key.field().to_string(),
+                value.clone(),
+            )])),
+        )]),
+    );
+
+    // Sections written in an unusual way, e.g. as inline tables, are rewritten as a whole
+    let edited = set_line(&text, key, &value);
+    let edited = if edited.parse::<toml::Table>().ok().as_ref() == Some(&expected) {
+        edited
+    } else {
+        toml::to_string(&expected).map_err(|e| GhostError::Config {
+            message: format!("Failed to write {}: {e}", path.display()),
+        })?
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, edited)?;
+    Ok(value)
+}
+
+/// The text with the key's line replaced, or added to its section
+fn set_line(text: &str, key: &ConfigKey, value: &toml::Value) -> String {
+    let line = format!("{} = {value}", key.field());
+    let header = format!("[{}]", key.section());
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+    match lines.iter().position(|l| l.trim() == header) {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|l| l.trim_start().starts_with('['))
+                .map_or(lines.len(), |offset| start + 1 + offset);
+            let existing = (start + 1..end).find(|&i| {
+                lines[i]
+                    .split_once('=')
+                    .is_some_and(|(field, _)| field.trim() == key.field())
+            });
+            match existing {
+                Some(i) => lines[i] = line,
+                None => {
+                    // After the section's last setting, before any blank lines
+                    let at = (start + 1..end)
+                        .rev()
+                        .find(|&i| !lines[i].trim().is_empty())
+                        .map_or(start + 1, |i| i + 1);
+                    lines.insert(at, line);
+                }
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(header);
+            lines.push(line);
+        }
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Parse a single TOML value, e.g. `30` or `"TERM"`
+fn parse_value(text: &str) -> Option<toml::Value> {
+    let mut table: toml::Table = format!("value = {text}").parse().ok()?;
+    table.remove("value")
+}
+
+fn check_string(value: &toml::Value) -> std::result::Result<(), String> {
+    expect_string(value).map(drop)
+}
+
+fn expect_string(value: &toml::Value) -> std::result::Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected a string, found {}", value.type_str()))
+}
+
+fn expect_integer(value: &toml::Value, min: i64) -> std::result::Result<(), String> {
+    match value.as_integer() {
+        Some(n) if n >= min => Ok(()),
+        Some(n) => Err(format!("expected a number of at least {min}, found {n}")),
+        None => Err(format!("expected a number, found {}", value.type_str())),
+    }
+}
+
+fn check_positive(value: &toml::Value) -> std::result::Result<(), String> {
+    expect_integer(value, 1)
+}
+
+fn check_refresh_interval(value: &toml::Value) -> std::result::Result<(), String> {
+    expect_integer(value, MIN_REFRESH_INTERVAL as i64)
+}
+
+fn check_bool(value: &toml::Value) -> std::result::Result<(), String> {
+    match value {
+        toml::Value::Boolean(_) => Ok(()),
+        _ => Err(format!(
+            "expected true or false, found {}",
+            value.type_str()
+        )),
+    }
+}
+
+fn check_string_list(value: &toml::Value) -> std::result::Result<(), String> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| format!("expected a list of strings, found {}", value.type_str()))?;
+    items.iter().try_for_each(check_string)
+}
+
+fn check_signal(value: &toml::Value) -> std::result::Result<(), String> {
+    process::parse_signal(expect_string(value)?).map(drop)
+}
+
+fn check_terminal_alert(value: &toml::Value) -> std::result::Result<(), String> {
+    expect_string(value)?.parse::<TerminalAlert>().map(drop)
+}
+
+fn check_keymap(value: &toml::Value) -> std::result::Result<(), String> {
+    expect_string(value)?.parse::<KeyPreset>().map(drop)
+}
+
+fn check_theme(value: &toml::Value) -> std::result::Result<(), String> {
+    expect_string(value)?.parse::<ThemePreset>().map(drop)
+}
+
 /// Environment variable parsing utilities
 pub mod env {
     use crate::app::error::{GhostError, Result};
@@ -90,6 +963,20 @@ pub mod env {
         }
         Ok(env_vars)
     }
+
+    /// The current environment with the given variables added or overridden
+    ///
+    /// Used for tasks that are started later by another ghost process.
+    pub fn inherit_env(overrides: Vec<(String, String)>) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = std::env::vars().collect();
+        for (key, value) in overrides {
+            match env.iter_mut().find(|(k, _)| *k == key) {
+                Some(existing) => existing.1 = value,
+                None => env.push((key, value)),
+            }
+        }
+        env
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +1013,241 @@ mod tests {
         assert!(config.log_dir.exists());
     }
 
+    #[test]
+    fn test_file_config_hooks() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        assert_eq!(FileConfig::load_from(&path).unwrap(), FileConfig::default());
+
+        std::fs::write(
+            &path,
+            "[hooks]\non_failure = 'notify-send \"build failed\"'\ntimeout = 30\n",
+        )
+        .unwrap();
+        let config = FileConfig::load_from(&path).unwrap();
+        assert_eq!(
+            config.hooks.on_failure.as_deref(),
+            Some("notify-send \"build failed\"")
+        );
+        assert_eq!(config.hooks.timeout, Some(30));
+
+        std::fs::write(&path, "[notifications]\nterminal = 'osc777'\n").unwrap();
+        let config = FileConfig::load_from(&path).unwrap();
+        assert_eq!(config.notifications.terminal, Some(TerminalAlert::Osc777));
+
+        std::fs::write(&path, "[hooks\n").unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("Invalid config file"), "{error}");
+    }
+
+    #[test]
+    fn test_file_config_tui_keys_and_theme() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+
+        std::fs::write(
+            &path,
+            "[tui]\nkeymap = 'emacs'\ntheme = 'high-contrast'\n\n[tui.keys]\nquit = 'Q'\n\n[tui.keys.log]\nfollow = 'f'\n\n[tui.colors]\nrunning = 'green'\n",
+        )
+        .unwrap();
+        let config = FileConfig::load_from(&path).unwrap();
+        let key_map = config.tui.key_map().unwrap();
+        assert_eq!(
+            key_map.hint(crate::app::tui::ViewMode::TaskList, &[keymap::Action::Quit]),
+            Some("Q".to_string())
+        );
+        let theme = config.tui.theme();
+        assert_eq!(theme.running, ratatui::style::Color::Green);
+        assert_eq!(theme.killed, Theme::HIGH_CONTRAST.killed);
+
+        // `C-n` already moves down in the emacs key map
+        std::fs::write(
+            &path,
+            "[tui]\nkeymap = 'emacs'\n\n[tui.keys]\nnew_task = 'C-n'\n",
+        )
+        .unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(
+            error.contains("`C-n` is bound to both `down` and `new_task` in the list view"),
+            "{error}"
+        );
+
+        std::fs::write(&path, "[tui]\ntheme = 'solarized'\n").unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("invalid value for `tui.theme`"), "{error}");
+    }
+
+    #[test]
+    fn test_config_key_defaults_are_valid() {
+        for key in CONFIG_KEYS {
+            if let Some(default) = key.default {
+                let value = key.default_value().expect(default);
+                assert_eq!((key.check)(&value), Ok(()), "{}", key.name);
+            }
+        }
+        assert_eq!(
+            sections(),
+            [
+                "paths",
+                "cleanup",
+                "stop",
+                "env",
+                "tui",
+                "logs",
+                "mcp",
+                "hooks",
+                "notifications"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_config_errors_name_the_key() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+
+        std::fs::write(&path, "[tui]\nrefresh = 500\n").unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("unknown key `tui.refresh`"), "{error}");
+        assert!(error.contains("tui.refresh_interval"), "{error}");
+
+        std::fs::write(&path, "[stop]\nsignal = 'TERMINATE'\n").unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("invalid value for `stop.signal`"), "{error}");
+
+        std::fs::write(&path, "[cleanup]\ndays = '7'\n").unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("expected a number, found string"), "{error}");
+
+        std::fs::write(&path, "[server]\nport = 1\n").unwrap();
+        let error = FileConfig::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("unknown section `server`"), "{error}");
+    }
+
+    #[test]
+    fn test_project_config_overrides_user_config() {
+        let temp_dir = tempdir().unwrap();
+        let user = temp_dir.path().join("config.toml");
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(&user, "[stop]\nsignal = 'INT'\ntimeout = 5\n").unwrap();
+        std::fs::write(&project, "[stop]\ntimeout = 10\n").unwrap();
+
+        let files: Vec<ConfigFile> = [
+            ConfigFile::read(&user, ConfigSource::User).unwrap(),
+            ConfigFile::read(&project, ConfigSource::Project).unwrap(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let config = FileConfig::from_files(&files).unwrap();
+        assert_eq!(config.stop.signal(), Signal::SIGINT);
+        assert_eq!(config.stop.timeout(), Duration::from_secs(10));
+        assert_eq!(config.cleanup.days(), DEFAULT_CLEANUP_DAYS);
+    }
+
+    #[test]
+    fn test_project_config_cannot_loosen_mcp_policy() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+
+        std::fs::write(
+            &project,
+            "[mcp]\nread_only = false\nallowed_commands = ['*']\n",
+        )
+        .unwrap();
+        let error = ConfigFile::read(&project, ConfigSource::Project)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("`[mcp]` can only be set in the user config file"),
+            "{error}"
+        );
+
+        std::fs::write(&project, "[hooks]\non_exit = 'curl example.com'\n").unwrap();
+        assert!(ConfigFile::read(&project, ConfigSource::Project).is_err());
+        std::fs::write(&project, "[paths]\ndata_dir = '/tmp/elsewhere'\n").unwrap();
+        assert!(ConfigFile::read(&project, ConfigSource::Project).is_err());
+
+        // The same settings are fine in the user's own file
+        std::fs::write(&project, "[mcp]\nread_only = false\n").unwrap();
+        assert!(ConfigFile::read(&project, ConfigSource::User).is_ok());
+
+        assert!(check_project_key("mcp.read_only").is_err());
+        assert!(check_project_key("stop.timeout").is_ok());
+    }
+
+    #[test]
+    fn test_set_value_keeps_the_rest_of_the_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("ghost").join("config.toml");
+
+        assert_eq!(
+            set_value(&path, "stop.timeout", "5").unwrap(),
+            toml::Value::Integer(5)
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[stop]\ntimeout = 5\n"
+        );
+
+        std::fs::write(
+            &path,
+            "# Stop gently\n[stop]\ntimeout = 5 # seconds\n\n[cleanup]\ndays = 7\n",
+        )
+        .unwrap();
+        set_value(&path, "stop.signal", "INT").unwrap();
+        set_value(&path, "cleanup.days", "14").unwrap();
+        set_value(&path, "env.redact", r#"["*KEY*"]"#).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Stop gently\n[stop]\ntimeout = 5 # seconds\nsignal = \"INT\"\n\n[cleanup]\ndays = 14\n\n[env]\nredact = [\"*KEY*\"]\n"
+        );
+
+        let error = set_value(&path, "stop.timeout", "soon")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Invalid value for `stop.timeout`"),
+            "{error}"
+        );
+        assert!(set_value(&path, "stop.grace", "5").is_err());
+    }
+
+    #[test]
+    fn test_env_redaction() {
+        let default = EnvConfig::default();
+        assert!(default.is_redacted("GITHUB_TOKEN"));
+        assert!(default.is_redacted("db_password"));
+        assert!(!default.is_redacted("PATH"));
+
+        let custom = EnvConfig {
+            redact: Some(vec!["AWS_*".to_string()]),
+        };
+        assert!(custom.is_redacted("AWS_SECRET_ACCESS_KEY"));
+        assert!(!custom.is_redacted("GITHUB_TOKEN"));
+
+        let mcp = McpConfig {
+            allowed_commands: Some(vec!["cargo *".to_string(), "npm test".to_string()]),
+            ..Default::default()
+        };
+        assert!(mcp.allows_command("cargo build --release"));
+        assert!(mcp.allows_command("npm test"));
+        assert!(!mcp.allows_command("rm -rf /"));
+        assert!(McpConfig::default().allows_command("rm -rf /"));
+
+        assert!(mcp.check_run_overrides(None, &[]).is_ok());
+        assert!(mcp.check_run_overrides(Some("/tmp"), &[]).is_err());
+        assert!(
+            mcp.check_run_overrides(None, &["RUSTC_WRAPPER=/tmp/x".to_string()])
+                .is_err()
+        );
+        assert!(
+            McpConfig::default()
+                .check_run_overrides(Some("/tmp"), &["A=1".to_string()])
+                .is_ok()
+        );
+    }
+
     #[test]
     fn test_parse_env_vars_valid() {
         let env_strings = vec!["KEY1=value1".to_string(), "KEY2=value2".to_string()];
@@ -0,0 +1,274 @@
+//! Cron expressions for recurring schedules
+//!
+//! The usual five fields are supported (minute, hour, day of month, month and
+//! day of week) with `*`, lists, ranges, steps and English month and weekday
+//! names, plus the `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`
+//! shorthands. Times are local. As in cron, when both the day of month and
+//! the day of week are restricted, a day matching either one is used.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+use crate::app::error::{GhostError, Result};
+
+/// How many years ahead to look for a matching time, e.g. for "0 0 30 2 *"
+const SEARCH_YEARS: i32 = 5;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Whether the day-of-month and day-of-week fields were something other than `*`
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    /// Parse a five-field cron expression or one of the `@` shorthands
+    pub fn parse(expression: &str) -> Result<Cron> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(
+                expression,
+                "expected 5 fields: minute hour day-of-month month day-of-week",
+            ));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAY_NAMES, 0)
+            .map_err(|message| invalid(expression, &format!("day of week: {message}")))?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Cron {
+            minutes: parse_field(minute, 0, 59, &[], 0)
+                .map_err(|message| invalid(expression, &format!("minute: {message}")))?,
+            hours: parse_field(hour, 0, 23, &[], 0)
+                .map_err(|message| invalid(expression, &format!("hour: {message}")))?
+                as u32,
+            days: parse_field(day, 1, 31, &[], 0)
+                .map_err(|message| invalid(expression, &format!("day of month: {message}")))?
+                as u32,
+            months: parse_field(month, 1, 12, &MONTH_NAMES, 1)
+                .map_err(|message| invalid(expression, &format!("month: {message}")))?
+                as u16,
+            weekdays: weekdays as u8,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    /// The first matching time strictly after `after`
+    ///
+    /// Times that do not exist locally because of a daylight saving change are
+    /// skipped. Returns None if nothing matches within the next few years.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let last_year = start.year() + SEARCH_YEARS;
+        let mut time = start;
+
+        while time.year() <= last_year {
+            if !self.matches_month(time.month()) {
+                time = first_of_next_month(time.date())?;
+            } else if !self.matches_day(time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+            } else {
+                match Local.from_local_datetime(&time).earliest() {
+                    Some(local) => return Some(local),
+                    None => time += Duration::minutes(1),
+                }
+            }
+        }
+
+        None
+    }
+
+    fn matches_month(&self, month: u32) -> bool {
+        self.months & (1 << month) != 0
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn invalid(expression: &str, message: &str) -> GhostError {
+    GhostError::InvalidArgument {
+        message: format!("Invalid cron expression '{expression}': {message}"),
+    }
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDateTime> {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+/// Parse one field into a bit set of the allowed values
+///
+/// `names[i]` stands for the value `i + name_offset`.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    name_offset: u32,
+) -> std::result::Result<u64, String> {
+    let value = |part: &str| -> std::result::Result<u32, String> {
+        if let Some(index) = names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(part))
+        {
+            return Ok(index as u32 + name_offset);
+        }
+        let value: u32 = part
+            .parse()
+            .map_err(|_| format!("invalid value '{part}'"))?;
+        if value < min || value > max {
+            return Err(format!("{value} is not between {min} and {max}"));
+        }
+        Ok(value)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{step}'"))?;
+                if step == 0 {
+                    return Err("step must be at least 1".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // "5/15" runs from 5 to the end of the field
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let value = value(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("range {start}-{end} is empty"));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn next(expression: &str, after: DateTime<Local>) -> DateTime<Local> {
+        Cron::parse(expression).unwrap().next_after(after).unwrap()
+    }
+
+    #[test]
+    fn test_next_after_hourly_and_steps() {
+        let after = local(2026, 3, 10, 10, 15);
+        assert_eq!(next("0 * * * *", after), local(2026, 3, 10, 11, 0));
+        assert_eq!(next("@hourly", after), local(2026, 3, 10, 11, 0));
+        assert_eq!(next("*/20 * * * *", after), local(2026, 3, 10, 10, 20));
+        assert_eq!(next("5/30 * * * *", after), local(2026, 3, 10, 10, 35));
+        assert_eq!(next("15 10 * * *", after), local(2026, 3, 11, 10, 15));
+    }
+
+    #[test]
+    fn test_next_after_lists_ranges_and_names() {
+        // 2026-03-10 is a Tuesday
+        let after = local(2026, 3, 10, 12, 0);
+        assert_eq!(next("0 9 * * mon-fri", after), local(2026, 3, 11, 9, 0));
+        assert_eq!(next("0 9 * * 0,6", after), local(2026, 3, 14, 9, 0));
+        assert_eq!(next("0 9 * * 7", after), local(2026, 3, 15, 9, 0));
+        assert_eq!(next("30 8 1 jan,jul *", after), local(2026, 7, 1, 8, 30));
+        assert_eq!(next("@yearly", after), local(2027, 1, 1, 0, 0));
+    }
+
+    #[test]
+    fn test_day_of_month_or_day_of_week() {
+        // Both restricted: the 20th or any Friday, whichever comes first
+        let after = local(2026, 3, 10, 12, 0);
+        assert_eq!(next("0 0 20 * fri", after), local(2026, 3, 13, 0, 0));
+        assert_eq!(next("0 0 11 * fri", after), local(2026, 3, 11, 0, 0));
+    }
+
+    #[test]
+    fn test_impossible_date_has_no_next_time() {
+        let cron = Cron::parse("0 0 30 2 *").unwrap();
+        assert_eq!(cron.next_after(local(2026, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_expressions() {
+        for expression in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "10-5 * * * *",
+            "x * * * *",
+            "@often",
+        ] {
+            assert!(
+                matches!(
+                    Cron::parse(expression),
+                    Err(GhostError::InvalidArgument { .. })
+                ),
+                "{expression:?} should be rejected"
+            );
+        }
+    }
+}
@@ -1,4 +1,16 @@
-use crate::app::storage::Task;
+use crate::app::config::ConfigValue;
+use crate::app::helpers::{format_bytes, format_duration_short};
+use crate::app::log_grep::{GrepMode, LogMatch};
+use crate::app::log_level::{LevelCounts, LogFormat};
+use crate::app::log_reader::ReadSummary;
+use crate::app::process_state;
+use crate::app::process_tree::{Orphan, ProcessEntry, ProcessNode};
+use crate::app::queue::QueueSummary;
+use crate::app::stats::{StatsSampler, TaskStats};
+use crate::app::storage::{
+    self, AfterCondition, FilterPreset, Schedule, Task, TaskEvent, TaskStatus, Template,
+};
+use crate::app::template;
 
 /// Display a list of tasks in a formatted table
 pub fn print_task_list(tasks: &[Task]) {
@@ -13,15 +25,11 @@ pub fn print_task_list(tasks: &[Task]) {
         let command_display = format_command_truncated(&task.command, 30);
         let started = format_timestamp(task.started_at, "%Y-%m-%d %H:%M");
         let cwd_display = task.cwd.as_deref().unwrap_or("-");
+        let status = process_state::status_label(task);
 
         println!(
             "{:<36} {:<8} {:<10} {:<20} {:<30} {}",
-            &task.id,
-            task.pid,
-            task.status.as_str(),
-            started,
-            command_display,
-            cwd_display
+            &task.id, task.pid, status, started, command_display, cwd_display
         );
     }
 }
@@ -39,6 +47,9 @@ fn print_table_header() {
 pub fn print_task_details(task: &Task) {
     let task_id = &task.id;
     println!("Task: {task_id}");
+    if let Some(ref name) = task.name {
+        println!("Name: {name}");
+    }
     let pid = task.pid;
     println!("PID: {pid}");
     let status = &task.status;
@@ -66,10 +77,504 @@ pub fn print_task_details(task: &Task) {
         println!("Exit code: {exit_code}");
     }
 
+    if let Some(ref exit_reason) = task.exit_reason {
+        println!("Exit reason: {exit_reason}");
+    }
+
+    let options = task.run_options();
+    if let (Some(timeout), Some(action)) = (options.idle_timeout, options.idle_action()) {
+        println!(
+            "Idle timeout: {} ({action})",
+            format_duration_short(timeout)
+        );
+    }
+    if let Some(idle) = process_state::idle_seconds(task) {
+        println!("Idle for: {}", format_duration_short(idle));
+    }
+    if let Some(ref queue) = task.queue {
+        match task
+            .queue_position
+            .filter(|_| task.status == TaskStatus::Queued)
+        {
+            Some(position) => println!("Queue: {queue} (position {position})"),
+            None => println!("Queue: {queue}"),
+        }
+    }
+    if let Some(ref schedule_id) = task.schedule_id {
+        println!("Schedule: {schedule_id}");
+    }
+    if let Some(ref watch) = options.watch {
+        println!(
+            "Watching: {} (debounce {}ms)",
+            watch.describe(),
+            watch.debounce_ms
+        );
+    }
+    if let Some(ref trigger) = task.watch_trigger {
+        println!("Last trigger: {trigger}");
+    }
+    if !options.on_output.is_empty() {
+        let triggers: Vec<String> = options.on_output.iter().map(|t| t.describe()).collect();
+        println!("Output triggers: {}", triggers.join(", "));
+    }
+    if !options.hooks.is_empty() {
+        println!("Hooks: {}", options.hooks.describe());
+    }
+    if let Some(notify) = &options.notify {
+        println!("Notify: {}", notify.describe());
+    }
+    if task.restart_count > 0 {
+        let restarts = task.restart_count;
+        println!("Restarts: {restarts}");
+    }
+
+    if !options.limits.is_empty() {
+        println!("Limits: {}", limits_description(task));
+    }
+
     let log_path = &task.log_path;
     println!("Log file: {log_path}");
 }
 
+/// Print the errors and warnings counted in a task's log
+pub fn print_log_levels(counts: LevelCounts, format: LogFormat) {
+    let plural = |count: usize, word: &str| match count {
+        1 => format!("1 {word}"),
+        count => format!("{count} {word}s"),
+    };
+    println!(
+        "Log levels: {}, {} ({format})",
+        plural(counts.errors, "error"),
+        plural(counts.warnings, "warning")
+    );
+}
+
+/// Display a task's event history, e.g. fired output triggers and restarts
+pub fn print_task_events(events: &[TaskEvent]) {
+    if events.is_empty() {
+        return;
+    }
+    if let Some(ready) = storage::ready_since(events) {
+        println!(
+            "Ready since: {}",
+            format_timestamp(ready, "%Y-%m-%d %H:%M:%S")
+        );
+    }
+    println!();
+    println!("Events:");
+    for event in events {
+        println!(
+            "  {}  {:<8} {}",
+            format_timestamp(event.created_at, "%Y-%m-%d %H:%M:%S"),
+            event.kind.as_str(),
+            event.message
+        );
+    }
+}
+
+/// Display resource usage of a single task
+pub fn print_task_stats(task: &Task, stats: &TaskStats) {
+    let task_id = &task.id;
+    println!("Task: {task_id}");
+    println!("CPU: {}", format_cpu(stats.cpu_percent));
+    println!("Memory (RSS): {}", format_bytes(stats.rss_bytes));
+    println!("Threads: {}", format_optional(stats.threads));
+    let processes = stats.processes;
+    println!("Processes: {processes}");
+    println!(
+        "I/O read: {}",
+        format_optional(stats.read_bytes.map(format_bytes))
+    );
+    println!(
+        "I/O write: {}",
+        format_optional(stats.write_bytes.map(format_bytes))
+    );
+}
+
+/// Redraw the `ghost top` screen, busiest tasks first
+pub fn print_top(tasks: &[Task], sampler: &StatsSampler) {
+    let mut rows: Vec<(&Task, Option<&TaskStats>)> = tasks
+        .iter()
+        .map(|task| (task, sampler.get(&task.id)))
+        .collect();
+    rows.sort_by(|(_, a), (_, b)| {
+        let cpu = |stats: &Option<&TaskStats>| stats.and_then(|s| s.cpu_percent).unwrap_or(0.0);
+        cpu(b).total_cmp(&cpu(a))
+    });
+
+    // Clear the screen and move the cursor home
+    print!("\x1b[2J\x1b[H");
+    println!(
+        "{:<36} {:<8} {:>6} {:>8} {:>5} {:>8} {:>8}  Command",
+        "Task ID", "PID", "CPU%", "MEM", "THR", "READ", "WRITE"
+    );
+    println!("{}", "-".repeat(120));
+
+    if rows.is_empty() {
+        println!("No running tasks.");
+    }
+
+    for (task, stats) in rows {
+        let command = format_command_truncated(&task.command, 30);
+        match stats {
+            Some(stats) => println!(
+                "{:<36} {:<8} {:>6} {:>8} {:>5} {:>8} {:>8}  {}",
+                task.id,
+                task.pid,
+                stats
+                    .cpu_percent
+                    .map_or("-".to_string(), |cpu| format!("{cpu:.1}")),
+                format_bytes(stats.rss_bytes),
+                format_optional(stats.threads),
+                format_optional(stats.read_bytes.map(format_bytes)),
+                format_optional(stats.write_bytes.map(format_bytes)),
+                command
+            ),
+            None => println!(
+                "{:<36} {:<8} {:>6} {:>8} {:>5} {:>8} {:>8}  {}",
+                task.id, task.pid, "-", "-", "-", "-", "-", command
+            ),
+        }
+    }
+    println!();
+    println!("Press Ctrl+C to exit");
+}
+
+/// Display the process tree of a task
+pub fn print_process_tree(task: &Task, nodes: &[ProcessNode]) {
+    let task_id = &task.id;
+    println!("Task: {task_id}");
+    println!("{:<8} {:<5} Command", "PID", "State");
+
+    for node in nodes {
+        let mut line = format!(
+            "{:<8} {:<5} {}{}",
+            node.pid,
+            node.state,
+            tree_indent(node.depth),
+            node.command
+        );
+        if !node.ports.is_empty() {
+            let ports: Vec<String> = node
+                .ports
+                .iter()
+                .map(|port| format!("{} {}", port.protocol, port.local_addr))
+                .collect();
+            line.push_str(&format!("  [{}]", ports.join(", ")));
+        }
+        if node.escaped {
+            line.push_str(&format!("  [escaped: pgid {}]", node.pgid));
+        }
+        println!("{line}");
+    }
+
+    let escaped = nodes.iter().filter(|node| node.escaped).count();
+    if escaped > 0 {
+        println!();
+        println!(
+            "Note: {escaped} process(es) left the task's process group; `ghost stop` tracks and stops them separately"
+        );
+    }
+}
+
+/// Warn about processes of a stopped task that are still running
+pub fn print_stop_survivors(survivors: &[ProcessEntry]) {
+    if survivors.is_empty() {
+        return;
+    }
+
+    println!(
+        "Warning: {} process(es) are still running:",
+        survivors.len()
+    );
+    for process in survivors {
+        println!("  {:<8} {}", process.pid, process.command);
+    }
+    println!("Use `ghost doctor --orphans --kill` to kill them");
+}
+
+/// Display processes left behind by finished tasks
+pub fn print_orphans(orphans: &[Orphan], killed: bool) {
+    if orphans.is_empty() {
+        println!("No orphaned processes found.");
+        return;
+    }
+
+    println!("{:<8} {:<36} {:<40} Command", "PID", "Task ID", "Reason");
+    for orphan in orphans {
+        println!(
+            "{:<8} {:<36} {:<40} {}",
+            orphan.process.pid, orphan.task_id, orphan.reason, orphan.process.command
+        );
+    }
+
+    println!();
+    if killed {
+        println!("Killed {} orphaned process(es)", orphans.len());
+    } else {
+        println!(
+            "Found {} orphaned process(es); run `ghost doctor --orphans --kill` to kill them",
+            orphans.len()
+        );
+    }
+}
+
+/// Indentation for a process at the given depth of a tree
+pub fn tree_indent(depth: usize) -> String {
+    if depth == 0 {
+        String::new()
+    } else {
+        format!("{}└─ ", "   ".repeat(depth - 1))
+    }
+}
+
+/// Display where a task added to a queue stands
+pub fn print_task_queued(task: &Task) {
+    let queue = task.queue.as_deref().unwrap_or("-");
+    if task.status == TaskStatus::Queued {
+        println!("Queued task in '{queue}':");
+        println!("  Task ID: {}", task.id);
+        if let Some(position) = task.queue_position {
+            println!("  Position: {position}");
+        }
+    } else {
+        println!("Started queued task in '{queue}':");
+        println!("  Task ID: {}", task.id);
+    }
+    println!("  Log file: {}", task.log_path);
+}
+
+/// Display tasks whose files are being watched
+pub fn print_watchers(tasks: &[Task]) {
+    if tasks.is_empty() {
+        println!("No tasks are being watched.");
+        return;
+    }
+
+    println!(
+        "{:<36} {:<10} {:<8} {:<30} {:<30} Last trigger",
+        "Task ID", "Status", "Restarts", "Command", "Watching"
+    );
+    for task in tasks {
+        let watching = task
+            .run_options()
+            .watch
+            .map(|watch| watch.describe())
+            .unwrap_or_default();
+        println!(
+            "{:<36} {:<10} {:<8} {:<30} {:<30} {}",
+            task.id,
+            process_state::status_label(task),
+            task.restart_count,
+            format_command_truncated(&task.command, 30),
+            truncate_string(&watching, 30),
+            task.watch_trigger.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+/// Display a schedule that was just added
+pub fn print_schedule_added(schedule: &Schedule) {
+    match schedule.cron {
+        Some(ref cron) => println!("Scheduled '{cron}':"),
+        None => println!("Scheduled to run once:"),
+    }
+    println!("  Schedule ID: {}", schedule.id);
+    println!("  Next run: {}", format_local_timestamp(schedule.next_run));
+}
+
+/// Display schedules with their next run time
+pub fn print_schedules(schedules: &[Schedule]) {
+    if schedules.is_empty() {
+        println!("No schedules found.");
+        return;
+    }
+
+    println!(
+        "{:<36} {:<20} {:<16} Command",
+        "Schedule ID", "Next run", "Repeat"
+    );
+    for schedule in schedules {
+        println!(
+            "{:<36} {:<20} {:<16} {}",
+            schedule.id,
+            format_local_timestamp(schedule.next_run),
+            schedule.cron.as_deref().unwrap_or("once"),
+            format_command_truncated(&schedule.command, 50)
+        );
+    }
+}
+
+/// Display a template that was just saved
+pub fn print_template_saved(template: &Template, replaced: bool) {
+    let action = if replaced { "Updated" } else { "Saved" };
+    println!("{action} template '{}':", template.name);
+    println!("  Command: {}", format_command_full(&template.command));
+    if let Some(ref cwd) = template.cwd {
+        println!("  Working directory: {cwd}");
+    }
+    let parameters = template::parameters(template);
+    if !parameters.is_empty() {
+        println!("  Parameters: {}", format_parameters(&parameters));
+    }
+    println!("Start it with: ghost start {}", template.name);
+}
+
+/// Display saved templates
+pub fn print_templates(templates: &[Template]) {
+    if templates.is_empty() {
+        println!("No templates found.");
+        return;
+    }
+
+    println!(
+        "{:<16} {:<24} {:<30} Directory",
+        "Name", "Parameters", "Command"
+    );
+    for saved in templates {
+        println!(
+            "{:<16} {:<24} {:<30} {}",
+            saved.name,
+            format_parameters(&template::parameters(saved)),
+            format_command_truncated(&saved.command, 30),
+            saved.cwd.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+/// Format parameters as "bin, port=8080"
+fn format_parameters(parameters: &[template::Parameter]) -> String {
+    if parameters.is_empty() {
+        return "-".to_string();
+    }
+    parameters
+        .iter()
+        .map(|parameter| match parameter.default {
+            Some(ref default) => format!("{}={default}", parameter.name),
+            None => parameter.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Display saved search presets
+pub fn print_filter_presets(presets: &[FilterPreset]) {
+    if presets.is_empty() {
+        println!("No filters found.");
+        return;
+    }
+
+    println!("{:<16} Search", "Name");
+    for preset in presets {
+        println!("{:<16} {}", preset.name, preset.query);
+    }
+}
+
+/// Display every config key with its value and the file it comes from
+pub fn print_config_values(values: &[ConfigValue]) {
+    println!("{:<24} {:<8} Value", "Key", "Source");
+    for setting in values {
+        let value = setting
+            .value
+            .as_ref()
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<24} {:<8} {value}",
+            setting.key.name,
+            setting.source.as_str()
+        );
+    }
+}
+
+/// Display a task that starts after another task
+pub fn print_task_waiting(task: &Task) {
+    match (&task.after_task, task.after_condition) {
+        (Some(after), Some(condition)) if task.status == TaskStatus::Waiting => {
+            let outcome = match condition {
+                AfterCondition::Success => "succeeds",
+                AfterCondition::Failure => "fails",
+            };
+            println!("Task will start once {after} {outcome}:");
+        }
+        _ => println!("Task is {}:", task.status),
+    }
+    println!("  Task ID: {}", task.id);
+    if let Some(ref exit_reason) = task.exit_reason {
+        println!("  Reason: {exit_reason}");
+    }
+    println!("  Log file: {}", task.log_path);
+}
+
+/// Display the steps of a chain, marking the given task
+pub fn print_chain(chain: &[(usize, Task)], current_task_id: &str) {
+    println!("{:<2}{:<36} {:<14} Command", "", "Task ID", "Status");
+    for (depth, step) in chain {
+        let marker = if step.id == current_task_id { "*" } else { "" };
+        let status = match step.exit_code {
+            Some(code) => format!("{} ({code})", step.status),
+            None => step.status.to_string(),
+        };
+        let condition = match step.after_condition {
+            Some(AfterCondition::Failure) => " (on failure)",
+            _ => "",
+        };
+        println!(
+            "{marker:<2}{:<36} {status:<14} {}{}{condition}",
+            step.id,
+            tree_indent(*depth),
+            format_command_truncated(&step.command, 50)
+        );
+    }
+}
+
+/// Display queues and the tasks waiting in them
+pub fn print_queues(summaries: &[QueueSummary]) {
+    if summaries.is_empty() {
+        println!("No queues found.");
+        return;
+    }
+
+    println!(
+        "{:<20} {:<9} {:<8} {:<7} State",
+        "Queue", "Parallel", "Running", "Queued"
+    );
+    for summary in summaries {
+        let state = if summary.queue.paused {
+            "paused"
+        } else {
+            "active"
+        };
+        println!(
+            "{:<20} {:<9} {:<8} {:<7} {state}",
+            summary.queue.name,
+            summary.queue.parallel,
+            summary.running,
+            summary.waiting.len()
+        );
+    }
+
+    let waiting: Vec<&Task> = summaries
+        .iter()
+        .flat_map(|summary| &summary.waiting)
+        .collect();
+    if waiting.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("{:<20} {:<4} {:<36} Command", "Queue", "#", "Task ID");
+    for task in waiting {
+        println!(
+            "{:<20} {:<4} {:<36} {}",
+            task.queue.as_deref().unwrap_or("-"),
+            task.queue_position.unwrap_or_default(),
+            task.id,
+            format_command_truncated(&task.command, 50)
+        );
+    }
+}
+
 /// Display information about a started process
 pub fn print_process_started(task_id: &str, pid: u32, log_path: &std::path::Path) {
     println!("Started background process:");
@@ -85,8 +590,61 @@ pub fn print_log_follow_header(task_id: &str, log_path: &str) {
     println!("{}", "-".repeat(40));
 }
 
+/// Display the header for following several task logs at once
+pub fn print_logs_follow_header(count: usize) {
+    println!("Following logs for {count} tasks (Ctrl+C to stop):");
+    println!("{}", "-".repeat(40));
+}
+
+/// Tell that a followed log is read again from its start
+pub fn print_log_reopened(label: &str, reason: &str) {
+    eprintln!("ghost: log of {label} {reason}; following it from the start");
+}
+
+/// Tell that following ended because every followed task finished
+pub fn print_logs_follow_finished() {
+    println!("{}", "-".repeat(40));
+    println!("All followed tasks have finished.");
+}
+
+/// Display a matching log line found by `ghost grep`
+pub fn print_log_match(found: &LogMatch) {
+    let task = found.name.as_deref().unwrap_or(&found.task_id);
+    println!("{task}:{}: {}", found.line, found.text);
+}
+
+/// Display what `ghost grep --count` or `--files-with-matches` found in a task's log
+pub fn print_log_search_result(task: &Task, count: usize, mode: GrepMode) {
+    match mode {
+        GrepMode::Lines => {}
+        GrepMode::Count => {
+            let label = task.name.as_deref().unwrap_or(&task.id);
+            println!("{label}: {count}");
+        }
+        GrepMode::FilesWithMatches => println!("{}", task.log_path),
+    }
+}
+
+/// Tell how to read the rest of a log that `ghost log --head` stopped in
+pub fn print_log_continuation(summary: &ReadSummary) {
+    eprintln!(
+        "(showing up to byte {} of {}; continue with --offset {})",
+        summary.next_offset, summary.total_size, summary.next_offset
+    );
+}
+
 // Helper functions for formatting
 
+/// Describe a task's resource limits, noting when its cgroup could not be created
+pub fn limits_description(task: &Task) -> String {
+    let limits = task.run_options().limits;
+    let mut description = limits.summary();
+    if limits.needs_cgroup() && task.cgroup.is_none() {
+        description.push_str(" (no cgroup: rlimits only)");
+    }
+    description
+}
+
 /// Format a command JSON string for display with truncation
 fn format_command_truncated(command_json: &str, max_length: usize) -> String {
     let command_str = format_command_full(command_json);
@@ -99,6 +657,16 @@ fn format_command_full(command_json: &str) -> String {
     command.join(" ")
 }
 
+/// Format a CPU percentage, or "-" before the second sample
+fn format_cpu(cpu_percent: Option<f64>) -> String {
+    cpu_percent.map_or("-".to_string(), |cpu| format!("{cpu:.1}%"))
+}
+
+/// Format an optional value, or "N/A" when the platform does not provide it
+fn format_optional(value: Option<impl std::fmt::Display>) -> String {
+    value.map_or("N/A".to_string(), |value| value.to_string())
+}
+
 /// Format a timestamp to a human-readable string
 fn format_timestamp(timestamp: i64, format_str: &str) -> String {
     chrono::DateTime::from_timestamp(timestamp, 0)
@@ -106,6 +674,17 @@ fn format_timestamp(timestamp: i64, format_str: &str) -> String {
         .unwrap_or_else(|| "Unknown".to_string())
 }
 
+/// Format a timestamp in local time, the time zone schedules are given in
+fn format_local_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
 /// Truncate a string to the specified length with ellipsis
 fn truncate_string(s: &str, max_length: usize) -> String {
     if s.len() > max_length {
@@ -1,10 +1,10 @@
 pub mod command_parser;
-pub mod file_watcher;
+pub mod size;
 pub mod task_validation;
 pub mod time;
 
 // Re-export for backward compatibility
 pub use command_parser::parse_command;
-pub use file_watcher::follow_log_file;
+pub use size::{format_bytes, parse_size};
 pub use task_validation::validate_task_running;
-pub use time::now_timestamp;
+pub use time::{format_duration_short, now_timestamp, parse_duration, parse_since};
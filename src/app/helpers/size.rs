@@ -0,0 +1,90 @@
+use crate::app::error::{GhostError, Result};
+
+const KIB: u64 = 1024;
+const MIB: u64 = KIB * 1024;
+const GIB: u64 = MIB * 1024;
+const TIB: u64 = GIB * 1024;
+
+/// Parse a human-friendly byte size such as "512M", "2G" or "1048576"
+///
+/// Units are binary (1K = 1024 bytes) and a trailing "B" or "iB" is accepted.
+/// A bare number is interpreted as bytes.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let trimmed = input.trim();
+    let split_at = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split_at);
+
+    let value: u64 = number.parse().map_err(|_| GhostError::InvalidArgument {
+        message: format!("Invalid size: {input}. Use e.g. 512M, 2G or a number of bytes"),
+    })?;
+
+    let unit = unit.to_ascii_uppercase();
+    let multiplier = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => KIB,
+        "M" => MIB,
+        "G" => GIB,
+        "T" => TIB,
+        _ => {
+            return Err(GhostError::InvalidArgument {
+                message: format!("Invalid size unit in {input}. Valid units: K, M, G, T"),
+            });
+        }
+    };
+
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| GhostError::InvalidArgument {
+            message: format!("Size is too large: {input}"),
+        })
+}
+
+/// Format a byte count compactly, e.g. "512B", "64K", "1.5G"
+pub fn format_bytes(bytes: u64) -> String {
+    let (value, unit) = match bytes {
+        b if b >= TIB => (b as f64 / TIB as f64, "T"),
+        b if b >= GIB => (b as f64 / GIB as f64, "G"),
+        b if b >= MIB => (b as f64 / MIB as f64, "M"),
+        b if b >= KIB => (b as f64 / KIB as f64, "K"),
+        b => return format!("{b}B"),
+    };
+
+    if value.fract() == 0.0 || value >= 100.0 {
+        format!("{value:.0}{unit}")
+    } else {
+        format!("{value:.1}{unit}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size_units() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("4K").unwrap(), 4 * KIB);
+        assert_eq!(parse_size("512M").unwrap(), 512 * MIB);
+        assert_eq!(parse_size("512MB").unwrap(), 512 * MIB);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 * GIB);
+        assert_eq!(parse_size("1g").unwrap(), GIB);
+    }
+
+    #[test]
+    fn test_parse_size_invalid() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(64 * KIB), "64K");
+        assert_eq!(format_bytes(3 * GIB / 2), "1.5G");
+        assert_eq!(format_bytes(512 * MIB), "512M");
+    }
+}
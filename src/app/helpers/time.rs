@@ -1,4 +1,8 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+use crate::app::error::{GhostError, Result};
 
 /// Get current Unix timestamp in seconds
 pub fn now_timestamp() -> i64 {
@@ -7,3 +11,160 @@ pub fn now_timestamp() -> i64 {
         .unwrap()
         .as_secs() as i64
 }
+
+/// Parse a human-friendly duration such as "300ms", "30s", "10m", "2h" or "1d"
+///
+/// A bare number is interpreted as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let trimmed = input.trim();
+    let split_at = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split_at);
+
+    let value: u64 = number.parse().map_err(|_| GhostError::InvalidArgument {
+        message: format!("Invalid duration: {input}. Use e.g. 300ms, 30s, 10m, 2h or 1d"),
+    })?;
+
+    let duration = match unit {
+        "ms" => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m" => Duration::from_secs(value * 60),
+        "h" => Duration::from_secs(value * 60 * 60),
+        "d" => Duration::from_secs(value * 24 * 60 * 60),
+        _ => {
+            return Err(GhostError::InvalidArgument {
+                message: format!("Invalid duration unit in {input}. Valid units: ms, s, m, h, d"),
+            });
+        }
+    };
+
+    Ok(duration)
+}
+
+/// Parse a point in the past as a Unix timestamp
+///
+/// Accepts a duration ago such as "10m" or "2d", "HH:MM[:SS]" meaning the
+/// last time the clock showed it, a local date and time such as
+/// "2026-05-01 14:30", or an RFC 3339 timestamp.
+pub fn parse_since(input: &str, now: DateTime<Local>) -> Result<i64> {
+    let input = input.trim();
+    let invalid = || GhostError::InvalidArgument {
+        message: format!(
+            "Invalid time: {input}. Use a duration such as 10m, HH:MM, or YYYY-MM-DD HH:MM"
+        ),
+    };
+
+    if let Ok(ago) = parse_duration(input) {
+        return Ok(now.timestamp() - ago.as_secs() as i64);
+    }
+    if let Ok(at) = DateTime::parse_from_rfc3339(input) {
+        return Ok(at.timestamp());
+    }
+
+    let naive = if let Some(time) = ["%H:%M", "%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(input, format).ok())
+    {
+        let today = now.date_naive().and_time(time);
+        if today <= now.naive_local() {
+            today
+        } else {
+            today.date().pred_opt().ok_or_else(invalid)?.and_time(time)
+        }
+    } else {
+        [
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%dT%H:%M:%S",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(invalid)?
+    };
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|at| at.timestamp())
+        .ok_or_else(invalid)
+}
+
+/// Format a number of seconds using its largest whole unit (e.g. "45s", "12m", "3h")
+pub fn format_duration_short(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{seconds}s")
+    } else if seconds < 60 * 60 {
+        format!("{}m", seconds / 60)
+    } else if seconds < 24 * 60 * 60 {
+        format!("{}h", seconds / (60 * 60))
+    } else {
+        format!("{}d", seconds / (24 * 60 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("300ms").unwrap(), Duration::from_millis(300));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+    }
+
+    #[test]
+    fn test_parse_duration_bare_number_is_seconds() {
+        assert_eq!(parse_duration("42").unwrap(), Duration::from_secs(42));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("-5s").is_err());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Local.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        let at = |y, mo, d, h, mi| {
+            Local
+                .with_ymd_and_hms(y, mo, d, h, mi, 0)
+                .unwrap()
+                .timestamp()
+        };
+
+        assert_eq!(parse_since("10m", now).unwrap(), now.timestamp() - 600);
+        assert_eq!(parse_since("11:30", now).unwrap(), at(2026, 5, 1, 11, 30));
+        // A time later than now means yesterday
+        assert_eq!(parse_since("13:00", now).unwrap(), at(2026, 4, 30, 13, 0));
+        assert_eq!(
+            parse_since("2026-04-01 08:15", now).unwrap(),
+            at(2026, 4, 1, 8, 15)
+        );
+        assert_eq!(
+            parse_since("2026-04-01T08:15:00Z", now).unwrap(),
+            1775031300
+        );
+        assert!(parse_since("yesterday", now).is_err());
+    }
+
+    #[test]
+    fn test_format_duration_short() {
+        assert_eq!(format_duration_short(45), "45s");
+        assert_eq!(format_duration_short(12 * 60 + 30), "12m");
+        assert_eq!(format_duration_short(3 * 60 * 60), "3h");
+        assert_eq!(format_duration_short(2 * 24 * 60 * 60), "2d");
+    }
+}
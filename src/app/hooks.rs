@@ -0,0 +1,388 @@
+//! Commands run around a task's lifecycle
+//!
+//! Hooks are shell commands run before a task starts (`pre-start`), after it
+//! has started (`post-start`), when it has exited (`on-exit`) and when it has
+//! failed (`on-failure`). They are given with `ghost run` or in the `[hooks]`
+//! table of the config file, which applies to every task; both run, global
+//! hooks first. Hooks run in the task's supervisor with their output appended
+//! to a log of their own, and are killed after their timeout. Whatever a hook
+//! does, the task itself is not affected.
+
+use std::fs::OpenOptions;
+use std::os::unix::process::{CommandExt as _, ExitStatusExt as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use nix::unistd::setsid;
+use serde::{Deserialize, Serialize};
+
+use crate::app::helpers::format_duration_short;
+use crate::app::process::{self, TASK_ID_ENV};
+use crate::app::storage::TaskStatus;
+
+/// How long a hook may run when no timeout is configured
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// How often a running hook is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// When a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// Before the task's command is started, including restarts
+    PreStart,
+    /// Once the task's command has started, including restarts
+    PostStart,
+    /// When the task has exited, however it ended
+    Exit,
+    /// When the task has failed: a non-zero exit code, or ended by a limit or trigger
+    Failure,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreStart => "pre-start",
+            HookEvent::PostStart => "post-start",
+            HookEvent::Exit => "on-exit",
+            HookEvent::Failure => "on-failure",
+        }
+    }
+}
+
+impl std::fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Hook commands of a task, or of every task in the config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_exit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<String>,
+    /// Seconds a hook may run before it is killed (defaults to 60)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl Hooks {
+    /// Whether no hook command is set
+    pub fn is_empty(&self) -> bool {
+        self.pre_start.is_none()
+            && self.post_start.is_none()
+            && self.on_exit.is_none()
+            && self.on_failure.is_none()
+    }
+
+    /// The command run on `event`, if any
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::PreStart => self.pre_start.as_deref(),
+            HookEvent::PostStart => self.post_start.as_deref(),
+            HookEvent::Exit => self.on_exit.as_deref(),
+            HookEvent::Failure => self.on_failure.as_deref(),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    /// Short description for `ghost status` and the TUI, e.g. "on-exit: make clean (timeout 30s)"
+    pub fn describe(&self) -> String {
+        let events = [
+            HookEvent::PreStart,
+            HookEvent::PostStart,
+            HookEvent::Exit,
+            HookEvent::Failure,
+        ];
+        let commands: Vec<String> = events
+            .iter()
+            .filter_map(|&event| Some(format!("{event}: {}", self.command(event)?)))
+            .collect();
+        let mut description = commands.join(", ");
+        if let Some(timeout) = self.timeout {
+            description.push_str(&format!(" (timeout {})", format_duration_short(timeout)));
+        }
+        description
+    }
+}
+
+/// The task a hook runs for, passed to it as `GHOST_*` environment variables
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub task_id: String,
+    pub command: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub log_path: PathBuf,
+    /// The task's own environment variables
+    pub env: Vec<(String, String)>,
+    pub status: Option<TaskStatus>,
+    pub exit_code: Option<i32>,
+    pub exit_reason: Option<String>,
+    /// Seconds the task ran for
+    pub duration: Option<u64>,
+}
+
+impl HookContext {
+    fn variables(&self, event: HookEvent) -> Vec<(&'static str, String)> {
+        let mut variables = vec![
+            ("GHOST_HOOK", event.to_string()),
+            (TASK_ID_ENV, self.task_id.clone()),
+            ("GHOST_COMMAND", self.command.join(" ")),
+            ("GHOST_LOG_PATH", self.log_path.display().to_string()),
+        ];
+        if let Some(cwd) = &self.cwd {
+            variables.push(("GHOST_CWD", cwd.display().to_string()));
+        }
+        if let Some(status) = self.status {
+            variables.push(("GHOST_STATUS", status.to_string()));
+        }
+        if let Some(code) = self.exit_code {
+            variables.push(("GHOST_EXIT_CODE", code.to_string()));
+        }
+        if let Some(reason) = &self.exit_reason {
+            variables.push(("GHOST_EXIT_REASON", reason.clone()));
+        }
+        if let Some(duration) = self.duration {
+            variables.push(("GHOST_DURATION", duration.to_string()));
+        }
+        variables
+    }
+}
+
+/// How a hook ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookResult {
+    Exited(i32),
+    Signaled(i32),
+    TimedOut,
+    /// The hook could not be started
+    Failed(String),
+}
+
+/// A hook that has finished, to be recorded in the task's event history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutcome {
+    pub event: HookEvent,
+    pub command: String,
+    pub result: HookResult,
+    pub elapsed: Duration,
+}
+
+impl HookOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.result == HookResult::Exited(0)
+    }
+
+    /// Message for the event history, e.g. "on-exit `make clean` exited with 0 after 1s"
+    pub fn describe(&self) -> String {
+        let elapsed = format_duration_short(self.elapsed.as_secs());
+        let result = match &self.result {
+            HookResult::Exited(code) => format!("exited with {code} after {elapsed}"),
+            HookResult::Signaled(signal) => format!("killed by signal {signal} after {elapsed}"),
+            HookResult::TimedOut => format!("timed out after {elapsed} and was killed"),
+            HookResult::Failed(message) => format!("failed to start: {message}"),
+        };
+        format!("{} `{}` {result}", self.event, self.command)
+    }
+}
+
+/// Log of the hooks of the task logging to `log_path`, e.g. `<id>.hooks.log`
+pub fn hook_log_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("hooks.log")
+}
+
+/// Run a hook and wait for it, killing it after `timeout`
+///
+/// The hook runs with `sh -c` in the task's directory and environment, in a
+/// session of its own so everything it started is killed on timeout.
+pub fn run(
+    event: HookEvent,
+    command: &str,
+    context: &HookContext,
+    timeout: Duration,
+) -> HookOutcome {
+    let started = Instant::now();
+    let result = run_command(event, command, context, timeout);
+    HookOutcome {
+        event,
+        command: command.to_string(),
+        result,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Run a hook on a thread of its own
+pub fn spawn(
+    event: HookEvent,
+    command: &str,
+    context: &HookContext,
+    timeout: Duration,
+) -> JoinHandle<HookOutcome> {
+    let command = command.to_string();
+    let context = context.clone();
+    std::thread::spawn(move || run(event, &command, &context, timeout))
+}
+
+fn run_command(
+    event: HookEvent,
+    command: &str,
+    context: &HookContext,
+    timeout: Duration,
+) -> HookResult {
+    use std::io::Write as _;
+
+    let log_path = hook_log_path(&context.log_path);
+    let mut log = match OpenOptions::new().create(true).append(true).open(&log_path) {
+        Ok(log) => log,
+        Err(e) => return HookResult::Failed(format!("{}: {e}", log_path.display())),
+    };
+    let _ = writeln!(
+        log,
+        "--- ghost: {event} hook at {}: {command} ---",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    let (Ok(stdout), Ok(stderr)) = (log.try_clone(), log.try_clone()) else {
+        return HookResult::Failed(format!("{}: cannot be shared", log_path.display()));
+    };
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .envs(context.env.iter().cloned())
+        .envs(context.variables(event))
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(stdout))
+        .stderr(Stdio::from(stderr));
+    if let Some(cwd) = context.cwd.as_ref().filter(|cwd| cwd.is_dir()) {
+        cmd.current_dir(cwd);
+    }
+    unsafe {
+        cmd.pre_exec(|| {
+            setsid()?;
+            Ok(())
+        });
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return HookResult::Failed(e.to_string()),
+    };
+
+    let deadline = Instant::now() + timeout;
+    let result = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                break match status.code() {
+                    Some(code) => HookResult::Exited(code),
+                    None => HookResult::Signaled(status.signal().unwrap_or(0)),
+                };
+            }
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(POLL_INTERVAL),
+            Ok(None) => {
+                let _ = process::kill_group(child.id() as i32, true);
+                let _ = child.wait();
+                break HookResult::TimedOut;
+            }
+            Err(e) => break HookResult::Failed(e.to_string()),
+        }
+    };
+
+    let _ = writeln!(
+        log,
+        "--- ghost: {event} hook {} ---",
+        match &result {
+            HookResult::Exited(code) => format!("exited with {code}"),
+            HookResult::Signaled(signal) => format!("killed by signal {signal}"),
+            HookResult::TimedOut => "timed out".to_string(),
+            HookResult::Failed(message) => format!("failed: {message}"),
+        }
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(dir: &Path) -> HookContext {
+        HookContext {
+            task_id: "task-1".to_string(),
+            command: vec!["make".to_string(), "test".to_string()],
+            cwd: Some(dir.to_path_buf()),
+            log_path: dir.join("task-1.log"),
+            env: vec![("FROM_TASK".to_string(), "yes".to_string())],
+            status: Some(TaskStatus::Exited),
+            exit_code: Some(2),
+            exit_reason: None,
+            duration: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_hook_gets_task_metadata_and_logs_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = context(dir.path());
+        let command = "echo $GHOST_HOOK $GHOST_TASK_ID $GHOST_EXIT_CODE $GHOST_DURATION $GHOST_STATUS $FROM_TASK; pwd; exit 3";
+
+        let outcome = run(
+            HookEvent::Failure,
+            command,
+            &context,
+            Duration::from_secs(10),
+        );
+
+        assert_eq!(outcome.result, HookResult::Exited(3));
+        assert!(!outcome.succeeded());
+        assert!(outcome.describe().starts_with("on-failure `echo"));
+        let log = std::fs::read_to_string(dir.path().join("task-1.hooks.log")).unwrap();
+        assert!(log.contains("on-failure task-1 2 42 exited yes\n"), "{log}");
+        assert!(log.contains(&dir.path().display().to_string()), "{log}");
+        assert!(
+            log.ends_with("--- ghost: on-failure hook exited with 3 ---\n"),
+            "{log}"
+        );
+    }
+
+    #[test]
+    fn test_hook_is_killed_after_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let started = Instant::now();
+
+        let outcome = run(
+            HookEvent::Exit,
+            "sleep 30",
+            &context(dir.path()),
+            Duration::from_millis(200),
+        );
+
+        assert_eq!(outcome.result, HookResult::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_describe_hooks() {
+        let hooks = Hooks {
+            on_exit: Some("make clean".to_string()),
+            on_failure: Some("notify-send failed".to_string()),
+            timeout: Some(30),
+            ..Default::default()
+        };
+        assert!(!hooks.is_empty());
+        assert_eq!(
+            hooks.describe(),
+            "on-exit: make clean, on-failure: notify-send failed (timeout 30s)"
+        );
+        assert!(Hooks::default().is_empty());
+    }
+}
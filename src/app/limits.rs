@@ -0,0 +1,513 @@
+//! Resource limits and scheduling priorities for spawned tasks
+//!
+//! rlimits, nice and ionice are applied in the child between fork and exec.
+//! Memory and CPU quota are additionally enforced through a per-task cgroup
+//! when a cgroup v2 hierarchy is delegated to the user running ghost.
+
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+
+use nix::sys::resource::{Resource, setrlimit};
+use serde::{Deserialize, Serialize};
+
+use crate::app::helpers::{format_bytes, format_duration_short};
+
+/// Scheduling class for block I/O (see ionice(1))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    Realtime,
+    BestEffort,
+    Idle,
+}
+
+/// I/O priority given to `--ionice`, e.g. "idle" or "best-effort:7"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IoPriority {
+    pub class: IoClass,
+    /// Priority within the class, 0 (highest) to 7 (lowest); unused for idle
+    pub level: u8,
+}
+
+impl IoPriority {
+    /// Value for the ioprio_set(2) system call
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn ioprio(&self) -> i32 {
+        const IOPRIO_CLASS_SHIFT: i32 = 13;
+        let class = match self.class {
+            IoClass::Realtime => 1,
+            IoClass::BestEffort => 2,
+            IoClass::Idle => 3,
+        };
+        (class << IOPRIO_CLASS_SHIFT) | i32::from(self.level)
+    }
+}
+
+impl std::fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.class {
+            IoClass::Idle => write!(f, "idle"),
+            IoClass::BestEffort => write!(f, "best-effort:{}", self.level),
+            IoClass::Realtime => write!(f, "realtime:{}", self.level),
+        }
+    }
+}
+
+impl std::str::FromStr for IoPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (class, level) = match s.split_once(':') {
+            Some((class, level)) => (class, Some(level)),
+            None => (s, None),
+        };
+
+        let class = match class {
+            "idle" => IoClass::Idle,
+            "best-effort" | "be" => IoClass::BestEffort,
+            "realtime" | "rt" => IoClass::Realtime,
+            _ => {
+                return Err(format!(
+                    "Invalid I/O class: {class}. Valid options: idle, best-effort[:0-7], realtime[:0-7]"
+                ));
+            }
+        };
+
+        let level = match (class, level) {
+            (IoClass::Idle, None) => 0,
+            (IoClass::Idle, Some(_)) => {
+                return Err("The idle I/O class does not take a priority level".to_string());
+            }
+            (_, None) => 4,
+            (_, Some(level)) => match level.parse::<u8>() {
+                Ok(level) if level <= 7 => level,
+                _ => return Err(format!("Invalid I/O priority level: {level}. Use 0-7")),
+            },
+        };
+
+        Ok(IoPriority { class, level })
+    }
+}
+
+impl TryFrom<String> for IoPriority {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<IoPriority> for String {
+    fn from(value: IoPriority) -> Self {
+        value.to_string()
+    }
+}
+
+/// Limits and priorities applied to a task's process
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Memory in bytes (cgroup memory.max, or RLIMIT_AS without a cgroup)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<u64>,
+    /// CPU time in seconds (RLIMIT_CPU)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time: Option<u64>,
+    /// Maximum number of open file descriptors (RLIMIT_NOFILE)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nofile: Option<u64>,
+    /// CPU quota in percent of one CPU (cgroup cpu.max)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_quota: Option<u32>,
+    /// Scheduling niceness (-20 to 19)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+    /// Block I/O priority (Linux only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ionice: Option<IoPriority>,
+}
+
+impl ResourceLimits {
+    /// Whether no limit or priority is set
+    pub fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+
+    /// Whether any limit can end the task, so its exit reason is worth reporting
+    pub fn can_end_task(&self) -> bool {
+        self.memory.is_some() || self.cpu_time.is_some() || self.nofile.is_some()
+    }
+
+    /// Whether enforcing these limits needs a cgroup
+    pub fn needs_cgroup(&self) -> bool {
+        self.memory.is_some() || self.cpu_quota.is_some()
+    }
+
+    /// Human-readable summary, e.g. "memory 512M, cpu time 1m, nice 10"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(memory) = self.memory {
+            parts.push(format!("memory {}", format_bytes(memory)));
+        }
+        if let Some(cpu_quota) = self.cpu_quota {
+            parts.push(format!("cpu quota {cpu_quota}%"));
+        }
+        if let Some(cpu_time) = self.cpu_time {
+            parts.push(format!("cpu time {}", format_duration_short(cpu_time)));
+        }
+        if let Some(nofile) = self.nofile {
+            parts.push(format!("open files {nofile}"));
+        }
+        if let Some(nice) = self.nice {
+            parts.push(format!("nice {nice}"));
+        }
+        if let Some(ionice) = self.ionice {
+            parts.push(format!("ionice {ionice}"));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Apply limits to the current process and move it into the task's cgroup
+///
+/// Runs in the forked child right before exec, so it must stay
+/// async-signal-safe: no allocation, no locks.
+pub(crate) fn apply_in_child(
+    limits: &ResourceLimits,
+    cgroup_procs: Option<&CStr>,
+) -> std::io::Result<()> {
+    if let Some(procs) = cgroup_procs {
+        join_cgroup(procs)?;
+    }
+
+    // With a cgroup, memory.max enforces the memory limit on resident memory;
+    // RLIMIT_AS is only a fallback because it also counts reserved address space.
+    if let (Some(memory), None) = (limits.memory, cgroup_procs) {
+        setrlimit(Resource::RLIMIT_AS, memory, memory)?;
+    }
+
+    if let Some(cpu_time) = limits.cpu_time {
+        // SIGXCPU at the soft limit, SIGKILL one second later
+        setrlimit(Resource::RLIMIT_CPU, cpu_time, cpu_time.saturating_add(1))?;
+    }
+
+    if let Some(nofile) = limits.nofile {
+        setrlimit(Resource::RLIMIT_NOFILE, nofile, nofile)?;
+    }
+
+    if let Some(nice) = limits.nice {
+        // SAFETY: setpriority only changes the scheduling priority of this process
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(ionice) = limits.ionice {
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        // SAFETY: ioprio_set only changes the I/O priority of this process
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ionice.ioprio()) }
+            == -1
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the current process into a cgroup by writing "0" to its cgroup.procs
+fn join_cgroup(procs: &CStr) -> std::io::Result<()> {
+    // SAFETY: open/write/close on a NUL-terminated path are async-signal-safe
+    unsafe {
+        let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = libc::write(fd, b"0".as_ptr().cast(), 1);
+        let error = std::io::Error::last_os_error();
+        libc::close(fd);
+        if written != 1 {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Per-task cgroup (cgroup v2) tracking the task's processes and enforcing
+/// memory and CPU quota limits
+#[derive(Debug, Clone)]
+pub struct TaskCgroup {
+    path: PathBuf,
+}
+
+impl TaskCgroup {
+    /// Refer to an existing task cgroup, e.g. one recorded in the database
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Create a cgroup for the task with the given limits
+    ///
+    /// Only limits that need one get a cgroup; other tasks are tracked through
+    /// their process group and the GHOST_TASK_ID marker. Returns None as well
+    /// when no delegated cgroup v2 hierarchy is available; the caller then
+    /// falls back to rlimits and process tree walks.
+    pub fn create(task_id: &str, limits: &ResourceLimits) -> Option<Self> {
+        if !limits.needs_cgroup() {
+            return None;
+        }
+        let root = cgroup_root()?;
+        enable_controllers(&root, limits);
+
+        let path = root.join(format!("ghost-{task_id}"));
+        // A cgroup left behind by a previous run of the same task is reused
+        if let Err(e) = std::fs::create_dir(&path)
+            && e.kind() != std::io::ErrorKind::AlreadyExists
+        {
+            tracing::debug!("Cannot create cgroup {}: {e}", path.display());
+            return None;
+        }
+
+        let cgroup = Self { path };
+        if let Err(e) = cgroup.write_limits(limits) {
+            tracing::debug!("Cannot set limits on cgroup {}: {e}", cgroup.path.display());
+            cgroup.remove();
+            return None;
+        }
+        Some(cgroup)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of cgroup.procs, prepared for use after fork
+    pub(crate) fn procs_path(&self) -> Option<CString> {
+        use std::os::unix::ffi::OsStrExt as _;
+        CString::new(self.path.join("cgroup.procs").as_os_str().as_bytes()).ok()
+    }
+
+    /// Whether the task ran out of memory under memory.max, going by the
+    /// `oom` and `oom_kill` counters of memory.events
+    pub fn oom_killed(&self) -> bool {
+        std::fs::read_to_string(self.path.join("memory.events"))
+            .map(|events| {
+                memory_events_count(&events, "oom") > 0
+                    || memory_events_count(&events, "oom_kill") > 0
+            })
+            .unwrap_or(false)
+    }
+
+    /// How long ago the cgroup was created
+    pub fn age(&self) -> Option<std::time::Duration> {
+        let created = std::fs::metadata(&self.path).ok()?.modified().ok()?;
+        created.elapsed().ok()
+    }
+
+    /// PIDs of the processes currently in the cgroup
+    pub fn pids(&self) -> Vec<u32> {
+        std::fs::read_to_string(self.path.join("cgroup.procs"))
+            .map(|procs| procs.lines().filter_map(|pid| pid.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// SIGKILL every process in the cgroup, best effort (needs Linux 5.14+)
+    pub fn kill(&self) {
+        let _ = std::fs::write(self.path.join("cgroup.kill"), "1");
+    }
+
+    /// Remove the cgroup; fails silently while processes are still inside
+    pub fn remove(&self) {
+        let _ = std::fs::remove_dir(&self.path);
+    }
+
+    fn write_limits(&self, limits: &ResourceLimits) -> std::io::Result<()> {
+        if let Some(memory) = limits.memory {
+            std::fs::write(self.path.join("memory.max"), memory.to_string())?;
+            // Keep the task from dodging the limit by swapping; not every kernel has this file
+            let _ = std::fs::write(self.path.join("memory.swap.max"), "0");
+        }
+        if let Some(cpu_quota) = limits.cpu_quota {
+            std::fs::write(self.path.join("cpu.max"), cpu_max(cpu_quota))?;
+        }
+        Ok(())
+    }
+}
+
+/// Task cgroups found under the cgroup root, with the IDs of their tasks
+///
+/// These include cgroups left behind by tasks that exited while nobody was
+/// supervising them.
+pub fn task_cgroups() -> Vec<(String, TaskCgroup)> {
+    let Some(entries) = cgroup_root().and_then(|root| std::fs::read_dir(root).ok()) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let task_id = name.to_str()?.strip_prefix("ghost-")?.to_string();
+            Some((task_id, TaskCgroup::open(entry.path())))
+        })
+        .collect()
+}
+
+/// Directory under which task cgroups are created
+///
+/// GHOST_CGROUP_ROOT points at a delegated cgroup explicitly; otherwise the
+/// cgroup ghost itself runs in is used.
+fn cgroup_root() -> Option<PathBuf> {
+    let root = match crate::app::config::get_cgroup_root() {
+        Some(root) => root,
+        None => {
+            let contents = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+            let relative = parse_cgroup_v2_path(&contents)?;
+            Path::new("/sys/fs/cgroup").join(relative.trim_start_matches('/'))
+        }
+    };
+
+    // Only a cgroup v2 hierarchy has cgroup.controllers
+    root.join("cgroup.controllers").exists().then_some(root)
+}
+
+/// Enable the controllers the limits need for child cgroups, best effort
+fn enable_controllers(root: &Path, limits: &ResourceLimits) {
+    let enabled = std::fs::read_to_string(root.join("cgroup.subtree_control")).unwrap_or_default();
+    let wanted = [
+        ("memory", limits.memory.is_some()),
+        ("cpu", limits.cpu_quota.is_some()),
+    ];
+
+    for (controller, needed) in wanted {
+        if needed && !enabled.split_whitespace().any(|c| c == controller) {
+            let _ = std::fs::write(
+                root.join("cgroup.subtree_control"),
+                format!("+{controller}"),
+            );
+        }
+    }
+}
+
+/// Extract the cgroup v2 path from the contents of /proc/<pid>/cgroup
+fn parse_cgroup_v2_path(contents: &str) -> Option<&str> {
+    contents.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+/// A counter from the contents of a memory.events file, e.g. "oom_kill"
+fn memory_events_count(events: &str, counter: &str) -> u64 {
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix(counter)?.strip_prefix(' '))
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// cpu.max value for a quota in percent of one CPU over a 100ms period
+fn cpu_max(cpu_quota: u32) -> String {
+    const PERIOD_US: u64 = 100_000;
+    let quota_us = u64::from(cpu_quota) * PERIOD_US / 100;
+    format!("{quota_us} {PERIOD_US}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_priority_parse() {
+        assert_eq!(
+            "idle".parse::<IoPriority>().unwrap(),
+            IoPriority {
+                class: IoClass::Idle,
+                level: 0
+            }
+        );
+        assert_eq!(
+            "best-effort:7".parse::<IoPriority>().unwrap(),
+            IoPriority {
+                class: IoClass::BestEffort,
+                level: 7
+            }
+        );
+        assert_eq!("rt".parse::<IoPriority>().unwrap().level, 4);
+        assert!("idle:3".parse::<IoPriority>().is_err());
+        assert!("be:8".parse::<IoPriority>().is_err());
+        assert!("turbo".parse::<IoPriority>().is_err());
+    }
+
+    #[test]
+    fn test_io_priority_round_trip() {
+        for value in ["idle", "best-effort:2", "realtime:0"] {
+            let priority: IoPriority = value.parse().unwrap();
+            assert_eq!(priority.to_string(), value);
+        }
+        let best_effort: IoPriority = "be:3".parse().unwrap();
+        assert_eq!(best_effort.ioprio(), (2 << 13) | 3);
+    }
+
+    #[test]
+    fn test_limits_summary() {
+        let limits = ResourceLimits {
+            memory: Some(512 * 1024 * 1024),
+            cpu_time: Some(120),
+            nofile: Some(256),
+            cpu_quota: Some(50),
+            nice: Some(10),
+            ionice: Some("idle".parse().unwrap()),
+        };
+        assert_eq!(
+            limits.summary(),
+            "memory 512M, cpu quota 50%, cpu time 2m, open files 256, nice 10, ionice idle"
+        );
+        assert!(limits.can_end_task());
+        assert!(limits.needs_cgroup());
+    }
+
+    #[test]
+    fn test_priorities_alone_do_not_end_task() {
+        let limits = ResourceLimits {
+            nice: Some(5),
+            ..Default::default()
+        };
+        assert!(!limits.is_empty());
+        assert!(!limits.can_end_task());
+        assert!(!limits.needs_cgroup());
+    }
+
+    #[test]
+    fn test_parse_cgroup_v2_path() {
+        let hybrid = "4:memory:/user.slice\n1:cpu:/\n0::/user.slice/app.scope\n";
+        assert_eq!(parse_cgroup_v2_path(hybrid), Some("/user.slice/app.scope"));
+        assert_eq!(parse_cgroup_v2_path("4:memory:/user.slice\n"), None);
+    }
+
+    #[test]
+    fn test_memory_events_count() {
+        let events = "low 0\nhigh 0\nmax 12\noom 2\noom_kill 1\n";
+        assert_eq!(memory_events_count(events, "oom"), 2);
+        assert_eq!(memory_events_count(events, "oom_kill"), 1);
+        assert_eq!(memory_events_count("oom 0\n", "oom_kill"), 0);
+    }
+
+    #[test]
+    fn test_cpu_max() {
+        assert_eq!(cpu_max(50), "50000 100000");
+        assert_eq!(cpu_max(200), "200000 100000");
+    }
+
+    #[test]
+    fn test_apply_in_child_sets_rlimits() {
+        let limits = ResourceLimits {
+            nofile: Some(64),
+            ..Default::default()
+        };
+        let mut cmd = std::process::Command::new("sh");
+        cmd.args(["-c", "ulimit -n"]);
+        unsafe {
+            use std::os::unix::process::CommandExt as _;
+            cmd.pre_exec(move || apply_in_child(&limits, None));
+        }
+        let output = cmd.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "64");
+    }
+}
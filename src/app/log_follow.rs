@@ -0,0 +1,572 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use notify::{EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::Connection;
+use tokio::sync::mpsc;
+
+use crate::app::ansi;
+use crate::app::display;
+use crate::app::error::{GhostError, Result};
+use crate::app::log_level::LevelTracker;
+use crate::app::log_reader::{self, LogQuery};
+use crate::app::storage::{self, Task};
+
+/// How often logs are checked for changes where inotify is not available
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// How often tasks are checked for having finished or started
+pub const TASK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Colors cycled through for task prefixes: cyan, yellow, green, magenta, blue, bright red
+const PREFIX_COLORS: [&str; 6] = ["36", "33", "32", "35", "34", "91"];
+
+/// Which tasks `ghost log -f` follows
+#[derive(Debug, Clone)]
+pub enum FollowTargets {
+    /// These task IDs or names; tasks started later under one of the names join in
+    Tasks(Vec<String>),
+    /// Every running task, including ones that start while following
+    AllRunning,
+}
+
+impl FollowTargets {
+    /// The tasks to follow right away
+    pub fn resolve(&self, conn: &Connection) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = Vec::new();
+        match self {
+            FollowTargets::AllRunning => {
+                tasks = storage::get_tasks_with_process_check(conn, Some("running"))?;
+                tasks.reverse();
+            }
+            FollowTargets::Tasks(targets) => {
+                for target in targets {
+                    tasks.extend(find_tasks(conn, target)?);
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        tasks.retain(|task| seen.insert(task.id.clone()));
+        Ok(tasks)
+    }
+
+    /// Tasks that should join in, e.g. ones started since following began
+    ///
+    /// Includes tasks already followed; the caller skips those.
+    pub fn joining(&self, conn: &Connection, started_since: i64) -> Result<Vec<Task>> {
+        match self {
+            FollowTargets::AllRunning => {
+                let mut tasks = storage::get_tasks_with_process_check(conn, Some("running"))?;
+                tasks.reverse();
+                Ok(tasks)
+            }
+            FollowTargets::Tasks(targets) => {
+                let mut tasks = Vec::new();
+                for target in targets {
+                    let mut named = storage::get_tasks_by_name(conn, target)?;
+                    named.retain(|task| task.started_at >= started_since);
+                    tasks.extend(named.into_iter().rev());
+                }
+                Ok(tasks)
+            }
+        }
+    }
+}
+
+/// Find tasks by ID or by name, oldest first
+///
+/// A name stands for its tasks that have not finished yet, or for the newest
+/// task with that name if they all have.
+pub fn find_tasks(conn: &Connection, id_or_name: &str) -> Result<Vec<Task>> {
+    match storage::get_task(conn, id_or_name) {
+        Ok(task) => return Ok(vec![task]),
+        Err(GhostError::TaskNotFound { .. }) => {}
+        Err(e) => return Err(e),
+    }
+
+    let (active, finished): (Vec<Task>, Vec<Task>) = storage::get_tasks_by_name(conn, id_or_name)?
+        .into_iter()
+        .partition(|task| !task.status.is_finished());
+    let tasks: Vec<Task> = if active.is_empty() {
+        finished.into_iter().take(1).collect()
+    } else {
+        active.into_iter().rev().collect()
+    };
+    if tasks.is_empty() {
+        return Err(GhostError::TaskNotFound {
+            task_id: id_or_name.to_string(),
+        });
+    }
+    Ok(tasks)
+}
+
+/// Wakes up followers of log files when the files change
+///
+/// Uses inotify where available and falls back to polling. The directories
+/// holding the logs are watched rather than the files, so a log that is
+/// replaced, e.g. by rotation, is noticed as well as one that grows or shrinks.
+pub struct LogWatcher {
+    watcher: Box<dyn Watcher + Send>,
+    files: Arc<Mutex<HashSet<PathBuf>>>,
+    dirs: HashSet<PathBuf>,
+    changes: mpsc::Receiver<()>,
+}
+
+impl LogWatcher {
+    pub fn new() -> Result<Self> {
+        let files = Arc::new(Mutex::new(HashSet::new()));
+        // A single pending wake-up stands for any number of changes
+        let (tx, changes) = mpsc::channel(1);
+        let handler = {
+            let files = Arc::clone(&files);
+            move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                let files = files.lock().unwrap_or_else(|e| e.into_inner());
+                if event.paths.iter().any(|path| files.contains(path)) {
+                    let _ = tx.try_send(());
+                }
+            }
+        };
+
+        let watcher: Box<dyn Watcher + Send> =
+            match RecommendedWatcher::new(handler.clone(), notify::Config::default()) {
+                Ok(watcher) => Box::new(watcher),
+                Err(_) => Box::new(
+                    PollWatcher::new(
+                        handler,
+                        notify::Config::default().with_poll_interval(POLL_INTERVAL),
+                    )
+                    .map_err(|e| GhostError::FileWatch {
+                        message: format!("Failed to create file watcher: {e}"),
+                    })?,
+                ),
+            };
+
+        Ok(Self {
+            watcher,
+            files,
+            dirs: HashSet::new(),
+            changes,
+        })
+    }
+
+    /// Wake up when this file is written to, truncated, replaced or removed
+    pub fn watch(&mut self, path: &Path) -> Result<()> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        if !self.dirs.contains(&dir) {
+            self.watcher
+                .watch(&dir, RecursiveMode::NonRecursive)
+                .map_err(|e| GhostError::FileWatch {
+                    message: format!("Failed to watch {}: {e}", dir.display()),
+                })?;
+            self.dirs.insert(dir);
+        }
+        self.files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Stop watching all files
+    pub fn clear(&mut self) {
+        for dir in self.dirs.drain() {
+            let _ = self.watcher.unwatch(&dir);
+        }
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Wait until a watched file changes
+    pub async fn changed(&mut self) {
+        if self.changes.recv().await.is_none() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// A log being followed
+#[derive(Debug)]
+struct FollowedLog {
+    task_id: String,
+    label: String,
+    color: &'static str,
+    path: PathBuf,
+    /// The log as opened, kept open to notice when it is replaced
+    file: Option<File>,
+    inode: Option<u64>,
+    /// Offset up to which the log has been read
+    position: u64,
+    /// The start of a line still being written
+    partial: Vec<u8>,
+    levels: LevelTracker,
+    finished: bool,
+}
+
+/// Prints new lines of several task logs as they are written
+///
+/// Lines are printed whole, so lines of different tasks never run into each
+/// other. Once more than one log is followed, each line starts with the name
+/// of its task (or the start of its ID) in brackets, like `docker compose logs`.
+#[derive(Debug)]
+pub struct LogFollower {
+    logs: Vec<FollowedLog>,
+    /// How new lines are filtered; only `strip_ansi`, `grep` and `level` apply
+    filter: LogQuery,
+    always_prefix: bool,
+    colors: bool,
+}
+
+impl LogFollower {
+    pub fn new(query: &LogQuery, always_prefix: bool, colors: bool) -> Self {
+        Self {
+            logs: Vec::new(),
+            filter: LogQuery {
+                strip_ansi: query.strip_ansi,
+                grep: query.grep.clone(),
+                level: query.level,
+                whole_lines: true,
+                ..Default::default()
+            },
+            always_prefix,
+            colors,
+        }
+    }
+
+    /// Start following a task, first printing the part of its log `initial` selects
+    pub fn add(&mut self, task: &Task, initial: &LogQuery, out: &mut impl Write) -> Result<()> {
+        let label = task
+            .name
+            .clone()
+            .unwrap_or_else(|| task.id.chars().take(8).collect());
+        self.logs.push(FollowedLog {
+            task_id: task.id.clone(),
+            label,
+            color: PREFIX_COLORS[self.logs.len() % PREFIX_COLORS.len()],
+            path: PathBuf::from(&task.log_path),
+            file: None,
+            inode: None,
+            position: 0,
+            partial: Vec::new(),
+            levels: LevelTracker::default(),
+            finished: false,
+        });
+
+        let index = self.logs.len() - 1;
+        // A queued task has no log yet; it is read from the start once it does
+        if !self.logs[index].path.exists() {
+            return Ok(());
+        }
+        let prefix = self.prefix(index);
+        let query = LogQuery {
+            whole_lines: true,
+            ..initial.clone()
+        };
+        let mut written = Ok(());
+        let summary = log_reader::read_log(&self.logs[index].path, &query, |line| {
+            if written.is_ok() {
+                written = write!(out, "{prefix}{line}");
+            }
+        })?;
+        written?;
+        self.logs[index].position = summary.next_offset;
+        Ok(())
+    }
+
+    pub fn is_following(&self, task_id: &str) -> bool {
+        self.logs.iter().any(|log| log.task_id == task_id)
+    }
+
+    /// IDs of the followed tasks that have not finished
+    pub fn unfinished(&self) -> Vec<String> {
+        self.logs
+            .iter()
+            .filter(|log| !log.finished)
+            .map(|log| log.task_id.clone())
+            .collect()
+    }
+
+    pub fn all_finished(&self) -> bool {
+        self.logs.iter().all(|log| log.finished)
+    }
+
+    /// Print the lines written to the logs since the last call
+    pub fn poll(&mut self, out: &mut impl Write) -> Result<()> {
+        for index in 0..self.logs.len() {
+            if !self.logs[index].finished {
+                self.read_new(index, false, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Print what is left of a finished task's log and stop following it
+    pub fn finish(&mut self, task_id: &str, out: &mut impl Write) -> Result<()> {
+        if let Some(index) = self.logs.iter().position(|log| log.task_id == task_id) {
+            self.read_new(index, true, out)?;
+            self.logs[index].finished = true;
+        }
+        Ok(())
+    }
+
+    /// Print new lines of a log; a last line without a newline only with `flush`
+    ///
+    /// The log stays open between calls, so lines written before it was
+    /// replaced, e.g. by log rotation, are still printed before switching to
+    /// the new file. A truncated log is followed again from its start.
+    fn read_new(&mut self, index: usize, flush: bool, out: &mut impl Write) -> Result<()> {
+        let prefix = self.prefix(index);
+        let Self { logs, filter, .. } = self;
+        let log = &mut logs[index];
+        let mut buffer = vec![0; 64 * 1024];
+
+        loop {
+            if log.file.is_none() {
+                let mut file = match File::open(&log.path) {
+                    Ok(file) => file,
+                    // A queued task has no log yet, and a rotated one may not be back yet
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+                    Err(e) => return Err(e.into()),
+                };
+                log.inode = Some(file.metadata()?.ino());
+                file.seek(SeekFrom::Start(log.position))?;
+                log.file = Some(file);
+            }
+            let file = log.file.as_mut().expect("log file was just opened");
+
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                log.position += read as u64;
+                log.partial.extend_from_slice(&buffer[..read]);
+                if let Some(end) = log.partial.iter().rposition(|byte| *byte == b'\n') {
+                    let rest = log.partial.split_off(end + 1);
+                    let lines = std::mem::replace(&mut log.partial, rest);
+                    for line in lines.split_inclusive(|byte| *byte == b'\n') {
+                        write_line(out, &prefix, filter, &mut log.levels, line)?;
+                    }
+                }
+            }
+
+            let reason = match std::fs::metadata(&log.path) {
+                Ok(metadata) if Some(metadata.ino()) != log.inode => "was replaced",
+                Ok(metadata) if metadata.len() < log.position => {
+                    log.partial.clear();
+                    "was truncated"
+                }
+                _ => break,
+            };
+            if !log.partial.is_empty() {
+                let line = std::mem::take(&mut log.partial);
+                write_line(out, &prefix, filter, &mut log.levels, &line)?;
+            }
+            display::print_log_reopened(&log.label, reason);
+            log.file = None;
+            log.position = 0;
+        }
+
+        if flush && !log.partial.is_empty() {
+            let line = std::mem::take(&mut log.partial);
+            write_line(out, &prefix, filter, &mut log.levels, &line)?;
+        }
+        Ok(())
+    }
+
+    /// The `[name] ` put before lines of a log, padded to line up with the others
+    fn prefix(&self, index: usize) -> String {
+        if !self.always_prefix && self.logs.len() < 2 {
+            return String::new();
+        }
+        let width = self
+            .logs
+            .iter()
+            .map(|log| log.label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let log = &self.logs[index];
+        let padding = " ".repeat(width - log.label.chars().count());
+        if self.colors {
+            format!("\x1b[{}m[{}]\x1b[0m{padding} ", log.color, log.label)
+        } else {
+            format!("[{}]{padding} ", log.label)
+        }
+    }
+}
+
+/// Print a line of a followed log if it passes the filter
+fn write_line(
+    out: &mut impl Write,
+    prefix: &str,
+    filter: &LogQuery,
+    levels: &mut LevelTracker,
+    line: &[u8],
+) -> Result<()> {
+    let raw = String::from_utf8_lossy(line);
+    let line = if filter.strip_ansi {
+        ansi::strip(&raw)
+    } else {
+        raw.into_owned()
+    };
+    if let Some(minimum) = filter.level
+        && !levels.keep(&line, minimum)
+    {
+        return Ok(());
+    }
+    if let Some(grep) = &filter.grep
+        && !grep.is_match(&ansi::strip(line.trim_end_matches(['\n', '\r'])))
+    {
+        return Ok(());
+    }
+    let newline = if line.ends_with('\n') { "" } else { "\n" };
+    write!(out, "{prefix}{line}{newline}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::TaskStatus;
+    use tempfile::TempDir;
+
+    fn task(dir: &TempDir, id: &str, name: Option<&str>, log: &str) -> Task {
+        let log_path = dir.path().join(format!("{id}.log"));
+        std::fs::write(&log_path, log).unwrap();
+        Task {
+            id: id.to_string(),
+            pid: 1,
+            pgid: None,
+            command: "[]".to_string(),
+            env: None,
+            cwd: None,
+            status: TaskStatus::Running,
+            exit_code: None,
+            started_at: 0,
+            finished_at: None,
+            log_path: log_path.to_string_lossy().into_owned(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: name.map(str::to_string),
+        }
+    }
+
+    fn append(task: &Task, text: &str) {
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&task.log_path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_lines_of_several_logs_get_prefixes() {
+        let dir = TempDir::new().unwrap();
+        let api = task(&dir, "0123456789", Some("api"), "listening\n");
+        let web = task(&dir, "abcdefghij", None, "");
+        let mut follower = LogFollower::new(&LogQuery::default(), false, false);
+        let mut out = Vec::new();
+
+        follower.add(&api, &LogQuery::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "listening\n");
+        follower.add(&web, &LogQuery::default(), &mut out).unwrap();
+
+        out.clear();
+        append(&web, "compiled\nhalf a li");
+        append(&api, "GET /\n");
+        follower.poll(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "[api]      GET /\n[abcdefgh] compiled\n"
+        );
+
+        // A partial line waits until it is complete or the task finishes
+        out.clear();
+        append(&web, "ne");
+        follower.poll(&mut out).unwrap();
+        assert!(out.is_empty());
+        follower.finish("abcdefghij", &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "[abcdefgh] half a line\n");
+        assert_eq!(follower.unfinished(), ["0123456789"]);
+        assert!(!follower.all_finished());
+    }
+
+    #[test]
+    fn test_new_lines_are_filtered() {
+        let dir = TempDir::new().unwrap();
+        let api = task(&dir, "api", None, "");
+        let query = LogQuery {
+            strip_ansi: true,
+            grep: Some(regex::Regex::new("error").unwrap()),
+            ..Default::default()
+        };
+        let mut follower = LogFollower::new(&query, true, true);
+        let mut out = Vec::new();
+        follower.add(&api, &query, &mut out).unwrap();
+
+        append(&api, "ok\n\x1b[31merror\x1b[0m: disk full\n");
+        follower.poll(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "\x1b[36m[api]\x1b[0m error: disk full\n"
+        );
+    }
+
+    #[test]
+    fn test_truncated_and_replaced_logs_are_followed_from_the_start() {
+        let dir = TempDir::new().unwrap();
+        let api = task(&dir, "api", None, "one\n");
+        let mut follower = LogFollower::new(&LogQuery::default(), false, false);
+        let mut out = Vec::new();
+        follower.add(&api, &LogQuery::default(), &mut out).unwrap();
+
+        out.clear();
+        std::fs::write(&api.log_path, "2\n").unwrap();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "2\n");
+
+        // Rotation: lines written to the old file are printed before the new file's
+        out.clear();
+        append(&api, "three\nfo");
+        follower.poll(&mut out).unwrap();
+        append(&api, "ur");
+        let rotated = dir.path().join("api.log.1");
+        std::fs::rename(&api.log_path, &rotated).unwrap();
+        std::fs::write(&api.log_path, "five\n").unwrap();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "three\nfour\nfive\n");
+    }
+
+    #[tokio::test]
+    async fn test_watcher_wakes_up_on_writes_to_watched_files() {
+        let dir = TempDir::new().unwrap();
+        let watched = dir.path().join("watched.log");
+        std::fs::write(&watched, "").unwrap();
+        let mut watcher = LogWatcher::new().unwrap();
+        watcher.watch(&watched).unwrap();
+
+        std::fs::write(&watched, "line\n").unwrap();
+        tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .expect("a write should wake the watcher");
+    }
+}
@@ -0,0 +1,385 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+use chrono::Local;
+use regex::Regex;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::app::ansi;
+use crate::app::error::{GhostError, Result};
+use crate::app::helpers;
+use crate::app::storage::{self, Task, TaskStatus};
+
+/// Hits buffered between the searching threads and the printer
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Which tasks' logs to search
+#[derive(Debug, Default, Clone)]
+pub struct LogFilter {
+    pub status: Option<TaskStatus>,
+    /// Only tasks still running at or after this Unix time
+    pub since: Option<i64>,
+    /// Only tasks run in this directory or below it
+    pub cwd: Option<PathBuf>,
+    pub name: Option<String>,
+}
+
+impl LogFilter {
+    /// Build a filter from user input; a relative `cwd` is taken from the current directory
+    pub fn parse(
+        status: Option<&str>,
+        since: Option<&str>,
+        cwd: Option<PathBuf>,
+        name: Option<String>,
+    ) -> Result<Self> {
+        let status = status
+            .map(|status| {
+                status
+                    .parse::<TaskStatus>()
+                    .map_err(|message| GhostError::InvalidArgument { message })
+            })
+            .transpose()?;
+        let since = since
+            .map(|since| helpers::parse_since(since, Local::now()))
+            .transpose()?;
+        let cwd = match cwd {
+            Some(cwd) if cwd.is_relative() => Some(std::env::current_dir()?.join(cwd)),
+            cwd => cwd,
+        };
+        Ok(Self {
+            status,
+            since,
+            cwd,
+            name,
+        })
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.status.is_none_or(|status| task.status == status)
+            && self
+                .since
+                .is_none_or(|since| task.finished_at.is_none_or(|at| at >= since))
+            && self.cwd.as_ref().is_none_or(|cwd| {
+                task.cwd
+                    .as_deref()
+                    .is_some_and(|dir| PathBuf::from(dir).starts_with(cwd))
+            })
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| task.name.as_ref() == Some(name))
+    }
+
+    /// The matching tasks, oldest first
+    pub fn tasks(&self, conn: &Connection) -> Result<Vec<Task>> {
+        let mut tasks = storage::get_tasks_with_process_check(conn, None)?;
+        tasks.retain(|task| self.matches(task));
+        tasks.reverse();
+        Ok(tasks)
+    }
+}
+
+/// Options of `ghost grep` that select the tasks to search
+#[derive(clap::Args, Debug, Default)]
+pub struct LogFilterArgs {
+    /// Only tasks with this status (queued, waiting, starting, running, exited, killed, cancelled)
+    #[arg(short, long)]
+    status: Option<String>,
+
+    /// Only tasks still running at or after TIME (e.g. 1d, 14:30, 2026-05-01 14:30)
+    #[arg(long, value_name = "TIME")]
+    since: Option<String>,
+
+    /// Only tasks run in this directory or below it
+    #[arg(long, value_name = "DIR")]
+    cwd: Option<PathBuf>,
+
+    /// Only tasks with this name
+    #[arg(long, value_name = "NAME")]
+    name: Option<String>,
+}
+
+impl LogFilterArgs {
+    /// Validate the flags and turn them into a filter
+    pub fn into_filter(self) -> Result<LogFilter> {
+        LogFilter::parse(
+            self.status.as_deref(),
+            self.since.as_deref(),
+            self.cwd,
+            self.name,
+        )
+    }
+}
+
+/// What to report about each log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrepMode {
+    /// Every matching line
+    Lines,
+    /// Only the number of matching lines
+    Count,
+    /// Only whether a log matches, so reading stops at the first match
+    FilesWithMatches,
+}
+
+/// A matching line of a task's log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogMatch {
+    pub task_id: String,
+    pub name: Option<String>,
+    /// Line number, counted from 1
+    pub line: usize,
+    /// The line without ANSI escape sequences
+    pub text: String,
+}
+
+/// What searching the logs reports, as soon as it is found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrepEvent {
+    /// A matching line, reported in `GrepMode::Lines` only
+    Match(LogMatch),
+    /// A log has been searched, with this many matching lines
+    Searched { task: usize, count: usize },
+}
+
+/// Search the logs of `tasks` for `pattern`, several logs at a time
+///
+/// Events are passed to `on_event` as they come in, so hits of different
+/// tasks may interleave. Returning false from `on_event` stops the search.
+/// Logs that do not exist (yet) or cannot be read count as having no matches.
+pub fn search_logs(
+    tasks: &[Task],
+    pattern: &Regex,
+    mode: GrepMode,
+    mut on_event: impl FnMut(GrepEvent) -> bool,
+) {
+    let threads = std::thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(tasks.len());
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let tx = tx.clone();
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(task) = tasks.get(index) else { break };
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let count = search_log(task, pattern, mode, stop, |found| {
+                        tx.send(GrepEvent::Match(found)).is_ok()
+                    });
+                    if tx.send(GrepEvent::Searched { task: index, count }).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for event in rx.iter() {
+            if !on_event(event) {
+                stop.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+        // Threads blocked on a full channel give up once it is dropped
+        drop(rx);
+    });
+}
+
+/// Search one log, returning the number of matching lines
+fn search_log(
+    task: &Task,
+    pattern: &Regex,
+    mode: GrepMode,
+    stop: &AtomicBool,
+    mut found: impl FnMut(LogMatch) -> bool,
+) -> usize {
+    let Ok(file) = File::open(&task.log_path) else {
+        return 0;
+    };
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    let mut count = 0;
+    let mut line = 0;
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        line += 1;
+        let text = ansi::strip(&String::from_utf8_lossy(&buffer));
+        let text = text.trim_end_matches(['\n', '\r']);
+        if !pattern.is_match(text) {
+            continue;
+        }
+
+        count += 1;
+        match mode {
+            GrepMode::FilesWithMatches => break,
+            GrepMode::Count => {}
+            GrepMode::Lines => {
+                let hit = LogMatch {
+                    task_id: task.id.clone(),
+                    name: task.name.clone(),
+                    line,
+                    text: text.to_string(),
+                };
+                if !found(hit) || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn task(dir: &TempDir, id: &str, log: &str) -> Task {
+        let log_path = dir.path().join(format!("{id}.log"));
+        std::fs::write(&log_path, log).unwrap();
+        Task {
+            id: id.to_string(),
+            pid: 1,
+            pgid: None,
+            command: "[]".to_string(),
+            env: None,
+            cwd: Some("/work/api".to_string()),
+            status: TaskStatus::Exited,
+            exit_code: Some(0),
+            started_at: 100,
+            finished_at: Some(200),
+            log_path: log_path.to_string_lossy().into_owned(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        }
+    }
+
+    fn search(tasks: &[Task], pattern: &str, mode: GrepMode) -> (Vec<LogMatch>, Vec<usize>) {
+        let mut matches = Vec::new();
+        let mut counts = vec![0; tasks.len()];
+        search_logs(tasks, &Regex::new(pattern).unwrap(), mode, |event| {
+            match event {
+                GrepEvent::Match(found) => matches.push(found),
+                GrepEvent::Searched { task, count } => counts[task] = count,
+            }
+            true
+        });
+        matches.sort_by(|a, b| (&a.task_id, a.line).cmp(&(&b.task_id, b.line)));
+        (matches, counts)
+    }
+
+    #[test]
+    fn test_search_logs_of_several_tasks() {
+        let dir = TempDir::new().unwrap();
+        let tasks = [
+            task(
+                &dir,
+                "a",
+                "ok\nError: \x1b[31mEADDRINUSE\x1b[0m\r\nok\nEADDRINUSE again\n",
+            ),
+            task(&dir, "b", "nothing here\n"),
+            task(&dir, "c", "EADDRINUSE"),
+        ];
+
+        let (matches, counts) = search(&tasks, "EADDRINUSE", GrepMode::Lines);
+        let hits: Vec<_> = matches
+            .iter()
+            .map(|m| (m.task_id.as_str(), m.line, m.text.as_str()))
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                ("a", 2, "Error: EADDRINUSE"),
+                ("a", 4, "EADDRINUSE again"),
+                ("c", 1, "EADDRINUSE"),
+            ]
+        );
+        assert_eq!(counts, [2, 0, 1]);
+
+        let (matches, counts) = search(&tasks, "EADDRINUSE", GrepMode::Count);
+        assert!(matches.is_empty());
+        assert_eq!(counts, [2, 0, 1]);
+
+        let (_, counts) = search(&tasks, "EADDRINUSE", GrepMode::FilesWithMatches);
+        assert_eq!(counts, [1, 0, 1]);
+    }
+
+    #[test]
+    fn test_search_stops_when_asked() {
+        let dir = TempDir::new().unwrap();
+        let log: String = (0..10_000).map(|i| format!("hit {i}\n")).collect();
+        let tasks = [task(&dir, "a", &log), task(&dir, "b", &log)];
+
+        let mut seen = 0;
+        search_logs(
+            &tasks,
+            &Regex::new("hit").unwrap(),
+            GrepMode::Lines,
+            |event| {
+                if matches!(event, GrepEvent::Match(_)) {
+                    seen += 1;
+                }
+                seen < 5
+            },
+        );
+        assert_eq!(seen, 5);
+    }
+
+    #[test]
+    fn test_filter() {
+        let dir = TempDir::new().unwrap();
+        let mut api = task(&dir, "a", "");
+        api.name = Some("api".to_string());
+
+        assert!(LogFilter::default().matches(&api));
+        let filter = |filter: LogFilter| filter.matches(&api);
+        assert!(filter(LogFilter {
+            status: Some(TaskStatus::Exited),
+            since: Some(150),
+            cwd: Some(PathBuf::from("/work")),
+            name: Some("api".to_string()),
+        }));
+        assert!(!filter(LogFilter {
+            status: Some(TaskStatus::Running),
+            ..Default::default()
+        }));
+        assert!(!filter(LogFilter {
+            since: Some(300),
+            ..Default::default()
+        }));
+        assert!(!filter(LogFilter {
+            cwd: Some(PathBuf::from("/work/ap")),
+            ..Default::default()
+        }));
+        assert!(!filter(LogFilter {
+            name: Some("web".to_string()),
+            ..Default::default()
+        }));
+    }
+}
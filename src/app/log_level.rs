@@ -0,0 +1,529 @@
+//! Log levels of task output
+//!
+//! Levels are taken from JSON lines (`{"level":"error",...}`, including
+//! pino's numeric levels), logfmt `level=warn` fields and conventional
+//! prefixes such as `ERROR`, `[WARN]` or `error:`. Lines without a level of
+//! their own, e.g. stack traces, belong to the entry above them.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::app::ansi;
+
+/// Keys JSON loggers put the level under
+const JSON_LEVEL_KEYS: [&str; 6] = [
+    "level",
+    "lvl",
+    "severity",
+    "log.level",
+    "levelname",
+    "loglevel",
+];
+/// Whitespace separated words at the start of a line looked at for a level,
+/// so that it may follow a timestamp and a logger name
+const PREFIX_WORDS: usize = 4;
+/// Lines looked at to tell which format a log is written in
+const FORMAT_SAMPLE_LINES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// The level of a name loggers use, in any case
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "trace" | "trc" => Some(LogLevel::Trace),
+            "debug" | "dbg" => Some(LogLevel::Debug),
+            "info" | "information" | "notice" => Some(LogLevel::Info),
+            "warn" | "warning" | "wrn" => Some(LogLevel::Warn),
+            "error" | "err" | "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg"
+            | "emergency" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    /// The level of a numeric level as written by pino and bunyan
+    fn from_number(number: f64) -> Self {
+        match number {
+            n if n <= 10.0 => LogLevel::Trace,
+            n if n <= 20.0 => LogLevel::Debug,
+            n if n <= 30.0 => LogLevel::Info,
+            n if n <= 40.0 => LogLevel::Warn,
+            _ => LogLevel::Error,
+        }
+    }
+
+    /// The next stricter minimum level when cycling through them, None
+    /// after `Error` for showing every line again
+    pub fn next_minimum(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(LogLevel::Debug),
+            Some(LogLevel::Trace) => Some(LogLevel::Debug),
+            Some(LogLevel::Debug) => Some(LogLevel::Info),
+            Some(LogLevel::Info) => Some(LogLevel::Warn),
+            Some(LogLevel::Warn) => Some(LogLevel::Error),
+            Some(LogLevel::Error) => None,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LogLevel::from_name(s)
+            .ok_or_else(|| format!("Unknown log level: {s}. Use trace, debug, info, warn or error"))
+    }
+}
+
+/// The level a line states, if it states one
+pub fn detect(line: &str) -> Option<LogLevel> {
+    let line = ansi::strip(line);
+    let line = line.trim();
+    if line.starts_with('{') {
+        return json_object(line).and_then(|object| json_level(&object));
+    }
+    logfmt_level(line).or_else(|| prefix_level(line))
+}
+
+fn json_object(line: &str) -> Option<Map<String, Value>> {
+    serde_json::from_str(line).ok()
+}
+
+fn json_level(object: &Map<String, Value>) -> Option<LogLevel> {
+    object.iter().find_map(|(key, value)| {
+        if !JSON_LEVEL_KEYS
+            .iter()
+            .any(|name| key.eq_ignore_ascii_case(name))
+        {
+            return None;
+        }
+        match value {
+            Value::String(name) => LogLevel::from_name(name),
+            Value::Number(number) => number.as_f64().map(LogLevel::from_number),
+            _ => None,
+        }
+    })
+}
+
+/// The level of a logfmt line such as `time=... level=warn msg=...`
+fn logfmt_level(line: &str) -> Option<LogLevel> {
+    line.split_whitespace().find_map(|field| {
+        let (key, value) = field.split_once('=')?;
+        if !["level", "lvl", "severity"].contains(&key) {
+            return None;
+        }
+        LogLevel::from_name(value.trim_matches('"'))
+    })
+}
+
+/// The level of a line starting with e.g. `ERROR`, `[WARN]`, `ERROR:root:`
+/// or, as compilers print, `warning: ...`
+fn prefix_level(line: &str) -> Option<LogLevel> {
+    line.split_whitespace()
+        .take(PREFIX_WORDS)
+        .enumerate()
+        .find_map(|(position, word)| {
+            let word = word.trim_start_matches(['[', '(', '<']);
+            let end = word
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(word.len());
+            let (name, rest) = word.split_at(end);
+            if !rest.is_empty() && !rest.starts_with([']', ')', '>', ':', '|', ',']) {
+                return None;
+            }
+            // Lower case words only count as `error:` at the very start, so
+            // that a sentence mentioning an error is not taken for one
+            let shouting = name.chars().all(|c| c.is_ascii_uppercase());
+            if !(shouting || position == 0 && rest.starts_with(':')) {
+                return None;
+            }
+            LogLevel::from_name(name)
+        })
+}
+
+/// Follows the level of entries that span several lines
+///
+/// Each line gets its own level if it states one, or else the level of the
+/// last line that did.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LevelTracker {
+    current: Option<LogLevel>,
+}
+
+impl LevelTracker {
+    /// The level of the next line
+    pub fn next(&mut self, line: &str) -> Option<LogLevel> {
+        if let Some(level) = detect(line) {
+            self.current = Some(level);
+        }
+        self.current
+    }
+
+    /// Whether the next line is at `minimum` or above
+    pub fn keep(&mut self, line: &str, minimum: LogLevel) -> bool {
+        self.next(line).is_some_and(|level| level >= minimum)
+    }
+}
+
+/// Expand a JSON object line into indented lines, keeping its key order
+///
+/// Returns None for lines that are not a JSON object.
+pub fn pretty_json(line: &str) -> Option<Vec<String>> {
+    let text = ansi::strip(line);
+    let text = text.trim();
+    if !text.starts_with('{') || json_object(text).is_none() {
+        return None;
+    }
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = text.chars().peekable();
+    let indent = |depth: usize| "  ".repeat(depth);
+    while let Some(c) = chars.next() {
+        if in_string {
+            current.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                current.push(c);
+            }
+            '{' | '[' => {
+                current.push(c);
+                // Keep empty objects and arrays on one line
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                if let Some(&close @ ('}' | ']')) = chars.peek() {
+                    chars.next();
+                    current.push(close);
+                } else {
+                    depth += 1;
+                    lines.push(std::mem::replace(&mut current, indent(depth)));
+                }
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                lines.push(std::mem::replace(&mut current, indent(depth)));
+                current.push(c);
+            }
+            ',' => {
+                current.push(c);
+                lines.push(std::mem::replace(&mut current, indent(depth)));
+            }
+            ':' => current.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => current.push(c),
+        }
+    }
+    lines.push(current);
+    Some(lines)
+}
+
+/// How a log is written, as far as levels are concerned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Mostly JSON objects, one per line
+    Json,
+    /// Text lines, some of which state a level
+    Leveled,
+    #[default]
+    Plain,
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogFormat::Json => "json",
+            LogFormat::Leveled => "leveled",
+            LogFormat::Plain => "plain",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Number of error and warning entries in a log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LevelCounts {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl LevelCounts {
+    pub fn is_empty(&self) -> bool {
+        self.errors == 0 && self.warnings == 0
+    }
+}
+
+/// Counts the errors and warnings of a log as it grows
+///
+/// Only lines that state their level count, so a stack trace below an error
+/// counts once. The log is read from where the last update stopped, and from
+/// its start again when it was truncated or replaced.
+#[derive(Debug)]
+pub struct LevelCounter {
+    path: PathBuf,
+    inode: Option<u64>,
+    position: u64,
+    /// The start of a line still being written
+    partial: Vec<u8>,
+    counts: LevelCounts,
+    /// Non-empty lines looked at to tell the format, and how many were JSON
+    /// objects or stated a level
+    sampled: usize,
+    json_lines: usize,
+    leveled_lines: usize,
+}
+
+impl LevelCounter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            inode: None,
+            position: 0,
+            partial: Vec::new(),
+            counts: LevelCounts::default(),
+            sampled: 0,
+            json_lines: 0,
+            leveled_lines: 0,
+        }
+    }
+
+    /// Read up to `max_bytes` of what was written since the last update
+    pub fn update(&mut self, max_bytes: u64) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        if self.inode.is_some_and(|inode| inode != metadata.ino()) || metadata.len() < self.position
+        {
+            *self = Self::new(std::mem::take(&mut self.path));
+        }
+        self.inode = Some(metadata.ino());
+        if metadata.len() == self.position {
+            return Ok(());
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut buffer = vec![0; 64 * 1024];
+        let mut reader = file.take(max_bytes);
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            self.position += read as u64;
+            self.partial.extend_from_slice(&buffer[..read]);
+            if let Some(end) = self.partial.iter().rposition(|byte| *byte == b'\n') {
+                let rest = self.partial.split_off(end + 1);
+                let lines = std::mem::replace(&mut self.partial, rest);
+                for line in lines.split(|byte| *byte == b'\n') {
+                    self.count(&String::from_utf8_lossy(line));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn count(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let level = detect(line);
+        match level {
+            Some(LogLevel::Error) => self.counts.errors += 1,
+            Some(LogLevel::Warn) => self.counts.warnings += 1,
+            _ => {}
+        }
+        if self.sampled < FORMAT_SAMPLE_LINES {
+            self.sampled += 1;
+            let plain = ansi::strip(line);
+            if plain.starts_with('{') && json_object(&plain).is_some() {
+                self.json_lines += 1;
+            }
+            if level.is_some() {
+                self.leveled_lines += 1;
+            }
+        }
+    }
+
+    pub fn counts(&self) -> LevelCounts {
+        self.counts
+    }
+
+    /// The format of the lines read so far
+    pub fn format(&self) -> LogFormat {
+        if self.sampled > 0 && self.json_lines * 2 > self.sampled {
+            LogFormat::Json
+        } else if self.leveled_lines > 0 {
+            LogFormat::Leveled
+        } else {
+            LogFormat::Plain
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_detect() {
+        let cases = [
+            (r#"{"level":"error","msg":"boom"}"#, Some(LogLevel::Error)),
+            (
+                r#"{"time":1,"level":40,"msg":"slow"}"#,
+                Some(LogLevel::Warn),
+            ),
+            (
+                r#"{"severity":"INFO","message":"up"}"#,
+                Some(LogLevel::Info),
+            ),
+            (r#"{"msg":"no level"}"#, None),
+            (
+                "time=12:00 level=warn msg=\"disk low\"",
+                Some(LogLevel::Warn),
+            ),
+            (
+                "2024-01-01T12:00:00Z ERROR api: failed",
+                Some(LogLevel::Error),
+            ),
+            (
+                "[2024-01-01 12:00:00] [DEBUG] cache miss",
+                Some(LogLevel::Debug),
+            ),
+            ("\x1b[32m INFO\x1b[0m server started", Some(LogLevel::Info)),
+            ("ERROR:root:connection refused", Some(LogLevel::Error)),
+            ("warning: unused variable `x`", Some(LogLevel::Warn)),
+            ("Found an error in the config", None),
+            ("Errors are fine", None),
+            ("INFORMATIONAL text", None),
+            ("", None),
+        ];
+        for (line, level) in cases {
+            assert_eq!(detect(line), level, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_tracker_gives_continuation_lines_the_entry_level() {
+        let mut tracker = LevelTracker::default();
+        let kept: Vec<_> = [
+            "before any level",
+            "INFO starting",
+            "ERROR failed",
+            "  at main.rs:10",
+            "WARN retrying",
+            "INFO done",
+        ]
+        .into_iter()
+        .filter(|line| tracker.keep(line, LogLevel::Warn))
+        .collect();
+        assert_eq!(kept, ["ERROR failed", "  at main.rs:10", "WARN retrying"]);
+    }
+
+    #[test]
+    fn test_pretty_json_keeps_key_order() {
+        let pretty = pretty_json(
+            r#"{"level":"error","msg":"a, b: {c}","ctx":{"id":1,"tags":[]},"list":[1,2]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            pretty,
+            [
+                "{",
+                r#"  "level": "error","#,
+                r#"  "msg": "a, b: {c}","#,
+                r#"  "ctx": {"#,
+                r#"    "id": 1,"#,
+                r#"    "tags": []"#,
+                "  },",
+                r#"  "list": ["#,
+                "    1,",
+                "    2",
+                "  ]",
+                "}",
+            ]
+        );
+        assert_eq!(pretty_json("plain text"), None);
+        assert_eq!(pretty_json("{not json"), None);
+    }
+
+    #[test]
+    fn test_counter_counts_new_lines_and_tells_the_format() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "{{\"level\":\"error\"}}\n{{\"level\":\"warn\"}}\n{{\"level\":\"info\"}}\n{{\"level\":\"err"
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let mut counter = LevelCounter::new(file.path());
+        counter.update(u64::MAX).unwrap();
+        assert_eq!(
+            counter.counts(),
+            LevelCounts {
+                errors: 1,
+                warnings: 1
+            }
+        );
+        assert_eq!(counter.format(), LogFormat::Json);
+
+        writeln!(file, "or\"}}").unwrap();
+        file.flush().unwrap();
+        counter.update(u64::MAX).unwrap();
+        assert_eq!(counter.counts().errors, 2);
+
+        std::fs::write(file.path(), "WARN once\nplain\n").unwrap();
+        counter.update(u64::MAX).unwrap();
+        assert_eq!(
+            counter.counts(),
+            LevelCounts {
+                errors: 0,
+                warnings: 1
+            }
+        );
+        assert_eq!(counter.format(), LogFormat::Leveled);
+    }
+}
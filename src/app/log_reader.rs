@@ -0,0 +1,529 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::app::ansi;
+use crate::app::error::{GhostError, Result};
+use crate::app::helpers;
+use crate::app::log_level::{LevelTracker, LogLevel};
+
+/// Bytes read per step when looking for the last lines of a log
+const TAIL_BLOCK_SIZE: u64 = 64 * 1024;
+
+/// Which part of a log to read
+///
+/// The log is streamed line by line, so only the selected lines and the
+/// requested context are ever held in memory.
+#[derive(Debug, Default, Clone)]
+pub struct LogQuery {
+    /// Byte offset to start at; an offset inside a line starts at the next one
+    pub offset: u64,
+    /// Number of lines to skip after the offset
+    pub from_line: Option<usize>,
+    /// Read only the last this many lines
+    pub tail: Option<usize>,
+    /// Stop after this many lines, leaving a cursor to continue from
+    pub limit: Option<usize>,
+    /// Skip lines until the first one timestamped at or after this Unix time
+    pub since: Option<i64>,
+    /// Only lines matching this pattern, plus their context
+    pub grep: Option<Regex>,
+    pub before_context: usize,
+    pub after_context: usize,
+    /// Only entries at this level or above; lines without a level of their
+    /// own go with the entry above them
+    pub level: Option<LogLevel>,
+    /// Remove ANSI escape sequences, which also applies to `grep` matching
+    pub strip_ansi: bool,
+    /// Leave a last line without a newline unread, so following the log
+    /// picks it up once it is complete
+    pub whole_lines: bool,
+}
+
+/// Where reading a log stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReadSummary {
+    /// Size of the log file in bytes
+    pub total_size: u64,
+    /// Offset of the first line not read, to continue from
+    pub next_offset: u64,
+    /// Whether the end of the log was reached
+    pub eof: bool,
+    /// Number of lines returned, not counting `--` group separators
+    pub lines: usize,
+}
+
+/// Options of `ghost log` that select which lines to show
+#[derive(clap::Args, Debug, Default)]
+pub struct LogQueryArgs {
+    /// Show only the last N lines
+    #[arg(short = 'n', long, value_name = "N", conflicts_with_all = ["head", "offset", "from_line"])]
+    tail: Option<usize>,
+
+    /// Show only the first N lines (after --offset/--from-line, if given)
+    #[arg(long, value_name = "N", conflicts_with = "follow")]
+    head: Option<usize>,
+
+    /// Start at the first line timestamped at or after TIME (e.g. 10m, 14:30, 2026-05-01 14:30)
+    #[arg(long, value_name = "TIME")]
+    since: Option<String>,
+
+    /// Show only lines matching this regular expression
+    #[arg(short, long, value_name = "REGEX")]
+    grep: Option<String>,
+
+    /// Lines of context to show after each match
+    #[arg(
+        short = 'A',
+        long,
+        value_name = "N",
+        requires = "grep",
+        default_value_t = 0
+    )]
+    after_context: usize,
+
+    /// Lines of context to show before each match
+    #[arg(
+        short = 'B',
+        long,
+        value_name = "N",
+        requires = "grep",
+        default_value_t = 0
+    )]
+    before_context: usize,
+
+    /// Show only entries at this level or above (trace, debug, info, warn, error)
+    #[arg(long, value_name = "LEVEL")]
+    level: Option<String>,
+
+    /// Start reading at this byte offset, e.g. one printed by an earlier --head
+    #[arg(long, value_name = "BYTES")]
+    offset: Option<u64>,
+
+    /// Skip this many lines before showing any
+    #[arg(long, value_name = "N")]
+    from_line: Option<usize>,
+}
+
+impl LogQueryArgs {
+    /// Validate the flags and turn them into a query
+    pub fn into_query(self, strip_ansi: bool) -> Result<LogQuery> {
+        Ok(LogQuery {
+            offset: self.offset.unwrap_or(0),
+            from_line: self.from_line,
+            tail: self.tail,
+            limit: self.head,
+            since: self
+                .since
+                .map(|value| helpers::parse_since(&value, Local::now()))
+                .transpose()?,
+            grep: self
+                .grep
+                .map(|pattern| compile_pattern(&pattern))
+                .transpose()?,
+            before_context: self.before_context,
+            after_context: self.after_context,
+            level: self.level.as_deref().map(parse_level).transpose()?,
+            strip_ansi,
+            whole_lines: false,
+        })
+    }
+}
+
+/// Compile a user supplied regular expression
+pub fn compile_pattern(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| GhostError::InvalidArgument {
+        message: format!("Invalid regular expression: {e}"),
+    })
+}
+
+/// Parse a minimum log level given by the user
+pub fn parse_level(level: &str) -> Result<LogLevel> {
+    level
+        .parse()
+        .map_err(|message| GhostError::InvalidArgument { message })
+}
+
+/// Read the lines of a log selected by `query`
+///
+/// Each line is passed to `emit` with its line ending, so the selected part of
+/// the log is reproduced byte for byte unless ANSI sequences are stripped.
+pub fn read_log(path: &Path, query: &LogQuery, mut emit: impl FnMut(&str)) -> Result<ReadSummary> {
+    let read_error = |e: io::Error| GhostError::InvalidArgument {
+        message: format!("Failed to read log file: {e}"),
+    };
+    let mut file = File::open(path).map_err(read_error)?;
+    let total_size = file.metadata().map_err(read_error)?.len();
+
+    let start = match query.tail {
+        Some(count) => tail_start(&mut file, total_size, count),
+        None => line_start(&mut file, query.offset.min(total_size)),
+    }
+    .map_err(read_error)?;
+    file.seek(SeekFrom::Start(start)).map_err(read_error)?;
+
+    let mut reader = BufReader::new(file);
+    let mut position = start;
+    let mut buffer = Vec::new();
+    let mut read_line = |reader: &mut BufReader<File>, position: &mut u64| {
+        buffer.clear();
+        let read = reader.read_until(b'\n', &mut buffer)?;
+        *position += read as u64;
+        io::Result::Ok((read > 0).then(|| (String::from_utf8_lossy(&buffer).into_owned(), read)))
+    };
+
+    for _ in 0..query.from_line.unwrap_or(0) {
+        if read_line(&mut reader, &mut position)
+            .map_err(read_error)?
+            .is_none()
+        {
+            break;
+        }
+    }
+
+    let mut lines = 0;
+    let mut since_reached = query.since.is_none();
+    let mut timestamp_seen = false;
+    let mut line_number = 0;
+    let mut last_emitted: Option<usize> = None;
+    let mut before: VecDeque<(usize, String)> = VecDeque::new();
+    let mut after_remaining = 0;
+    let mut levels = LevelTracker::default();
+
+    loop {
+        if query.limit.is_some_and(|limit| lines >= limit) {
+            break;
+        }
+        let Some((raw, read)) = read_line(&mut reader, &mut position).map_err(read_error)? else {
+            break;
+        };
+        if query.whole_lines && !raw.ends_with('\n') {
+            position -= read as u64;
+            break;
+        }
+        let line = if query.strip_ansi {
+            ansi::strip(&raw)
+        } else {
+            raw
+        };
+        line_number += 1;
+
+        if !since_reached {
+            if let Some(timestamp) = line_timestamp(&line) {
+                timestamp_seen = true;
+                since_reached = query.since.is_some_and(|since| timestamp >= since);
+            }
+            if !since_reached {
+                continue;
+            }
+        }
+        if let Some(minimum) = query.level
+            && !levels.keep(&line, minimum)
+        {
+            continue;
+        }
+
+        let Some(pattern) = &query.grep else {
+            emit(&line);
+            lines += 1;
+            continue;
+        };
+
+        let text = line.trim_end_matches(['\n', '\r']);
+        let text = if query.strip_ansi {
+            text.to_string()
+        } else {
+            ansi::strip(text)
+        };
+        if pattern.is_match(&text) {
+            let first = before.front().map_or(line_number, |(number, _)| *number);
+            let has_context = query.before_context > 0 || query.after_context > 0;
+            if has_context && last_emitted.is_some_and(|last| first > last + 1) {
+                emit("--\n");
+            }
+            for (_, context) in before.drain(..) {
+                emit(&context);
+                lines += 1;
+            }
+            emit(&line);
+            lines += 1;
+            last_emitted = Some(line_number);
+            after_remaining = query.after_context;
+        } else if after_remaining > 0 {
+            emit(&line);
+            lines += 1;
+            last_emitted = Some(line_number);
+            after_remaining -= 1;
+        } else if query.before_context > 0 {
+            if before.len() == query.before_context {
+                before.pop_front();
+            }
+            before.push_back((line_number, line));
+        }
+    }
+
+    if query.since.is_some() && line_number > 0 && !timestamp_seen {
+        return Err(GhostError::InvalidArgument {
+            message: "--since needs log lines that start with a date and time".to_string(),
+        });
+    }
+
+    Ok(ReadSummary {
+        total_size,
+        next_offset: position,
+        eof: position >= total_size,
+        lines,
+    })
+}
+
+/// Offset of the first line starting at or after `offset`
+fn line_start(file: &mut File, offset: u64) -> io::Result<u64> {
+    if offset == 0 {
+        return Ok(0);
+    }
+    file.seek(SeekFrom::Start(offset - 1))?;
+    let mut reader = BufReader::new(file);
+    let mut skipped = Vec::new();
+    let read = reader.read_until(b'\n', &mut skipped)?;
+    Ok(offset - 1 + read as u64)
+}
+
+/// Offset where the last `count` lines of the file start
+///
+/// The file is read backwards in blocks, so this is quick for any file size.
+fn tail_start(file: &mut File, size: u64, count: usize) -> io::Result<u64> {
+    if count == 0 {
+        return Ok(size);
+    }
+    let mut end = size;
+    let mut found = 0;
+    let mut block = vec![0; TAIL_BLOCK_SIZE as usize];
+    // A newline ending the last line does not start another one
+    let mut skip_last = true;
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_BLOCK_SIZE);
+        let block = &mut block[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+        for (index, byte) in block.iter().enumerate().rev() {
+            if *byte != b'\n' {
+                skip_last = false;
+                continue;
+            }
+            if std::mem::take(&mut skip_last) {
+                continue;
+            }
+            found += 1;
+            if found == count {
+                return Ok(start + index as u64 + 1);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Unix time of the date and time a log line starts with, if it has one
+///
+/// Recognizes RFC 3339 timestamps and "YYYY-MM-DD HH:MM:SS" (optionally with
+/// fractional seconds or a "T" separator), optionally inside brackets. Times
+/// without a UTC offset are taken as local time.
+pub fn line_timestamp(line: &str) -> Option<i64> {
+    let line = ansi::strip(line);
+    let line = line.trim_start().trim_start_matches('[');
+    if !line.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut tokens = line.split_whitespace();
+    let trim = |token: &str| token.trim_end_matches([']', ',', ':']).to_string();
+    let first = trim(tokens.next()?);
+    if let Ok(at) = DateTime::parse_from_rfc3339(&first) {
+        return Some(at.timestamp());
+    }
+
+    let local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|at| at.timestamp())
+    };
+    if let Ok(naive) = NaiveDateTime::parse_from_str(&first, "%Y-%m-%dT%H:%M:%S%.f") {
+        return local(naive);
+    }
+    let second = trim(tokens.next()?);
+    NaiveDateTime::parse_from_str(&format!("{first} {second}"), "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .and_then(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn log_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    fn read(file: &NamedTempFile, query: &LogQuery) -> (String, ReadSummary) {
+        let mut output = String::new();
+        let summary = read_log(file.path(), query, |line| output.push_str(line)).unwrap();
+        (output, summary)
+    }
+
+    fn numbered(lines: usize) -> String {
+        (1..=lines).map(|i| format!("line {i}\n")).collect()
+    }
+
+    #[test]
+    fn test_tail_reads_the_last_lines() {
+        let file = log_file(&numbered(100_000));
+        let query = LogQuery {
+            tail: Some(2),
+            ..Default::default()
+        };
+        let (output, summary) = read(&file, &query);
+        assert_eq!(output, "line 99999\nline 100000\n");
+        assert!(summary.eof);
+        assert_eq!(summary.lines, 2);
+
+        // A last line without a newline counts as a line
+        let file = log_file("a\nb\nc");
+        let (output, _) = read(&file, &query);
+        assert_eq!(output, "b\nc");
+
+        let query = LogQuery {
+            tail: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(read(&file, &query).0, "a\nb\nc");
+
+        // Following leaves the unfinished last line for later
+        let query = LogQuery {
+            whole_lines: true,
+            ..Default::default()
+        };
+        let (output, summary) = read(&file, &query);
+        assert_eq!(output, "a\nb\n");
+        assert_eq!(summary.next_offset, 4);
+    }
+
+    #[test]
+    fn test_limit_leaves_a_cursor_to_continue_from() {
+        let file = log_file(&numbered(5));
+        let mut query = LogQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let (output, summary) = read(&file, &query);
+        assert_eq!(output, "line 1\nline 2\n");
+        assert!(!summary.eof);
+        assert_eq!(summary.next_offset, 14);
+        assert_eq!(summary.total_size, 35);
+
+        query.offset = summary.next_offset;
+        assert_eq!(read(&file, &query).0, "line 3\nline 4\n");
+
+        // An offset inside a line starts at the next one
+        query.offset = 9;
+        assert_eq!(read(&file, &query).0, "line 3\nline 4\n");
+
+        query.offset = 0;
+        query.from_line = Some(3);
+        let (output, summary) = read(&file, &query);
+        assert_eq!(output, "line 4\nline 5\n");
+        assert!(summary.eof);
+    }
+
+    #[test]
+    fn test_grep_with_context() {
+        let file = log_file("a\nerror 1\nb\nc\nd\ne\nerror 2\nf\n");
+        let query = LogQuery {
+            grep: Some(Regex::new("error").unwrap()),
+            before_context: 1,
+            after_context: 1,
+            ..Default::default()
+        };
+        let (output, summary) = read(&file, &query);
+        assert_eq!(output, "a\nerror 1\nb\n--\ne\nerror 2\nf\n");
+        assert_eq!(summary.lines, 6);
+
+        // Matching ignores colors but the lines keep them
+        let file = log_file("\x1b[31mer\x1b[0mror\nok\n");
+        let query = LogQuery {
+            grep: Some(Regex::new("error").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(read(&file, &query).0, "\x1b[31mer\x1b[0mror\n");
+    }
+
+    #[test]
+    fn test_level_keeps_entries_at_or_above_it() {
+        let file = log_file(
+            "INFO up\n{\"level\":\"error\",\"msg\":\"db\"}\n  caused by: timeout\nWARN slow\nDEBUG x\n",
+        );
+        let query = LogQuery {
+            level: Some(LogLevel::Warn),
+            ..Default::default()
+        };
+        let (output, summary) = read(&file, &query);
+        assert_eq!(
+            output,
+            "{\"level\":\"error\",\"msg\":\"db\"}\n  caused by: timeout\nWARN slow\n"
+        );
+        assert_eq!(summary.lines, 3);
+        assert!(parse_level("verbose").is_err());
+    }
+
+    #[test]
+    fn test_since_starts_at_the_first_later_line() {
+        let file = log_file(
+            "2026-05-01 10:00:00 starting\n\
+             2026-05-01 11:00:00.250 ready\n\
+             continued\n\
+             [2026-05-01T12:00:00] done\n",
+        );
+        let since = Local
+            .with_ymd_and_hms(2026, 5, 1, 10, 30, 0)
+            .unwrap()
+            .timestamp();
+        let query = LogQuery {
+            since: Some(since),
+            ..Default::default()
+        };
+        let (output, _) = read(&file, &query);
+        assert_eq!(
+            output,
+            "2026-05-01 11:00:00.250 ready\ncontinued\n[2026-05-01T12:00:00] done\n"
+        );
+
+        let file = log_file("no timestamps here\n");
+        let error = read_log(file.path(), &query, |_| {}).unwrap_err();
+        assert!(error.to_string().contains("--since needs log lines"));
+    }
+
+    #[test]
+    fn test_line_timestamp() {
+        assert_eq!(
+            line_timestamp("2026-04-01T08:15:00Z GET /"),
+            Some(1775031300)
+        );
+        assert_eq!(
+            line_timestamp("2026-04-01T10:15:00.5+02:00 GET /"),
+            Some(1775031300)
+        );
+        assert!(line_timestamp("[2026-04-01 08:15:00] GET /").is_some());
+        assert_eq!(line_timestamp("GET / 2026-04-01 08:15:00"), None);
+        assert_eq!(line_timestamp("12 requests"), None);
+    }
+}
@@ -1,11 +1,31 @@
+pub mod ansi;
+pub mod chain;
 pub mod commands;
 pub mod config;
+pub mod cron;
 pub mod display;
 pub mod error;
 pub mod helpers;
+pub mod hooks;
+pub mod limits;
+pub mod log_follow;
+pub mod log_grep;
+pub mod log_level;
+pub mod log_reader;
 pub mod logging;
+pub mod notify;
+pub mod output_trigger;
 pub mod port_detector;
 pub mod process;
 pub mod process_state;
+pub mod process_tree;
+pub mod queue;
+pub mod run_options;
+pub mod scheduler;
+pub mod search;
+pub mod stats;
 pub mod storage;
+pub mod supervisor;
+pub mod template;
 pub mod tui;
+pub mod watch;
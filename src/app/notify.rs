@@ -0,0 +1,316 @@
+//! Notifications about tasks that ended or restarted
+//!
+//! The TUI shows them as toasts and keeps a history of them. A terminal is
+//! notified with an escape sequence: a bell, or a desktop notification via
+//! OSC 9 (iTerm2, WezTerm, kitty, Windows Terminal) or OSC 777 (foot, urxvt
+//! and VTE-based terminals).
+
+use std::ffi::CStr;
+use std::fs::OpenOptions;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::helpers::{self, format_duration_short};
+use crate::app::storage::TaskStatus;
+use crate::app::storage::task::Task;
+
+/// Longest part of a task's command shown in a notification
+const MAX_LABEL_LENGTH: usize = 60;
+
+/// Escape sequence a terminal is notified with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalAlert {
+    /// Ring the terminal bell
+    Bell,
+    /// Desktop notification understood by iTerm2, WezTerm, kitty and Windows Terminal
+    Osc9,
+    /// Desktop notification understood by foot, urxvt and VTE-based terminals
+    Osc777,
+}
+
+impl TerminalAlert {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TerminalAlert::Bell => "bell",
+            TerminalAlert::Osc9 => "osc9",
+            TerminalAlert::Osc777 => "osc777",
+        }
+    }
+
+    /// The escape sequence notifying of `title` and `body`
+    pub fn escape(&self, title: &str, body: &str) -> String {
+        match self {
+            TerminalAlert::Bell => "\x07".to_string(),
+            TerminalAlert::Osc9 => {
+                format!("\x1b]9;{}: {}\x07", printable(title), printable(body))
+            }
+            // Fields are separated by semicolons, so the title may not contain one
+            TerminalAlert::Osc777 => format!(
+                "\x1b]777;notify;{};{}\x07",
+                printable(title).replace(';', ","),
+                printable(body)
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for TerminalAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for TerminalAlert {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "bell" => Ok(TerminalAlert::Bell),
+            "osc9" => Ok(TerminalAlert::Osc9),
+            "osc777" => Ok(TerminalAlert::Osc777),
+            _ => Err(format!(
+                "Invalid notification: {s}. Valid options: bell, osc9, osc777"
+            )),
+        }
+    }
+}
+
+/// The terminal `ghost run --notify` was started from, notified when the task ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalNotify {
+    pub alert: TerminalAlert,
+    pub tty: PathBuf,
+}
+
+impl TerminalNotify {
+    /// Short description for `ghost status`, e.g. "osc9 on /dev/pts/3"
+    pub fn describe(&self) -> String {
+        format!("{} on {}", self.alert, self.tty.display())
+    }
+
+    /// Write the notification to the terminal, if it is still there
+    pub fn send(&self, notification: &Notification) -> io::Result<()> {
+        let mut tty = OpenOptions::new().write(true).open(&self.tty)?;
+        let escape = self
+            .alert
+            .escape(&notification.title, &notification.message);
+        tty.write_all(escape.as_bytes())?;
+        tty.flush()
+    }
+}
+
+/// What happened to a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// The task's process exited with code 0
+    Exited,
+    /// The task's process exited with another code, or was killed
+    Failed,
+    /// The task's process was started again
+    Restarted,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Exited => "exited",
+            NotificationKind::Failed => "failed",
+            NotificationKind::Restarted => "restarted",
+        }
+    }
+}
+
+impl std::fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub created_at: i64,
+    pub kind: NotificationKind,
+    pub task_id: String,
+    pub title: String,
+    pub message: String,
+}
+
+impl Notification {
+    /// Notification about a task that is no longer running, e.g.
+    /// "cargo build exited with code 101 after 1m"
+    pub fn for_task_end(task: &Task) -> Self {
+        let failed = task.status != TaskStatus::Exited
+            || task.exit_code != Some(0)
+            || task.exit_reason.is_some();
+        let ended = match (task.status, task.exit_code) {
+            (TaskStatus::Exited, Some(code)) => format!("exited with code {code}"),
+            (TaskStatus::Killed, _) => "was killed".to_string(),
+            (status, _) => format!("ended ({status})"),
+        };
+        let reason = task
+            .exit_reason
+            .as_ref()
+            .map(|reason| format!(" ({reason})"))
+            .unwrap_or_default();
+        let duration = task
+            .finished_at
+            .map(|finished| {
+                let seconds = (finished - task.started_at).max(0) as u64;
+                format!(" after {}", format_duration_short(seconds))
+            })
+            .unwrap_or_default();
+
+        let (kind, title) = if failed {
+            (NotificationKind::Failed, "Task failed")
+        } else {
+            (NotificationKind::Exited, "Task finished")
+        };
+        Self::new(
+            task,
+            kind,
+            title,
+            format!("{} {ended}{reason}{duration}", task_label(task)),
+        )
+    }
+
+    /// Notification about a task whose process was started again
+    pub fn for_restart(task: &Task) -> Self {
+        let cause = task
+            .watch_trigger
+            .as_ref()
+            .map(|path| format!(", {path} changed"))
+            .unwrap_or_default();
+        Self::new(
+            task,
+            NotificationKind::Restarted,
+            "Task restarted",
+            format!(
+                "{} was restarted (restart {}{cause})",
+                task_label(task),
+                task.restart_count
+            ),
+        )
+    }
+
+    fn new(task: &Task, kind: NotificationKind, title: &str, message: String) -> Self {
+        Self {
+            created_at: helpers::now_timestamp(),
+            kind,
+            task_id: task.id.clone(),
+            title: title.to_string(),
+            message,
+        }
+    }
+}
+
+/// The task's name, or else its command
+fn task_label(task: &Task) -> String {
+    let label = match &task.name {
+        Some(name) => name.clone(),
+        None => serde_json::from_str::<Vec<String>>(&task.command)
+            .map(|command| command.join(" "))
+            .unwrap_or_else(|_| task.command.clone()),
+    };
+    if label.chars().count() > MAX_LABEL_LENGTH {
+        let truncated: String = label.chars().take(MAX_LABEL_LENGTH - 1).collect();
+        format!("{truncated}…")
+    } else {
+        label
+    }
+}
+
+/// Text without control characters, which would end the escape sequence early
+fn printable(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// The terminal this process was started from, if any
+pub fn current_tty() -> Option<PathBuf> {
+    let mut buffer = [0 as libc::c_char; 256];
+    [libc::STDERR_FILENO, libc::STDOUT_FILENO, libc::STDIN_FILENO]
+        .into_iter()
+        .find_map(|fd| {
+            // SAFETY: ttyname_r writes a nul-terminated path of at most buffer.len() bytes
+            let found = unsafe { libc::ttyname_r(fd, buffer.as_mut_ptr(), buffer.len()) } == 0;
+            found.then(|| {
+                let path = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+                PathBuf::from(path.to_string_lossy().into_owned())
+            })
+        })
+        .filter(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(status: TaskStatus, exit_code: Option<i32>) -> Task {
+        Task {
+            id: "abc".to_string(),
+            pid: 1,
+            pgid: None,
+            command: r#"["cargo","build"]"#.to_string(),
+            env: None,
+            cwd: None,
+            status,
+            exit_code,
+            started_at: 100,
+            finished_at: Some(165),
+            log_path: "/tmp/abc.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_notification_for_task_end() {
+        let finished = Notification::for_task_end(&task(TaskStatus::Exited, Some(0)));
+        assert_eq!(finished.kind, NotificationKind::Exited);
+        assert_eq!(finished.message, "cargo build exited with code 0 after 1m");
+
+        let mut killed = task(TaskStatus::Killed, None);
+        killed.exit_reason = Some("memory limit".to_string());
+        let failed = Notification::for_task_end(&killed);
+        assert_eq!(failed.kind, NotificationKind::Failed);
+        assert_eq!(failed.title, "Task failed");
+        assert_eq!(
+            failed.message,
+            "cargo build was killed (memory limit) after 1m"
+        );
+    }
+
+    #[test]
+    fn test_terminal_alert_escapes() {
+        for alert in [
+            TerminalAlert::Bell,
+            TerminalAlert::Osc9,
+            TerminalAlert::Osc777,
+        ] {
+            assert_eq!(alert.as_str().parse::<TerminalAlert>().unwrap(), alert);
+        }
+        assert!("beep".parse::<TerminalAlert>().is_err());
+
+        assert_eq!(TerminalAlert::Bell.escape("Task failed", "x"), "\x07");
+        assert_eq!(
+            TerminalAlert::Osc9.escape("Task failed", "make\x07 exited"),
+            "\x1b]9;Task failed: make exited\x07"
+        );
+        assert_eq!(
+            TerminalAlert::Osc777.escape("a;b", "done"),
+            "\x1b]777;notify;a,b;done\x07"
+        );
+    }
+}
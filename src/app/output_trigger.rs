@@ -0,0 +1,331 @@
+//! Acting on lines a task writes to its log
+//!
+//! A task started with `--on-output REGEX --do ACTION` runs under a
+//! supervisor, which reads what the task appends to its log and runs the
+//! action of every trigger matching a new line. Fired triggers are recorded in
+//! the task's event history.
+
+use std::fs::File;
+use std::io::{self, Read as _, Seek as _, SeekFrom};
+use std::os::unix::fs::MetadataExt as _;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::app::ansi;
+use crate::app::error::{GhostError, Result};
+
+/// How much of the log is read per check, so a burst of output cannot stall the supervisor
+const MAX_BYTES_PER_POLL: u64 = 1024 * 1024;
+
+/// Longest part of a matched line kept in the event history
+const MAX_RECORDED_LINE: usize = 200;
+
+/// What to do when a trigger's pattern matches a line of the log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum TriggerAction {
+    /// Record the match and show a desktop notification
+    Notify,
+    /// Mark the task as ready, e.g. once a server is listening
+    Ready,
+    /// Stop the task
+    Stop,
+    /// Stop the task and start it again with the same command
+    Restart,
+    /// Run a shell command, with the matched line in `GHOST_TRIGGER_LINE`
+    Exec(String),
+}
+
+impl TriggerAction {
+    /// Action name without the command of `exec`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerAction::Notify => "notify",
+            TriggerAction::Ready => "ready",
+            TriggerAction::Stop => "stop",
+            TriggerAction::Restart => "restart",
+            TriggerAction::Exec(_) => "exec",
+        }
+    }
+}
+
+impl std::fmt::Display for TriggerAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriggerAction::Exec(command) => write!(f, "exec:{command}"),
+            action => write!(f, "{}", action.name()),
+        }
+    }
+}
+
+impl std::str::FromStr for TriggerAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "notify" => Ok(TriggerAction::Notify),
+            "ready" => Ok(TriggerAction::Ready),
+            "stop" => Ok(TriggerAction::Stop),
+            "restart" => Ok(TriggerAction::Restart),
+            _ => match s.strip_prefix("exec:").map(str::trim) {
+                Some(command) if !command.is_empty() => {
+                    Ok(TriggerAction::Exec(command.to_string()))
+                }
+                _ => Err(format!(
+                    "Invalid output action: {s}. Valid options: notify, ready, stop, restart, exec:<command>"
+                )),
+            },
+        }
+    }
+}
+
+impl From<TriggerAction> for String {
+    fn from(action: TriggerAction) -> Self {
+        action.to_string()
+    }
+}
+
+impl TryFrom<String> for TriggerAction {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A pattern to look for in the log and the action taken when a line matches it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputTrigger {
+    pub pattern: String,
+    pub action: TriggerAction,
+}
+
+impl OutputTrigger {
+    /// Check the pattern, so a bad one is reported by `ghost run`
+    pub fn new(pattern: String, action: TriggerAction) -> Result<Self> {
+        Regex::new(&pattern).map_err(|e| GhostError::InvalidArgument {
+            message: format!("Invalid output pattern '{pattern}': {e}"),
+        })?;
+        Ok(Self { pattern, action })
+    }
+
+    /// Short description for `ghost status` and the TUI, e.g. "/Listening on/ ready"
+    pub fn describe(&self) -> String {
+        format!("/{}/ {}", self.pattern, self.action)
+    }
+}
+
+/// A line that matched a trigger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerMatch {
+    pub trigger: OutputTrigger,
+    /// The line without ANSI escape sequences
+    pub line: String,
+}
+
+impl TriggerMatch {
+    /// Message recorded in the event history, e.g. "/FATAL/ restart: FATAL: connection lost"
+    pub fn describe(&self) -> String {
+        let line: String = self.line.chars().take(MAX_RECORDED_LINE).collect();
+        format!("{}: {line}", self.trigger.describe())
+    }
+}
+
+/// Reads what a task appends to its log and matches each new line against its triggers
+#[derive(Debug)]
+pub struct OutputMatcher {
+    triggers: Vec<(Regex, OutputTrigger)>,
+    inode: Option<u64>,
+    position: u64,
+    /// The start of a line still being written
+    partial: Vec<u8>,
+}
+
+impl OutputMatcher {
+    pub fn new(triggers: &[OutputTrigger]) -> Result<Self> {
+        let triggers = triggers
+            .iter()
+            .map(|trigger| {
+                let regex =
+                    Regex::new(&trigger.pattern).map_err(|e| GhostError::InvalidArgument {
+                        message: format!("Invalid output pattern '{}': {e}", trigger.pattern),
+                    })?;
+                Ok((regex, trigger.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            triggers,
+            inode: None,
+            position: 0,
+            partial: Vec::new(),
+        })
+    }
+
+    /// Match the lines written to the log since the last poll
+    ///
+    /// A line still being written is matched once it is complete. The log is
+    /// read from the start again when it was truncated or replaced.
+    pub fn poll(&mut self, log_path: &Path) -> Vec<TriggerMatch> {
+        let mut matches = Vec::new();
+        let Ok(bytes) = self.read_new(log_path) else {
+            return matches;
+        };
+        self.partial.extend_from_slice(&bytes);
+        let Some(end) = self.partial.iter().rposition(|byte| *byte == b'\n') else {
+            return matches;
+        };
+        let rest = self.partial.split_off(end + 1);
+        let lines = std::mem::replace(&mut self.partial, rest);
+
+        for line in lines.split(|byte| *byte == b'\n') {
+            let line = ansi::strip(&String::from_utf8_lossy(line));
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            for (regex, trigger) in &self.triggers {
+                if regex.is_match(line) {
+                    matches.push(TriggerMatch {
+                        trigger: trigger.clone(),
+                        line: line.to_string(),
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    /// Ignore everything written to the log so far, e.g. by a process that was just stopped
+    pub fn skip_to_end(&mut self, log_path: &Path) {
+        if let Ok(metadata) = std::fs::metadata(log_path) {
+            self.inode = Some(metadata.ino());
+            self.position = metadata.len();
+        }
+        self.partial.clear();
+    }
+
+    fn read_new(&mut self, log_path: &Path) -> io::Result<Vec<u8>> {
+        let mut file = File::open(log_path)?;
+        let metadata = file.metadata()?;
+        if self.inode.is_some_and(|inode| inode != metadata.ino()) || metadata.len() < self.position
+        {
+            self.position = 0;
+            self.partial.clear();
+        }
+        self.inode = Some(metadata.ino());
+
+        let mut bytes = Vec::new();
+        if metadata.len() > self.position {
+            file.seek(SeekFrom::Start(self.position))?;
+            file.take(MAX_BYTES_PER_POLL).read_to_end(&mut bytes)?;
+            self.position += bytes.len() as u64;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Show a desktop notification, if the system has a way to
+pub fn notify_desktop(title: &str, body: &str) {
+    use std::process::{Command, Stdio};
+
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        );
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(script);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg(title).arg(body);
+        command
+    };
+
+    let spawned = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Ok(mut child) = spawned {
+        std::thread::spawn(move || child.wait());
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_trigger_action_round_trip() {
+        for action in [
+            TriggerAction::Notify,
+            TriggerAction::Ready,
+            TriggerAction::Stop,
+            TriggerAction::Restart,
+            TriggerAction::Exec("make reload".to_string()),
+        ] {
+            assert_eq!(action.to_string().parse::<TriggerAction>().unwrap(), action);
+        }
+        assert!("exec:".parse::<TriggerAction>().is_err());
+        assert!("reboot".parse::<TriggerAction>().is_err());
+        assert_eq!(
+            serde_json::to_string(&TriggerAction::Exec("echo hi".to_string())).unwrap(),
+            r#""exec:echo hi""#
+        );
+        assert!(OutputTrigger::new("(".to_string(), TriggerAction::Notify).is_err());
+    }
+
+    #[test]
+    fn test_matcher_reads_new_lines() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let mut matcher = OutputMatcher::new(&[
+            OutputTrigger::new("^FATAL".to_string(), TriggerAction::Restart).unwrap(),
+            OutputTrigger::new("Listening on".to_string(), TriggerAction::Ready).unwrap(),
+        ])
+        .unwrap();
+
+        write!(
+            file,
+            "starting\n\x1b[31mFATAL\x1b[0m: connection lost\r\nListening"
+        )
+        .unwrap();
+        file.flush().unwrap();
+        let matches = matcher.poll(file.path());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].trigger.action, TriggerAction::Restart);
+        assert_eq!(matches[0].line, "FATAL: connection lost");
+        assert_eq!(
+            matches[0].describe(),
+            "/^FATAL/ restart: FATAL: connection lost"
+        );
+
+        // The unfinished line is matched once it is complete
+        writeln!(file, " on :8080").unwrap();
+        file.flush().unwrap();
+        let matches = matcher.poll(file.path());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, "Listening on :8080");
+        assert!(matcher.poll(file.path()).is_empty());
+
+        writeln!(file, "FATAL again").unwrap();
+        file.flush().unwrap();
+        matcher.skip_to_end(file.path());
+        assert!(matcher.poll(file.path()).is_empty());
+
+        // A truncated log is read from the start
+        file.as_file().set_len(0).unwrap();
+        file.as_file().seek(SeekFrom::Start(0)).unwrap();
+        writeln!(file, "FATAL").unwrap();
+        file.flush().unwrap();
+        assert_eq!(matcher.poll(file.path()).len(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::app::error::{GhostError, Result};
+use std::collections::HashMap;
 use std::process::Command;
 use std::sync::OnceLock;
 
@@ -109,6 +110,52 @@ pub fn detect_listening_ports(pid: u32) -> Result<Vec<ListeningPort>> {
     return Ok(Vec::new());
 }
 
+/// Detect listening ports for several processes with a single lsof call
+pub fn detect_listening_ports_for_pids(pids: &[u32]) -> Result<HashMap<u32, Vec<ListeningPort>>> {
+    check_lsof_availability()?;
+
+    if pids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let pid_list = pids
+        .iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let output = Command::new("lsof")
+        .args(["-nP", "-i", "-a", "-p", &pid_list, "-F"])
+        .output()
+        .map_err(|e| GhostError::ProcessOperation {
+            message: format!("Failed to execute lsof: {e}"),
+        })?;
+
+    // lsof exits with an error when none of the processes have network connections
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_lsof_output_by_pid(&stdout))
+}
+
+/// Split lsof -F output into per-process sections and parse each of them
+fn parse_lsof_output_by_pid(output: &str) -> HashMap<u32, Vec<ListeningPort>> {
+    let mut sections: Vec<(u32, String)> = Vec::new();
+    for line in output.lines() {
+        if let Some(pid) = line.strip_prefix('p') {
+            if let Ok(pid) = pid.parse() {
+                sections.push((pid, String::new()));
+            }
+        } else if let Some((_, section)) = sections.last_mut() {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+
+    sections
+        .into_iter()
+        .map(|(pid, section)| (pid, parse_lsof_machine_format(&section)))
+        .filter(|(_, ports)| !ports.is_empty())
+        .collect()
+}
+
 /// Common implementation for macOS and Linux using lsof
 #[cfg(any(target_os = "macos", target_os = "linux"))]
 fn detect_ports_using_lsof(pid: u32) -> Result<Vec<ListeningPort>> {
@@ -169,6 +216,19 @@ mod tests {
         assert_eq!(ports[0].state, "LISTEN");
     }
 
+    #[test]
+    fn test_parse_lsof_output_by_pid() {
+        let sample_output = "p100\nf5\ntIPv4\nPTCP\nn*:3000\nTST=LISTEN\n\
+                             p200\nf7\ntIPv6\nPTCP\nn[::1]:9229\nTST=LISTEN\n\
+                             p300\nfcwd\ntDIR\nn/tmp\n";
+
+        let ports = parse_lsof_output_by_pid(sample_output);
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[&100][0].local_addr, "*:3000");
+        assert_eq!(ports[&200][0].local_addr, "[::1]:9229");
+        assert!(!ports.contains_key(&300));
+    }
+
     #[test]
     fn test_parse_malformed_lsof_output() {
         // Test with completely empty output
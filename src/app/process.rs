@@ -1,13 +1,13 @@
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
 use nix::unistd::setsid;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::os::unix::process::CommandExt as _;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use uuid::Uuid;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProcessInfo {
     pub id: String,
     pub pid: u32,
@@ -15,9 +15,16 @@ pub struct ProcessInfo {
     pub command: Vec<String>,
     pub log_path: PathBuf,
     pub env: Vec<(String, String)>,
+    /// Per-task cgroup holding the task's processes, if one was created
+    #[serde(default)]
+    pub cgroup: Option<PathBuf>,
 }
 
 use crate::app::error::{GhostError, Result};
+use crate::app::limits::{self, ResourceLimits, TaskCgroup};
+
+/// Environment variable carrying the task ID into every process of a task
+pub const TASK_ID_ENV: &str = "GHOST_TASK_ID";
 
 /// Spawn a background process with logging
 /// Returns both ProcessInfo and Child handle to allow proper cleanup
@@ -37,20 +44,116 @@ pub fn spawn_background_process_with_env(
     log_dir: Option<PathBuf>,
     custom_env: Vec<(String, String)>,
 ) -> Result<(ProcessInfo, Child)> {
-    // Generate task ID and prepare paths
-    let task_id = Uuid::new_v4().to_string();
-    let log_dir = log_dir.unwrap_or_else(crate::app::config::get_log_dir);
+    spawn_background_process_with_limits(
+        command,
+        cwd,
+        log_dir,
+        custom_env,
+        &ResourceLimits::default(),
+    )
+}
+
+/// Spawn a background process with logging, custom environment variables and resource limits
+/// Returns both ProcessInfo and Child handle to allow proper cleanup
+pub fn spawn_background_process_with_limits(
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    log_dir: Option<PathBuf>,
+    custom_env: Vec<(String, String)>,
+    limits: &ResourceLimits,
+) -> Result<(ProcessInfo, Child)> {
+    spawn_background_process_with_id(new_task_id(), command, cwd, log_dir, custom_env, limits)
+}
+
+/// Generate the ID of a new task
+pub fn new_task_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Path of the log file of a new task
+pub fn log_path_for(task_id: &str, log_dir: Option<&Path>) -> PathBuf {
+    match log_dir {
+        Some(log_dir) => log_dir.join(format!("{task_id}.log")),
+        None => crate::app::config::get_log_dir().join(format!("{task_id}.log")),
+    }
+}
+
+/// Spawn a background process for a new task whose ID was chosen beforehand
+/// Returns both ProcessInfo and Child handle to allow proper cleanup
+pub fn spawn_background_process_with_id(
+    task_id: String,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    log_dir: Option<PathBuf>,
+    custom_env: Vec<(String, String)>,
+    limits: &ResourceLimits,
+) -> Result<(ProcessInfo, Child)> {
+    let log_path = log_path_for(&task_id, log_dir.as_deref());
+    let log_dir = log_path.parent().unwrap_or(Path::new("."));
 
     // Create log directory if it doesn't exist
-    std::fs::create_dir_all(&log_dir)?;
+    std::fs::create_dir_all(log_dir)?;
+
+    // Create log file; appending keeps writes at the end after the log is truncated
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| GhostError::LogFileCreation {
+            path: log_path.to_string_lossy().to_string(),
+            source: e,
+        })?;
+
+    spawn_with_log_file(
+        task_id, command, cwd, log_path, log_file, custom_env, limits,
+    )
+}
 
-    let log_path = log_dir.join(format!("{task_id}.log"));
+/// Start a task's command again, appending to its existing log file
+/// Returns both ProcessInfo and Child handle to allow proper cleanup
+pub fn respawn_background_process(
+    task_id: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    log_path: &Path,
+    custom_env: Vec<(String, String)>,
+    limits: &ResourceLimits,
+) -> Result<(ProcessInfo, Child)> {
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .map_err(|e| GhostError::LogFileCreation {
+            path: log_path.to_string_lossy().to_string(),
+            source: e,
+        })?;
+
+    spawn_with_log_file(
+        task_id.to_string(),
+        command,
+        cwd,
+        log_path.to_path_buf(),
+        log_file,
+        custom_env,
+        limits,
+    )
+}
 
-    // Create log file
-    let log_file = File::create(&log_path).map_err(|e| GhostError::LogFileCreation {
-        path: log_path.to_string_lossy().to_string(),
-        source: e,
-    })?;
+/// Spawn a command in its own session with stdout/stderr redirected to the log file
+fn spawn_with_log_file(
+    task_id: String,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    log_path: PathBuf,
+    log_file: File,
+    custom_env: Vec<(String, String)>,
+    limits: &ResourceLimits,
+) -> Result<(ProcessInfo, Child)> {
+    if command.is_empty() {
+        return Err(GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
 
     // Setup command
     let mut cmd = Command::new(&command[0]);
@@ -78,16 +181,30 @@ pub fn spawn_background_process_with_env(
         }
     }
 
+    // Inherited by all descendants, so they can be found even after they daemonize
+    cmd.env(TASK_ID_ENV, &task_id);
+
+    // Everything the child needs after fork is prepared here, before spawning
+    let cgroup = TaskCgroup::create(&task_id, limits);
+    let cgroup_procs = cgroup.as_ref().and_then(TaskCgroup::procs_path);
+    let child_limits = limits.clone();
+
     unsafe {
-        cmd.pre_exec(|| {
+        cmd.pre_exec(move || {
             setsid()?;
+            limits::apply_in_child(&child_limits, cgroup_procs.as_deref())?;
             Ok(())
         });
     }
 
     // Spawn the process
-    let child = cmd.spawn().map_err(|e| GhostError::ProcessSpawn {
-        message: format!("Failed to spawn process: {e}"),
+    let child = cmd.spawn().map_err(|e| {
+        if let Some(cgroup) = &cgroup {
+            cgroup.remove();
+        }
+        GhostError::ProcessSpawn {
+            message: format!("Failed to spawn process: {e}"),
+        }
     })?;
 
     let pid = child.id();
@@ -102,13 +219,19 @@ pub fn spawn_background_process_with_env(
         command,
         log_path,
         env: all_env,
+        cgroup: cgroup.map(|cgroup| cgroup.path().to_path_buf()),
     };
 
     Ok((info, child))
 }
 
 /// Check if a process is still running
+///
+/// PID 0 is never a task's process: signalling it would reach our own process group.
 pub fn exists(pid: u32) -> bool {
+    if pid == 0 {
+        return false;
+    }
     {
         // Send signal 0 to check if process exists
         // We need to check errno to distinguish between "no permission" and "no process"
@@ -122,36 +245,88 @@ pub fn exists(pid: u32) -> bool {
 
 /// Kill a process
 pub fn kill(pid: u32, force: bool) -> Result<()> {
-    {
-        let signal = if force {
+    send_signal(
+        pid,
+        if force {
             Signal::SIGKILL
         } else {
             Signal::SIGTERM
-        };
-        signal::kill(Pid::from_raw(pid as i32), signal)?;
-        Ok(())
-    }
+        },
+    )
 }
 
 /// Kill a process group
 pub fn kill_group(pgid: i32, force: bool) -> Result<()> {
-    let signal = if force {
-        Signal::SIGKILL
-    } else {
-        Signal::SIGTERM
-    };
-    // Negative PID means process group
-    signal::kill(Pid::from_raw(-pgid), signal)?;
+    signal_group(
+        pgid,
+        if force {
+            Signal::SIGKILL
+        } else {
+            Signal::SIGTERM
+        },
+    )
+}
 
+/// Kill a process just spawned for a task that must not run after all, and reap it
+pub fn discard(pgid: i32, mut child: Child) {
+    let _ = kill_group(pgid, true);
+    let _ = child.wait();
+}
+
+/// Send a signal to a process
+pub fn send_signal(pid: u32, signal: Signal) -> Result<()> {
+    // kill(0) would signal our own process group
+    if pid == 0 {
+        return Err(nix::errno::Errno::ESRCH.into());
+    }
+    signal::kill(Pid::from_raw(pid as i32), signal)?;
     Ok(())
 }
 
+/// Send a signal to a process group
+pub fn signal_group(pgid: i32, signal: Signal) -> Result<()> {
+    // Negative PID means process group; 0 would be our own
+    if pgid <= 0 {
+        return Err(nix::errno::Errno::ESRCH.into());
+    }
+    signal::kill(Pid::from_raw(-pgid), signal)?;
+    Ok(())
+}
+
+/// Parse a signal name such as "TERM", "sigint" or "SIGHUP"
+pub fn parse_signal(name: &str) -> std::result::Result<Signal, String> {
+    let upper = name.trim().to_uppercase();
+    let full = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{upper}")
+    };
+    full.parse()
+        .map_err(|_| format!("Invalid signal: {name}. Use a name such as TERM, INT or HUP"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::thread;
     use std::time::Duration;
 
+    #[test]
+    fn test_pid_zero_is_never_a_live_process() {
+        assert!(!exists(0));
+        assert!(send_signal(0, Signal::SIGTERM).is_err());
+        assert!(signal_group(0, Signal::SIGTERM).is_err());
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("TERM"), Ok(Signal::SIGTERM));
+        assert_eq!(parse_signal("sigint"), Ok(Signal::SIGINT));
+        assert_eq!(parse_signal(" SIGHUP "), Ok(Signal::SIGHUP));
+        assert!(parse_signal("NOPE").is_err());
+        assert!(parse_signal("15").is_err());
+    }
+
     #[test]
     fn test_spawn_background_process() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -1,6 +1,7 @@
 use crate::app::{
     process,
     storage::{Task, TaskStatus},
+    watch,
 };
 
 /// Check and update the status of a single task based on process existence
@@ -23,6 +24,37 @@ pub fn determine_task_status(pid: u32) -> TaskStatus {
     }
 }
 
+/// Seconds since the task's log last grew, if it has been idle for at least its idle timeout
+pub fn idle_seconds(task: &Task) -> Option<u64> {
+    if task.status != TaskStatus::Running {
+        return None;
+    }
+    let timeout = task.run_options().idle_timeout?;
+
+    let last_output = std::fs::metadata(&task.log_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_secs() as i64)
+        .unwrap_or(task.started_at);
+
+    let idle = (crate::app::helpers::now_timestamp() - last_output).max(0) as u64;
+    (idle >= timeout).then_some(idle)
+}
+
+/// Short status label for tables, e.g. "running", "idle 12m" or "watching"
+///
+/// A watched task that exited is "watching" while its supervisor waits for changes.
+pub fn status_label(task: &Task) -> String {
+    match idle_seconds(task) {
+        Some(idle) => format!("idle {}", crate::app::helpers::format_duration_short(idle)),
+        None if task.status != TaskStatus::Running && watch::is_watching(task) => {
+            "watching".to_string()
+        }
+        None => task.status.as_str().to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,6 +74,18 @@ mod tests {
             started_at: 0,
             finished_at: None,
             log_path: "/tmp/test.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         };
 
         let updated = update_task_status_if_needed(&mut task);
@@ -64,10 +108,75 @@ mod tests {
             started_at: 0,
             finished_at: None,
             log_path: "/tmp/test.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         };
 
         let updated = update_task_status_if_needed(&mut task);
         assert!(!updated);
         assert_eq!(task.status, TaskStatus::Exited);
     }
+
+    fn running_task_with_log(log_path: &std::path::Path, options: Option<&str>) -> Task {
+        Task {
+            id: "idle".to_string(),
+            pid: std::process::id(),
+            pgid: None,
+            command: "[]".to_string(),
+            env: None,
+            cwd: None,
+            status: TaskStatus::Running,
+            exit_code: None,
+            started_at: 0,
+            finished_at: None,
+            log_path: log_path.to_string_lossy().to_string(),
+            options: options.map(String::from),
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_idle_seconds_without_timeout() {
+        let log = tempfile::NamedTempFile::new().unwrap();
+        let task = running_task_with_log(log.path(), None);
+        assert_eq!(idle_seconds(&task), None);
+        assert_eq!(status_label(&task), "running");
+    }
+
+    #[test]
+    fn test_idle_seconds_recent_output_is_not_idle() {
+        let log = tempfile::NamedTempFile::new().unwrap();
+        let task = running_task_with_log(log.path(), Some(r#"{"idle_timeout":600}"#));
+        assert_eq!(idle_seconds(&task), None);
+    }
+
+    #[test]
+    fn test_idle_seconds_missing_log_uses_start_time() {
+        let task = running_task_with_log(
+            std::path::Path::new("/non/existent/ghost.log"),
+            Some(r#"{"idle_timeout":600}"#),
+        );
+        assert!(idle_seconds(&task).is_some());
+        assert!(status_label(&task).starts_with("idle "));
+    }
 }
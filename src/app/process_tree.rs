@@ -0,0 +1,644 @@
+//! Descendant processes of a running task
+//!
+//! The tree is rooted at the task's leader process. Processes that are still
+//! in the task's process group but were reparented (because their parent
+//! exited) are listed as additional roots. Descendants that called `setsid`
+//! or otherwise changed process group are flagged as escaped, since signals
+//! sent to the task's process group never reach them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+
+use crate::app::limits::TaskCgroup;
+use crate::app::port_detector::{
+    ListeningPort, detect_listening_ports_for_pids, is_lsof_available,
+};
+use crate::app::process::TASK_ID_ENV;
+use crate::app::stats::running_pgid;
+use crate::app::storage::{Task, TaskStatus};
+
+/// How often to check whether signalled processes are gone
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A process as read from the process table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: i32,
+    /// Single-letter process state, e.g. "S" for sleeping
+    pub state: String,
+    pub command: String,
+}
+
+impl ProcessEntry {
+    pub fn is_zombie(&self) -> bool {
+        self.state == "Z"
+    }
+}
+
+/// A process in a task's tree
+#[derive(Debug, Clone)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: i32,
+    pub state: String,
+    pub command: String,
+    /// Distance from the root of the tree the process was found under
+    pub depth: usize,
+    /// The process left the task's process group and will not receive group signals
+    pub escaped: bool,
+    pub ports: Vec<ListeningPort>,
+}
+
+/// Process tree of a running task, with listening ports when lsof is available
+pub fn task_tree(task: &Task) -> Vec<ProcessNode> {
+    let Some(pgid) = running_pgid(task) else {
+        return Vec::new();
+    };
+
+    let mut nodes = build_tree(task.pid, pgid, &list_processes());
+
+    if is_lsof_available() && !nodes.is_empty() {
+        let pids: Vec<u32> = nodes.iter().map(|node| node.pid).collect();
+        if let Ok(mut ports) = detect_listening_ports_for_pids(&pids) {
+            for node in &mut nodes {
+                node.ports = ports.remove(&node.pid).unwrap_or_default();
+            }
+        }
+    }
+
+    nodes
+}
+
+/// Arrange processes into a depth-first tree below the leader process
+///
+/// Processes in the task's process group that are not descendants of the
+/// leader (their parent already exited) become extra roots.
+pub fn build_tree(leader_pid: u32, pgid: i32, processes: &[ProcessEntry]) -> Vec<ProcessNode> {
+    let mut children: BTreeMap<u32, Vec<&ProcessEntry>> = BTreeMap::new();
+    for process in processes {
+        children.entry(process.ppid).or_default().push(process);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|process| process.pid);
+    }
+
+    let mut nodes = Vec::new();
+    let mut visited = HashSet::new();
+    if let Some(leader) = processes.iter().find(|process| process.pid == leader_pid) {
+        visit(leader, 0, pgid, &children, &mut visited, &mut nodes);
+    }
+
+    let mut strays: Vec<&ProcessEntry> = processes
+        .iter()
+        .filter(|process| process.pgid == pgid && !visited.contains(&process.pid))
+        .collect();
+    strays.sort_by_key(|process| process.pid);
+    for stray in strays {
+        // visit skips strays already listed below an earlier stray
+        visit(stray, 0, pgid, &children, &mut visited, &mut nodes);
+    }
+
+    nodes
+}
+
+fn visit(
+    process: &ProcessEntry,
+    depth: usize,
+    pgid: i32,
+    children: &BTreeMap<u32, Vec<&ProcessEntry>>,
+    visited: &mut HashSet<u32>,
+    nodes: &mut Vec<ProcessNode>,
+) {
+    if !visited.insert(process.pid) {
+        return;
+    }
+
+    nodes.push(ProcessNode {
+        pid: process.pid,
+        ppid: process.ppid,
+        pgid: process.pgid,
+        state: process.state.clone(),
+        command: process.command.clone(),
+        depth,
+        escaped: process.pgid != pgid,
+        ports: Vec::new(),
+    });
+
+    if let Some(kids) = children.get(&process.pid) {
+        for child in kids {
+            visit(child, depth + 1, pgid, children, visited, nodes);
+        }
+    }
+}
+
+/// Everything that identifies the processes of one task
+///
+/// A task's processes are its leader's descendants, the members of its
+/// process group and cgroup, and any process carrying its `GHOST_TASK_ID` in
+/// the environment (which survives daemonizing, unlike the parent link).
+#[derive(Debug, Clone)]
+pub struct TaskProcesses<'a> {
+    pub task_id: &'a str,
+    pub pid: u32,
+    pub pgid: i32,
+    pub cgroup: Option<&'a Path>,
+}
+
+impl<'a> TaskProcesses<'a> {
+    pub fn of(task: &'a Task) -> Self {
+        Self {
+            task_id: &task.id,
+            pid: task.pid,
+            pgid: task.pgid.unwrap_or(task.pid as i32),
+            cgroup: task.cgroup.as_deref().map(Path::new),
+        }
+    }
+
+    /// Live (non-zombie) processes belonging to the task, excluding ghost itself
+    pub fn find(&self) -> Vec<ProcessEntry> {
+        let processes = list_processes();
+
+        let mut pids: HashSet<u32> = build_tree(self.pid, self.pgid, &processes)
+            .iter()
+            .map(|node| node.pid)
+            .collect();
+        if let Some(cgroup) = self.cgroup {
+            pids.extend(TaskCgroup::open(cgroup).pids());
+        }
+
+        let own_pid = std::process::id();
+        processes
+            .into_iter()
+            .filter(|process| process.pid != own_pid && !process.is_zombie())
+            .filter(|process| {
+                pids.contains(&process.pid)
+                    || ghost_task_id(process.pid).as_deref() == Some(self.task_id)
+            })
+            .collect()
+    }
+
+    /// Send SIGTERM (or SIGKILL when forced) to every process of the task
+    ///
+    /// Returns the processes that were signalled.
+    pub fn signal(&self, force: bool) -> Vec<ProcessEntry> {
+        self.send(if force {
+            Signal::SIGKILL
+        } else {
+            Signal::SIGTERM
+        })
+    }
+
+    /// Send `sig` to every process of the task
+    ///
+    /// Returns the processes that were signalled.
+    pub fn send(&self, sig: Signal) -> Vec<ProcessEntry> {
+        if sig == Signal::SIGKILL
+            && let Some(cgroup) = self.cgroup
+        {
+            TaskCgroup::open(cgroup).kill();
+        }
+
+        self.find()
+            .into_iter()
+            .filter(|process| signal::kill(Pid::from_raw(process.pid as i32), sig).is_ok())
+            .collect()
+    }
+
+    /// Wait up to `timeout` for the task's processes to exit and return the survivors
+    pub fn wait_for_exit(&self, timeout: Duration) -> Vec<ProcessEntry> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = self.find();
+            if remaining.is_empty() || Instant::now() >= deadline {
+                return remaining;
+            }
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+}
+
+/// A process left behind by a task that is no longer running
+#[derive(Debug, Clone)]
+pub struct Orphan {
+    pub process: ProcessEntry,
+    pub task_id: String,
+    pub reason: &'static str,
+}
+
+/// Find processes that belong to finished tasks
+///
+/// `tasks` should have up-to-date statuses. Only positive proof counts, since
+/// the result may be killed: processes carrying the `GHOST_TASK_ID` of a task
+/// that is not running are reported first, then members of finished tasks'
+/// cgroups, and members of their process groups that started while the task
+/// ran, which also catches leftovers of tasks started before the environment
+/// marker existed. Task IDs this database does not know are skipped, as they
+/// may belong to another `GHOST_DATA_DIR`.
+pub fn find_orphans(tasks: &[Task]) -> Vec<Orphan> {
+    let known: HashMap<&str, &Task> = tasks.iter().map(|task| (task.id.as_str(), task)).collect();
+    let own_pid = std::process::id();
+    let processes: Vec<ProcessEntry> = list_processes()
+        .into_iter()
+        .filter(|process| process.pid != own_pid && !process.is_zombie())
+        .collect();
+
+    let mut orphans: Vec<Orphan> = Vec::new();
+    for process in &processes {
+        let Some(task_id) = ghost_task_id(process.pid) else {
+            continue;
+        };
+        match known.get(task_id.as_str()) {
+            Some(task) if task.status != TaskStatus::Running => {}
+            _ => continue,
+        }
+        orphans.push(Orphan {
+            process: process.clone(),
+            task_id,
+            reason: "task is no longer running",
+        });
+    }
+
+    for task in tasks
+        .iter()
+        .filter(|task| task.status != TaskStatus::Running)
+    {
+        let cgroup_pids: HashSet<u32> = task
+            .cgroup
+            .as_deref()
+            .map(|cgroup| TaskCgroup::open(cgroup).pids().into_iter().collect())
+            .unwrap_or_default();
+
+        for process in &processes {
+            let reason = if cgroup_pids.contains(&process.pid) {
+                "in the cgroup of a finished task"
+            } else if task.pgid == Some(process.pgid)
+                && started_during(task, platform::start_time(process.pid))
+            {
+                "in the process group of a finished task"
+            } else {
+                continue;
+            };
+            if orphans
+                .iter()
+                .any(|orphan| orphan.process.pid == process.pid)
+            {
+                continue;
+            }
+            orphans.push(Orphan {
+                process: process.clone(),
+                task_id: task.id.clone(),
+                reason,
+            });
+        }
+    }
+
+    orphans.sort_by_key(|orphan| orphan.process.pid);
+    orphans
+}
+
+/// Whether a process started while the task ran, give or take a second for rounding
+///
+/// A matching process group alone proves nothing: PIDs and process group IDs
+/// are reused, while finished tasks are kept for weeks.
+fn started_during(task: &Task, process_started: Option<i64>) -> bool {
+    let (Some(started), Some(finished_at)) = (process_started, task.finished_at) else {
+        return false;
+    };
+    (task.started_at - 1..=finished_at + 1).contains(&started)
+}
+
+/// Read every process in the system process table
+pub fn list_processes() -> Vec<ProcessEntry> {
+    platform::list_processes()
+}
+
+/// The task ID a process inherited from ghost through its environment
+///
+/// Only available on Linux, and only for processes of the current user.
+pub fn ghost_task_id(pid: u32) -> Option<String> {
+    platform::ghost_task_id(pid)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::ProcessEntry;
+
+    pub(super) fn list_processes() -> Vec<ProcessEntry> {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+                // Processes may exit at any point while we read their files
+                let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+                let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).unwrap_or_default();
+                parse_process(pid, &stat, &cmdline)
+            })
+            .collect()
+    }
+
+    pub(super) fn ghost_task_id(pid: u32) -> Option<String> {
+        let environ = std::fs::read(format!("/proc/{pid}/environ")).ok()?;
+        parse_environ_var(&environ, super::TASK_ID_ENV)
+    }
+
+    /// When a process started, as a Unix timestamp
+    pub(super) fn start_time(pid: u32) -> Option<i64> {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        let start_ticks = parse_start_ticks(&stat)?;
+        let boot_time = parse_boot_time(&std::fs::read_to_string("/proc/stat").ok()?)?;
+        // SAFETY: sysconf has no preconditions
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+        Some(boot_time + (start_ticks / ticks_per_second) as i64)
+    }
+
+    /// Field 22 (starttime) of /proc/<pid>/stat, in clock ticks after boot
+    pub(super) fn parse_start_ticks(stat: &str) -> Option<u64> {
+        let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+        // fields[0] is field 3 (state)
+        fields.get(19)?.parse().ok()
+    }
+
+    /// Boot time as a Unix timestamp, from the btime line of /proc/stat
+    pub(super) fn parse_boot_time(stat: &str) -> Option<i64> {
+        stat.lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|btime| btime.trim().parse().ok())
+    }
+
+    /// Look up a variable in the NUL-separated contents of /proc/<pid>/environ
+    pub(super) fn parse_environ_var(environ: &[u8], name: &str) -> Option<String> {
+        environ.split(|byte| *byte == 0).find_map(|entry| {
+            let entry = std::str::from_utf8(entry).ok()?;
+            let (key, value) = entry.split_once('=')?;
+            (key == name).then(|| value.to_string())
+        })
+    }
+
+    /// Build an entry from /proc/<pid>/stat and /proc/<pid>/cmdline
+    pub(super) fn parse_process(pid: u32, stat: &str, cmdline: &[u8]) -> Option<ProcessEntry> {
+        let open = stat.find('(')?;
+        let close = stat.rfind(')')?;
+        let comm = &stat[open + 1..close];
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+
+        // Kernel threads and zombies have an empty command line
+        let command = cmdline
+            .split(|byte| *byte == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let command = if command.is_empty() {
+            format!("[{comm}]")
+        } else {
+            command
+        };
+
+        Some(ProcessEntry {
+            pid,
+            state: fields.first()?.to_string(),
+            ppid: fields.get(1)?.parse().ok()?,
+            pgid: fields.get(2)?.parse().ok()?,
+            command,
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::ProcessEntry;
+    use std::process::Command;
+
+    pub(super) fn ghost_task_id(_pid: u32) -> Option<String> {
+        // Other processes' environments cannot be read without /proc
+        None
+    }
+
+    /// When a process started, as a Unix timestamp, from its elapsed time
+    pub(super) fn start_time(pid: u32) -> Option<i64> {
+        let output = Command::new("ps")
+            .args(["-o", "etime=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let elapsed = parse_elapsed(String::from_utf8_lossy(&output.stdout).trim())?;
+        Some(chrono::Utc::now().timestamp() - elapsed)
+    }
+
+    /// Parse ps ETIME values such as "05:02", "01:02:03" or "2-01:02:03" into seconds
+    fn parse_elapsed(value: &str) -> Option<i64> {
+        let (days, rest) = match value.split_once('-') {
+            Some((days, rest)) => (days.parse::<i64>().ok()?, rest),
+            None => (0, value),
+        };
+        let mut seconds = 0;
+        for part in rest.split(':') {
+            seconds = seconds * 60 + part.parse::<i64>().ok()?;
+        }
+        Some(days * 86400 + seconds)
+    }
+
+    pub(super) fn list_processes() -> Vec<ProcessEntry> {
+        let Ok(output) = Command::new("ps")
+            .args(["-A", "-o", "pid=,ppid=,pgid=,state=,command="])
+            .output()
+        else {
+            return Vec::new();
+        };
+
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pid = fields.next()?.parse().ok()?;
+                let ppid = fields.next()?.parse().ok()?;
+                let pgid = fields.next()?.parse().ok()?;
+                // ps reports extra state flags such as "Ss"; keep the primary state
+                let state = fields.next()?.chars().next()?.to_string();
+                let command = fields.collect::<Vec<_>>().join(" ");
+                Some(ProcessEntry {
+                    pid,
+                    ppid,
+                    pgid,
+                    state,
+                    command,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pid: u32, ppid: u32, pgid: i32, command: &str) -> ProcessEntry {
+        ProcessEntry {
+            pid,
+            ppid,
+            pgid,
+            state: "S".to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_tree_orders_descendants_depth_first() {
+        let processes = vec![
+            entry(1, 0, 1, "init"),
+            entry(100, 1, 100, "npm run dev"),
+            entry(120, 100, 100, "node server.js"),
+            entry(110, 100, 100, "sh -c build"),
+            entry(111, 110, 100, "esbuild"),
+            entry(130, 120, 130, "worker"),
+            entry(200, 1, 200, "unrelated"),
+        ];
+
+        let tree = build_tree(100, 100, &processes);
+        let summary: Vec<(u32, usize, bool)> = tree
+            .iter()
+            .map(|node| (node.pid, node.depth, node.escaped))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (100, 0, false),
+                (110, 1, false),
+                (111, 2, false),
+                (120, 1, false),
+                (130, 2, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_tree_keeps_reparented_group_members() {
+        // The leader exited and its child was reparented to init
+        let processes = vec![entry(1, 0, 1, "init"), entry(101, 1, 100, "orphan")];
+
+        let tree = build_tree(100, 100, &processes);
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].pid, 101);
+        assert_eq!(tree[0].depth, 0);
+        assert!(!tree[0].escaped);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_process() {
+        let stat = "4242 (my (odd) cmd) S 4200 4242 4242 0 -1 4194560 0 0 0 0 0 0";
+        let process = platform::parse_process(4242, stat, b"node\0server.js\0").unwrap();
+        assert_eq!(process.ppid, 4200);
+        assert_eq!(process.pgid, 4242);
+        assert_eq!(process.state, "S");
+        assert_eq!(process.command, "node server.js");
+
+        let kernel_thread = platform::parse_process(2, "2 (kthreadd) S 0 0 0", b"").unwrap();
+        assert_eq!(kernel_thread.command, "[kthreadd]");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_environ_var() {
+        let environ = b"PATH=/usr/bin\0GHOST_TASK_ID=abc-123\0EMPTY=\0";
+        assert_eq!(
+            platform::parse_environ_var(environ, "GHOST_TASK_ID").as_deref(),
+            Some("abc-123")
+        );
+        assert_eq!(
+            platform::parse_environ_var(environ, "EMPTY").as_deref(),
+            Some("")
+        );
+        assert_eq!(platform::parse_environ_var(environ, "HOME"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_start_time() {
+        let stat = "4242 (my (odd) cmd) S 4200 4242 4242 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 123456 1000 200";
+        assert_eq!(platform::parse_start_ticks(stat), Some(123456));
+        assert_eq!(
+            platform::parse_boot_time("cpu  1 2 3\nbtime 1700000000\nprocesses 42\n"),
+            Some(1700000000)
+        );
+        assert!(platform::start_time(std::process::id()).is_some());
+    }
+
+    #[test]
+    fn test_started_during_needs_the_task_window() {
+        let task = Task {
+            id: "finished".to_string(),
+            pid: 100,
+            pgid: Some(100),
+            command: r#"["sleep","5"]"#.to_string(),
+            env: None,
+            cwd: None,
+            status: TaskStatus::Exited,
+            exit_code: Some(0),
+            started_at: 1000,
+            finished_at: Some(2000),
+            log_path: "/tmp/finished.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        };
+
+        assert!(started_during(&task, Some(1500)));
+        assert!(started_during(&task, Some(999)));
+        // A later process that was given the same process group ID
+        assert!(!started_during(&task, Some(5000)));
+        assert!(!started_during(&task, None));
+        let unfinished = Task {
+            finished_at: None,
+            ..task
+        };
+        assert!(!started_during(&unfinished, Some(1500)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_find_orphans_skips_unknown_task_ids() {
+        let mut foreign = std::process::Command::new("sleep")
+            .arg("5")
+            .env(TASK_ID_ENV, "task-of-another-data-dir")
+            .spawn()
+            .unwrap();
+
+        let orphans = find_orphans(&[]);
+        assert!(
+            !orphans
+                .iter()
+                .any(|orphan| orphan.process.pid == foreign.id())
+        );
+
+        foreign.kill().unwrap();
+        foreign.wait().unwrap();
+    }
+
+    #[test]
+    fn test_list_processes_includes_current_process() {
+        let pid = std::process::id();
+        assert!(list_processes().iter().any(|process| process.pid == pid));
+    }
+}
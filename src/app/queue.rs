@@ -0,0 +1,369 @@
+//! Named queues of tasks that start as slots free up
+//!
+//! There is no daemon. A queued task always runs under a supervisor, and the
+//! supervisor promotes the next waiting tasks when its task exits. Every ghost
+//! invocation promotes as well, which picks up anything missed, e.g. after a
+//! queue is resumed or a supervisor was killed. Chain steps (see `app::chain`)
+//! are started by the same promotion.
+
+use std::path::{Path, PathBuf};
+use std::process::Child;
+
+use rusqlite::Connection;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::app::config;
+use crate::app::error::{GhostError, Result};
+use crate::app::process::{self, ProcessInfo};
+use crate::app::run_options::RunOptions;
+use crate::app::storage::{self, Queue, Task, TaskStatus};
+use crate::app::supervisor::{self, SupervisorSpec};
+
+/// Exit code recorded for a task whose process could not be started
+const LAUNCH_FAILED_EXIT_CODE: i32 = 127;
+
+/// A queue together with its running and waiting tasks
+#[derive(Debug, Clone)]
+pub struct QueueSummary {
+    pub queue: Queue,
+    pub running: u32,
+    /// Waiting tasks in the order they will start
+    pub waiting: Vec<Task>,
+}
+
+/// Summarize one queue, or all queues when no name is given
+pub fn summaries(conn: &Connection, name: Option<&str>) -> Result<Vec<QueueSummary>> {
+    let queues = match name {
+        Some(name) => vec![storage::get_queue(conn, name)?],
+        None => storage::get_queues(conn)?,
+    };
+
+    queues
+        .into_iter()
+        .map(|queue| {
+            Ok(QueueSummary {
+                running: storage::count_running_in_queue(conn, &queue.name)?,
+                waiting: storage::get_queued_tasks(conn, Some(&queue.name))?,
+                queue,
+            })
+        })
+        .collect()
+}
+
+/// Add a task to the end of a queue
+///
+/// The task's environment and working directory are captured now, so it runs
+/// the same way no matter which ghost process eventually starts it.
+pub fn enqueue(
+    conn: &Connection,
+    queue: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<Task> {
+    let task_id = Uuid::new_v4().to_string();
+    let log_path = config::get_log_dir().join(format!("{task_id}.log"));
+    let cwd = cwd.or_else(|| std::env::current_dir().ok());
+    let env = config::env::inherit_env(env_vars);
+
+    storage::insert_queued_task(
+        conn,
+        &task_id,
+        &command,
+        Some(&env),
+        cwd.as_deref(),
+        &log_path,
+        queue,
+        options,
+    )?;
+
+    storage::get_task(conn, &task_id)
+}
+
+/// Start as many queued tasks as the queues' free slots allow, and the chain
+/// steps whose previous step finished the way they wait for
+///
+/// Returns the tasks that were started. A task that fails to start is
+/// recorded as exited with code 127, like a shell that cannot run a command,
+/// and the failure as its exit reason.
+pub fn promote(conn: &Connection) -> Result<Vec<Task>> {
+    let mut started = Vec::new();
+
+    let mut claimed = storage::claim_queued_tasks(conn)?;
+    claimed.extend(storage::claim_ready_tasks(conn)?);
+
+    for task in claimed {
+        match launch(&task) {
+            Ok(()) => started.push(storage::get_task(conn, &task.id)?),
+            Err(e) => record_launch_failure(conn, &task.id, &e)?,
+        }
+    }
+
+    Ok(started)
+}
+
+/// Record that a claimed task's process could not be started
+pub(crate) fn record_launch_failure(
+    conn: &Connection,
+    task_id: &str,
+    error: &GhostError,
+) -> Result<()> {
+    warn!("Failed to start queued task {task_id}: {error}");
+    storage::record_task_exit(
+        conn,
+        task_id,
+        Some(LAUNCH_FAILED_EXIT_CODE),
+        TaskStatus::Exited,
+        Some(&format!("failed to start: {error}")),
+    )
+}
+
+/// Start a claimed task under a supervisor, which records how it starts and exits
+fn launch(task: &Task) -> Result<()> {
+    let spec = SupervisorSpec {
+        command: serde_json::from_str(&task.command)?,
+        cwd: task.cwd.as_ref().map(PathBuf::from),
+        env: task
+            .env
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+        options: task.run_options(),
+        task_id: Some(task.id.clone()),
+    };
+
+    // The supervisor runs the pre-start hooks and spawns the task on its own
+    let mut supervisor = supervisor::launch_claimed(&spec)?;
+
+    // Reap the supervisor when it exits so long-running callers (TUI, MCP) keep no zombies
+    std::thread::spawn(move || {
+        let _ = supervisor.wait();
+    });
+
+    Ok(())
+}
+
+/// Spawn the process of a claimed task, keeping its task ID and log file
+pub(crate) fn spawn_claimed(
+    conn: &Connection,
+    task_id: &str,
+    spec: &SupervisorSpec,
+) -> Result<(ProcessInfo, Child)> {
+    let task = storage::get_task(conn, task_id)?;
+
+    let (info, child) = process::respawn_background_process(
+        task_id,
+        spec.command.clone(),
+        spec.cwd.clone(),
+        Path::new(&task.log_path),
+        spec.env.clone(),
+        &spec.options.limits,
+    )?;
+
+    if !storage::record_task_start(conn, task_id, info.pid, info.pgid, info.cgroup.as_deref())? {
+        // The task was given up on or ended while its process was being started
+        process::discard(info.pgid, child);
+        return Err(GhostError::TaskOperation {
+            task_id: task_id.to_string(),
+            message: "Task is no longer starting".to_string(),
+        });
+    }
+
+    Ok((info, child))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        storage::database::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn enqueue_sleep(conn: &Connection, queue: &str) -> Task {
+        enqueue(
+            conn,
+            queue,
+            vec!["sleep".to_string(), "1".to_string()],
+            None,
+            vec![("QUEUE_TEST".to_string(), "1".to_string())],
+            &RunOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_enqueue_captures_environment_and_position() {
+        let conn = setup_test_db();
+
+        let first = enqueue_sleep(&conn, "build");
+        let second = enqueue_sleep(&conn, "build");
+        let other = enqueue_sleep(&conn, "deploy");
+
+        assert_eq!(first.status, TaskStatus::Queued);
+        assert_eq!(first.queue.as_deref(), Some("build"));
+        assert_eq!(first.queue_position, Some(1));
+        assert_eq!(second.queue_position, Some(2));
+        assert_eq!(other.queue_position, Some(1));
+        assert!(first.env.unwrap().contains("QUEUE_TEST"));
+        assert!(first.cwd.is_some());
+
+        let queue = storage::get_queue(&conn, "build").unwrap();
+        assert_eq!(queue.parallel, 1);
+        assert!(!queue.paused);
+    }
+
+    #[test]
+    fn test_claim_respects_parallel_limit_and_order() {
+        let conn = setup_test_db();
+        let first = enqueue_sleep(&conn, "build");
+        let second = enqueue_sleep(&conn, "build");
+        let third = enqueue_sleep(&conn, "build");
+        storage::set_queue_parallel(&conn, "build", 2).unwrap();
+        storage::move_queued_task(&conn, &third.id, 1).unwrap();
+
+        let claimed: Vec<String> = storage::claim_queued_tasks(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(claimed, vec![third.id.clone(), first.id.clone()]);
+
+        // Both slots are taken until a claimed task finishes
+        assert!(storage::claim_queued_tasks(&conn).unwrap().is_empty());
+
+        storage::record_task_exit(&conn, &third.id, Some(0), TaskStatus::Exited, None).unwrap();
+        let claimed = storage::claim_queued_tasks(&conn).unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, second.id);
+    }
+
+    #[test]
+    fn test_stale_claim_is_failed_and_frees_its_slot() {
+        let conn = setup_test_db();
+        let first = enqueue_sleep(&conn, "build");
+        let second = enqueue_sleep(&conn, "build");
+
+        let claimed = storage::claim_queued_tasks(&conn).unwrap();
+        assert_eq!(claimed[0].id, first.id);
+        let starting = storage::get_task(&conn, &first.id).unwrap();
+        assert_eq!(starting.status, TaskStatus::Starting);
+        assert!(storage::claim_queued_tasks(&conn).unwrap().is_empty());
+
+        // The ghost process that claimed it died before spawning anything
+        conn.execute(
+            "UPDATE tasks SET started_at = started_at - ?1 WHERE id = ?2",
+            (storage::CLAIM_TIMEOUT_SECS, &first.id),
+        )
+        .unwrap();
+        let claimed = storage::claim_queued_tasks(&conn).unwrap();
+        assert_eq!(claimed[0].id, second.id);
+
+        let stale = storage::get_task(&conn, &first.id).unwrap();
+        assert_eq!(stale.status, TaskStatus::Unknown);
+        assert_eq!(
+            stale.exit_reason.as_deref(),
+            Some("did not start within 30s")
+        );
+    }
+
+    #[test]
+    fn test_claim_held_by_a_live_supervisor_is_not_failed() {
+        let conn = setup_test_db();
+        let first = enqueue_sleep(&conn, "build");
+        let second = enqueue_sleep(&conn, "build");
+        storage::claim_queued_tasks(&conn).unwrap();
+
+        // Its supervisor has run a pre-start hook for longer than the claim timeout
+        let mut hook = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        storage::set_supervisor_pid(&conn, &first.id, hook.id()).unwrap();
+        conn.execute(
+            "UPDATE tasks SET started_at = started_at - ?1 WHERE id = ?2",
+            (storage::CLAIM_TIMEOUT_SECS + 5, &first.id),
+        )
+        .unwrap();
+        assert_eq!(storage::fail_stale_claims(&conn).unwrap(), 0);
+        assert!(storage::claim_queued_tasks(&conn).unwrap().is_empty());
+        assert_eq!(
+            storage::get_task(&conn, &first.id).unwrap().status,
+            TaskStatus::Starting
+        );
+
+        // Once the supervisor is gone, the claim is given up on
+        hook.kill().unwrap();
+        hook.wait().unwrap();
+        let claimed = storage::claim_queued_tasks(&conn).unwrap();
+        assert_eq!(claimed[0].id, second.id);
+        assert_eq!(
+            storage::get_task(&conn, &first.id).unwrap().status,
+            TaskStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_given_up_claim_is_not_started() {
+        let conn = setup_test_db();
+        let task = enqueue_sleep(&conn, "build");
+        storage::claim_queued_tasks(&conn).unwrap();
+        storage::record_task_exit(&conn, &task.id, None, TaskStatus::Unknown, Some("gone"))
+            .unwrap();
+
+        let spec = SupervisorSpec {
+            command: vec!["sleep".to_string(), "30".to_string()],
+            cwd: None,
+            env: vec![],
+            options: RunOptions::default(),
+            task_id: Some(task.id.clone()),
+        };
+        assert!(spawn_claimed(&conn, &task.id, &spec).is_err());
+        let task = storage::get_task(&conn, &task.id).unwrap();
+        assert_eq!(task.status, TaskStatus::Unknown);
+        assert_eq!(task.pid, 0);
+
+        // Nor is a finished task brought back by a restart
+        assert!(!storage::mark_task_restarting(&conn, &task.id, false).unwrap());
+        assert!(!storage::record_task_restart(&conn, &task.id, 1, 1).unwrap());
+        assert_eq!(
+            storage::get_task(&conn, &task.id).unwrap().status,
+            TaskStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_paused_queue_starts_nothing() {
+        let conn = setup_test_db();
+        let task = enqueue_sleep(&conn, "build");
+        storage::set_queue_paused(&conn, "build", true).unwrap();
+
+        assert!(storage::claim_queued_tasks(&conn).unwrap().is_empty());
+
+        storage::set_queue_paused(&conn, "build", false).unwrap();
+        let claimed = storage::claim_queued_tasks(&conn).unwrap();
+        assert_eq!(claimed[0].id, task.id);
+    }
+
+    #[test]
+    fn test_clear_removes_only_waiting_tasks() {
+        let conn = setup_test_db();
+        let running = enqueue_sleep(&conn, "build");
+        enqueue_sleep(&conn, "build");
+        enqueue_sleep(&conn, "build");
+        storage::claim_queued_tasks(&conn).unwrap();
+
+        assert_eq!(storage::delete_queued_tasks(&conn, "build").unwrap(), 2);
+        assert!(storage::get_task(&conn, &running.id).is_ok());
+        assert!(
+            storage::get_queued_tasks(&conn, Some("build"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,544 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::error::{GhostError, Result};
+use crate::app::helpers;
+use crate::app::hooks::Hooks;
+use crate::app::limits::ResourceLimits;
+use crate::app::notify::{self, TerminalAlert, TerminalNotify};
+use crate::app::output_trigger::{OutputTrigger, TriggerAction};
+use crate::app::watch::{self, WatchOptions};
+
+/// What to do with a task whose log has not grown for its idle timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleAction {
+    /// Only flag the task as idle in `ghost list` and the TUI
+    Warn,
+    /// Stop the task
+    Stop,
+    /// Stop the task and start it again with the same command
+    Restart,
+}
+
+impl IdleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdleAction::Warn => "warn",
+            IdleAction::Stop => "stop",
+            IdleAction::Restart => "restart",
+        }
+    }
+}
+
+impl std::fmt::Display for IdleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for IdleAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(IdleAction::Warn),
+            "stop" => Ok(IdleAction::Stop),
+            "restart" => Ok(IdleAction::Restart),
+            _ => Err(format!(
+                "Invalid idle action: {s}. Valid options: warn, stop, restart"
+            )),
+        }
+    }
+}
+
+/// Options of `ghost run` that are stored with the task
+#[derive(clap::Args, Debug)]
+pub struct RunOptionArgs {
+    /// Mark the task idle after this long without log output (e.g. 30s, 10m, 1h)
+    #[arg(long, value_name = "DURATION")]
+    idle_timeout: Option<String>,
+
+    /// What to do with an idle task: warn, stop or restart (default: warn)
+    #[arg(long, value_name = "ACTION", requires = "idle_timeout")]
+    idle_action: Option<String>,
+
+    /// Memory limit (e.g. 512M, 2G); uses a cgroup when available, otherwise RLIMIT_AS
+    #[arg(long, value_name = "SIZE")]
+    limit_mem: Option<String>,
+
+    /// CPU time limit (e.g. 90s, 10m)
+    #[arg(long, value_name = "DURATION")]
+    limit_cpu_time: Option<String>,
+
+    /// Maximum number of open files
+    #[arg(long, value_name = "N")]
+    limit_nofile: Option<u64>,
+
+    /// CPU quota in percent of one CPU (e.g. 50, 200); requires cgroup v2 delegation
+    #[arg(long, value_name = "PERCENT")]
+    cpu_quota: Option<u32>,
+
+    /// Scheduling niceness, from -20 (highest priority) to 19 (lowest)
+    #[arg(long, value_name = "N", allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    nice: Option<i32>,
+
+    /// I/O priority: idle, best-effort[:0-7] or realtime[:0-7] (Linux only)
+    #[arg(long, value_name = "CLASS[:LEVEL]")]
+    ionice: Option<String>,
+
+    /// Restart the task when files under this path change (repeatable)
+    #[arg(long, value_name = "PATH")]
+    watch: Vec<PathBuf>,
+
+    /// Ignore changes to paths matching this pattern, e.g. target/ or *.log (repeatable)
+    #[arg(long, value_name = "PATTERN", requires = "watch")]
+    watch_ignore: Vec<String>,
+
+    /// How long changes have to settle before the task is restarted (default: 500ms)
+    #[arg(long, value_name = "DURATION", requires = "watch")]
+    watch_debounce: Option<String>,
+
+    /// Run an action when a line of the task's output matches this regex (repeatable)
+    #[arg(long, value_name = "REGEX")]
+    on_output: Vec<String>,
+
+    /// Action of the --on-output given in the same position: notify, ready, stop,
+    /// restart or exec:<command> (default: notify)
+    #[arg(long = "do", value_name = "ACTION", requires = "on_output")]
+    output_action: Vec<String>,
+
+    /// Shell command run before the task starts, and before every restart
+    #[arg(long, value_name = "COMMAND")]
+    pre_start: Option<String>,
+
+    /// Shell command run once the task has started, and after every restart
+    #[arg(long, value_name = "COMMAND")]
+    post_start: Option<String>,
+
+    /// Shell command run when the task has exited
+    #[arg(long, value_name = "COMMAND")]
+    on_exit: Option<String>,
+
+    /// Shell command run when the task has failed (non-zero exit code, or ended by a limit)
+    #[arg(long, value_name = "COMMAND")]
+    on_failure: Option<String>,
+
+    /// How long a hook may run before it is killed (default: 60s)
+    #[arg(long, value_name = "DURATION")]
+    hook_timeout: Option<String>,
+
+    /// Notify this terminal when the task ends: --notify=bell, osc9 or osc777 (default: bell)
+    #[arg(
+        long,
+        value_name = "ALERT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "bell"
+    )]
+    notify: Option<String>,
+}
+
+impl RunOptionArgs {
+    /// Parse `ghost run` option flags given as words, e.g. typed into the TUI
+    pub fn parse_flags(flags: &[String]) -> Result<RunOptions> {
+        #[derive(clap::Parser)]
+        #[command(no_binary_name = true)]
+        struct Flags {
+            #[command(flatten)]
+            options: RunOptionArgs,
+        }
+
+        let flags = <Flags as clap::Parser>::try_parse_from(flags).map_err(|e| {
+            // Keep clap's message on one line, without the usage that follows it
+            let message = e.to_string();
+            let message: Vec<&str> = message
+                .lines()
+                .take_while(|line| !line.starts_with("Usage"))
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect();
+            GhostError::InvalidArgument {
+                message: message.join(" ").trim_start_matches("error: ").to_string(),
+            }
+        })?;
+        flags.options.into_options()
+    }
+
+    /// Validate the flags and turn them into options
+    pub fn into_options(self) -> Result<RunOptions> {
+        let idle_timeout = self
+            .idle_timeout
+            .map(|value| helpers::parse_duration(&value))
+            .transpose()?
+            .map(|duration| duration.as_secs().max(1));
+
+        let idle_action = self
+            .idle_action
+            .map(|value| {
+                value
+                    .parse::<IdleAction>()
+                    .map_err(|message| GhostError::InvalidArgument { message })
+            })
+            .transpose()?;
+
+        let limits = ResourceLimits {
+            memory: self
+                .limit_mem
+                .map(|value| helpers::parse_size(&value))
+                .transpose()?,
+            cpu_time: self
+                .limit_cpu_time
+                .map(|value| helpers::parse_duration(&value))
+                .transpose()?
+                .map(|duration| duration.as_secs().max(1)),
+            nofile: self.limit_nofile,
+            cpu_quota: self.cpu_quota,
+            nice: self.nice,
+            ionice: self
+                .ionice
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|message| GhostError::InvalidArgument { message })
+                })
+                .transpose()?,
+        };
+
+        let watch = if self.watch.is_empty() {
+            None
+        } else {
+            let debounce_ms = match self.watch_debounce {
+                Some(value) => helpers::parse_duration(&value)?.as_millis() as u64,
+                None => watch::DEFAULT_DEBOUNCE_MS,
+            };
+            Some(WatchOptions {
+                paths: self.watch,
+                ignore: self.watch_ignore,
+                debounce_ms,
+            })
+        };
+
+        if self.output_action.len() > self.on_output.len() {
+            return Err(GhostError::InvalidArgument {
+                message: "Each --do needs an --on-output pattern before it".to_string(),
+            });
+        }
+        let mut actions = self.output_action.into_iter();
+        let on_output = self
+            .on_output
+            .into_iter()
+            .map(|pattern| {
+                let action = match actions.next() {
+                    Some(action) => action
+                        .parse()
+                        .map_err(|message| GhostError::InvalidArgument { message })?,
+                    None => TriggerAction::Notify,
+                };
+                OutputTrigger::new(pattern, action)
+            })
+            .collect::<Result<_>>()?;
+
+        let hooks = Hooks {
+            pre_start: self.pre_start,
+            post_start: self.post_start,
+            on_exit: self.on_exit,
+            on_failure: self.on_failure,
+            timeout: self
+                .hook_timeout
+                .map(|value| helpers::parse_duration(&value))
+                .transpose()?
+                .map(|duration| duration.as_secs().max(1)),
+        };
+
+        let notify = self
+            .notify
+            .map(|value| {
+                let alert: TerminalAlert = value
+                    .parse()
+                    .map_err(|message| GhostError::InvalidArgument { message })?;
+                let tty = notify::current_tty().ok_or_else(|| GhostError::InvalidArgument {
+                    message: "--notify needs a terminal to notify".to_string(),
+                })?;
+                Ok::<_, GhostError>(TerminalNotify { alert, tty })
+            })
+            .transpose()?;
+
+        Ok(RunOptions {
+            idle_timeout,
+            idle_action,
+            limits,
+            watch,
+            on_output,
+            hooks,
+            notify,
+        })
+    }
+}
+
+/// Per-task options given to `ghost run`, stored as JSON with the task
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunOptions {
+    /// Seconds without log output before the task counts as idle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+    /// Action taken once the task is idle (defaults to warn)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_action: Option<IdleAction>,
+    /// Resource limits and priorities applied to the task's process
+    #[serde(default, skip_serializing_if = "ResourceLimits::is_empty")]
+    pub limits: ResourceLimits,
+    /// Files to watch; the task is restarted when they change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch: Option<WatchOptions>,
+    /// Actions run when a line of the task's output matches a pattern
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_output: Vec<OutputTrigger>,
+    /// Commands run before the task starts, after it started and when it ends
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Terminal told when the task ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<TerminalNotify>,
+}
+
+impl RunOptions {
+    /// Whether no option differs from the defaults
+    pub fn is_empty(&self) -> bool {
+        *self == RunOptions::default()
+    }
+
+    /// Effective idle action, if an idle timeout is configured
+    pub fn idle_action(&self) -> Option<IdleAction> {
+        self.idle_timeout
+            .map(|_| self.idle_action.unwrap_or(IdleAction::Warn))
+    }
+
+    /// Whether the task needs a supervisor process to enforce these options
+    ///
+    /// Limits that can end the task are supervised so the reason can be recorded.
+    pub fn needs_supervisor(&self) -> bool {
+        matches!(
+            self.idle_action(),
+            Some(IdleAction::Stop | IdleAction::Restart)
+        ) || self.limits.can_end_task()
+            || self.limits.needs_cgroup()
+            || self.watch.is_some()
+            || !self.on_output.is_empty()
+            || !self.hooks.is_empty()
+            || self.notify.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_action_round_trip() {
+        for action in [IdleAction::Warn, IdleAction::Stop, IdleAction::Restart] {
+            assert_eq!(action.as_str().parse::<IdleAction>().unwrap(), action);
+        }
+        assert!("sleep".parse::<IdleAction>().is_err());
+    }
+
+    #[test]
+    fn test_default_options_are_empty() {
+        let options = RunOptions::default();
+        assert!(options.is_empty());
+        assert!(!options.needs_supervisor());
+        assert_eq!(serde_json::to_string(&options).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_idle_action_defaults_to_warn() {
+        let options = RunOptions {
+            idle_timeout: Some(600),
+            ..Default::default()
+        };
+        assert_eq!(options.idle_action(), Some(IdleAction::Warn));
+        assert!(!options.needs_supervisor());
+    }
+
+    #[test]
+    fn test_stop_and_restart_need_supervisor() {
+        for action in [IdleAction::Stop, IdleAction::Restart] {
+            let options = RunOptions {
+                idle_timeout: Some(600),
+                idle_action: Some(action),
+                ..Default::default()
+            };
+            assert!(options.needs_supervisor());
+        }
+    }
+
+    #[test]
+    fn test_limits_need_supervisor() {
+        let options = RunOptions {
+            limits: ResourceLimits {
+                memory: Some(1024 * 1024 * 1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(options.needs_supervisor());
+
+        let options = RunOptions {
+            limits: ResourceLimits {
+                nice: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(!options.needs_supervisor());
+        assert_eq!(
+            serde_json::to_string(&options).unwrap(),
+            r#"{"limits":{"nice":10}}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let flags: Vec<String> = [
+            "--idle-timeout",
+            "10m",
+            "--idle-action",
+            "stop",
+            "--nice",
+            "5",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect();
+        let options = RunOptionArgs::parse_flags(&flags).unwrap();
+        assert_eq!(options.idle_timeout, Some(600));
+        assert_eq!(options.idle_action, Some(IdleAction::Stop));
+        assert_eq!(options.limits.nice, Some(5));
+
+        assert!(RunOptionArgs::parse_flags(&[]).unwrap().is_empty());
+        let error = RunOptionArgs::parse_flags(&["--bogus".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("--bogus"), "{error}");
+        assert!(!error.contains("Usage"), "{error}");
+    }
+
+    #[test]
+    fn test_notify_needs_supervisor() {
+        let options = RunOptions {
+            notify: Some(TerminalNotify {
+                alert: TerminalAlert::Osc9,
+                tty: "/dev/pts/3".into(),
+            }),
+            ..Default::default()
+        };
+        assert!(options.needs_supervisor());
+        assert_eq!(
+            serde_json::to_string(&options).unwrap(),
+            r#"{"notify":{"alert":"osc9","tty":"/dev/pts/3"}}"#
+        );
+
+        let flags = ["--notify=beep".to_string()];
+        let error = RunOptionArgs::parse_flags(&flags).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Valid options: bell, osc9, osc777")
+        );
+    }
+
+    #[test]
+    fn test_watch_needs_supervisor() {
+        let options = RunOptions {
+            watch: Some(WatchOptions {
+                paths: vec!["src".into()],
+                ignore: vec![],
+                debounce_ms: 300,
+            }),
+            ..Default::default()
+        };
+        assert!(options.needs_supervisor());
+        assert_eq!(
+            serde_json::to_string(&options).unwrap(),
+            r#"{"watch":{"paths":["src"],"debounce_ms":300}}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_output_triggers() {
+        let flags = |flags: &[&str]| {
+            let flags: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+            RunOptionArgs::parse_flags(&flags)
+        };
+
+        let options = flags(&[
+            "--on-output",
+            "FATAL: connection lost",
+            "--do",
+            "restart",
+            "--on-output",
+            "Listening on",
+            "--do",
+            "exec:curl -s localhost:8080/warmup",
+            "--on-output",
+            "deprecated",
+        ])
+        .unwrap();
+        let actions: Vec<String> = options
+            .on_output
+            .iter()
+            .map(OutputTrigger::describe)
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                "/FATAL: connection lost/ restart",
+                "/Listening on/ exec:curl -s localhost:8080/warmup",
+                "/deprecated/ notify",
+            ]
+        );
+        assert!(options.needs_supervisor());
+        let json = serde_json::to_string(&options).unwrap();
+        assert!(
+            json.contains(r#"{"pattern":"deprecated","action":"notify"}"#),
+            "{json}"
+        );
+        assert_eq!(serde_json::from_str::<RunOptions>(&json).unwrap(), options);
+
+        assert!(flags(&["--do", "stop"]).is_err());
+        assert!(flags(&["--on-output", "a", "--do", "stop", "--do", "restart"]).is_err());
+        assert!(flags(&["--on-output", "a", "--do", "reboot"]).is_err());
+        assert!(flags(&["--on-output", "(", "--do", "stop"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let flags: Vec<String> = [
+            "--on-exit",
+            "docker compose down",
+            "--on-failure",
+            "notify-send 'build failed'",
+            "--hook-timeout",
+            "2m",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect();
+        let options = RunOptionArgs::parse_flags(&flags).unwrap();
+        assert_eq!(
+            options.hooks.on_exit.as_deref(),
+            Some("docker compose down")
+        );
+        assert_eq!(
+            options.hooks.on_failure.as_deref(),
+            Some("notify-send 'build failed'")
+        );
+        assert_eq!(options.hooks.timeout, Some(120));
+        assert!(options.needs_supervisor());
+        assert_eq!(
+            serde_json::to_string(&options).unwrap(),
+            r#"{"hooks":{"on_exit":"docker compose down","on_failure":"notify-send 'build failed'","timeout":120}}"#
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Delayed starts and recurring schedules
+//!
+//! A schedule holds a command and the time it runs next. When that time has
+//! come, the schedule creates a task that is started under a supervisor like a
+//! chain step, and either moves on to its next cron time or, if it ran only
+//! once, is removed. Due schedules are fired by `ghost scheduler`, which is
+//! started automatically when a schedule is added, and by every other ghost
+//! invocation, so runs missed while no scheduler was running happen late
+//! rather than never. Missed runs of a recurring schedule are not repeated.
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write as _};
+use std::os::unix::process::CommandExt as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use nix::unistd::setsid;
+use rusqlite::Connection;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::app::config;
+use crate::app::cron::Cron;
+use crate::app::error::{GhostError, Result};
+use crate::app::helpers::now_timestamp;
+use crate::app::process::{self, TASK_ID_ENV};
+use crate::app::queue;
+use crate::app::run_options::RunOptions;
+use crate::app::storage::{self, Schedule, Task};
+
+/// How often `ghost scheduler` looks for due schedules
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of the file holding the PID of the running scheduler
+const PID_FILE: &str = "scheduler.pid";
+
+/// Parse the time given to `ghost run --at`
+///
+/// Accepts "HH:MM[:SS]", meaning the next time the clock shows it, or a full
+/// local date and time such as "2026-05-01 14:30".
+pub fn parse_at(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let input = input.trim();
+    let invalid = || GhostError::InvalidArgument {
+        message: format!("Invalid time: {input}. Use HH:MM, HH:MM:SS or YYYY-MM-DD HH:MM"),
+    };
+
+    let naive = if let Some(time) = ["%H:%M", "%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(input, format).ok())
+    {
+        let today = now.date_naive().and_time(time);
+        if today > now.naive_local() {
+            today
+        } else {
+            today.date().succ_opt().ok_or_else(invalid)?.and_time(time)
+        }
+    } else {
+        [
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%dT%H:%M:%S",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(invalid)?
+    };
+
+    let at = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| GhostError::InvalidArgument {
+            message: format!("{input} does not exist in the local time zone"),
+        })?;
+    if at <= now {
+        return Err(GhostError::InvalidArgument {
+            message: format!("{input} is in the past"),
+        });
+    }
+
+    Ok(at)
+}
+
+/// Schedule a command to run once at the given time
+pub fn schedule_once(
+    conn: &Connection,
+    at: DateTime<Local>,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<Schedule> {
+    insert(conn, None, at.timestamp(), command, cwd, env_vars, options)
+}
+
+/// Schedule a command to run every time the cron expression matches
+pub fn schedule_cron(
+    conn: &Connection,
+    expression: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<Schedule> {
+    let next_run = Cron::parse(expression)?
+        .next_after(Local::now())
+        .ok_or_else(|| GhostError::InvalidArgument {
+            message: format!("Cron expression '{expression}' never matches"),
+        })?;
+    let expression = expression.trim();
+    insert(
+        conn,
+        Some(expression),
+        next_run.timestamp(),
+        command,
+        cwd,
+        env_vars,
+        options,
+    )
+}
+
+/// Store a schedule, capturing the environment and working directory now
+fn insert(
+    conn: &Connection,
+    cron: Option<&str>,
+    next_run: i64,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<Schedule> {
+    let id = Uuid::new_v4().to_string();
+    let cwd = cwd.or_else(|| std::env::current_dir().ok());
+    let env = config::env::inherit_env(env_vars);
+
+    storage::insert_schedule(
+        conn,
+        &id,
+        &command,
+        Some(&env),
+        cwd.as_deref(),
+        options,
+        cron,
+        next_run,
+    )?;
+
+    storage::get_schedule(conn, &id)
+}
+
+/// Create a task for every schedule that is due, and start them
+///
+/// Returns the created tasks. Like queue promotion, the schedules are claimed
+/// in a single write transaction so concurrent ghost processes never run the
+/// same schedule twice.
+pub fn fire_due(conn: &Connection) -> Result<Vec<Task>> {
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let created = match create_due_tasks(conn, now_timestamp()) {
+        Ok(created) => {
+            conn.execute_batch("COMMIT")?;
+            created
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+    };
+
+    if created.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    queue::promote(conn)?;
+    created
+        .iter()
+        .map(|task_id| storage::get_task(conn, task_id))
+        .collect()
+}
+
+/// Insert a waiting task for each due schedule and move the schedules on
+fn create_due_tasks(conn: &Connection, now: i64) -> Result<Vec<String>> {
+    let mut created = Vec::new();
+
+    for schedule in storage::get_due_schedules(conn, now)? {
+        let task_id = Uuid::new_v4().to_string();
+        let log_path = config::get_log_dir().join(format!("{task_id}.log"));
+        let command: Vec<String> = serde_json::from_str(&schedule.command)?;
+        let env: Option<Vec<(String, String)>> = schedule
+            .env
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()?;
+
+        // Without a previous task, the next promotion starts it under a supervisor
+        storage::insert_waiting_task(
+            conn,
+            &task_id,
+            &command,
+            env.as_deref(),
+            schedule.cwd.as_deref().map(Path::new),
+            &log_path,
+            None,
+            &schedule.run_options(),
+        )?;
+        storage::set_task_schedule(conn, &task_id, &schedule.id)?;
+
+        let next_run = match schedule.cron.as_deref() {
+            Some(expression) => Cron::parse(expression)?
+                .next_after(Local::now().max(local_time(schedule.next_run)))
+                .map(|next| next.timestamp()),
+            None => None,
+        };
+        match next_run {
+            Some(next_run) => {
+                storage::record_schedule_run(conn, &schedule.id, now, &task_id, next_run)?
+            }
+            None => storage::delete_schedule(conn, &schedule.id)?,
+        }
+
+        created.push(task_id);
+    }
+
+    Ok(created)
+}
+
+fn local_time(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_else(Local::now)
+}
+
+/// Entry point of `ghost scheduler`: fire due schedules until interrupted
+///
+/// With `exit_when_idle`, the scheduler exits once no schedules are left,
+/// which is how an automatically started scheduler ends.
+pub fn run(conn: &Connection, exit_when_idle: bool) -> Result<()> {
+    let pid_file = config::get_data_dir().join(PID_FILE);
+    claim_pid_file(&pid_file)?;
+    info!("Scheduler started (PID {})", std::process::id());
+
+    let result = poll(conn, exit_when_idle);
+
+    let _ = std::fs::remove_file(&pid_file);
+    info!("Scheduler stopped");
+    result
+}
+
+fn poll(conn: &Connection, exit_when_idle: bool) -> Result<()> {
+    loop {
+        match fire_due(conn) {
+            Ok(tasks) => {
+                for task in tasks {
+                    let schedule_id = task.schedule_id.as_deref().unwrap_or("-");
+                    info!("Schedule {schedule_id} started task {}", task.id);
+                    println!("Schedule {schedule_id} started task {}", task.id);
+                }
+            }
+            Err(e) => warn!("Failed to run due schedules: {e}"),
+        }
+
+        if exit_when_idle && storage::get_schedules(conn)?.is_empty() {
+            return Ok(());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Start a scheduler in the background unless one is already running
+pub fn ensure_running() -> Result<()> {
+    if running_pid(&config::get_data_dir().join(PID_FILE)).is_some() {
+        return Ok(());
+    }
+
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.args(["scheduler", "--exit-when-idle"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // A scheduler started from inside a task is not part of that task
+        .env_remove(TASK_ID_ENV);
+
+    // Detach the scheduler from the launching terminal and process group
+    unsafe {
+        cmd.pre_exec(|| {
+            setsid()?;
+            Ok(())
+        });
+    }
+
+    cmd.spawn().map_err(|e| GhostError::ProcessSpawn {
+        message: format!("Failed to start scheduler: {e}"),
+    })?;
+
+    Ok(())
+}
+
+/// PID of the scheduler recorded in the PID file, if it is still alive
+fn running_pid(pid_file: &Path) -> Option<u32> {
+    std::fs::read_to_string(pid_file)
+        .ok()
+        .and_then(|content| content.trim().parse().ok())
+        .filter(|&pid| process::exists(pid))
+}
+
+/// Record this process as the scheduler, failing if another one is running
+fn claim_pid_file(pid_file: &Path) -> Result<()> {
+    // Retry once after removing the file of a scheduler that is gone
+    for _ in 0..2 {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(pid_file)
+        {
+            Ok(mut file) => {
+                write!(file, "{}", std::process::id())?;
+                return Ok(());
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if let Some(pid) = running_pid(pid_file) {
+                    return Err(GhostError::ProcessOperation {
+                        message: format!("Scheduler is already running (PID {pid})"),
+                    });
+                }
+                std::fs::remove_file(pid_file)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(GhostError::ProcessOperation {
+        message: "Another scheduler is starting".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        storage::database::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn command() -> Vec<String> {
+        vec!["echo".to_string(), "hello".to_string()]
+    }
+
+    #[test]
+    fn test_parse_at_clock_time_is_the_next_occurrence() {
+        let now = local(2026, 3, 10, 12, 0);
+        assert_eq!(parse_at("14:30", now).unwrap(), local(2026, 3, 10, 14, 30));
+        assert_eq!(parse_at("02:00", now).unwrap(), local(2026, 3, 11, 2, 0));
+        assert_eq!(parse_at("12:00", now).unwrap(), local(2026, 3, 11, 12, 0));
+    }
+
+    #[test]
+    fn test_parse_at_full_date() {
+        let now = local(2026, 3, 10, 12, 0);
+        assert_eq!(
+            parse_at("2026-05-01 14:30", now).unwrap(),
+            local(2026, 5, 1, 14, 30)
+        );
+        assert_eq!(
+            parse_at("2026-05-01T08:00", now).unwrap(),
+            local(2026, 5, 1, 8, 0)
+        );
+        assert!(parse_at("2026-03-01 10:00", now).is_err());
+        assert!(parse_at("25:00", now).is_err());
+        assert!(parse_at("tomorrow", now).is_err());
+    }
+
+    #[test]
+    fn test_due_one_off_schedule_creates_a_task_and_is_removed() {
+        let conn = setup_test_db();
+        let schedule = schedule_once(
+            &conn,
+            Local::now() + chrono::Duration::minutes(5),
+            command(),
+            None,
+            vec![],
+            &RunOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(schedule.cron, None);
+
+        assert!(create_due_tasks(&conn, now_timestamp()).unwrap().is_empty());
+
+        let created = create_due_tasks(&conn, schedule.next_run).unwrap();
+        assert_eq!(created.len(), 1);
+        let task = storage::get_task(&conn, &created[0]).unwrap();
+        assert_eq!(task.schedule_id.as_deref(), Some(schedule.id.as_str()));
+        assert_eq!(task.status, storage::TaskStatus::Waiting);
+        assert_eq!(task.command, schedule.command);
+        assert!(storage::get_schedules(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_due_cron_schedule_moves_to_its_next_run() {
+        let conn = setup_test_db();
+        let schedule = schedule_cron(
+            &conn,
+            "*/5 * * * *",
+            command(),
+            None,
+            vec![],
+            &RunOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(schedule.cron.as_deref(), Some("*/5 * * * *"));
+        assert!(schedule.next_run > now_timestamp());
+
+        let created = create_due_tasks(&conn, schedule.next_run).unwrap();
+        assert_eq!(created.len(), 1);
+
+        let schedule = storage::get_schedule(&conn, &schedule.id).unwrap();
+        assert_eq!(schedule.last_task_id.as_deref(), Some(created[0].as_str()));
+        assert!(schedule.next_run > schedule.last_run.unwrap());
+        assert_eq!(schedule.next_run % 300, 0);
+    }
+
+    #[test]
+    fn test_impossible_cron_schedule_is_rejected() {
+        let conn = setup_test_db();
+        let result = schedule_cron(
+            &conn,
+            "0 0 31 2 *",
+            command(),
+            None,
+            vec![],
+            &RunOptions::default(),
+        );
+        assert!(matches!(result, Err(GhostError::InvalidArgument { .. })));
+    }
+}
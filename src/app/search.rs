@@ -0,0 +1,371 @@
+//! Task list search used by the TUI
+//!
+//! A search is a list of terms that all have to match. Plain words are
+//! matched fuzzily against the command, name and directory of a task, as an
+//! ID prefix, or as a listening port when they are numbers. `key:value` terms
+//! narrow the search to one field:
+//!
+//! - `status:running` matches the status, or a prefix of it (`status:run`)
+//! - `id:3fa8` matches an ID prefix
+//! - `cmd:cargo` and `name:api` match part of the command or name
+//! - `cwd:~/api` matches directories below a path, `cwd:api` part of one
+//! - `port:8080` matches a listening port
+//!
+//! A `-` before a field term inverts it (`-status:exited`), and `@name`
+//! inserts the terms of a saved filter preset.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::app::error::{GhostError, Result};
+use crate::app::helpers;
+use crate::app::port_detector;
+use crate::app::process_state;
+use crate::app::storage::{self, FilterPreset, Task, TaskStatus};
+
+/// A parsed search
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    kind: TermKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TermKind {
+    Text(String),
+    Status(String),
+    Id(String),
+    Command(String),
+    Name(String),
+    Cwd(String),
+    Port(u16),
+}
+
+impl Query {
+    /// Parse a search; `@name` refers to one of `presets`
+    pub fn parse(input: &str, presets: &[FilterPreset]) -> Result<Query> {
+        let mut terms = Vec::new();
+        if input.trim().is_empty() {
+            return Ok(Query { terms });
+        }
+
+        for word in helpers::parse_command(input)? {
+            if let Some(name) = word.strip_prefix('@') {
+                let preset = presets
+                    .iter()
+                    .find(|preset| preset.name == name)
+                    .ok_or_else(|| GhostError::InvalidArgument {
+                        message: format!("Unknown filter preset: {name}"),
+                    })?;
+                // Presets cannot refer to other presets
+                terms.extend(Query::parse(&preset.query, &[])?.terms);
+            } else if let Some(term) = parse_term(&word)? {
+                terms.push(term);
+            }
+        }
+        Ok(Query { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Whether matching looks at listening ports, which are slow to detect
+    pub fn needs_ports(&self) -> bool {
+        self.terms.iter().any(|term| match &term.kind {
+            TermKind::Port(_) => true,
+            TermKind::Text(text) => text.parse::<u16>().is_ok(),
+            _ => false,
+        })
+    }
+
+    /// Whether a task matches every term; `ports` are the task's listening ports
+    pub fn matches(&self, task: &Task, ports: &[u16]) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.kind.matches(task, ports) != term.negated)
+    }
+}
+
+fn parse_term(word: &str) -> Result<Option<Term>> {
+    let (negated, field) = match word.strip_prefix('-') {
+        Some(rest) if rest.contains(':') => (true, rest),
+        _ => (false, word),
+    };
+
+    let Some((key, value)) = field.split_once(':') else {
+        return Ok(Some(Term {
+            negated: false,
+            kind: TermKind::Text(word.to_lowercase()),
+        }));
+    };
+    // A field without a value yet, e.g. while typing `status:`
+    if value.is_empty() && is_field(key) {
+        return Ok(None);
+    }
+
+    let value = value.to_lowercase();
+    let kind = match key {
+        "status" => TermKind::Status(value),
+        "id" => TermKind::Id(value),
+        "cmd" => TermKind::Command(value),
+        "name" => TermKind::Name(value),
+        "cwd" => TermKind::Cwd(expand_home(&value)),
+        "port" => TermKind::Port(value.parse().map_err(|_| GhostError::InvalidArgument {
+            message: format!("Invalid port: {value}"),
+        })?),
+        // Anything else, e.g. a URL, is searched for as it is
+        _ => {
+            return Ok(Some(Term {
+                negated: false,
+                kind: TermKind::Text(word.to_lowercase()),
+            }));
+        }
+    };
+    Ok(Some(Term { negated, kind }))
+}
+
+fn is_field(key: &str) -> bool {
+    matches!(key, "status" | "id" | "cmd" | "name" | "cwd" | "port")
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{rest}", home.display()).to_lowercase()
+        }
+        _ => path.to_string(),
+    }
+}
+
+impl TermKind {
+    fn matches(&self, task: &Task, ports: &[u16]) -> bool {
+        match self {
+            TermKind::Text(text) => {
+                fuzzy_match(text, &command_text(task))
+                    || task
+                        .name
+                        .as_deref()
+                        .is_some_and(|name| fuzzy_match(text, name))
+                    || task
+                        .cwd
+                        .as_deref()
+                        .is_some_and(|cwd| fuzzy_match(text, cwd))
+                    || task.id.starts_with(text.as_str())
+                    || text.parse().is_ok_and(|port: u16| ports.contains(&port))
+            }
+            TermKind::Status(status) => {
+                process_state::status_label(task).starts_with(status.as_str())
+                    || task.status.as_str().starts_with(status.as_str())
+            }
+            TermKind::Id(prefix) => task.id.starts_with(prefix.as_str()),
+            TermKind::Command(text) => command_text(task).to_lowercase().contains(text),
+            TermKind::Name(text) => task
+                .name
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase().contains(text)),
+            TermKind::Cwd(path) => task.cwd.as_deref().is_some_and(|cwd| {
+                let cwd = cwd.to_lowercase();
+                if path.starts_with('/') {
+                    cwd.starts_with(path.as_str())
+                } else {
+                    cwd.contains(path.as_str())
+                }
+            }),
+            TermKind::Port(port) => ports.contains(port),
+        }
+    }
+}
+
+fn command_text(task: &Task) -> String {
+    serde_json::from_str::<Vec<String>>(&task.command)
+        .map(|command| command.join(" "))
+        .unwrap_or_else(|_| task.command.clone())
+}
+
+/// Whether the characters of `pattern` appear in `text` in order, ignoring case
+pub fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| text.any(|c| c == wanted))
+}
+
+/// Listening ports of running tasks, keyed by task ID
+///
+/// Empty when `lsof` is not available.
+pub fn task_ports(tasks: &[Task]) -> HashMap<String, Vec<u16>> {
+    let pids: Vec<u32> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Running)
+        .map(|task| task.pid)
+        .collect();
+    let Ok(mut ports_by_pid) = port_detector::detect_listening_ports_for_pids(&pids) else {
+        return HashMap::new();
+    };
+
+    tasks
+        .iter()
+        .filter_map(|task| {
+            let ports = ports_by_pid.remove(&task.pid)?;
+            let ports = ports
+                .iter()
+                .filter_map(|port| port.local_addr.rsplit_once(':')?.1.parse().ok())
+                .collect();
+            Some((task.id.clone(), ports))
+        })
+        .collect()
+}
+
+/// Save a search under a name, replacing any preset with the same name
+pub fn save_preset(conn: &Connection, name: &str, query: &str) -> Result<FilterPreset> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(GhostError::InvalidArgument {
+            message: format!("Invalid preset name: {name}. Use letters, digits, '-', '_' and '.'"),
+        });
+    }
+    if Query::parse(query, &[])?.is_empty() {
+        return Err(GhostError::InvalidArgument {
+            message: "Enter a search to save".to_string(),
+        });
+    }
+
+    storage::insert_filter_preset(conn, name, query.trim())?;
+    storage::get_filter_presets(conn)?
+        .into_iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| GhostError::InvalidArgument {
+            message: format!("Filter preset not found: {name}"),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, command: &[&str], cwd: &str, status: TaskStatus) -> Task {
+        Task {
+            id: id.to_string(),
+            pid: 1,
+            pgid: None,
+            command: serde_json::to_string(command).unwrap(),
+            env: None,
+            cwd: Some(cwd.to_string()),
+            status,
+            exit_code: None,
+            started_at: 0,
+            finished_at: None,
+            log_path: String::new(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        }
+    }
+
+    fn matching<'a>(query: &str, tasks: &'a [Task]) -> Vec<&'a str> {
+        let query = Query::parse(query, &[]).unwrap();
+        tasks
+            .iter()
+            .filter(|task| query.matches(task, &[]))
+            .map(|task| task.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("crn", "cargo run"));
+        assert!(fuzzy_match("CR", "cargo run"));
+        assert!(!fuzzy_match("rc", "cargo"));
+        assert!(fuzzy_match("", "anything"));
+    }
+
+    #[test]
+    fn test_plain_words_match_any_field() {
+        let mut api = task("a1", &["cargo", "run"], "/work/api", TaskStatus::Running);
+        api.name = Some("backend".to_string());
+        let tasks = vec![
+            api,
+            task("b2", &["npm", "start"], "/work/web", TaskStatus::Exited),
+        ];
+
+        assert_eq!(matching("crun", &tasks), vec!["a1"]);
+        assert_eq!(matching("backend", &tasks), vec!["a1"]);
+        assert_eq!(matching("web", &tasks), vec!["b2"]);
+        assert_eq!(matching("b2", &tasks), vec!["b2"]);
+        assert_eq!(matching("npm web", &tasks), vec!["b2"]);
+        assert!(matching("npm api", &tasks).is_empty());
+    }
+
+    #[test]
+    fn test_field_terms() {
+        let tasks = vec![
+            task("a1", &["cargo", "run"], "/work/api", TaskStatus::Running),
+            task(
+                "a2",
+                &["cargo", "test"],
+                "/work/api/sub",
+                TaskStatus::Exited,
+            ),
+            task("b3", &["npm", "start"], "/work/web", TaskStatus::Killed),
+        ];
+
+        assert_eq!(matching("status:run", &tasks), vec!["a1"]);
+        assert_eq!(matching("-status:exited", &tasks), vec!["a1", "b3"]);
+        assert_eq!(matching("id:a", &tasks), vec!["a1", "a2"]);
+        assert_eq!(matching("cmd:TEST", &tasks), vec!["a2"]);
+        assert_eq!(matching("cwd:/work/api", &tasks), vec!["a1", "a2"]);
+        assert_eq!(matching("cwd:web", &tasks), vec!["b3"]);
+        assert_eq!(matching("cwd:/work/api status:exited", &tasks), vec!["a2"]);
+        // A field being typed does not filter yet
+        assert_eq!(matching("status:", &tasks).len(), 3);
+    }
+
+    #[test]
+    fn test_port_terms() {
+        let web = task("b2", &["npm", "start"], "/work/web", TaskStatus::Running);
+        let query = Query::parse("port:8080", &[]).unwrap();
+        assert!(query.needs_ports());
+        assert!(query.matches(&web, &[8080]));
+        assert!(!query.matches(&web, &[3000]));
+        assert!(Query::parse("3000", &[]).unwrap().matches(&web, &[3000]));
+        assert!(Query::parse("port:http", &[]).is_err());
+        assert!(!Query::parse("cmd:npm", &[]).unwrap().needs_ports());
+    }
+
+    #[test]
+    fn test_presets() {
+        let presets = vec![FilterPreset {
+            name: "live".to_string(),
+            query: "status:running cwd:/work".to_string(),
+            created_at: 0,
+        }];
+        let query = Query::parse("@live npm", &presets).unwrap();
+        assert_eq!(
+            query,
+            Query::parse("status:running cwd:/work npm", &[]).unwrap()
+        );
+
+        let error = Query::parse("@missing", &presets).unwrap_err().to_string();
+        assert!(error.contains("Unknown filter preset: missing"), "{error}");
+    }
+}
@@ -0,0 +1,485 @@
+//! CPU, memory, thread and I/O usage of running tasks
+//!
+//! Usage is summed over every process in a task's process group. On Linux it
+//! is read from `/proc/<pid>/stat`, `status` and `io`; elsewhere `ps` is used,
+//! which provides CPU time and RSS only. Either way all processes are listed
+//! once per sample, however many tasks are running.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::app::storage::{Task, TaskStatus};
+
+/// Number of samples kept per task for sparklines
+pub const HISTORY_LEN: usize = 60;
+
+/// Samples taken closer together than this are too noisy for a CPU percentage
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Raw usage counters of one process group at a point in time
+#[derive(Debug, Clone)]
+pub struct GroupSample {
+    pub taken_at: Instant,
+    /// Total CPU time (user + system) consumed so far
+    pub cpu_time: Duration,
+    pub rss_bytes: u64,
+    pub threads: Option<u64>,
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+    pub processes: usize,
+}
+
+impl GroupSample {
+    /// Sample every process in the process group; None if the group has no processes
+    pub fn take(pgid: i32) -> Option<Self> {
+        Self::take_all(&[pgid]).remove(&pgid)
+    }
+
+    /// Sample several process groups in a single pass over all processes,
+    /// keyed by process group; groups without processes are left out
+    pub fn take_all(pgids: &[i32]) -> HashMap<i32, Self> {
+        let pgids: HashSet<i32> = pgids.iter().copied().collect();
+        platform::sample_groups(&pgids, Instant::now())
+    }
+}
+
+/// Resource usage of a task, as reported to users
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskStats {
+    /// CPU usage since the previous sample, in percent of one CPU
+    pub cpu_percent: Option<f64>,
+    pub rss_bytes: u64,
+    pub threads: Option<u64>,
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+    pub processes: usize,
+}
+
+impl TaskStats {
+    /// Build stats from the current sample, using the previous one for the CPU percentage
+    pub fn from_samples(previous: Option<&GroupSample>, current: &GroupSample) -> Self {
+        let cpu_percent = previous.and_then(|previous| {
+            let wall = current.taken_at.checked_duration_since(previous.taken_at)?;
+            if wall.is_zero() {
+                return None;
+            }
+            let cpu = current.cpu_time.saturating_sub(previous.cpu_time);
+            Some(cpu.as_secs_f64() / wall.as_secs_f64() * 100.0)
+        });
+
+        Self {
+            cpu_percent,
+            rss_bytes: current.rss_bytes,
+            threads: current.threads,
+            read_bytes: current.read_bytes,
+            write_bytes: current.write_bytes,
+            processes: current.processes,
+        }
+    }
+}
+
+/// Take two samples `window` apart and report the task's usage in between
+pub fn sample_task(task: &Task, window: Duration) -> Option<TaskStats> {
+    let pgid = running_pgid(task)?;
+    let first = GroupSample::take(pgid)?;
+    std::thread::sleep(window);
+    let second = GroupSample::take(pgid)?;
+    Some(TaskStats::from_samples(Some(&first), &second))
+}
+
+/// Process group to sample for a task, if it is running
+pub fn running_pgid(task: &Task) -> Option<i32> {
+    if task.status != TaskStatus::Running || task.pid == 0 {
+        return None;
+    }
+    Some(task.pgid.unwrap_or(task.pid as i32))
+}
+
+/// Keeps the latest stats and a short history for each running task
+#[derive(Debug, Default)]
+pub struct StatsSampler {
+    previous: HashMap<String, GroupSample>,
+    latest: HashMap<String, TaskStats>,
+    history: HashMap<String, VecDeque<TaskStats>>,
+}
+
+impl StatsSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sample all running tasks; data of tasks that are no longer running is dropped
+    pub fn sample(&mut self, tasks: &[Task]) {
+        let now = Instant::now();
+        let mut sampled = HashMap::new();
+
+        // Keep the last result when called again too soon (e.g. after a key press)
+        let mut due = Vec::new();
+        for task in tasks {
+            let Some(pgid) = running_pgid(task) else {
+                continue;
+            };
+            match self.previous.get(&task.id) {
+                Some(previous) if now.duration_since(previous.taken_at) < MIN_SAMPLE_INTERVAL => {
+                    sampled.insert(task.id.clone(), previous.clone());
+                }
+                _ => due.push((task, pgid)),
+            }
+        }
+
+        let pgids: Vec<i32> = due.iter().map(|(_, pgid)| *pgid).collect();
+        let samples = GroupSample::take_all(&pgids);
+
+        for (task, pgid) in due {
+            // Tasks sharing a process group get the same sample
+            let Some(sample) = samples.get(&pgid).cloned() else {
+                continue;
+            };
+
+            let stats = TaskStats::from_samples(self.previous.get(&task.id), &sample);
+            let history = self.history.entry(task.id.clone()).or_default();
+            if history.len() == HISTORY_LEN {
+                history.pop_front();
+            }
+            history.push_back(stats.clone());
+            self.latest.insert(task.id.clone(), stats);
+            sampled.insert(task.id.clone(), sample);
+        }
+
+        self.latest.retain(|id, _| sampled.contains_key(id));
+        self.history.retain(|id, _| sampled.contains_key(id));
+        self.previous = sampled;
+    }
+
+    /// Latest stats of a task
+    pub fn get(&self, task_id: &str) -> Option<&TaskStats> {
+        self.latest.get(task_id)
+    }
+
+    /// Latest stats of all sampled tasks, keyed by task ID
+    pub fn latest(&self) -> &HashMap<String, TaskStats> {
+        &self.latest
+    }
+
+    /// Recent stats of a task, oldest first
+    pub fn history(&self, task_id: &str) -> Vec<&TaskStats> {
+        self.history
+            .get(task_id)
+            .map(|history| history.iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::GroupSample;
+    use std::collections::{HashMap, HashSet};
+    use std::time::{Duration, Instant};
+
+    pub(super) fn sample_groups(
+        pgids: &HashSet<i32>,
+        taken_at: Instant,
+    ) -> HashMap<i32, GroupSample> {
+        // SAFETY: sysconf has no preconditions
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+
+        let mut samples = HashMap::new();
+        if pgids.is_empty() {
+            return samples;
+        }
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return samples;
+        };
+
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(pid) = file_name
+                .to_str()
+                .filter(|name| name.parse::<u32>().is_ok())
+            else {
+                continue;
+            };
+
+            // Processes may exit at any point while we read their files
+            let Ok(stat) = std::fs::read_to_string(format!("/proc/{pid}/stat")) else {
+                continue;
+            };
+            let Some(stat) = parse_stat(&stat) else {
+                continue;
+            };
+            if !pgids.contains(&stat.pgrp) {
+                continue;
+            }
+
+            let sample = samples.entry(stat.pgrp).or_insert_with(|| GroupSample {
+                taken_at,
+                cpu_time: Duration::ZERO,
+                rss_bytes: 0,
+                threads: Some(0),
+                read_bytes: None,
+                write_bytes: None,
+                processes: 0,
+            });
+            sample.processes += 1;
+            sample.cpu_time += Duration::from_millis(
+                (stat.utime + stat.stime).saturating_mul(1000) / ticks_per_second,
+            );
+
+            if let Ok(status) = std::fs::read_to_string(format!("/proc/{pid}/status")) {
+                let (rss_kib, threads) = parse_status(&status);
+                sample.rss_bytes += rss_kib * 1024;
+                sample.threads = sample.threads.map(|total| total + threads);
+            }
+
+            // io is only readable for our own processes
+            if let Ok(io) = std::fs::read_to_string(format!("/proc/{pid}/io")) {
+                let (read_bytes, write_bytes) = parse_io(&io);
+                sample.read_bytes = Some(sample.read_bytes.unwrap_or(0) + read_bytes);
+                sample.write_bytes = Some(sample.write_bytes.unwrap_or(0) + write_bytes);
+            }
+        }
+
+        samples
+    }
+
+    pub(super) struct ProcStat {
+        pub pgrp: i32,
+        pub utime: u64,
+        pub stime: u64,
+    }
+
+    /// Parse the fields we need from /proc/<pid>/stat
+    pub(super) fn parse_stat(contents: &str) -> Option<ProcStat> {
+        // The command name may contain spaces and parentheses, so split after the last ')'
+        let after_comm = &contents[contents.rfind(')')? + 1..];
+        let fields: Vec<&str> = after_comm.split_whitespace().collect();
+
+        // Field numbers in proc(5) start at 1 with pid and comm; fields[0] is field 3 (state)
+        Some(ProcStat {
+            pgrp: fields.get(2)?.parse().ok()?,
+            utime: fields.get(11)?.parse().ok()?,
+            stime: fields.get(12)?.parse().ok()?,
+        })
+    }
+
+    /// RSS in KiB and thread count from /proc/<pid>/status
+    pub(super) fn parse_status(contents: &str) -> (u64, u64) {
+        let value = |key: &str| {
+            contents
+                .lines()
+                .find_map(|line| line.strip_prefix(key))
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0)
+        };
+        (value("VmRSS:"), value("Threads:"))
+    }
+
+    /// Bytes read from and written to storage, from /proc/<pid>/io
+    pub(super) fn parse_io(contents: &str) -> (u64, u64) {
+        let value = |key: &str| {
+            contents
+                .lines()
+                .find_map(|line| line.strip_prefix(key))
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(0)
+        };
+        (value("read_bytes:"), value("write_bytes:"))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::GroupSample;
+    use std::collections::{HashMap, HashSet};
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    pub(super) fn sample_groups(
+        pgids: &HashSet<i32>,
+        taken_at: Instant,
+    ) -> HashMap<i32, GroupSample> {
+        let mut samples = HashMap::new();
+        if pgids.is_empty() {
+            return samples;
+        }
+        let Ok(output) = Command::new("ps")
+            .args(["-A", "-o", "pgid=,rss=,time="])
+            .output()
+        else {
+            return samples;
+        };
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [group, rss_kib, time] = fields[..] else {
+                continue;
+            };
+            let Some(pgid) = group
+                .parse::<i32>()
+                .ok()
+                .filter(|pgid| pgids.contains(pgid))
+            else {
+                continue;
+            };
+            let sample = samples.entry(pgid).or_insert_with(|| GroupSample {
+                taken_at,
+                cpu_time: Duration::ZERO,
+                rss_bytes: 0,
+                threads: None,
+                read_bytes: None,
+                write_bytes: None,
+                processes: 0,
+            });
+            sample.processes += 1;
+            sample.rss_bytes += rss_kib.parse::<u64>().unwrap_or(0) * 1024;
+            sample.cpu_time += parse_cpu_time(time).unwrap_or_default();
+        }
+
+        samples
+    }
+
+    /// Parse ps TIME values such as "0:01.23", "1:02:03" or "2-01:02:03"
+    fn parse_cpu_time(value: &str) -> Option<Duration> {
+        let (days, rest) = match value.split_once('-') {
+            Some((days, rest)) => (days.parse::<u64>().ok()?, rest),
+            None => (0, value),
+        };
+        let mut seconds = 0.0;
+        for part in rest.split(':') {
+            seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+        }
+        Some(Duration::from_secs_f64(seconds + (days * 86400) as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_at(taken_at: Instant, cpu_ms: u64) -> GroupSample {
+        GroupSample {
+            taken_at,
+            cpu_time: Duration::from_millis(cpu_ms),
+            rss_bytes: 4096,
+            threads: Some(3),
+            read_bytes: None,
+            write_bytes: None,
+            processes: 2,
+        }
+    }
+
+    #[test]
+    fn test_cpu_percent_from_samples() {
+        let start = Instant::now();
+        let first = sample_at(start, 1000);
+        let second = sample_at(start + Duration::from_secs(2), 2000);
+
+        let stats = TaskStats::from_samples(Some(&first), &second);
+        assert_eq!(stats.cpu_percent, Some(50.0));
+        assert_eq!(stats.rss_bytes, 4096);
+        assert_eq!(stats.processes, 2);
+
+        let first_only = TaskStats::from_samples(None, &first);
+        assert_eq!(first_only.cpu_percent, None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_proc_files() {
+        let stat = "1234 (my (weird) cmd) S 1 1234 1234 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 4 0 1000 1000000 200";
+        let parsed = platform::parse_stat(stat).unwrap();
+        assert_eq!(parsed.pgrp, 1234);
+        assert_eq!(parsed.utime, 250);
+        assert_eq!(parsed.stime, 50);
+
+        let status = "Name:\tsleep\nVmRSS:\t    1536 kB\nThreads:\t4\n";
+        assert_eq!(platform::parse_status(status), (1536, 4));
+
+        let io = "rchar: 10\nwchar: 20\nread_bytes: 4096\nwrite_bytes: 8192\n";
+        assert_eq!(platform::parse_io(io), (4096, 8192));
+    }
+
+    #[test]
+    fn test_take_all_samples_each_group_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spawn = || {
+            crate::app::process::spawn_background_process(
+                vec!["sleep".to_string(), "5".to_string()],
+                None,
+                Some(temp_dir.path().to_path_buf()),
+            )
+            .unwrap()
+        };
+        let (first, mut first_child) = spawn();
+        let (second, mut second_child) = spawn();
+
+        let samples = GroupSample::take_all(&[first.pgid, second.pgid, i32::MAX]);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[&first.pgid].processes, 1);
+        assert_eq!(samples[&second.pgid].processes, 1);
+        assert!(GroupSample::take_all(&[]).is_empty());
+
+        for child in [&mut first_child, &mut second_child] {
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_sampler_tracks_running_task() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (info, mut child) = crate::app::process::spawn_background_process(
+            vec!["sleep".to_string(), "5".to_string()],
+            None,
+            Some(temp_dir.path().to_path_buf()),
+        )
+        .unwrap();
+
+        let task = Task {
+            id: info.id.clone(),
+            pid: info.pid,
+            pgid: Some(info.pgid),
+            command: r#"["sleep","5"]"#.to_string(),
+            env: None,
+            cwd: None,
+            status: TaskStatus::Running,
+            exit_code: None,
+            started_at: 0,
+            finished_at: None,
+            log_path: info.log_path.to_string_lossy().to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        };
+
+        let mut sampler = StatsSampler::new();
+        sampler.sample(std::slice::from_ref(&task));
+        let stats = sampler.get(&task.id).unwrap();
+        assert_eq!(stats.processes, 1);
+        assert!(stats.rss_bytes > 0);
+        assert_eq!(sampler.history(&task.id).len(), 1);
+
+        // Finished tasks are dropped from the sampler
+        let finished = Task {
+            status: TaskStatus::Exited,
+            ..task
+        };
+        sampler.sample(&[finished]);
+        assert!(sampler.latest().is_empty());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
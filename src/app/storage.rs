@@ -1,15 +1,44 @@
+pub mod chain_repository;
 pub mod cleanup;
 pub mod database;
+pub mod event_repository;
+pub mod filter_preset_repository;
+pub mod queue_repository;
+pub mod schedule_repository;
 pub mod task;
 pub mod task_repository;
 pub mod task_status;
+pub mod template_repository;
 
 // Re-export for backward compatibility
+pub use chain_repository::{
+    StepState, cancel_waiting_task, claim_ready_tasks, get_chain, get_dependent_tasks,
+    insert_waiting_task, step_state,
+};
 pub use cleanup::{cleanup_old_tasks, cleanup_tasks_by_criteria, get_cleanup_candidates};
 pub use database::{init_database, init_database_with_config};
-pub use task::Task;
+pub use event_repository::{EventKind, TaskEvent, get_task_events, insert_task_event, ready_since};
+pub use filter_preset_repository::{
+    FilterPreset, delete_filter_preset, get_filter_presets, insert_filter_preset,
+};
+pub use queue_repository::{
+    CLAIM_TIMEOUT_SECS, DEFAULT_QUEUE, Queue, claim_queued_tasks, count_running_in_queue,
+    delete_queued_tasks, ensure_queue, fail_stale_claims, get_queue, get_queued_tasks, get_queues,
+    insert_queued_task, move_queued_task, set_queue_parallel, set_queue_paused,
+};
+pub use schedule_repository::{
+    Schedule, delete_schedule, get_due_schedules, get_schedule, get_schedules, insert_schedule,
+    record_schedule_run, set_task_schedule,
+};
+pub use task::{AfterCondition, Task};
 pub use task_repository::{
-    delete_task, get_task, get_tasks, get_tasks_with_process_check, insert_task, row_to_task,
-    update_task_status, update_task_status_by_process_check,
+    clear_supervisor_pid, delete_task, get_task, get_tasks, get_tasks_by_name,
+    get_tasks_with_process_check, insert_task, mark_task_restarting, record_task_exit,
+    record_task_restart, record_task_start, row_to_task, set_supervisor_pid, set_task_cgroup,
+    set_task_name, set_task_options, set_watch_trigger, update_task_status,
+    update_task_status_by_process_check,
 };
 pub use task_status::TaskStatus;
+pub use template_repository::{
+    Template, delete_template, get_template, get_templates, insert_template,
+};
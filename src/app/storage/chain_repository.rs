@@ -0,0 +1,323 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use super::queue_repository::fail_stale_claims;
+use super::task::{AfterCondition, Task};
+use super::task_repository::{
+    TASK_COLUMNS, get_task, row_to_task, update_task_status_by_process_check,
+};
+use super::task_status::TaskStatus;
+use crate::app::error::{GhostError, Result};
+use crate::app::run_options::RunOptions;
+
+/// What should happen to a waiting task, given the task it runs after
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepState {
+    /// The previous task has not finished yet
+    Wait,
+    /// The task can start now
+    Start,
+    /// The task will never run, for the given reason
+    Cancel(String),
+}
+
+/// Decide what happens to a task waiting for `previous` to finish
+///
+/// `previous` is None when the task runs after nothing or after a deleted task;
+/// `after` names the task it was meant to run after in the latter case.
+pub fn step_state(
+    after: Option<&str>,
+    condition: AfterCondition,
+    previous: Option<&Task>,
+) -> StepState {
+    let Some(previous) = previous else {
+        return match after {
+            Some(task_id) => StepState::Cancel(format!("task {task_id} no longer exists")),
+            None => StepState::Start,
+        };
+    };
+
+    let succeeded = match previous.status {
+        TaskStatus::Queued | TaskStatus::Waiting | TaskStatus::Starting | TaskStatus::Running => {
+            return StepState::Wait;
+        }
+        TaskStatus::Cancelled => return StepState::Cancel("previous step was cancelled".into()),
+        TaskStatus::Killed => false,
+        TaskStatus::Exited | TaskStatus::Unknown => match previous.exit_code {
+            Some(code) => code == 0,
+            None => {
+                return StepState::Cancel("exit code of the previous step is unknown".into());
+            }
+        },
+    };
+
+    match (condition, succeeded) {
+        (AfterCondition::Success, true) | (AfterCondition::Failure, false) => StepState::Start,
+        (AfterCondition::Success, false) => StepState::Cancel("previous step failed".into()),
+        (AfterCondition::Failure, true) => StepState::Cancel("previous step succeeded".into()),
+    }
+}
+
+/// Insert a task that starts once the task it runs after has finished
+///
+/// Without `after` the task starts at the next promotion; this is how the
+/// first step of a chain gets a supervisor that records its exit code.
+#[allow(clippy::too_many_arguments)]
+pub fn insert_waiting_task(
+    conn: &Connection,
+    id: &str,
+    command: &[String],
+    env: Option<&[(String, String)]>,
+    cwd: Option<&Path>,
+    log_path: &Path,
+    after: Option<(&str, AfterCondition)>,
+    options: &RunOptions,
+) -> Result<()> {
+    let command_json = serde_json::to_string(command)?;
+    let env_json = env.map(serde_json::to_string).transpose()?;
+    let cwd_str = cwd.map(|p| p.to_string_lossy().to_string());
+    let options_json = if options.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(options)?)
+    };
+
+    conn.execute(
+        r#"
+        INSERT INTO tasks (
+            id, pid, command, env, cwd, status, started_at, log_path,
+            options, after_task, after_condition
+        ) VALUES (?1, 0, ?2, ?3, ?4, 'waiting', ?5, ?6, ?7, ?8, ?9)
+        "#,
+        (
+            id,
+            command_json,
+            env_json,
+            cwd_str,
+            crate::app::helpers::now_timestamp(),
+            log_path.to_string_lossy(),
+            options_json,
+            after.map(|(task_id, _)| task_id),
+            after.map(|(_, condition)| condition.as_str()),
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Get the tasks that run after the given task, in the order they were added
+pub fn get_dependent_tasks(conn: &Connection, task_id: &str) -> Result<Vec<Task>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks WHERE after_task = ?1 ORDER BY rowid"
+    ))?;
+    let tasks = stmt
+        .query_map([task_id], row_to_task)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+/// Get the whole chain a task belongs to, from its first step onwards
+///
+/// Steps are returned depth-first, each paired with its distance from the
+/// first step. A task that is not part of a chain is returned on its own.
+pub fn get_chain(conn: &Connection, task_id: &str) -> Result<Vec<(usize, Task)>> {
+    // A step can only run after a task that existed before it, so there are no cycles
+    let mut root = get_task(conn, task_id)?;
+    while let Some(previous) = root
+        .after_task
+        .as_deref()
+        .and_then(|id| get_task(conn, id).ok())
+    {
+        root = previous;
+    }
+
+    let mut chain = Vec::new();
+    let mut stack = vec![(0, root)];
+    while let Some((depth, task)) = stack.pop() {
+        for dependent in get_dependent_tasks(conn, &task.id)?.into_iter().rev() {
+            stack.push((depth + 1, dependent));
+        }
+        chain.push((depth, task));
+    }
+
+    Ok(chain)
+}
+
+/// Claim the waiting tasks that may start now, and cancel those that never will
+///
+/// Claimed tasks are marked starting, like those of `claim_queued_tasks`.
+/// Cancellation cascades: a cancelled step cancels the steps waiting for it.
+/// Like `claim_queued_tasks`, this runs in a single write transaction so
+/// concurrent ghost processes never start the same task twice.
+pub fn claim_ready_tasks(conn: &Connection) -> Result<Vec<Task>> {
+    fail_stale_claims(conn)?;
+
+    // Finished predecessors must be noticed before the decision is made
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT after_task FROM tasks WHERE status = 'waiting' AND after_task IS NOT NULL",
+    )?;
+    let previous: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for task_id in previous {
+        match update_task_status_by_process_check(conn, &task_id) {
+            Ok(_) | Err(GhostError::TaskNotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    match claim_ready(conn) {
+        Ok(claimed) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(claimed)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+fn claim_ready(conn: &Connection) -> Result<Vec<Task>> {
+    let mut claimed = Vec::new();
+
+    // Repeat until nothing changes so cancellations reach the end of a chain
+    loop {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE status = 'waiting' ORDER BY rowid"
+        ))?;
+        let waiting = stmt
+            .query_map([], row_to_task)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut changed = false;
+        for task in waiting {
+            let previous = match task.after_task.as_deref() {
+                Some(id) => match get_task(conn, id) {
+                    Ok(previous) => Some(previous),
+                    Err(GhostError::TaskNotFound { .. }) => None,
+                    Err(e) => return Err(e),
+                },
+                None => None,
+            };
+            let condition = task.after_condition.unwrap_or(AfterCondition::Success);
+
+            match step_state(task.after_task.as_deref(), condition, previous.as_ref()) {
+                StepState::Wait => {}
+                StepState::Start => {
+                    conn.execute(
+                        "UPDATE tasks SET status = 'starting', started_at = ?1 WHERE id = ?2 AND status = 'waiting'",
+                        (crate::app::helpers::now_timestamp(), &task.id),
+                    )?;
+                    claimed.push(task);
+                }
+                StepState::Cancel(reason) => {
+                    cancel_waiting_task(conn, &task.id, &reason)?;
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return Ok(claimed);
+        }
+    }
+}
+
+/// Mark a waiting (or queued) task as cancelled so it never runs
+pub fn cancel_waiting_task(conn: &Connection, task_id: &str, reason: &str) -> Result<()> {
+    conn.execute(
+        r#"
+        UPDATE tasks SET
+            status = 'cancelled',
+            finished_at = ?1,
+            exit_reason = ?2
+        WHERE id = ?3 AND status IN ('waiting', 'queued')
+        "#,
+        (crate::app::helpers::now_timestamp(), reason, task_id),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(status: TaskStatus, exit_code: Option<i32>) -> Task {
+        Task {
+            id: "previous".to_string(),
+            pid: 0,
+            pgid: None,
+            command: "[]".to_string(),
+            env: None,
+            cwd: None,
+            status,
+            exit_code,
+            started_at: 0,
+            finished_at: None,
+            log_path: "/tmp/previous.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_step_state_follows_condition() {
+        let success = finished(TaskStatus::Exited, Some(0));
+        let failure = finished(TaskStatus::Exited, Some(2));
+        let killed = finished(TaskStatus::Killed, None);
+        let after = Some("previous");
+
+        assert_eq!(
+            step_state(after, AfterCondition::Success, Some(&success)),
+            StepState::Start
+        );
+        assert!(matches!(
+            step_state(after, AfterCondition::Success, Some(&failure)),
+            StepState::Cancel(_)
+        ));
+        assert_eq!(
+            step_state(after, AfterCondition::Failure, Some(&killed)),
+            StepState::Start
+        );
+        assert!(matches!(
+            step_state(after, AfterCondition::Failure, Some(&success)),
+            StepState::Cancel(_)
+        ));
+    }
+
+    #[test]
+    fn test_step_state_waits_and_handles_missing_previous() {
+        let running = finished(TaskStatus::Running, None);
+        let unknown = finished(TaskStatus::Exited, None);
+
+        assert_eq!(
+            step_state(Some("previous"), AfterCondition::Success, Some(&running)),
+            StepState::Wait
+        );
+        assert_eq!(
+            step_state(Some("previous"), AfterCondition::Success, Some(&unknown)),
+            StepState::Cancel("exit code of the previous step is unknown".to_string())
+        );
+        assert_eq!(
+            step_state(Some("gone"), AfterCondition::Success, None),
+            StepState::Cancel("task gone no longer exists".to_string())
+        );
+        assert_eq!(
+            step_state(None, AfterCondition::Success, None),
+            StepState::Start
+        );
+    }
+}
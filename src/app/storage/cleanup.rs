@@ -1,7 +1,7 @@
 use rusqlite::Connection;
 
 use super::task::Task;
-use super::task_repository::{row_to_task, update_task_status_by_process_check};
+use super::task_repository::{TASK_COLUMNS, row_to_task, update_task_status_by_process_check};
 use super::task_status::TaskStatus;
 use crate::app::error::Result;
 
@@ -10,7 +10,7 @@ pub fn cleanup_old_tasks(conn: &Connection, days: u64) -> Result<usize> {
     let cutoff_time = crate::app::helpers::now_timestamp() - (days * 24 * 60 * 60) as i64;
 
     let rows_affected = conn.execute(
-        "DELETE FROM tasks WHERE status IN ('exited', 'killed') AND finished_at IS NOT NULL AND finished_at < ?1",
+        "DELETE FROM tasks WHERE status IN ('exited', 'killed', 'cancelled') AND finished_at IS NOT NULL AND finished_at < ?1",
         [cutoff_time],
     )?;
 
@@ -36,7 +36,7 @@ pub fn get_cleanup_candidates(
     }
 
     // Now get cleanup candidates with filters applied
-    let mut sql = "SELECT id, pid, pgid, command, env, cwd, status, exit_code, started_at, finished_at, log_path FROM tasks".to_string();
+    let mut sql = format!("SELECT {TASK_COLUMNS} FROM tasks");
     let mut params: Vec<Box<dyn rusqlite::ToSql + '_>> = Vec::new();
     let mut conditions = Vec::new();
 
@@ -106,6 +106,10 @@ pub fn cleanup_tasks_by_criteria(
                 task.log_path, e
             );
         }
+        // The log of the task's hooks, if it had any
+        let _ = std::fs::remove_file(crate::app::hooks::hook_log_path(std::path::Path::new(
+            &task.log_path,
+        )));
     }
 
     // Then delete from database using task IDs
@@ -17,12 +17,121 @@ pub(crate) fn init_schema(conn: &Connection) -> Result<()> {
             exit_code INTEGER,
             started_at INTEGER NOT NULL,
             finished_at INTEGER,
-            log_path TEXT NOT NULL
+            log_path TEXT NOT NULL,
+            options TEXT,
+            restart_count INTEGER NOT NULL DEFAULT 0,
+            supervisor_pid INTEGER,
+            cgroup TEXT,
+            exit_reason TEXT,
+            queue TEXT,
+            queue_position INTEGER,
+            after_task TEXT,
+            after_condition TEXT,
+            schedule_id TEXT,
+            watch_trigger TEXT,
+            name TEXT
         )
         "#,
         [],
     )?;
 
+    // Create queues table; a queue's tasks reference it by name
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS queues (
+            name TEXT PRIMARY KEY,
+            parallel INTEGER NOT NULL DEFAULT 1,
+            paused INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+        [],
+    )?;
+
+    // Create schedules table; a schedule without cron runs only once
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS schedules (
+            id TEXT PRIMARY KEY,
+            command TEXT NOT NULL,
+            env TEXT,
+            cwd TEXT,
+            options TEXT,
+            cron TEXT,
+            next_run INTEGER NOT NULL,
+            last_run INTEGER,
+            last_task_id TEXT,
+            created_at INTEGER NOT NULL
+        )
+        "#,
+        [],
+    )?;
+
+    // Create templates table for commands started with `ghost start`
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS templates (
+            name TEXT PRIMARY KEY,
+            command TEXT NOT NULL,
+            env TEXT,
+            cwd TEXT,
+            options TEXT,
+            created_at INTEGER NOT NULL
+        )
+        "#,
+        [],
+    )?;
+
+    // Create filter_presets table for searches saved in the TUI
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS filter_presets (
+            name TEXT PRIMARY KEY,
+            query TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )
+        "#,
+        [],
+    )?;
+
+    // Create task_events table for the event history shown in a task's details
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS task_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            message TEXT NOT NULL
+        )
+        "#,
+        [],
+    )?;
+
+    // A task's history goes away with the task, whichever way it is deleted
+    conn.execute(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS delete_task_events AFTER DELETE ON tasks
+        BEGIN
+            DELETE FROM task_events WHERE task_id = OLD.id;
+        END
+        "#,
+        [],
+    )?;
+
+    // Add columns introduced after the initial schema to existing databases
+    add_column_if_missing(conn, "tasks", "options", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "restart_count", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "tasks", "supervisor_pid", "INTEGER")?;
+    add_column_if_missing(conn, "tasks", "cgroup", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "exit_reason", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "queue", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "queue_position", "INTEGER")?;
+    add_column_if_missing(conn, "tasks", "after_task", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "after_condition", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "schedule_id", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "watch_trigger", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "name", "TEXT")?;
+
     // Create indexes for performance
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)",
@@ -36,6 +145,50 @@ pub(crate) fn init_schema(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tasks_queue ON tasks(queue, status)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tasks_after_task ON tasks(after_task)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_task_events_task_id ON task_events(task_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_schedules_next_run ON schedules(next_run)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Add a column to a table unless it already exists
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            [],
+        )?;
+    }
+
     Ok(())
 }
 
@@ -60,3 +213,59 @@ pub fn init_database_with_config(config: Option<crate::app::config::Config>) ->
 
     Ok(conn)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_schema_migrates_old_tasks_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            r#"
+            CREATE TABLE tasks (
+                id TEXT PRIMARY KEY,
+                pid INTEGER NOT NULL,
+                pgid INTEGER,
+                command TEXT NOT NULL,
+                env TEXT,
+                cwd TEXT,
+                status TEXT NOT NULL DEFAULT 'running',
+                exit_code INTEGER,
+                started_at INTEGER NOT NULL,
+                finished_at INTEGER,
+                log_path TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO tasks (id, pid, command, started_at, log_path) VALUES ('old', 1, '[]', 0, '/tmp/old.log')",
+            [],
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        // Running it twice must be a no-op
+        init_schema(&conn).unwrap();
+
+        let task = crate::app::storage::get_task(&conn, "old").unwrap();
+        assert_eq!(task.options, None);
+        assert_eq!(task.restart_count, 0);
+        assert_eq!(task.supervisor_pid, None);
+        assert_eq!(task.queue, None);
+        assert_eq!(task.queue_position, None);
+        assert_eq!(task.after_task, None);
+        assert_eq!(task.after_condition, None);
+        assert_eq!(task.schedule_id, None);
+        assert_eq!(task.watch_trigger, None);
+        assert_eq!(task.name, None);
+        assert!(crate::app::storage::get_queues(&conn).unwrap().is_empty());
+        assert!(
+            crate::app::storage::get_schedules(&conn)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use rusqlite::{Connection, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::app::error::Result;
+
+/// What happened to a task, as recorded in its event history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    /// An output trigger matched a line of the task's log
+    Trigger,
+    /// An output trigger marked the task as ready
+    Ready,
+    /// The supervisor started the task's command again
+    Restart,
+    /// A lifecycle hook finished
+    Hook,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Trigger => "trigger",
+            EventKind::Ready => "ready",
+            EventKind::Restart => "restart",
+            EventKind::Hook => "hook",
+        }
+    }
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for EventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "trigger" => Ok(EventKind::Trigger),
+            "ready" => Ok(EventKind::Ready),
+            "restart" => Ok(EventKind::Restart),
+            "hook" => Ok(EventKind::Hook),
+            _ => Err(format!("Unknown event kind: {s}")),
+        }
+    }
+}
+
+/// An entry of a task's event history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub task_id: String,
+    pub created_at: i64,
+    pub kind: EventKind,
+    pub message: String,
+}
+
+/// Add an event to a task's history
+pub fn insert_task_event(
+    conn: &Connection,
+    task_id: &str,
+    kind: EventKind,
+    message: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO task_events (task_id, created_at, kind, message) VALUES (?1, ?2, ?3, ?4)",
+        (
+            task_id,
+            crate::app::helpers::now_timestamp(),
+            kind.as_str(),
+            message,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Get a task's events, oldest first
+pub fn get_task_events(conn: &Connection, task_id: &str) -> Result<Vec<TaskEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT task_id, created_at, kind, message FROM task_events WHERE task_id = ?1 ORDER BY id",
+    )?;
+    let events = stmt
+        .query_map([task_id], row_to_task_event)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+/// When the task was last marked ready, unless it has been restarted since
+pub fn ready_since(events: &[TaskEvent]) -> Option<i64> {
+    events
+        .iter()
+        .rev()
+        .find(|event| matches!(event.kind, EventKind::Ready | EventKind::Restart))
+        .filter(|event| event.kind == EventKind::Ready)
+        .map(|event| event.created_at)
+}
+
+fn row_to_task_event(row: &Row) -> rusqlite::Result<TaskEvent> {
+    let kind: String = row.get(2)?;
+    Ok(TaskEvent {
+        task_id: row.get(0)?,
+        created_at: row.get(1)?,
+        kind: kind.parse().map_err(|_| {
+            rusqlite::Error::InvalidColumnType(2, "kind".to_string(), rusqlite::types::Type::Text)
+        })?,
+        message: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::database::init_schema;
+
+    #[test]
+    fn test_task_events_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO tasks (id, pid, command, started_at, log_path) VALUES ('a', 1, '[]', 0, '/tmp/a.log')",
+            [],
+        )
+        .unwrap();
+
+        insert_task_event(
+            &conn,
+            "a",
+            EventKind::Ready,
+            "/Listening/: Listening on :8080",
+        )
+        .unwrap();
+        insert_task_event(&conn, "b", EventKind::Trigger, "other task").unwrap();
+
+        let events = get_task_events(&conn, "a").unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Ready);
+        assert!(ready_since(&events).is_some());
+
+        insert_task_event(&conn, "a", EventKind::Restart, "src/main.rs changed").unwrap();
+        let events = get_task_events(&conn, "a").unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(ready_since(&events), None);
+
+        // Deleting a task deletes its history
+        crate::app::storage::delete_task(&conn, "a").unwrap();
+        assert!(get_task_events(&conn, "a").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use rusqlite::{Connection, Row};
+
+use crate::app::error::{GhostError, Result};
+
+/// A task list search saved under a name, used as `@name` in a search
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FilterPreset {
+    pub name: String,
+    pub query: String,
+    pub created_at: i64,
+}
+
+/// Save a filter preset, replacing any preset with the same name
+pub fn insert_filter_preset(conn: &Connection, name: &str, query: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO filter_presets (name, query, created_at) VALUES (?1, ?2, ?3)",
+        (name, query, crate::app::helpers::now_timestamp()),
+    )?;
+    Ok(())
+}
+
+/// Get all filter presets, sorted by name
+pub fn get_filter_presets(conn: &Connection) -> Result<Vec<FilterPreset>> {
+    let mut stmt =
+        conn.prepare("SELECT name, query, created_at FROM filter_presets ORDER BY name")?;
+    let presets = stmt
+        .query_map([], row_to_filter_preset)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(presets)
+}
+
+/// Delete a filter preset by name
+pub fn delete_filter_preset(conn: &Connection, name: &str) -> Result<()> {
+    let rows_affected = conn.execute("DELETE FROM filter_presets WHERE name = ?1", [name])?;
+
+    if rows_affected == 0 {
+        return Err(GhostError::InvalidArgument {
+            message: format!("Filter preset not found: {name}"),
+        });
+    }
+
+    Ok(())
+}
+
+fn row_to_filter_preset(row: &Row) -> rusqlite::Result<FilterPreset> {
+    Ok(FilterPreset {
+        name: row.get(0)?,
+        query: row.get(1)?,
+        created_at: row.get(2)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::database::init_schema;
+
+    #[test]
+    fn test_filter_preset_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+
+        insert_filter_preset(&conn, "web", "port:8080").unwrap();
+        insert_filter_preset(&conn, "api", "cwd:~/api").unwrap();
+        insert_filter_preset(&conn, "web", "status:running port:8080").unwrap();
+
+        let presets = get_filter_presets(&conn).unwrap();
+        let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "web"]);
+        assert_eq!(presets[1].query, "status:running port:8080");
+
+        delete_filter_preset(&conn, "api").unwrap();
+        assert!(delete_filter_preset(&conn, "api").is_err());
+        assert_eq!(get_filter_presets(&conn).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OptionalExtension as _};
+
+use super::task::Task;
+use super::task_repository::{TASK_COLUMNS, row_to_task, update_task_status_by_process_check};
+use super::task_status::TaskStatus;
+use crate::app::error::{GhostError, Result};
+use crate::app::process;
+use crate::app::run_options::RunOptions;
+
+/// Queue used when no queue name is given
+pub const DEFAULT_QUEUE: &str = "default";
+
+/// Seconds a claimed task may take to get a process before it is given up on
+pub const CLAIM_TIMEOUT_SECS: i64 = 30;
+
+/// A named queue and its concurrency limit
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Queue {
+    pub name: String,
+    /// How many of the queue's tasks may run at the same time
+    pub parallel: u32,
+    /// Paused queues keep their waiting tasks but start none of them
+    pub paused: bool,
+}
+
+/// Create the queue with default settings unless it already exists
+pub fn ensure_queue(conn: &Connection, name: &str) -> Result<()> {
+    conn.execute("INSERT OR IGNORE INTO queues (name) VALUES (?1)", [name])?;
+    Ok(())
+}
+
+/// Get a queue by name
+pub fn get_queue(conn: &Connection, name: &str) -> Result<Queue> {
+    conn.query_row(
+        "SELECT name, parallel, paused FROM queues WHERE name = ?1",
+        [name],
+        row_to_queue,
+    )
+    .optional()?
+    .ok_or_else(|| GhostError::InvalidArgument {
+        message: format!("Queue not found: {name}"),
+    })
+}
+
+/// Get all queues, ordered by name
+pub fn get_queues(conn: &Connection) -> Result<Vec<Queue>> {
+    let mut stmt = conn.prepare("SELECT name, parallel, paused FROM queues ORDER BY name")?;
+    let queues = stmt
+        .query_map([], row_to_queue)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(queues)
+}
+
+/// Set how many of a queue's tasks may run at once, creating the queue if needed
+pub fn set_queue_parallel(conn: &Connection, name: &str, parallel: u32) -> Result<()> {
+    ensure_queue(conn, name)?;
+    conn.execute(
+        "UPDATE queues SET parallel = ?1 WHERE name = ?2",
+        (parallel, name),
+    )?;
+    Ok(())
+}
+
+/// Pause or resume a queue, creating the queue if needed
+///
+/// Pausing a queue before adding to it lets a batch of tasks be lined up first.
+pub fn set_queue_paused(conn: &Connection, name: &str, paused: bool) -> Result<()> {
+    ensure_queue(conn, name)?;
+    conn.execute(
+        "UPDATE queues SET paused = ?1 WHERE name = ?2",
+        (paused, name),
+    )?;
+    Ok(())
+}
+
+/// Insert a task that waits at the end of a queue
+#[allow(clippy::too_many_arguments)]
+pub fn insert_queued_task(
+    conn: &Connection,
+    id: &str,
+    command: &[String],
+    env: Option<&[(String, String)]>,
+    cwd: Option<&Path>,
+    log_path: &Path,
+    queue: &str,
+    options: &RunOptions,
+) -> Result<()> {
+    ensure_queue(conn, queue)?;
+
+    let command_json = serde_json::to_string(command)?;
+    let env_json = env.map(serde_json::to_string).transpose()?;
+    let cwd_str = cwd.map(|p| p.to_string_lossy().to_string());
+    let options_json = if options.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(options)?)
+    };
+
+    conn.execute(
+        r#"
+        INSERT INTO tasks (
+            id, pid, command, env, cwd, status, started_at, log_path,
+            options, queue, queue_position
+        ) VALUES (
+            ?1, 0, ?2, ?3, ?4, 'queued', ?5, ?6, ?7, ?8,
+            (SELECT COALESCE(MAX(queue_position), 0) + 1 FROM tasks WHERE queue = ?8)
+        )
+        "#,
+        (
+            id,
+            command_json,
+            env_json,
+            cwd_str,
+            crate::app::helpers::now_timestamp(),
+            log_path.to_string_lossy(),
+            options_json,
+            queue,
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Get the tasks waiting in a queue (or in all queues), in the order they will start
+pub fn get_queued_tasks(conn: &Connection, queue: Option<&str>) -> Result<Vec<Task>> {
+    let base_sql = format!("SELECT {TASK_COLUMNS} FROM tasks WHERE status = 'queued'");
+    let order_clause = " ORDER BY queue, queue_position";
+
+    let tasks = match queue {
+        Some(queue) => {
+            let mut stmt = conn.prepare(&format!("{base_sql} AND queue = ?1{order_clause}"))?;
+            stmt.query_map([queue], row_to_task)?
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        }
+        None => {
+            let mut stmt = conn.prepare(&format!("{base_sql}{order_clause}"))?;
+            stmt.query_map([], row_to_task)?
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        }
+    };
+
+    Ok(tasks)
+}
+
+/// Count the running tasks of a queue, including claimed ones still starting
+pub fn count_running_in_queue(conn: &Connection, queue: &str) -> Result<u32> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM tasks WHERE queue = ?1 AND status IN ('starting', 'running')",
+        [queue],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Move a waiting task to a 1-based position within its queue
+pub fn move_queued_task(conn: &Connection, task_id: &str, position: usize) -> Result<()> {
+    let task = super::task_repository::get_task(conn, task_id)?;
+    let Some(queue) = task.queue.filter(|_| task.status == TaskStatus::Queued) else {
+        return Err(GhostError::TaskOperation {
+            task_id: task_id.to_string(),
+            message: format!("Task is not queued (status: {})", task.status),
+        });
+    };
+
+    let mut order: Vec<String> = get_queued_tasks(conn, Some(&queue))?
+        .into_iter()
+        .map(|task| task.id)
+        .filter(|id| id != task_id)
+        .collect();
+    let index = position.saturating_sub(1).min(order.len());
+    order.insert(index, task_id.to_string());
+
+    let tx = conn.unchecked_transaction()?;
+    for (index, id) in order.iter().enumerate() {
+        tx.execute(
+            "UPDATE tasks SET queue_position = ?1 WHERE id = ?2",
+            (index as i64 + 1, id),
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Remove the waiting tasks of a queue; running tasks are left alone
+pub fn delete_queued_tasks(conn: &Connection, queue: &str) -> Result<usize> {
+    let rows_affected = conn.execute(
+        "DELETE FROM tasks WHERE queue = ?1 AND status = 'queued'",
+        [queue],
+    )?;
+    Ok(rows_affected)
+}
+
+/// Claim the waiting tasks that may start now
+///
+/// For every queue that is not paused, as many waiting tasks as there are free
+/// slots are marked starting and returned; the claim time is kept in
+/// `started_at` until the process is recorded. Claiming happens in a single
+/// write transaction, so concurrent ghost processes never start the same task
+/// twice.
+pub fn claim_queued_tasks(conn: &Connection) -> Result<Vec<Task>> {
+    // Tasks whose process is gone, or never came, free their slot
+    fail_stale_claims(conn)?;
+    let mut stmt =
+        conn.prepare("SELECT id FROM tasks WHERE queue IS NOT NULL AND status = 'running'")?;
+    let running: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for task_id in running {
+        update_task_status_by_process_check(conn, &task_id)?;
+    }
+
+    // Take the write lock up front so claims of concurrent promoters serialize
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    match claim_free_slots(conn) {
+        Ok(claimed) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(claimed)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+fn claim_free_slots(conn: &Connection) -> Result<Vec<Task>> {
+    let mut claimed = Vec::new();
+    for queue in get_queues(conn)?.into_iter().filter(|queue| !queue.paused) {
+        let running = count_running_in_queue(conn, &queue.name)?;
+        let free = queue.parallel.saturating_sub(running) as usize;
+
+        for task in get_queued_tasks(conn, Some(&queue.name))?
+            .into_iter()
+            .take(free)
+        {
+            conn.execute(
+                "UPDATE tasks SET status = 'starting', started_at = ?1 WHERE id = ?2 AND status = 'queued'",
+                (crate::app::helpers::now_timestamp(), &task.id),
+            )?;
+            claimed.push(task);
+        }
+    }
+    Ok(claimed)
+}
+
+/// Give up on claimed tasks that got no process within `CLAIM_TIMEOUT_SECS`,
+/// e.g. because the ghost process or the supervisor starting them died
+///
+/// They are marked unknown rather than queued again, since their process may
+/// have been spawned without being recorded.
+pub fn fail_stale_claims(conn: &Connection) -> Result<usize> {
+    let now = crate::app::helpers::now_timestamp();
+    let mut stmt = conn.prepare(
+        "SELECT id, supervisor_pid FROM tasks WHERE status = 'starting' AND started_at <= ?1",
+    )?;
+    let stale: Vec<(String, Option<i64>)> = stmt
+        .query_map([now - CLAIM_TIMEOUT_SECS], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut failed = 0;
+    for (task_id, supervisor_pid) in stale {
+        // A supervisor that is still there owns the claim, e.g. while its
+        // pre-start hooks run, and records how it ends
+        if supervisor_pid.is_some_and(|pid| process::exists(pid as u32)) {
+            continue;
+        }
+        failed += conn.execute(
+            r#"
+            UPDATE tasks SET
+                status = 'unknown',
+                finished_at = ?1,
+                exit_reason = ?2
+            WHERE id = ?3 AND status = 'starting'
+            "#,
+            (
+                now,
+                format!("did not start within {CLAIM_TIMEOUT_SECS}s"),
+                &task_id,
+            ),
+        )?;
+    }
+    Ok(failed)
+}
+
+fn row_to_queue(row: &rusqlite::Row) -> rusqlite::Result<Queue> {
+    Ok(Queue {
+        name: row.get(0)?,
+        parallel: row.get(1)?,
+        paused: row.get(2)?,
+    })
+}
@@ -0,0 +1,234 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OptionalExtension as _, Row};
+
+use crate::app::error::{GhostError, Result};
+use crate::app::run_options::RunOptions;
+
+/// Columns selected for a schedule, in the order expected by `row_to_schedule`
+const SCHEDULE_COLUMNS: &str =
+    "id, command, env, cwd, options, cron, next_run, last_run, last_task_id, created_at";
+
+/// A command that starts at a later time, once or repeatedly
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub command: String,     // JSON serialized Vec<String>
+    pub env: Option<String>, // JSON serialized environment variables
+    pub cwd: Option<String>,
+    pub options: Option<String>, // JSON serialized RunOptions
+    /// Cron expression of a recurring schedule; None for a one-off delayed start
+    pub cron: Option<String>,
+    pub next_run: i64, // Unix timestamp
+    pub last_run: Option<i64>,
+    /// Task created by the most recent run
+    pub last_task_id: Option<String>,
+    pub created_at: i64,
+}
+
+impl Schedule {
+    /// Get the run options stored with the schedule
+    pub fn run_options(&self) -> RunOptions {
+        self.options
+            .as_deref()
+            .and_then(|options| serde_json::from_str(options).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Insert a schedule that first runs at `next_run`
+#[allow(clippy::too_many_arguments)]
+pub fn insert_schedule(
+    conn: &Connection,
+    id: &str,
+    command: &[String],
+    env: Option<&[(String, String)]>,
+    cwd: Option<&Path>,
+    options: &RunOptions,
+    cron: Option<&str>,
+    next_run: i64,
+) -> Result<()> {
+    let command_json = serde_json::to_string(command)?;
+    let env_json = env.map(serde_json::to_string).transpose()?;
+    let cwd_str = cwd.map(|p| p.to_string_lossy().to_string());
+    let options_json = if options.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(options)?)
+    };
+
+    conn.execute(
+        r#"
+        INSERT INTO schedules (
+            id, command, env, cwd, options, cron, next_run, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+        (
+            id,
+            command_json,
+            env_json,
+            cwd_str,
+            options_json,
+            cron,
+            next_run,
+            crate::app::helpers::now_timestamp(),
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Get a schedule by ID
+pub fn get_schedule(conn: &Connection, id: &str) -> Result<Schedule> {
+    conn.query_row(
+        &format!("SELECT {SCHEDULE_COLUMNS} FROM schedules WHERE id = ?1"),
+        [id],
+        row_to_schedule,
+    )
+    .optional()?
+    .ok_or_else(|| GhostError::InvalidArgument {
+        message: format!("Schedule not found: {id}"),
+    })
+}
+
+/// Get all schedules, the next one to run first
+pub fn get_schedules(conn: &Connection) -> Result<Vec<Schedule>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SCHEDULE_COLUMNS} FROM schedules ORDER BY next_run, created_at"
+    ))?;
+    let schedules = stmt
+        .query_map([], row_to_schedule)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(schedules)
+}
+
+/// Get the schedules whose next run is at or before `now`
+pub fn get_due_schedules(conn: &Connection, now: i64) -> Result<Vec<Schedule>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SCHEDULE_COLUMNS} FROM schedules WHERE next_run <= ?1 ORDER BY next_run, created_at"
+    ))?;
+    let schedules = stmt
+        .query_map([now], row_to_schedule)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(schedules)
+}
+
+/// Record that a schedule ran and when it runs next
+pub fn record_schedule_run(
+    conn: &Connection,
+    id: &str,
+    ran_at: i64,
+    task_id: &str,
+    next_run: i64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE schedules SET last_run = ?1, last_task_id = ?2, next_run = ?3 WHERE id = ?4",
+        (ran_at, task_id, next_run, id),
+    )?;
+    Ok(())
+}
+
+/// Delete a schedule by ID; tasks it already created are kept
+pub fn delete_schedule(conn: &Connection, id: &str) -> Result<()> {
+    let rows_affected = conn.execute("DELETE FROM schedules WHERE id = ?1", [id])?;
+
+    if rows_affected == 0 {
+        return Err(GhostError::InvalidArgument {
+            message: format!("Schedule not found: {id}"),
+        });
+    }
+
+    Ok(())
+}
+
+/// Link a task to the schedule that created it
+pub fn set_task_schedule(conn: &Connection, task_id: &str, schedule_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE tasks SET schedule_id = ?1 WHERE id = ?2",
+        (schedule_id, task_id),
+    )?;
+    Ok(())
+}
+
+fn row_to_schedule(row: &Row) -> rusqlite::Result<Schedule> {
+    Ok(Schedule {
+        id: row.get(0)?,
+        command: row.get(1)?,
+        env: row.get(2)?,
+        cwd: row.get(3)?,
+        options: row.get(4)?,
+        cron: row.get(5)?,
+        next_run: row.get(6)?,
+        last_run: row.get(7)?,
+        last_task_id: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::database::init_schema;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn insert(conn: &Connection, id: &str, cron: Option<&str>, next_run: i64) {
+        insert_schedule(
+            conn,
+            id,
+            &["echo".to_string(), id.to_string()],
+            None,
+            None,
+            &RunOptions::default(),
+            cron,
+            next_run,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_due_schedules_in_order() {
+        let conn = setup_test_db();
+        insert(&conn, "later", Some("0 * * * *"), 300);
+        insert(&conn, "soon", None, 100);
+        insert(&conn, "now", Some("* * * * *"), 200);
+
+        let due: Vec<String> = get_due_schedules(&conn, 200)
+            .unwrap()
+            .into_iter()
+            .map(|schedule| schedule.id)
+            .collect();
+        assert_eq!(due, vec!["soon", "now"]);
+
+        let all: Vec<String> = get_schedules(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|schedule| schedule.id)
+            .collect();
+        assert_eq!(all, vec!["soon", "now", "later"]);
+    }
+
+    #[test]
+    fn test_record_run_and_delete() {
+        let conn = setup_test_db();
+        insert(&conn, "hourly", Some("0 * * * *"), 100);
+
+        record_schedule_run(&conn, "hourly", 100, "task-1", 3700).unwrap();
+        let schedule = get_schedule(&conn, "hourly").unwrap();
+        assert_eq!(schedule.next_run, 3700);
+        assert_eq!(schedule.last_run, Some(100));
+        assert_eq!(schedule.last_task_id.as_deref(), Some("task-1"));
+        assert_eq!(schedule.cron.as_deref(), Some("0 * * * *"));
+
+        delete_schedule(&conn, "hourly").unwrap();
+        assert!(matches!(
+            get_schedule(&conn, "hourly"),
+            Err(GhostError::InvalidArgument { .. })
+        ));
+        assert!(delete_schedule(&conn, "hourly").is_err());
+    }
+}
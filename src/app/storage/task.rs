@@ -1,4 +1,5 @@
 use super::task_status::TaskStatus;
+use crate::app::run_options::RunOptions;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Task {
@@ -13,4 +14,75 @@ pub struct Task {
     pub started_at: i64, // Unix timestamp
     pub finished_at: Option<i64>,
     pub log_path: String,
+    #[serde(default)]
+    pub options: Option<String>, // JSON serialized RunOptions
+    #[serde(default)]
+    pub restart_count: u32,
+    #[serde(default)]
+    pub supervisor_pid: Option<u32>,
+    #[serde(default)]
+    pub cgroup: Option<String>, // Per-task cgroup enforcing resource limits
+    #[serde(default)]
+    pub exit_reason: Option<String>, // e.g. "memory limit" when a limit ended the task
+    #[serde(default)]
+    pub queue: Option<String>, // Name of the queue the task was added to
+    #[serde(default)]
+    pub queue_position: Option<i64>, // Order among the queue's waiting tasks
+    #[serde(default)]
+    pub after_task: Option<String>, // Task that has to finish before this one starts
+    #[serde(default)]
+    pub after_condition: Option<AfterCondition>, // How after_task has to finish
+    #[serde(default)]
+    pub schedule_id: Option<String>, // Schedule whose run created the task
+    #[serde(default)]
+    pub watch_trigger: Option<String>, // Changed path that caused the last watch restart
+    #[serde(default)]
+    pub name: Option<String>, // Label given when the task was started from the TUI
+}
+
+/// How the previous task has to finish for a dependent task to start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterCondition {
+    /// The previous task exited with code 0
+    Success,
+    /// The previous task exited with another code or was killed
+    Failure,
+}
+
+impl AfterCondition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AfterCondition::Success => "success",
+            AfterCondition::Failure => "failure",
+        }
+    }
+}
+
+impl std::fmt::Display for AfterCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for AfterCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(AfterCondition::Success),
+            "failure" => Ok(AfterCondition::Failure),
+            _ => Err(format!("Unknown condition: {s}")),
+        }
+    }
+}
+
+impl Task {
+    /// Parse the run options stored with this task
+    pub fn run_options(&self) -> RunOptions {
+        self.options
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
 }
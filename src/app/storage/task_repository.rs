@@ -5,7 +5,11 @@ use rusqlite::{Connection, Result as SqliteResult, Row};
 use super::task::Task;
 use super::task_status::TaskStatus;
 use crate::app::error::Result;
-use crate::app::process_state;
+use crate::app::run_options::RunOptions;
+use crate::app::{process, process_state};
+
+/// Columns selected for a task, in the order expected by `row_to_task`
+pub const TASK_COLUMNS: &str = "id, pid, pgid, command, env, cwd, status, exit_code, started_at, finished_at, log_path, options, restart_count, supervisor_pid, cgroup, exit_reason, queue, queue_position, after_task, after_condition, schedule_id, watch_trigger, name";
 
 /// Insert a new task into the database
 #[allow(clippy::too_many_arguments)]
@@ -48,9 +52,7 @@ pub fn insert_task(
 
 /// Get a task by ID
 pub fn get_task(conn: &Connection, task_id: &str) -> Result<Task> {
-    let mut stmt = conn.prepare(
-        "SELECT id, pid, pgid, command, env, cwd, status, exit_code, started_at, finished_at, log_path FROM tasks WHERE id = ?1"
-    )?;
+    let mut stmt = conn.prepare(&format!("SELECT {TASK_COLUMNS} FROM tasks WHERE id = ?1"))?;
 
     let task = stmt
         .query_row([task_id], row_to_task)
@@ -65,7 +67,7 @@ pub fn get_task(conn: &Connection, task_id: &str) -> Result<Task> {
 
 /// Get all tasks, optionally filtered by status
 pub fn get_tasks(conn: &Connection, status_filter: Option<&str>) -> Result<Vec<Task>> {
-    let base_sql = "SELECT id, pid, pgid, command, env, cwd, status, exit_code, started_at, finished_at, log_path FROM tasks";
+    let base_sql = format!("SELECT {TASK_COLUMNS} FROM tasks");
     let order_clause = " ORDER BY started_at DESC";
 
     let sql = match status_filter {
@@ -88,6 +90,17 @@ pub fn get_tasks(conn: &Connection, status_filter: Option<&str>) -> Result<Vec<T
     Ok(tasks)
 }
 
+/// Get the tasks with the given name, newest first
+pub fn get_tasks_by_name(conn: &Connection, name: &str) -> Result<Vec<Task>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks WHERE name = ?1 ORDER BY started_at DESC"
+    ))?;
+    let tasks = stmt
+        .query_map([name], row_to_task)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(tasks)
+}
+
 /// Get all tasks with process status checking
 pub fn get_tasks_with_process_check(
     conn: &Connection,
@@ -129,10 +142,13 @@ pub fn update_task_status(
 }
 
 /// Update task status by checking if the process is still running
+///
+/// A task whose supervisor is alive is left alone: the supervisor records how
+/// it ends, and its process may just have exited and not been recorded yet.
 pub fn update_task_status_by_process_check(conn: &Connection, task_id: &str) -> Result<Task> {
     let task = get_task(conn, task_id)?;
 
-    if task.status == TaskStatus::Running {
+    if task.status == TaskStatus::Running && !task.supervisor_pid.is_some_and(process::exists) {
         let new_status = process_state::determine_task_status(task.pid);
         update_task_status(conn, task_id, new_status, None)?;
 
@@ -143,6 +159,157 @@ pub fn update_task_status_by_process_check(conn: &Connection, task_id: &str) ->
     }
 }
 
+/// Store the run options a task was started with
+pub fn set_task_options(conn: &Connection, task_id: &str, options: &RunOptions) -> Result<()> {
+    let options_json = serde_json::to_string(options)?;
+    conn.execute(
+        "UPDATE tasks SET options = ?1 WHERE id = ?2",
+        (options_json, task_id),
+    )?;
+    Ok(())
+}
+
+/// Record the cgroup created to enforce a task's resource limits
+pub fn set_task_cgroup(conn: &Connection, task_id: &str, cgroup: &Path) -> Result<()> {
+    conn.execute(
+        "UPDATE tasks SET cgroup = ?1 WHERE id = ?2",
+        (cgroup.to_string_lossy(), task_id),
+    )?;
+    Ok(())
+}
+
+/// Record the PID of the supervisor process watching a task
+pub fn set_supervisor_pid(conn: &Connection, task_id: &str, supervisor_pid: u32) -> Result<()> {
+    conn.execute(
+        "UPDATE tasks SET supervisor_pid = ?1 WHERE id = ?2",
+        (supervisor_pid as i64, task_id),
+    )?;
+    Ok(())
+}
+
+/// Forget a task's supervisor, e.g. once it has been stopped
+pub fn clear_supervisor_pid(conn: &Connection, task_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE tasks SET supervisor_pid = NULL WHERE id = ?1",
+        [task_id],
+    )?;
+    Ok(())
+}
+
+/// Record how a task's process ended
+///
+/// The status is only changed while the task is still starting or running, so a
+/// status set by `ghost stop` is kept and merely gains the exit code.
+pub fn record_task_exit(
+    conn: &Connection,
+    task_id: &str,
+    exit_code: Option<i32>,
+    status: TaskStatus,
+    exit_reason: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        r#"
+        UPDATE tasks SET
+            exit_code = ?1,
+            status = CASE WHEN status IN ('starting', 'running') THEN ?2 ELSE status END,
+            finished_at = COALESCE(finished_at, ?3),
+            exit_reason = ?4
+        WHERE id = ?5
+        "#,
+        (
+            exit_code,
+            status.as_str(),
+            crate::app::helpers::now_timestamp(),
+            exit_reason,
+            task_id,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Mark a task as starting again before its supervisor replaces its process
+///
+/// A running task is restarted, or with `exited` one whose supervisor saw it
+/// exit and keeps watching it. Returns false when something else, such as
+/// `ghost stop`, ended the task meanwhile, so it must not be started again.
+pub fn mark_task_restarting(conn: &Connection, task_id: &str, exited: bool) -> Result<bool> {
+    let sql = if exited {
+        "UPDATE tasks SET status = 'starting' WHERE id = ?1 AND status IN ('exited', 'killed')"
+    } else {
+        "UPDATE tasks SET status = 'starting' WHERE id = ?1 AND status = 'running'"
+    };
+    Ok(conn.execute(sql, [task_id])? > 0)
+}
+
+/// Point a restarting task at its freshly spawned process
+///
+/// Returns false, changing nothing, unless the task is still starting.
+pub fn record_task_restart(conn: &Connection, task_id: &str, pid: u32, pgid: i32) -> Result<bool> {
+    let updated = conn.execute(
+        r#"
+        UPDATE tasks SET
+            pid = ?1,
+            pgid = ?2,
+            status = 'running',
+            exit_code = NULL,
+            finished_at = NULL,
+            exit_reason = NULL,
+            restart_count = restart_count + 1
+        WHERE id = ?3 AND status = 'starting'
+        "#,
+        (pid as i64, pgid as i64, task_id),
+    )?;
+    Ok(updated > 0)
+}
+
+/// Point a claimed task at the process that was started for it
+///
+/// Returns false, changing nothing, unless the task is still starting, e.g.
+/// because its claim was given up on.
+pub fn record_task_start(
+    conn: &Connection,
+    task_id: &str,
+    pid: u32,
+    pgid: i32,
+    cgroup: Option<&Path>,
+) -> Result<bool> {
+    let updated = conn.execute(
+        r#"
+        UPDATE tasks SET
+            pid = ?1,
+            pgid = ?2,
+            status = 'running',
+            started_at = ?3,
+            cgroup = ?4,
+            queue_position = NULL
+        WHERE id = ?5 AND status = 'starting'
+        "#,
+        (
+            pid as i64,
+            pgid as i64,
+            crate::app::helpers::now_timestamp(),
+            cgroup.map(|path| path.to_string_lossy().to_string()),
+            task_id,
+        ),
+    )?;
+    Ok(updated > 0)
+}
+
+/// Give a task a name to show next to its command
+pub fn set_task_name(conn: &Connection, task_id: &str, name: &str) -> Result<()> {
+    conn.execute("UPDATE tasks SET name = ?1 WHERE id = ?2", (name, task_id))?;
+    Ok(())
+}
+
+/// Record the changed path that made a watched task restart
+pub fn set_watch_trigger(conn: &Connection, task_id: &str, path: &Path) -> Result<()> {
+    conn.execute(
+        "UPDATE tasks SET watch_trigger = ?1 WHERE id = ?2",
+        (path.to_string_lossy(), task_id),
+    )?;
+    Ok(())
+}
+
 /// Delete a task by ID
 pub fn delete_task(conn: &Connection, task_id: &str) -> Result<()> {
     let rows_affected = conn.execute("DELETE FROM tasks WHERE id = ?1", [task_id])?;
@@ -170,5 +337,19 @@ pub fn row_to_task(row: &Row) -> SqliteResult<Task> {
         started_at: row.get(8)?,
         finished_at: row.get(9)?,
         log_path: row.get(10)?,
+        options: row.get(11)?,
+        restart_count: row.get::<_, i64>(12)? as u32,
+        supervisor_pid: row.get::<_, Option<i64>>(13)?.map(|p| p as u32),
+        cgroup: row.get(14)?,
+        exit_reason: row.get(15)?,
+        queue: row.get(16)?,
+        queue_position: row.get(17)?,
+        after_task: row.get(18)?,
+        after_condition: row
+            .get::<_, Option<String>>(19)?
+            .and_then(|condition| condition.parse().ok()),
+        schedule_id: row.get(20)?,
+        watch_trigger: row.get(21)?,
+        name: row.get(22)?,
     })
 }
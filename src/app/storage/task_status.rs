@@ -2,9 +2,17 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TaskStatus {
+    /// Waiting in a queue for a free slot
+    Queued,
+    /// Waiting for the task it runs after to finish
+    Waiting,
+    /// Claimed from a queue or chain; its process is being spawned
+    Starting,
     Running,
     Exited,
     Killed,
+    /// Will never run, e.g. because an earlier step of its chain failed
+    Cancelled,
     Unknown,
 }
 
@@ -18,13 +26,25 @@ impl TaskStatus {
     /// Convert TaskStatus to string for database storage
     pub fn as_str(&self) -> &'static str {
         match self {
+            TaskStatus::Queued => "queued",
+            TaskStatus::Waiting => "waiting",
+            TaskStatus::Starting => "starting",
             TaskStatus::Running => "running",
             TaskStatus::Exited => "exited",
             TaskStatus::Killed => "killed",
+            TaskStatus::Cancelled => "cancelled",
             TaskStatus::Unknown => "unknown",
         }
     }
 
+    /// Whether the task is done for good and will write no more output
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Exited | TaskStatus::Killed | TaskStatus::Cancelled | TaskStatus::Unknown
+        )
+    }
+
     /// Parse TaskStatus from string (for database retrieval)
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> TaskStatus {
@@ -37,9 +57,13 @@ impl std::str::FromStr for TaskStatus {
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s {
+            "queued" => Ok(TaskStatus::Queued),
+            "waiting" => Ok(TaskStatus::Waiting),
+            "starting" => Ok(TaskStatus::Starting),
             "running" => Ok(TaskStatus::Running),
             "exited" => Ok(TaskStatus::Exited),
             "killed" => Ok(TaskStatus::Killed),
+            "cancelled" => Ok(TaskStatus::Cancelled),
             "unknown" => Ok(TaskStatus::Unknown),
             _ => Err(format!("Unknown task status: {s}")),
         }
@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OptionalExtension as _, Row};
+
+use crate::app::error::{GhostError, Result};
+use crate::app::run_options::RunOptions;
+
+/// Columns selected for a template, in the order expected by `row_to_template`
+const TEMPLATE_COLUMNS: &str = "name, command, env, cwd, options, created_at";
+
+/// A saved command that `ghost start` runs by name
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub command: String,     // JSON serialized Vec<String>
+    pub env: Option<String>, // JSON serialized environment variables given with -e
+    pub cwd: Option<String>,
+    pub options: Option<String>, // JSON serialized RunOptions
+    pub created_at: i64,
+}
+
+impl Template {
+    /// Get the run options stored with the template
+    pub fn run_options(&self) -> RunOptions {
+        self.options
+            .as_deref()
+            .and_then(|options| serde_json::from_str(options).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Save a template, replacing any template with the same name
+pub fn insert_template(
+    conn: &Connection,
+    name: &str,
+    command: &[String],
+    env: &[(String, String)],
+    cwd: Option<&Path>,
+    options: &RunOptions,
+) -> Result<()> {
+    let command_json = serde_json::to_string(command)?;
+    let env_json = if env.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(env)?)
+    };
+    let cwd_str = cwd.map(|p| p.to_string_lossy().to_string());
+    let options_json = if options.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(options)?)
+    };
+
+    conn.execute(
+        r#"
+        INSERT OR REPLACE INTO templates (
+            name, command, env, cwd, options, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+        (
+            name,
+            command_json,
+            env_json,
+            cwd_str,
+            options_json,
+            crate::app::helpers::now_timestamp(),
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Get a template by name
+pub fn get_template(conn: &Connection, name: &str) -> Result<Template> {
+    conn.query_row(
+        &format!("SELECT {TEMPLATE_COLUMNS} FROM templates WHERE name = ?1"),
+        [name],
+        row_to_template,
+    )
+    .optional()?
+    .ok_or_else(|| GhostError::InvalidArgument {
+        message: format!("Template not found: {name}"),
+    })
+}
+
+/// Get all templates, sorted by name
+pub fn get_templates(conn: &Connection) -> Result<Vec<Template>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {TEMPLATE_COLUMNS} FROM templates ORDER BY name"
+    ))?;
+    let templates = stmt
+        .query_map([], row_to_template)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(templates)
+}
+
+/// Delete a template by name
+pub fn delete_template(conn: &Connection, name: &str) -> Result<()> {
+    let rows_affected = conn.execute("DELETE FROM templates WHERE name = ?1", [name])?;
+
+    if rows_affected == 0 {
+        return Err(GhostError::InvalidArgument {
+            message: format!("Template not found: {name}"),
+        });
+    }
+
+    Ok(())
+}
+
+fn row_to_template(row: &Row) -> rusqlite::Result<Template> {
+    Ok(Template {
+        name: row.get(0)?,
+        command: row.get(1)?,
+        env: row.get(2)?,
+        cwd: row.get(3)?,
+        options: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::database::init_schema;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_save_replaces_template_with_same_name() {
+        let conn = setup_test_db();
+        let command = vec!["cargo".to_string(), "run".to_string()];
+        insert_template(&conn, "api", &command, &[], None, &RunOptions::default()).unwrap();
+        insert_template(&conn, "web", &command, &[], None, &RunOptions::default()).unwrap();
+
+        let env = vec![("RUST_LOG".to_string(), "debug".to_string())];
+        insert_template(
+            &conn,
+            "api",
+            &command,
+            &env,
+            Some(Path::new("/work/api")),
+            &RunOptions::default(),
+        )
+        .unwrap();
+
+        let names: Vec<String> = get_templates(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|template| template.name)
+            .collect();
+        assert_eq!(names, vec!["api", "web"]);
+
+        let api = get_template(&conn, "api").unwrap();
+        assert_eq!(api.cwd.as_deref(), Some("/work/api"));
+        assert_eq!(api.env.as_deref(), Some(r#"[["RUST_LOG","debug"]]"#));
+        assert_eq!(get_template(&conn, "web").unwrap().env, None);
+    }
+
+    #[test]
+    fn test_delete_template() {
+        let conn = setup_test_db();
+        insert_template(
+            &conn,
+            "api",
+            &["true".to_string()],
+            &[],
+            None,
+            &RunOptions::default(),
+        )
+        .unwrap();
+
+        delete_template(&conn, "api").unwrap();
+        assert!(matches!(
+            get_template(&conn, "api"),
+            Err(GhostError::InvalidArgument { .. })
+        ));
+        assert!(delete_template(&conn, "api").is_err());
+    }
+}
@@ -0,0 +1,1278 @@
+//! Per-task supervisor process
+//!
+//! Some run options, such as an idle timeout that stops or restarts a task,
+//! have to be enforced after `ghost run` has returned. Instead of a resident
+//! daemon, ghost starts one small supervisor per task: `ghost supervise`
+//! spawns the command as its own child, reports the task back to the
+//! launching process and then waits for it, enforcing the task's options and
+//! recording its exit code. It also reads the task's log to run the actions
+//! of output triggers, and runs the task's lifecycle hooks.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::process::{CommandExt as _, ExitStatusExt as _};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use nix::sys::signal::Signal;
+use nix::unistd::setsid;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::app::commands;
+use crate::app::config::FileConfig;
+use crate::app::error::{GhostError, Result};
+use crate::app::helpers::format_duration_short;
+use crate::app::hooks::{self, HookContext, HookEvent, HookOutcome, Hooks};
+use crate::app::limits::TaskCgroup;
+use crate::app::notify::Notification;
+use crate::app::output_trigger::{self, OutputMatcher, TriggerAction};
+use crate::app::process::{self, ProcessInfo, TASK_ID_ENV};
+use crate::app::process_tree::TaskProcesses;
+use crate::app::queue;
+use crate::app::run_options::{IdleAction, RunOptions};
+use crate::app::storage::{self, EventKind, TaskStatus};
+use crate::app::watch::FileWatcher;
+
+/// How often the supervisor checks on its task
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a task gets to exit after SIGTERM before it is killed
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Everything the supervisor needs to start (and restart) a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisorSpec {
+    pub command: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub options: RunOptions,
+    /// Claimed queued task to start instead of registering a new one
+    #[serde(default)]
+    pub task_id: Option<String>,
+}
+
+/// First line written by the supervisor to the launching process
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+enum LaunchReport {
+    Started {
+        process: ProcessInfo,
+    },
+    /// The supervisor took over a claimed task and records how its start goes
+    Claimed,
+    Failed {
+        message: String,
+    },
+}
+
+/// Start a supervisor for the spec and wait until it has spawned the task
+///
+/// Returns the task's ProcessInfo and the Child handle of the supervisor.
+pub fn launch(spec: &SupervisorSpec) -> Result<(ProcessInfo, Child)> {
+    match start(spec)? {
+        (LaunchReport::Started { process }, supervisor) => Ok((process, supervisor)),
+        (_, supervisor) => Err(unexpected_report(supervisor)),
+    }
+}
+
+/// Start a supervisor for a claimed task and wait only until it has taken
+/// the task over, not for its pre-start hooks or its process
+///
+/// Returns the Child handle of the supervisor.
+pub fn launch_claimed(spec: &SupervisorSpec) -> Result<Child> {
+    match start(spec)? {
+        (LaunchReport::Claimed, supervisor) => Ok(supervisor),
+        (_, supervisor) => Err(unexpected_report(supervisor)),
+    }
+}
+
+/// Spawn `ghost supervise`, hand it the spec and read its launch report
+fn start(spec: &SupervisorSpec) -> Result<(LaunchReport, Child)> {
+    let exe = std::env::current_exe()?;
+
+    let mut cmd = Command::new(exe);
+    cmd.arg("supervise")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        // A supervisor started from inside another task is not part of that task
+        .env_remove(TASK_ID_ENV);
+
+    // Detach the supervisor from the launching terminal and process group
+    unsafe {
+        cmd.pre_exec(|| {
+            setsid()?;
+            Ok(())
+        });
+    }
+
+    let mut supervisor = cmd.spawn().map_err(|e| GhostError::ProcessSpawn {
+        message: format!("Failed to start supervisor: {e}"),
+    })?;
+
+    if let Some(mut stdin) = supervisor.stdin.take() {
+        serde_json::to_writer(&mut stdin, spec)?;
+        // Dropping stdin closes the pipe so the supervisor sees the whole spec
+    }
+
+    let mut line = String::new();
+    if let Some(stdout) = supervisor.stdout.take() {
+        BufReader::new(stdout).read_line(&mut line)?;
+    }
+
+    match serde_json::from_str::<LaunchReport>(&line) {
+        Ok(LaunchReport::Failed { message }) => {
+            let _ = supervisor.wait();
+            Err(GhostError::ProcessSpawn { message })
+        }
+        Ok(report) => Ok((report, supervisor)),
+        Err(_) => {
+            let _ = supervisor.wait();
+            Err(GhostError::ProcessSpawn {
+                message: "Supervisor exited before starting the task".to_string(),
+            })
+        }
+    }
+}
+
+/// A supervisor reported a launch other than the one asked for; it cannot be
+/// told what to do any more, so it is stopped
+fn unexpected_report(mut supervisor: Child) -> GhostError {
+    let _ = supervisor.kill();
+    let _ = supervisor.wait();
+    GhostError::ProcessSpawn {
+        message: "Supervisor sent an unexpected launch report".to_string(),
+    }
+}
+
+/// Entry point of `ghost supervise`: read the spec from stdin, start the task and supervise it
+pub fn run(conn: &Connection) -> Result<()> {
+    let spec: SupervisorSpec = serde_json::from_reader(std::io::stdin())?;
+    let hooks = task_hooks(&spec.options.hooks);
+
+    // A claimed task is this supervisor's from here on, which keeps its claim
+    // from going stale, and the launching process, often one promoting queues
+    // on the way to something else, need not wait for the pre-start hooks
+    if let Some(task_id) = &spec.task_id {
+        let claimed = storage::set_supervisor_pid(conn, task_id, std::process::id());
+        let report = match &claimed {
+            Ok(()) => LaunchReport::Claimed,
+            Err(e) => LaunchReport::Failed {
+                message: e.to_string(),
+            },
+        };
+        write_report(&report)?;
+        claimed?;
+    }
+
+    // Watch before starting the task so a path that cannot be watched is reported
+    let started = spec
+        .options
+        .watch
+        .as_ref()
+        .map(|watch| FileWatcher::new(watch, spec.cwd.as_deref()))
+        .transpose()
+        .and_then(|watcher| {
+            let output = (!spec.options.on_output.is_empty())
+                .then(|| OutputMatcher::new(&spec.options.on_output))
+                .transpose()?;
+            Ok((watcher, output))
+        })
+        .and_then(|(watcher, output)| {
+            // The task starts whatever its pre-start hooks do
+            let task_id = spec.task_id.clone().unwrap_or_else(process::new_task_id);
+            let log_path = match &spec.task_id {
+                Some(task_id) => PathBuf::from(storage::get_task(conn, task_id)?.log_path),
+                None => process::log_path_for(&task_id, None),
+            };
+            let context = hook_context(&spec, &task_id, &log_path);
+            run_hooks_now(conn, &hooks, HookEvent::PreStart, &context);
+
+            let (info, child) = match &spec.task_id {
+                Some(task_id) => queue::spawn_claimed(conn, task_id, &spec),
+                None => commands::register_process(
+                    conn,
+                    task_id,
+                    spec.command.clone(),
+                    spec.cwd.clone(),
+                    spec.env.clone(),
+                    &spec.options,
+                ),
+            }?;
+            storage::set_supervisor_pid(conn, &info.id, std::process::id())?;
+            Ok((info, child, watcher, output))
+        });
+
+    match (&spec.task_id, &started) {
+        (None, Ok((info, _, _, _))) => write_report(&LaunchReport::Started {
+            process: info.clone(),
+        })?,
+        (None, Err(e)) => write_report(&LaunchReport::Failed {
+            message: e.to_string(),
+        })?,
+        // Nobody waits for the report of a claimed task any more
+        (Some(task_id), Err(e)) => {
+            queue::record_launch_failure(conn, task_id, e)?;
+            if let Err(e) = queue::promote(conn) {
+                warn!("Failed to start queued tasks: {e}");
+            }
+        }
+        (Some(_), Ok(_)) => {}
+    }
+
+    let (info, child, watcher, output) = started?;
+    info!("Supervising task {} (pid {})", info.id, info.pid);
+
+    let result = Supervisor::new(spec, info, child)
+        .with_watcher(watcher)
+        .with_output_matcher(output)
+        .with_hooks(hooks)
+        .run(conn);
+
+    // The task's slot is free now; start whatever waits in the queues
+    if let Err(e) = queue::promote(conn) {
+        warn!("Failed to start queued tasks: {e}");
+    }
+
+    result
+}
+
+/// Write the launch report, the single line the launching process reads;
+/// nothing else may be written to stdout afterwards because nobody is reading
+/// it any more
+fn write_report(report: &LaunchReport) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    serde_json::to_writer(&mut stdout, report)?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Hooks a task runs: those of the config file, then its own
+fn task_hooks(own: &Hooks) -> Vec<Hooks> {
+    let global = FileConfig::load()
+        .map(|config| config.hooks)
+        .unwrap_or_else(|e| {
+            warn!("Ignoring the hooks of the config file: {e}");
+            Hooks::default()
+        });
+    [global, own.clone()]
+        .into_iter()
+        .filter(|hooks| !hooks.is_empty())
+        .collect()
+}
+
+fn hook_context(spec: &SupervisorSpec, task_id: &str, log_path: &Path) -> HookContext {
+    HookContext {
+        task_id: task_id.to_string(),
+        command: spec.command.clone(),
+        cwd: spec.cwd.clone(),
+        log_path: log_path.to_path_buf(),
+        env: spec.env.clone(),
+        ..Default::default()
+    }
+}
+
+/// Run the hooks for `event` one after another and wait for them
+fn run_hooks_now(conn: &Connection, hooks: &[Hooks], event: HookEvent, context: &HookContext) {
+    for hooks in hooks {
+        if let Some(command) = hooks.command(event) {
+            let outcome = hooks::run(event, command, context, hooks.timeout());
+            record_hook(conn, &context.task_id, &outcome);
+        }
+    }
+}
+
+/// Add a finished hook to the task's event history
+///
+/// A hook that failed, or could not even be recorded, does not affect the task.
+fn record_hook(conn: &Connection, task_id: &str, outcome: &HookOutcome) {
+    let message = outcome.describe();
+    if outcome.succeeded() {
+        info!("Task {task_id}: {message}");
+    } else {
+        warn!("Task {task_id}: {message}");
+    }
+    if let Err(e) = storage::insert_task_event(conn, task_id, EventKind::Hook, &message) {
+        warn!("Failed to record hook of task {task_id}: {e}");
+    }
+}
+
+/// Watches a single task's process until it has exited for good
+struct Supervisor {
+    spec: SupervisorSpec,
+    task_id: String,
+    log_path: PathBuf,
+    child: Child,
+    pgid: i32,
+    cgroup: Option<TaskCgroup>,
+    last_start: SystemTime,
+    /// Whether the current process has not been reaped yet
+    running: bool,
+    /// How the current process exited and the CPU seconds it used, once reaped
+    exited: Option<(ExitStatus, u64)>,
+    watcher: Option<FileWatcher>,
+    output: Option<OutputMatcher>,
+    hooks: Vec<Hooks>,
+    /// Hooks started on threads that have not been recorded yet
+    running_hooks: Vec<JoinHandle<HookOutcome>>,
+}
+
+/// What the actions of output triggers did to the task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Triggered {
+    Nothing,
+    Restarted,
+    Stopped,
+}
+
+impl Supervisor {
+    fn new(spec: SupervisorSpec, info: ProcessInfo, child: Child) -> Self {
+        Self {
+            spec,
+            task_id: info.id,
+            log_path: info.log_path,
+            child,
+            pgid: info.pgid,
+            cgroup: info.cgroup.map(TaskCgroup::open),
+            last_start: SystemTime::now(),
+            running: true,
+            exited: None,
+            watcher: None,
+            output: None,
+            hooks: Vec::new(),
+            running_hooks: Vec::new(),
+        }
+    }
+
+    /// Restart the task whenever the watcher reports a change
+    fn with_watcher(mut self, watcher: Option<FileWatcher>) -> Self {
+        self.watcher = watcher;
+        self
+    }
+
+    /// Run the actions of output triggers on lines the task writes to its log
+    fn with_output_matcher(mut self, output: Option<OutputMatcher>) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Run these hooks around the task's lifecycle
+    fn with_hooks(mut self, hooks: Vec<Hooks>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Supervise until the task's process has exited and will not be restarted
+    ///
+    /// In watch mode that never happens; the supervisor is stopped by `ghost stop`.
+    fn run(mut self, conn: &Connection) -> Result<()> {
+        self.start_hooks(HookEvent::PostStart, &self.hook_context());
+        let result = self.supervise(conn);
+        // On-exit hooks may still be running
+        self.collect_hooks(conn, true);
+        result
+    }
+
+    fn supervise(&mut self, conn: &Connection) -> Result<()> {
+        loop {
+            self.collect_hooks(conn, false);
+
+            if let Some(path) = self.watcher.as_mut().and_then(FileWatcher::poll) {
+                self.restart_for_change(conn, &path)?;
+            }
+
+            if self.running
+                && self.check_output(conn)? == Triggered::Stopped
+                && self.watcher.is_none()
+            {
+                return Ok(());
+            }
+
+            if !self.running {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            if let Some(status) = self.try_wait()? {
+                // Lines written just before the exit may still restart or stop the task
+                match self.check_output(conn)? {
+                    Triggered::Stopped if self.watcher.is_none() => return Ok(()),
+                    Triggered::Stopped | Triggered::Restarted => continue,
+                    Triggered::Nothing => {}
+                }
+                let reason = self.exit_reason(status);
+                info!(
+                    "Task {} exited with {status} ({})",
+                    self.task_id,
+                    reason.unwrap_or("no limit hit")
+                );
+                self.record_exit(conn, status, task_status_for_exit(status), reason)?;
+                self.remove_cgroup();
+                if self.watcher.is_none() {
+                    return Ok(());
+                }
+                // Keep watching; the next change starts the task again
+                self.running = false;
+                continue;
+            }
+
+            if let Some(idle) = self.idle_for() {
+                match self.spec.options.idle_action() {
+                    Some(IdleAction::Stop) => {
+                        warn!(
+                            "Task {} produced no output for {}, stopping it",
+                            self.task_id,
+                            format_duration_short(idle.as_secs())
+                        );
+                        let status = self.terminate()?;
+                        self.record_exit(conn, status, TaskStatus::Killed, Some("idle timeout"))?;
+                        self.remove_cgroup();
+                        return Ok(());
+                    }
+                    Some(IdleAction::Restart) => self.restart(conn, idle)?,
+                    Some(IdleAction::Warn) | None => {}
+                }
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Record how the task's process exited and start the on-exit and on-failure hooks
+    fn record_exit(
+        &mut self,
+        conn: &Connection,
+        status: ExitStatus,
+        task_status: TaskStatus,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        storage::record_task_exit(conn, &self.task_id, status.code(), task_status, reason)?;
+        self.notify_terminal(conn);
+
+        let context = HookContext {
+            status: Some(task_status),
+            exit_code: status.code(),
+            exit_reason: reason.map(str::to_string),
+            duration: Some(
+                SystemTime::now()
+                    .duration_since(self.last_start)
+                    .unwrap_or_default()
+                    .as_secs(),
+            ),
+            ..self.hook_context()
+        };
+        self.start_hooks(HookEvent::Exit, &context);
+        if is_failure(status, reason, FileConfig::current().stop.signal()) {
+            self.start_hooks(HookEvent::Failure, &context);
+        }
+        Ok(())
+    }
+
+    /// Tell the terminal `ghost run --notify` was started from that the task ended
+    fn notify_terminal(&self, conn: &Connection) {
+        let Some(notify) = &self.spec.options.notify else {
+            return;
+        };
+        let notification = match storage::get_task(conn, &self.task_id) {
+            Ok(task) => Notification::for_task_end(&task),
+            Err(e) => {
+                warn!(
+                    "Failed to read task {} to notify of its end: {e}",
+                    self.task_id
+                );
+                return;
+            }
+        };
+        // The terminal may well have been closed since
+        if let Err(e) = notify.send(&notification) {
+            info!("Could not notify {}: {e}", notify.tty.display());
+        }
+    }
+
+    fn hook_context(&self) -> HookContext {
+        hook_context(&self.spec, &self.task_id, &self.log_path)
+    }
+
+    /// Start the hooks for `event` on threads of their own
+    fn start_hooks(&mut self, event: HookEvent, context: &HookContext) {
+        for hooks in &self.hooks {
+            if let Some(command) = hooks.command(event) {
+                self.running_hooks
+                    .push(hooks::spawn(event, command, context, hooks.timeout()));
+            }
+        }
+    }
+
+    /// Record the hooks that have finished, or with `wait`, wait for all of them
+    fn collect_hooks(&mut self, conn: &Connection, wait: bool) {
+        let (finished, running) = std::mem::take(&mut self.running_hooks)
+            .into_iter()
+            .partition(|hook| wait || hook.is_finished());
+        self.running_hooks = running;
+        for hook in finished {
+            if let Ok(outcome) = hook.join() {
+                record_hook(conn, &self.task_id, &outcome);
+            }
+        }
+    }
+
+    /// Run the actions of the output triggers matching lines written since the last check
+    fn check_output(&mut self, conn: &Connection) -> Result<Triggered> {
+        let matches = match self.output.as_mut() {
+            Some(output) => output.poll(&self.log_path),
+            None => return Ok(Triggered::Nothing),
+        };
+
+        for found in matches {
+            let message = found.describe();
+            let kind = match found.trigger.action {
+                TriggerAction::Ready => EventKind::Ready,
+                _ => EventKind::Trigger,
+            };
+            info!("Output trigger of task {} fired: {message}", self.task_id);
+            storage::insert_task_event(conn, &self.task_id, kind, &message)?;
+
+            match &found.trigger.action {
+                TriggerAction::Notify => {
+                    let title = format!("ghost: task {}", short_id(&self.task_id));
+                    output_trigger::notify_desktop(&title, &found.line);
+                }
+                TriggerAction::Ready => {}
+                TriggerAction::Exec(command) => self.exec_for_output(command, &found.line),
+                TriggerAction::Stop => {
+                    let status = self.terminate()?;
+                    self.record_exit(
+                        conn,
+                        status,
+                        task_status_for_exit(status),
+                        Some("output trigger"),
+                    )?;
+                    self.remove_cgroup();
+                    self.running = false;
+                    return Ok(Triggered::Stopped);
+                }
+                TriggerAction::Restart => {
+                    // Later lines were written by the process being replaced
+                    self.respawn(conn, &format!("output matched /{}/", found.trigger.pattern))?;
+                    return Ok(Triggered::Restarted);
+                }
+            }
+        }
+        Ok(Triggered::Nothing)
+    }
+
+    /// Start the command of an exec trigger without waiting for it
+    fn exec_for_output(&self, command: &str, line: &str) {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .envs(self.spec.env.iter().cloned())
+            .env(TASK_ID_ENV, &self.task_id)
+            .env("GHOST_TRIGGER_LINE", line)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(cwd) = &self.spec.cwd {
+            cmd.current_dir(cwd);
+        }
+        match cmd.spawn() {
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => warn!("Failed to run '{command}' for task {}: {e}", self.task_id),
+        }
+    }
+
+    /// How long the task has been silent, if that exceeds its idle timeout
+    fn idle_for(&self) -> Option<Duration> {
+        let timeout = Duration::from_secs(self.spec.options.idle_timeout?);
+
+        // Output written before the last (re)start does not count
+        let last_output = std::fs::metadata(&self.log_path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(self.last_start)
+            .max(self.last_start);
+
+        let idle = SystemTime::now()
+            .duration_since(last_output)
+            .unwrap_or_default();
+        (idle >= timeout).then_some(idle)
+    }
+
+    /// Stop the task's whole process tree with the configured stop signal,
+    /// escalating to SIGKILL after the grace period
+    fn terminate(&mut self) -> Result<ExitStatus> {
+        let signal = FileConfig::current().stop.signal();
+        let _ = process::signal_group(self.pgid, signal);
+        self.task_processes().send(signal);
+
+        let deadline = Instant::now() + STOP_GRACE_PERIOD;
+        let status = loop {
+            if let Some(status) = self.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = process::kill_group(self.pgid, true);
+                break self.wait()?;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        // Descendants that outlived the leader, e.g. daemonized children
+        self.task_processes().signal(true);
+        Ok(status)
+    }
+
+    /// Reap the task's process if it has exited, without blocking
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        self.wait4(libc::WNOHANG)
+    }
+
+    /// Wait for the task's process to exit
+    fn wait(&mut self) -> Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.wait4(0)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Reap the task's process with wait4(2), which also reports the CPU time
+    /// of that process alone, unlike the RUSAGE_CHILDREN total that includes
+    /// earlier runs, hooks and trigger commands
+    fn wait4(&mut self, options: libc::c_int) -> Result<Option<ExitStatus>> {
+        if let Some((status, _)) = self.exited {
+            return Ok(Some(status));
+        }
+
+        let mut status = 0;
+        // SAFETY: rusage is plain data, valid when zeroed
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: wait4 only writes to the status and usage it is given
+        let pid = unsafe {
+            libc::wait4(
+                self.child.id() as libc::pid_t,
+                &mut status,
+                options,
+                &mut usage,
+            )
+        };
+        match pid {
+            0 => return Ok(None),
+            -1 => {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    return Ok(None);
+                }
+                return Err(error.into());
+            }
+            _ => {}
+        }
+
+        let cpu_seconds = (usage.ru_utime.tv_sec + usage.ru_stime.tv_sec).max(0) as u64;
+        let status = ExitStatus::from_raw(status);
+        self.exited = Some((status, cpu_seconds));
+        Ok(Some(status))
+    }
+
+    fn task_processes(&self) -> TaskProcesses<'_> {
+        TaskProcesses {
+            task_id: &self.task_id,
+            pid: self.child.id(),
+            pgid: self.pgid,
+            cgroup: self.cgroup.as_ref().map(TaskCgroup::path),
+        }
+    }
+
+    /// Stop the task and start its command again under the same task ID and log
+    fn restart(&mut self, conn: &Connection, idle: Duration) -> Result<()> {
+        warn!(
+            "Task {} produced no output for {}, restarting it",
+            self.task_id,
+            format_duration_short(idle.as_secs())
+        );
+        self.respawn(
+            conn,
+            &format!("no output for {}", format_duration_short(idle.as_secs())),
+        )
+    }
+
+    /// Restart the task, or start it again if it has exited, because a watched file changed
+    fn restart_for_change(&mut self, conn: &Connection, path: &Path) -> Result<()> {
+        info!(
+            "{} changed, restarting task {}",
+            path.display(),
+            self.task_id
+        );
+        storage::set_watch_trigger(conn, &self.task_id, path)?;
+        self.respawn(conn, &format!("{} changed", path.display()))
+    }
+
+    /// Stop the task if it is still running and spawn its command again
+    ///
+    /// `cause` is written to the log, e.g. "--- ghost: src/main.rs changed, restarting ---".
+    fn respawn(&mut self, conn: &Connection, cause: &str) -> Result<()> {
+        // While restarting, the task is starting; one that `ghost stop` ended
+        // is not started again, and its exit is recorded as usual
+        if !storage::mark_task_restarting(conn, &self.task_id, !self.running)? {
+            info!("Task {} was stopped, not restarting it", self.task_id);
+            return Ok(());
+        }
+        if self.running {
+            self.terminate()?;
+        }
+        self.remove_cgroup();
+
+        if let Ok(mut log) = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.log_path)
+        {
+            let _ = writeln!(log, "--- ghost: {cause}, restarting ---");
+        }
+        // Output of the stopped process and the line above do not trigger anything
+        if let Some(output) = self.output.as_mut() {
+            output.skip_to_end(&self.log_path);
+        }
+        run_hooks_now(conn, &self.hooks, HookEvent::PreStart, &self.hook_context());
+
+        let (info, child) = process::respawn_background_process(
+            &self.task_id,
+            self.spec.command.clone(),
+            self.spec.cwd.clone(),
+            &self.log_path,
+            self.spec.env.clone(),
+            &self.spec.options.limits,
+        )?;
+        if !storage::record_task_restart(conn, &self.task_id, info.pid, info.pgid)? {
+            process::discard(info.pgid, child);
+            return Err(GhostError::TaskOperation {
+                task_id: self.task_id.clone(),
+                message: "Task is no longer restarting".to_string(),
+            });
+        }
+        storage::insert_task_event(conn, &self.task_id, EventKind::Restart, cause)?;
+
+        self.child = child;
+        self.exited = None;
+        self.pgid = info.pgid;
+        self.cgroup = info.cgroup.map(TaskCgroup::open);
+        self.last_start = SystemTime::now();
+        self.running = true;
+
+        self.start_hooks(HookEvent::PostStart, &self.hook_context());
+
+        // Changes made while the task was restarting, e.g. by its own startup, do not count
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.clear();
+        }
+        Ok(())
+    }
+}
+
+impl Supervisor {
+    /// Which resource limit, if any, ended the task
+    ///
+    /// Only what the kernel reports counts: the OOM kills in the cgroup's
+    /// `memory.events` and the CPU time of the process itself. rlimits on
+    /// memory (without a cgroup) and open files make system calls fail
+    /// instead, which cannot be told apart from other failures, so a failed
+    /// task with one of those limits gets "unknown".
+    fn exit_reason(&self, status: ExitStatus) -> Option<&'static str> {
+        let limits = &self.spec.options.limits;
+
+        if self.cgroup.as_ref().is_some_and(TaskCgroup::oom_killed) {
+            return Some("memory limit");
+        }
+
+        let signal = status.signal().and_then(|s| Signal::try_from(s).ok());
+        if let Some(cpu_time) = limits.cpu_time {
+            let cpu_seconds = self.exited.map_or(0, |(_, cpu_seconds)| cpu_seconds);
+            let hit_cpu_limit = match signal {
+                Some(Signal::SIGXCPU) => true,
+                // The hard limit is enforced with SIGKILL; tell it apart from `ghost stop --force`
+                Some(Signal::SIGKILL) => cpu_seconds >= cpu_time,
+                _ => false,
+            };
+            if hit_cpu_limit {
+                return Some("cpu time limit");
+            }
+        }
+
+        let undetectable =
+            (limits.memory.is_some() && self.cgroup.is_none()) || limits.nofile.is_some();
+        (undetectable && !status.success()).then_some("unknown")
+    }
+
+    fn remove_cgroup(&mut self) {
+        if let Some(cgroup) = self.cgroup.take() {
+            cgroup.remove();
+        }
+    }
+}
+
+/// Whether an exit runs the on-failure hooks
+///
+/// A task ended by a limit, idle timeout or trigger failed, as did one that
+/// exited with a non-zero code or crashed; one stopped with `ghost stop`, by
+/// `stop_signal` or SIGKILL, or with SIGTERM or SIGINT, did not.
+fn is_failure(status: ExitStatus, reason: Option<&str>, stop_signal: Signal) -> bool {
+    let stopped = status.signal().is_some_and(|signal| {
+        [
+            stop_signal,
+            Signal::SIGTERM,
+            Signal::SIGKILL,
+            Signal::SIGINT,
+        ]
+        .iter()
+        .any(|stop| *stop as i32 == signal)
+    });
+    reason.is_some()
+        || status.code().is_some_and(|code| code != 0)
+        || (status.code().is_none() && !stopped)
+}
+
+/// First part of a task ID, as shown in notifications
+fn short_id(task_id: &str) -> &str {
+    task_id.get(..8).unwrap_or(task_id)
+}
+
+/// Map a process exit status to the task status it should be recorded as
+fn task_status_for_exit(status: ExitStatus) -> TaskStatus {
+    if status.signal().is_some() {
+        TaskStatus::Killed
+    } else {
+        TaskStatus::Exited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        storage::database::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn start_supervisor(
+        conn: &Connection,
+        log_dir: &std::path::Path,
+        command: &[&str],
+        options: RunOptions,
+    ) -> Supervisor {
+        let command: Vec<String> = command.iter().map(|s| s.to_string()).collect();
+        let (info, child) = process::spawn_background_process_with_limits(
+            command.clone(),
+            None,
+            Some(log_dir.to_path_buf()),
+            vec![],
+            &options.limits,
+        )
+        .unwrap();
+        storage::insert_task(
+            conn,
+            &info.id,
+            info.pid,
+            Some(info.pgid),
+            &command,
+            None,
+            None,
+            &info.log_path,
+        )
+        .unwrap();
+
+        let spec = SupervisorSpec {
+            command,
+            cwd: None,
+            env: vec![],
+            options,
+            task_id: None,
+        };
+        Supervisor::new(spec, info, child)
+    }
+
+    #[test]
+    fn test_supervisor_records_exit_code() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let supervisor = start_supervisor(
+            &conn,
+            temp_dir.path(),
+            &["sh", "-c", "exit 3"],
+            RunOptions::default(),
+        );
+        let task_id = supervisor.task_id.clone();
+
+        supervisor.run(&conn).unwrap();
+
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Exited);
+        assert_eq!(task.exit_code, Some(3));
+        assert!(task.finished_at.is_some());
+    }
+
+    #[test]
+    fn test_supervisor_runs_exit_hooks() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let out = temp_dir.path().join("hooks.out");
+        let hooks = Hooks {
+            post_start: Some(format!("echo started >> {}", out.display())),
+            on_exit: Some(format!(
+                "echo \"exit $GHOST_EXIT_CODE $GHOST_STATUS\" >> {}",
+                out.display()
+            )),
+            on_failure: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            hooks: hooks.clone(),
+            ..Default::default()
+        };
+        let supervisor = start_supervisor(&conn, temp_dir.path(), &["sh", "-c", "exit 3"], options)
+            .with_hooks(vec![hooks]);
+        let task_id = supervisor.task_id.clone();
+
+        supervisor.run(&conn).unwrap();
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert!(written.contains("started\n"));
+        assert!(written.contains("exit 3 exited\n"));
+        // A failed hook does not change how the task exited
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.exit_code, Some(3));
+        let mut messages: Vec<String> = storage::get_task_events(&conn, &task_id)
+            .unwrap()
+            .into_iter()
+            .filter(|event| event.kind == EventKind::Hook)
+            .map(|event| event.message)
+            .collect();
+        messages.sort();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].starts_with("on-exit `echo"));
+        assert!(messages[1].starts_with("on-failure `exit 1` exited with 1"));
+        assert!(messages[2].starts_with("post-start `echo"));
+    }
+
+    #[test]
+    fn test_supervisor_stops_idle_task() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            idle_timeout: Some(1),
+            idle_action: Some(IdleAction::Stop),
+            ..Default::default()
+        };
+        let supervisor = start_supervisor(&conn, temp_dir.path(), &["sleep", "30"], options);
+        let task_id = supervisor.task_id.clone();
+        let pid = supervisor.child.id();
+
+        let started = Instant::now();
+        supervisor.run(&conn).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!process::exists(pid));
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Killed);
+        assert_eq!(task.exit_reason.as_deref(), Some("idle timeout"));
+    }
+
+    #[test]
+    fn test_supervisor_reports_cpu_time_limit() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            limits: crate::app::limits::ResourceLimits {
+                cpu_time: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let command = ["sh", "-c", "while :; do :; done"];
+        let supervisor = start_supervisor(&conn, temp_dir.path(), &command, options);
+        let task_id = supervisor.task_id.clone();
+
+        supervisor.run(&conn).unwrap();
+
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Killed);
+        assert_eq!(task.exit_reason.as_deref(), Some("cpu time limit"));
+    }
+
+    #[test]
+    fn test_supervisor_does_not_guess_open_file_limit() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            limits: crate::app::limits::ResourceLimits {
+                nofile: Some(16),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let command = ["sh", "-c", "echo 'open: Too many open files' >&2; exit 1"];
+        let supervisor = start_supervisor(&conn, temp_dir.path(), &command, options);
+        let task_id = supervisor.task_id.clone();
+
+        supervisor.run(&conn).unwrap();
+
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Exited);
+        assert_eq!(task.exit_code, Some(1));
+        assert_eq!(task.exit_reason.as_deref(), Some("unknown"));
+    }
+
+    #[test]
+    fn test_supervisor_tells_sigkill_from_cpu_time_limit() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            limits: crate::app::limits::ResourceLimits {
+                cpu_time: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // CPU time used by an earlier child does not count towards the task
+        let mut busy = Command::new("sh")
+            .args(["-c", "i=0; while [ $i -lt 2000000 ]; do i=$((i+1)); done"])
+            .spawn()
+            .unwrap();
+        busy.wait().unwrap();
+
+        let supervisor = start_supervisor(&conn, temp_dir.path(), &["sleep", "30"], options);
+        let task_id = supervisor.task_id.clone();
+        process::kill(supervisor.child.id(), true).unwrap();
+
+        supervisor.run(&conn).unwrap();
+
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Killed);
+        assert_eq!(task.exit_reason, None);
+    }
+
+    #[test]
+    fn test_supervisor_restart_keeps_task_id_and_log() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            idle_timeout: Some(1),
+            idle_action: Some(IdleAction::Restart),
+            ..Default::default()
+        };
+        let mut supervisor = start_supervisor(&conn, temp_dir.path(), &["sleep", "30"], options);
+        let old_pid = supervisor.child.id();
+
+        supervisor.restart(&conn, Duration::from_secs(60)).unwrap();
+
+        let task = storage::get_task(&conn, &supervisor.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Running);
+        assert_eq!(task.restart_count, 1);
+        assert_ne!(task.pid, old_pid);
+        assert_eq!(task.pid, supervisor.child.id());
+
+        let log = std::fs::read_to_string(&supervisor.log_path).unwrap();
+        assert!(log.contains("no output for 1m, restarting"));
+
+        supervisor.terminate().unwrap();
+    }
+
+    #[test]
+    fn test_is_failure_counts_the_stop_signal_as_a_stop() {
+        let signalled = |signal: Signal| ExitStatus::from_raw(signal as i32);
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+
+        assert!(!is_failure(signalled(Signal::SIGHUP), None, Signal::SIGHUP));
+        assert!(!is_failure(
+            signalled(Signal::SIGTERM),
+            None,
+            Signal::SIGHUP
+        ));
+        assert!(!is_failure(
+            signalled(Signal::SIGKILL),
+            None,
+            Signal::SIGTERM
+        ));
+        assert!(is_failure(signalled(Signal::SIGHUP), None, Signal::SIGTERM));
+        assert!(is_failure(
+            signalled(Signal::SIGSEGV),
+            None,
+            Signal::SIGTERM
+        ));
+        assert!(is_failure(
+            signalled(Signal::SIGTERM),
+            Some("idle timeout"),
+            Signal::SIGTERM
+        ));
+        assert!(is_failure(exited(1), None, Signal::SIGTERM));
+        assert!(!is_failure(exited(0), None, Signal::SIGTERM));
+    }
+
+    #[test]
+    fn test_chain_step_survives_restart_of_its_predecessor() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            idle_timeout: Some(1),
+            idle_action: Some(IdleAction::Restart),
+            ..Default::default()
+        };
+        let mut supervisor = start_supervisor(&conn, temp_dir.path(), &["sleep", "30"], options);
+        // This test process stands in for the live supervisor
+        storage::set_supervisor_pid(&conn, &supervisor.task_id, std::process::id()).unwrap();
+        storage::insert_waiting_task(
+            &conn,
+            "next-step",
+            &["true".to_string()],
+            None,
+            None,
+            &temp_dir.path().join("next-step.log"),
+            Some((&supervisor.task_id, storage::AfterCondition::Success)),
+            &RunOptions::default(),
+        )
+        .unwrap();
+        let step_status = |conn: &Connection| storage::get_task(conn, "next-step").unwrap().status;
+
+        // The old process is gone, but neither its exit nor the restart is recorded yet
+        let _ = process::kill_group(supervisor.pgid, true);
+        supervisor.wait().unwrap();
+        assert!(storage::claim_ready_tasks(&conn).unwrap().is_empty());
+        assert_eq!(step_status(&conn), TaskStatus::Waiting);
+        assert_eq!(
+            storage::get_task(&conn, &supervisor.task_id)
+                .unwrap()
+                .status,
+            TaskStatus::Running
+        );
+
+        supervisor.restart(&conn, Duration::from_secs(1)).unwrap();
+        assert!(storage::claim_ready_tasks(&conn).unwrap().is_empty());
+        assert_eq!(step_status(&conn), TaskStatus::Waiting);
+
+        let status = supervisor.terminate().unwrap();
+        supervisor
+            .record_exit(&conn, status, TaskStatus::Killed, None)
+            .unwrap();
+        storage::claim_ready_tasks(&conn).unwrap();
+        assert_eq!(step_status(&conn), TaskStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_supervisor_starts_exited_task_again_on_change() {
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut supervisor =
+            start_supervisor(&conn, temp_dir.path(), &["true"], RunOptions::default());
+        let status = supervisor.wait().unwrap();
+        supervisor
+            .record_exit(&conn, status, TaskStatus::Exited, None)
+            .unwrap();
+        supervisor.running = false;
+
+        let changed = temp_dir.path().join("main.rs");
+        supervisor.restart_for_change(&conn, &changed).unwrap();
+
+        let task = storage::get_task(&conn, &supervisor.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Running);
+        assert_eq!(task.restart_count, 1);
+        assert_eq!(
+            task.watch_trigger.as_deref(),
+            Some(changed.to_string_lossy().as_ref())
+        );
+
+        let log = std::fs::read_to_string(&supervisor.log_path).unwrap();
+        assert!(log.contains("main.rs changed, restarting"));
+
+        supervisor.wait().unwrap();
+    }
+
+    #[test]
+    fn test_supervisor_runs_output_triggers() {
+        use crate::app::output_trigger::OutputTrigger;
+
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            on_output: vec![
+                OutputTrigger::new("^Listening on".to_string(), TriggerAction::Ready).unwrap(),
+                OutputTrigger::new("FATAL".to_string(), TriggerAction::Stop).unwrap(),
+            ],
+            ..Default::default()
+        };
+        let command = [
+            "sh",
+            "-c",
+            "echo 'Listening on :8080'; echo 'FATAL: lost'; sleep 30",
+        ];
+        let supervisor = start_supervisor(&conn, temp_dir.path(), &command, options.clone());
+        let supervisor =
+            supervisor.with_output_matcher(Some(OutputMatcher::new(&options.on_output).unwrap()));
+        let task_id = supervisor.task_id.clone();
+
+        let started = Instant::now();
+        supervisor.run(&conn).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        let task = storage::get_task(&conn, &task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Killed);
+        assert_eq!(task.exit_reason.as_deref(), Some("output trigger"));
+
+        let events = storage::get_task_events(&conn, &task_id).unwrap();
+        let events: Vec<(EventKind, &str)> = events
+            .iter()
+            .map(|event| (event.kind, event.message.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (
+                    EventKind::Ready,
+                    "/^Listening on/ ready: Listening on :8080"
+                ),
+                (EventKind::Trigger, "/FATAL/ stop: FATAL: lost"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_supervisor_restarts_on_output() {
+        use crate::app::output_trigger::OutputTrigger;
+
+        let conn = setup_test_db();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            on_output: vec![
+                OutputTrigger::new("connection lost".to_string(), TriggerAction::Restart).unwrap(),
+            ],
+            ..Default::default()
+        };
+        let command = ["sh", "-c", "echo 'FATAL: connection lost'; sleep 30"];
+        let mut supervisor = start_supervisor(&conn, temp_dir.path(), &command, options.clone())
+            .with_output_matcher(Some(OutputMatcher::new(&options.on_output).unwrap()));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while supervisor.check_output(&conn).unwrap() != Triggered::Restarted {
+            assert!(Instant::now() < deadline, "trigger did not fire");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        let task = storage::get_task(&conn, &supervisor.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Running);
+        assert_eq!(task.restart_count, 1);
+        let log = std::fs::read_to_string(&supervisor.log_path).unwrap();
+        assert!(log.contains("--- ghost: output matched /connection lost/, restarting ---"));
+        let kinds: Vec<EventKind> = storage::get_task_events(&conn, &supervisor.task_id)
+            .unwrap()
+            .iter()
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(kinds, [EventKind::Trigger, EventKind::Restart]);
+
+        supervisor.terminate().unwrap();
+    }
+}
@@ -0,0 +1,408 @@
+//! Saved command templates started with `ghost start`
+//!
+//! A template stores a command with its working directory, environment
+//! variables and run options under a name. The command, directory and
+//! variable values may contain `{{name}}` or `{{name:default}}` placeholders,
+//! which are filled in from the parameters given when the template is started.
+
+use std::path::PathBuf;
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::app::error::{GhostError, Result};
+use crate::app::run_options::RunOptions;
+use crate::app::storage::{self, Template};
+
+/// A `{{placeholder}}` used by a template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    /// Value used when the parameter is not given
+    pub default: Option<String>,
+}
+
+/// Everything needed to start a template, with its placeholders filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Launch {
+    pub command: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub options: RunOptions,
+}
+
+/// Save a command as a template, replacing any template with the same name
+///
+/// A relative working directory is resolved now, so the template can be
+/// started from anywhere.
+pub fn save(
+    conn: &Connection,
+    name: &str,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+    env_vars: Vec<(String, String)>,
+    options: &RunOptions,
+) -> Result<Template> {
+    validate_name(name)?;
+    if command.is_empty() {
+        return Err(GhostError::InvalidArgument {
+            message: "No command specified".to_string(),
+        });
+    }
+
+    let cwd = match cwd {
+        Some(cwd) if cwd.is_relative() && !contains_placeholder(&cwd.to_string_lossy()) => {
+            Some(std::env::current_dir()?.join(cwd))
+        }
+        cwd => cwd,
+    };
+
+    storage::insert_template(conn, name, &command, &env_vars, cwd.as_deref(), options)?;
+    storage::get_template(conn, name)
+}
+
+/// The placeholders of a template, in order of first use
+pub fn parameters(template: &Template) -> Vec<Parameter> {
+    let mut parameters: Vec<Parameter> = Vec::new();
+    for text in template_texts(template) {
+        for parameter in placeholders(&text) {
+            match parameters.iter_mut().find(|p| p.name == parameter.name) {
+                // The first default given for a parameter wins
+                Some(existing) => {
+                    if existing.default.is_none() {
+                        existing.default = parameter.default;
+                    }
+                }
+                None => parameters.push(parameter),
+            }
+        }
+    }
+    parameters
+}
+
+/// Fill in a template's placeholders and append extra arguments to its command
+pub fn prepare(
+    template: &Template,
+    params: &[(String, String)],
+    extra_args: &[String],
+) -> Result<Launch> {
+    let parameters = parameters(template);
+    if let Some((unknown, _)) = params
+        .iter()
+        .find(|(name, _)| !parameters.iter().any(|p| p.name == *name))
+    {
+        return Err(GhostError::InvalidArgument {
+            message: format!("Template '{}' has no parameter '{unknown}'", template.name),
+        });
+    }
+
+    let mut values = Vec::new();
+    let mut missing = Vec::new();
+    for parameter in &parameters {
+        // A parameter given more than once takes the last value
+        match params
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == parameter.name)
+            .map(|(_, value)| value)
+            .or(parameter.default.as_ref())
+        {
+            Some(value) => values.push((parameter.name.clone(), value.clone())),
+            None => missing.push(parameter.name.as_str()),
+        }
+    }
+    if !missing.is_empty() {
+        let flags: Vec<String> = missing
+            .iter()
+            .map(|name| format!("--param {name}=VALUE"))
+            .collect();
+        return Err(GhostError::InvalidArgument {
+            message: format!(
+                "Template '{}' needs a value for: {} (use {})",
+                template.name,
+                missing.join(", "),
+                flags.join(" ")
+            ),
+        });
+    }
+
+    let command: Vec<String> = serde_json::from_str(&template.command)?;
+    let mut command: Vec<String> = command.iter().map(|arg| fill(arg, &values)).collect();
+    command.extend(extra_args.iter().cloned());
+
+    let env = stored_env(template)?
+        .into_iter()
+        .map(|(key, value)| (key, fill(&value, &values)))
+        .collect();
+
+    Ok(Launch {
+        command,
+        cwd: template
+            .cwd
+            .as_deref()
+            .map(|cwd| PathBuf::from(fill(cwd, &values))),
+        env,
+        options: template.run_options(),
+    })
+}
+
+/// Parse `NAME=VALUE` parameters given on the command line
+pub fn parse_params(params: &[String]) -> Result<Vec<(String, String)>> {
+    params
+        .iter()
+        .map(|param| match param.split_once('=') {
+            Some((name, value)) if is_parameter_name(name) => {
+                Ok((name.to_string(), value.to_string()))
+            }
+            _ => Err(GhostError::InvalidArgument {
+                message: format!("Invalid parameter: {param}. Use NAME=VALUE"),
+            }),
+        })
+        .collect()
+}
+
+/// Template names are used on the command line, so keep them simple
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(GhostError::InvalidArgument {
+            message: format!(
+                "Invalid template name: {name}. Use letters, digits, '-', '_' and '.'"
+            ),
+        })
+    }
+}
+
+fn stored_env(template: &Template) -> Result<Vec<(String, String)>> {
+    match template.env {
+        Some(ref env) => Ok(serde_json::from_str(env)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The stored strings that may contain placeholders
+fn template_texts(template: &Template) -> Vec<String> {
+    let mut texts: Vec<String> = serde_json::from_str(&template.command).unwrap_or_default();
+    texts.extend(template.cwd.clone());
+    texts.extend(
+        stored_env(template)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, value)| value),
+    );
+    texts
+}
+
+fn is_parameter_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+}
+
+/// Split `{{name}}` or `{{name:default}}` contents into a parameter
+fn parse_placeholder(inner: &str) -> Option<Parameter> {
+    let (name, default) = match inner.split_once(':') {
+        Some((name, default)) => (name.trim(), Some(default.trim().to_string())),
+        None => (inner.trim(), None),
+    };
+    is_parameter_name(name).then(|| Parameter {
+        name: name.to_string(),
+        default,
+    })
+}
+
+/// Walk the placeholders in `text`; `f` gets each one and its byte range
+fn for_each_placeholder(text: &str, mut f: impl FnMut(std::ops::Range<usize>, Parameter)) {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{").map(|i| offset + i) {
+        let Some(end) = text[start + 2..].find("}}").map(|i| start + 2 + i) else {
+            break;
+        };
+        match parse_placeholder(&text[start + 2..end]) {
+            Some(parameter) => {
+                f(start..end + 2, parameter);
+                offset = end + 2;
+            }
+            // Not a placeholder, e.g. "{{{" or "{{ .Name }}"; keep it as it is
+            None => offset = start + 1,
+        }
+    }
+}
+
+fn placeholders(text: &str) -> Vec<Parameter> {
+    let mut found = Vec::new();
+    for_each_placeholder(text, |_, parameter| found.push(parameter));
+    found
+}
+
+fn contains_placeholder(text: &str) -> bool {
+    !placeholders(text).is_empty()
+}
+
+/// Replace the placeholders in `text` with their values
+fn fill(text: &str, values: &[(String, String)]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut copied = 0;
+    for_each_placeholder(text, |range, parameter| {
+        if let Some((_, value)) = values.iter().find(|(name, _)| *name == parameter.name) {
+            filled.push_str(&text[copied..range.start]);
+            filled.push_str(value);
+            copied = range.end;
+        }
+    });
+    filled.push_str(&text[copied..]);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::database::init_schema;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn api_template(conn: &Connection) -> Template {
+        save(
+            conn,
+            "api",
+            vec![
+                "cargo".to_string(),
+                "run".to_string(),
+                "--bin".to_string(),
+                "{{bin}}".to_string(),
+                "--port={{ port:8080 }}".to_string(),
+            ],
+            Some(PathBuf::from("/work/{{bin}}")),
+            params(&[("RUST_LOG", "{{level:debug}}")]),
+            &RunOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parameters_in_order_of_use() {
+        let conn = setup_test_db();
+        let template = api_template(&conn);
+
+        assert_eq!(
+            parameters(&template),
+            vec![
+                Parameter {
+                    name: "bin".to_string(),
+                    default: None
+                },
+                Parameter {
+                    name: "port".to_string(),
+                    default: Some("8080".to_string())
+                },
+                Parameter {
+                    name: "level".to_string(),
+                    default: Some("debug".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prepare_fills_placeholders_and_appends_args() {
+        let conn = setup_test_db();
+        let template = api_template(&conn);
+
+        let launch = prepare(
+            &template,
+            &params(&[("bin", "api"), ("level", "trace")]),
+            &["--release".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            launch.command,
+            vec!["cargo", "run", "--bin", "api", "--port=8080", "--release"]
+        );
+        assert_eq!(launch.cwd, Some(PathBuf::from("/work/api")));
+        assert_eq!(launch.env, params(&[("RUST_LOG", "trace")]));
+    }
+
+    #[test]
+    fn test_prepare_rejects_missing_and_unknown_parameters() {
+        let conn = setup_test_db();
+        let template = api_template(&conn);
+
+        let error = prepare(&template, &[], &[]).unwrap_err().to_string();
+        assert!(error.contains("needs a value for: bin"), "{error}");
+
+        let error = prepare(&template, &params(&[("bin", "api"), ("prot", "1")]), &[])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("has no parameter 'prot'"), "{error}");
+    }
+
+    #[test]
+    fn test_text_that_is_not_a_placeholder_is_kept() {
+        let values = params(&[("name", "ghost")]);
+        assert_eq!(fill("{{name}}-{{name}}", &values), "ghost-ghost");
+        assert_eq!(fill("{{ .Name }} {{name", &values), "{{ .Name }} {{name");
+        assert_eq!(fill("{{{name}}}", &values), "{ghost}");
+    }
+
+    #[test]
+    fn test_save_validates_name_and_resolves_cwd() {
+        let conn = setup_test_db();
+        let command = vec!["true".to_string()];
+
+        for name in ["", "-x", "has space", "a/b"] {
+            assert!(
+                save(
+                    &conn,
+                    name,
+                    command.clone(),
+                    None,
+                    vec![],
+                    &RunOptions::default()
+                )
+                .is_err(),
+                "{name:?} should be rejected"
+            );
+        }
+
+        let template = save(
+            &conn,
+            "rel",
+            command,
+            Some(PathBuf::from("sub")),
+            vec![],
+            &RunOptions::default(),
+        )
+        .unwrap();
+        let cwd = PathBuf::from(template.cwd.unwrap());
+        assert!(cwd.is_absolute());
+        assert!(cwd.ends_with("sub"));
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(
+            parse_params(&["port=80".to_string(), "url=a=b".to_string()]).unwrap(),
+            params(&[("port", "80"), ("url", "a=b")])
+        );
+        assert!(parse_params(&["port".to_string()]).is_err());
+        assert!(parse_params(&["=80".to_string()]).is_err());
+    }
+}
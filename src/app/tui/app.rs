@@ -2,32 +2,43 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{Frame, layout::Rect};
 use rusqlite::Connection;
 use std::collections::HashMap;
-use std::fs;
 use std::process::Child;
-use std::time::SystemTime;
 use tui_scrollview::ScrollViewState;
 
-use super::log_viewer_scrollview::LogViewerScrollWidget;
+use super::help::HelpOverlay;
+use super::keymap::{Action, KeyMap};
+use super::log_index::LogIndex;
+use super::log_level_filter::{self, LevelFilter};
+use super::log_search::{LogSearch, SearchPrompt};
+use super::log_viewer_scrollview::{LogScrollState, LogViewerScrollWidget};
+use super::new_task::{self, FormAction, NewTaskForm};
+use super::notifications::{self, Notifications};
 use super::table_state_scroll::TableScroll;
+use super::theme::Theme;
 use super::{TaskFilter, ViewMode};
-use crate::app::config::Config;
+use crate::app::ansi::AnsiMode;
+use crate::app::commands;
+use crate::app::config::{Config, EnvConfig, FileConfig};
 use crate::app::error::Result;
+use crate::app::helpers;
+use crate::app::log_level::{self, LevelCounter, LevelCounts, LogFormat, LogLevel};
+use crate::app::notify::Notification;
+use crate::app::process_tree::{self, ProcessNode};
+use crate::app::queue;
+use crate::app::scheduler;
+use crate::app::search::{self, Query};
+use crate::app::stats::StatsSampler;
 use crate::app::storage;
 use crate::app::storage::task::Task;
 use crate::app::storage::task_repository;
+use crate::app::storage::{FilterPreset, Schedule, TaskEvent, Template};
+use crate::app::template;
 
-/// Cache for log file content
-struct LogCache {
-    content: Vec<String>,
-    last_modified: SystemTime,
-    file_size: u64,
-}
-
-enum UpdateStrategy {
-    FullReload,
-    Incremental(u64), // previous file size
-    UseCache,
-}
+/// Lines kept visible above a search match the viewer jumps to
+const SEARCH_SCROLL_MARGIN: usize = 3;
+/// Log bytes read per task and refresh to count errors and warnings, so
+/// large logs are counted over several refreshes
+const LEVEL_COUNT_BYTES_PER_REFRESH: u64 = 8 * 1024 * 1024;
 
 pub struct TuiApp {
     pub tasks: Vec<Task>,
@@ -37,19 +48,65 @@ pub struct TuiApp {
     pub view_mode: ViewMode,
     pub log_scroll_offset: usize,
     pub log_lines_count: usize,
-    pub log_scroll_state: ScrollViewState,
+    pub log_scroll_state: LogScrollState,
     pub selected_task_id: Option<String>,
     pub env_scroll_state: ScrollViewState,
     pub last_render_area: Rect,
     pub auto_scroll_enabled: bool,
     conn: Connection,
-    log_cache: HashMap<String, LogCache>,
+    /// Line indexes of the logs opened in the viewer, by path
+    log_indexes: HashMap<String, LogIndex>,
     child_processes: HashMap<String, Child>,
+    stats: StatsSampler,
+    process_tree: Vec<ProcessNode>,
+    chain: Vec<(usize, Task)>,
+    /// Event history of the task shown in the details view
+    events: Vec<TaskEvent>,
+    schedules: Vec<Schedule>,
+    templates: Vec<Template>,
+    template_index: usize,
+    /// Parameter values being typed before starting the selected template
+    template_input: Option<String>,
+    template_error: Option<String>,
+    new_task: Option<NewTaskForm>,
+    /// Search typed after `/`; `search` is the last version of it that parsed
+    search_input: String,
+    search: Query,
+    search_error: Option<String>,
+    /// Name being typed to save the search as a preset
+    preset_name: Option<String>,
+    filter_presets: Vec<FilterPreset>,
+    preset_index: Option<usize>,
+    log_search: Option<LogSearch>,
+    /// How escape sequences in logs are shown, kept across tasks
+    ansi_mode: AnsiMode,
+    log_search_prompt: Option<SearchPrompt>,
+    /// Line number or percentage being typed after `:` in the log viewer
+    log_goto: Option<String>,
+    log_goto_error: Option<String>,
+    /// Errors and warnings counted in the logs of listed tasks, by task ID
+    level_counters: HashMap<String, LevelCounter>,
+    /// Minimum level of the lines shown in the log viewer, kept across tasks
+    log_level: Option<LogLevel>,
+    log_level_filter: Option<LevelFilter>,
+    /// Expand JSON lines in the log viewer
+    pretty_json: bool,
+    /// Tasks that ended or restarted since the TUI was opened
+    notifications: Notifications,
+    notification_index: usize,
+    /// Environment variables whose values the details view hides
+    redact: EnvConfig,
+    /// Keys of the actions in each view
+    key_map: KeyMap,
+    theme: Theme,
+    /// Whether the keys of the current view are listed over it
+    show_help: bool,
 }
 
 impl TuiApp {
     pub fn new() -> Result<Self> {
         let conn = storage::init_database()?;
+        let tui = &FileConfig::current().tui;
 
         Ok(Self {
             tasks: Vec::new(),
@@ -59,14 +116,46 @@ impl TuiApp {
             view_mode: ViewMode::TaskList,
             log_scroll_offset: 0,
             log_lines_count: 0,
-            log_scroll_state: ScrollViewState::default(),
+            log_scroll_state: LogScrollState::default(),
             selected_task_id: None,
             env_scroll_state: ScrollViewState::default(),
             last_render_area: Rect::default(),
             auto_scroll_enabled: true, // Auto-scroll enabled by default
             conn,
-            log_cache: HashMap::new(),
+            log_indexes: HashMap::new(),
             child_processes: HashMap::new(),
+            stats: StatsSampler::new(),
+            process_tree: Vec::new(),
+            chain: Vec::new(),
+            events: Vec::new(),
+            schedules: Vec::new(),
+            templates: Vec::new(),
+            template_index: 0,
+            template_input: None,
+            template_error: None,
+            new_task: None,
+            search_input: String::new(),
+            search: Query::default(),
+            search_error: None,
+            preset_name: None,
+            filter_presets: Vec::new(),
+            preset_index: None,
+            log_search: None,
+            ansi_mode: AnsiMode::default(),
+            log_search_prompt: None,
+            log_goto: None,
+            log_goto_error: None,
+            level_counters: HashMap::new(),
+            log_level: None,
+            log_level_filter: None,
+            pretty_json: false,
+            notifications: Notifications::default(),
+            notification_index: 0,
+            redact: FileConfig::current().env.clone(),
+            // Conflicting keys were reported when the config files were read
+            key_map: tui.key_map().unwrap_or_default(),
+            theme: tui.theme(),
+            show_help: false,
         })
     }
 
@@ -82,22 +171,80 @@ impl TuiApp {
             view_mode: ViewMode::TaskList,
             log_scroll_offset: 0,
             log_lines_count: 0,
-            log_scroll_state: ScrollViewState::default(),
+            log_scroll_state: LogScrollState::default(),
             selected_task_id: None,
             env_scroll_state: ScrollViewState::default(),
             last_render_area: Rect::default(),
             auto_scroll_enabled: true, // Auto-scroll enabled by default
             conn,
-            log_cache: HashMap::new(),
+            log_indexes: HashMap::new(),
             child_processes: HashMap::new(),
+            stats: StatsSampler::new(),
+            process_tree: Vec::new(),
+            chain: Vec::new(),
+            events: Vec::new(),
+            schedules: Vec::new(),
+            templates: Vec::new(),
+            template_index: 0,
+            template_input: None,
+            template_error: None,
+            new_task: None,
+            search_input: String::new(),
+            search: Query::default(),
+            search_error: None,
+            preset_name: None,
+            filter_presets: Vec::new(),
+            preset_index: None,
+            log_search: None,
+            ansi_mode: AnsiMode::default(),
+            log_search_prompt: None,
+            log_goto: None,
+            log_goto_error: None,
+            level_counters: HashMap::new(),
+            log_level: None,
+            log_level_filter: None,
+            pretty_json: false,
+            notifications: Notifications::default(),
+            notification_index: 0,
+            redact: EnvConfig::default(),
+            key_map: KeyMap::default(),
+            theme: Theme::default(),
+            show_help: false,
         })
     }
 
+    /// Use other keys than those of the config file
+    pub fn with_key_map(mut self, key_map: KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    /// Use other colors than those of the config file
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Whether the help overlay is shown
+    pub fn is_showing_help(&self) -> bool {
+        self.show_help
+    }
+
     /// Load tasks from database
     pub fn refresh_tasks(&mut self) -> Result<()> {
         // Clean up finished child processes first
         self.cleanup_finished_processes();
 
+        // The TUI stands in for the scheduler while it is open
+        if let Err(e) = scheduler::fire_due(&self.conn) {
+            tracing::warn!("Failed to run due schedules: {e}");
+        }
+
+        // Start queued tasks whose queue has a free slot, e.g. after a supervisor was killed
+        if let Err(e) = queue::promote(&self.conn) {
+            tracing::warn!("Failed to start queued tasks: {e}");
+        }
+
         // Filter status for database query
         let status_filter = match self.filter {
             TaskFilter::All => None,
@@ -106,7 +253,38 @@ impl TuiApp {
             TaskFilter::Killed => Some("killed"),
         };
 
-        self.tasks = task_repository::get_tasks_with_process_check(&self.conn, status_filter)?;
+        // Every task is loaded, so tasks outside the filter are notified of too
+        self.tasks = task_repository::get_tasks_with_process_check(&self.conn, None)?;
+        self.notifications.update(&self.tasks);
+        if let Some(status) = status_filter {
+            self.tasks.retain(|task| task.status.as_str() == status);
+        }
+        if !self.search.is_empty() {
+            // Ports take an lsof call, so only look them up when the search needs them
+            let ports = if self.search.needs_ports() {
+                search::task_ports(&self.tasks)
+            } else {
+                HashMap::new()
+            };
+            self.tasks.retain(|task| {
+                let task_ports = ports.get(&task.id).map_or(&[][..], Vec::as_slice);
+                self.search.matches(task, task_ports)
+            });
+        }
+        // Like `ghost list`, schedules are only listed without a status filter or search
+        self.schedules = match status_filter {
+            None if self.search.is_empty() => storage::get_schedules(&self.conn)?,
+            _ => Vec::new(),
+        };
+
+        for task in &self.tasks {
+            let counter = self
+                .level_counters
+                .entry(task.id.clone())
+                .or_insert_with(|| LevelCounter::new(&task.log_path));
+            // Queued tasks have no log yet
+            let _ = counter.update(LEVEL_COUNT_BYTES_PER_REFRESH);
+        }
 
         // Update table scroll with new item count
         self.table_scroll.set_total_items(self.tasks.len());
@@ -114,126 +292,465 @@ impl TuiApp {
         Ok(())
     }
 
+    /// Sample CPU and memory usage of running tasks
+    pub fn sample_stats(&mut self) {
+        self.stats.sample(&self.tasks);
+    }
+
+    /// Refresh the process tree of the task shown in the details view
+    pub fn refresh_process_tree(&mut self) {
+        self.process_tree = match (&self.view_mode, &self.selected_task_id) {
+            (ViewMode::ProcessDetails, Some(task_id)) => self
+                .tasks
+                .iter()
+                .find(|task| task.id == *task_id)
+                .map(process_tree::task_tree)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+    }
+
+    /// Refresh the chain of the task shown in the details view
+    pub fn refresh_chain(&mut self) {
+        self.chain = match (&self.view_mode, &self.selected_task_id) {
+            (ViewMode::ProcessDetails, Some(task_id)) => {
+                storage::get_chain(&self.conn, task_id).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Refresh the event history of the task shown in the details view
+    pub fn refresh_events(&mut self) {
+        self.events = match (&self.view_mode, &self.selected_task_id) {
+            (ViewMode::ProcessDetails, Some(task_id)) => {
+                storage::get_task_events(&self.conn, task_id).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+    }
+
     /// Handle keyboard input
     pub fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.show_help {
+            // Any key closes the help, and the quit keys still quit
+            self.show_help = false;
+            if self.key_map.action(self.view_mode, key) == Some(Action::Quit) {
+                self.should_quit = true;
+            }
+            return Ok(());
+        }
+
         match self.view_mode {
             ViewMode::TaskList => self.handle_task_list_key(key),
             ViewMode::LogView => self.handle_log_view_key(key),
             ViewMode::ProcessDetails => self.handle_process_details_key(key),
+            ViewMode::TemplateLauncher => self.handle_template_launcher_key(key),
+            ViewMode::NewTask => self.handle_new_task_key(key),
+            ViewMode::Search => self.handle_search_key(key),
+            ViewMode::Notifications => self.handle_notifications_key(key),
         }
     }
 
     fn handle_task_list_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('q') => {
+        match self.key_map.action(ViewMode::TaskList, key) {
+            Some(Action::Quit) => {
                 self.should_quit = true;
             }
-            KeyCode::Char('j') => {
+            Some(Action::Help) => {
+                self.show_help = true;
+            }
+            Some(Action::Down) => {
                 self.table_scroll.next();
             }
-            KeyCode::Char('k') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::Up) => {
                 self.table_scroll.previous();
             }
-            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::NONE) => {
+            Some(Action::Top) => {
                 self.table_scroll.first();
             }
-            KeyCode::Char('G') => {
+            Some(Action::Bottom) => {
                 self.table_scroll.last();
             }
-            KeyCode::Char('l') => {
+            Some(Action::OpenLog) => {
                 if !self.tasks.is_empty() {
                     self.view_mode = ViewMode::LogView;
                     self.initialize_log_view();
                 }
             }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
-            }
-            KeyCode::Char('s') => {
+            Some(Action::Stop) => {
                 if !self.tasks.is_empty() {
                     self.stop_task(false);
                 }
             }
-            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::Kill) => {
                 if !self.tasks.is_empty() {
                     self.stop_task(true);
                 }
             }
-            KeyCode::Tab => {
+            Some(Action::CycleFilter) => {
                 self.cycle_filter();
                 self.refresh_tasks()?;
             }
-            KeyCode::Enter => {
+            Some(Action::Select) => {
                 if !self.tasks.is_empty() {
                     let selected_task = &self.tasks[self.selected_index()];
                     self.selected_task_id = Some(selected_task.id.clone());
                     self.view_mode = ViewMode::ProcessDetails;
                     self.env_scroll_state = ScrollViewState::default();
+                    self.refresh_process_tree();
+                    self.refresh_chain();
+                    self.refresh_events();
                 }
             }
-            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::PageDown) => {
                 let page_size = self.calculate_table_page_size();
                 self.table_scroll.page_down(page_size);
             }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::PageUp) => {
                 let page_size = self.calculate_table_page_size();
                 self.table_scroll.page_up(page_size);
             }
-            KeyCode::Char('r') => {
+            Some(Action::Rerun) => {
                 self.rerun_selected_command()?;
             }
+            Some(Action::Templates) => {
+                self.open_template_launcher()?;
+            }
+            Some(Action::NewTask) => {
+                self.open_new_task()?;
+            }
+            Some(Action::Search) => {
+                self.open_search()?;
+            }
+            Some(Action::Notifications) => {
+                self.notifications.dismiss_toasts();
+                self.notification_index = 0;
+                self.view_mode = ViewMode::Notifications;
+            }
+            Some(Action::Back) if !self.search_input.is_empty() => {
+                self.search_input.clear();
+                self.apply_search()?;
+            }
             _ => {}
         }
 
         Ok(())
     }
 
-    fn handle_log_view_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_notifications_key(&mut self, key: KeyEvent) -> Result<()> {
+        match self.key_map.action(ViewMode::Notifications, key) {
+            Some(Action::Back) => {
+                self.view_mode = ViewMode::TaskList;
+            }
+            Some(Action::Quit) => {
+                self.should_quit = true;
+            }
+            Some(Action::Help) => {
+                self.show_help = true;
+            }
+            Some(Action::Down) => {
+                if self.notification_index + 1 < self.notifications.len() {
+                    self.notification_index += 1;
+                }
+            }
+            Some(Action::Up) => {
+                self.notification_index = self.notification_index.saturating_sub(1);
+            }
+            Some(Action::Select) => {
+                // Show the task, unless it was deleted or is hidden by the filter
+                let task_id = self
+                    .notifications
+                    .history()
+                    .nth(self.notification_index)
+                    .map(|notification| notification.task_id.clone());
+                if let Some(task_id) = task_id
+                    && self.tasks.iter().any(|task| task.id == task_id)
+                {
+                    self.selected_task_id = Some(task_id);
+                    self.view_mode = ViewMode::ProcessDetails;
+                    self.env_scroll_state = ScrollViewState::default();
+                    self.refresh_process_tree();
+                    self.refresh_chain();
+                    self.refresh_events();
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> Result<()> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if key.code == KeyCode::Char('c') && ctrl {
+            self.should_quit = true;
+            return Ok(());
+        }
+
+        if let Some(name) = self.preset_name.as_mut() {
+            match key.code {
+                KeyCode::Enter => match search::save_preset(&self.conn, name, &self.search_input) {
+                    Ok(_) => {
+                        self.preset_name = None;
+                        self.search_error = None;
+                        self.filter_presets = storage::get_filter_presets(&self.conn)?;
+                    }
+                    Err(e) => self.search_error = Some(e.to_string()),
+                },
+                KeyCode::Esc => {
+                    self.preset_name = None;
+                    self.search_error = None;
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) if !ctrl => name.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
         match key.code {
+            KeyCode::Enter if self.search_error.is_none() => {
+                self.view_mode = ViewMode::TaskList;
+            }
             KeyCode::Esc => {
+                self.search_input.clear();
+                self.view_mode = ViewMode::TaskList;
+                self.apply_search()?;
+            }
+            KeyCode::Up | KeyCode::Down if !self.filter_presets.is_empty() => {
+                let count = self.filter_presets.len();
+                let index = match (self.preset_index, key.code) {
+                    (None, KeyCode::Up) => count - 1,
+                    (None, _) => 0,
+                    (Some(index), KeyCode::Up) => (index + count - 1) % count,
+                    (Some(index), _) => (index + 1) % count,
+                };
+                self.preset_index = Some(index);
+                self.search_input = format!("@{}", self.filter_presets[index].name);
+                self.apply_search()?;
+            }
+            KeyCode::Char('s') if ctrl => {
+                self.preset_name = Some(String::new());
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.search_input.clear();
+                self.apply_search()?;
+            }
+            KeyCode::Backspace => {
+                self.search_input.pop();
+                self.apply_search()?;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.search_input.push(c);
+                self.apply_search()?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn handle_new_task_key(&mut self, key: KeyEvent) -> Result<()> {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_quit = true;
+            return Ok(());
+        }
+
+        let Some(form) = self.new_task.as_mut() else {
+            self.view_mode = ViewMode::TaskList;
+            return Ok(());
+        };
+        match form.handle_key(key) {
+            FormAction::Continue => {}
+            FormAction::Cancel => {
+                self.new_task = None;
+                self.view_mode = ViewMode::TaskList;
+            }
+            FormAction::Submit => self.start_new_task()?,
+        }
+
+        Ok(())
+    }
+
+    fn handle_template_launcher_key(&mut self, key: KeyEvent) -> Result<()> {
+        // While parameters are being typed, keys go to the input line
+        if let Some(input) = self.template_input.as_mut() {
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.should_quit = true;
+                }
+                KeyCode::Esc => {
+                    self.template_input = None;
+                    self.template_error = None;
+                }
+                KeyCode::Enter => self.start_selected_template()?,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match self.key_map.action(ViewMode::TemplateLauncher, key) {
+            Some(Action::Back) => {
+                self.view_mode = ViewMode::TaskList;
+                self.template_error = None;
+            }
+            Some(Action::Quit) => {
+                self.should_quit = true;
+            }
+            Some(Action::Help) => {
+                self.show_help = true;
+            }
+            Some(Action::Down) => {
+                if self.template_index + 1 < self.templates.len() {
+                    self.template_index += 1;
+                }
+            }
+            Some(Action::Up) => {
+                self.template_index = self.template_index.saturating_sub(1);
+            }
+            Some(Action::Select) => {
+                if let Some(selected) = self.templates.get(self.template_index) {
+                    let parameters = template::parameters(selected);
+                    if parameters.is_empty() {
+                        self.start_selected_template()?;
+                    } else {
+                        // Offer every parameter, filled with its default if it has one
+                        let prefilled: Vec<String> = parameters
+                            .into_iter()
+                            .map(|parameter| {
+                                format!(
+                                    "{}={}",
+                                    parameter.name,
+                                    parameter.default.unwrap_or_default()
+                                )
+                            })
+                            .collect();
+                        self.template_input = Some(prefilled.join(" "));
+                        self.template_error = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn handle_log_view_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.log_search_prompt.is_some() {
+            self.handle_log_search_prompt_key(key);
+            return Ok(());
+        }
+        if self.log_goto.is_some() {
+            self.handle_log_goto_key(key);
+            return Ok(());
+        }
+
+        match self.key_map.action(ViewMode::LogView, key) {
+            Some(Action::Back) if self.log_search.is_some() => {
+                self.log_search = None;
+            }
+            Some(Action::Back) => {
                 self.view_mode = ViewMode::TaskList;
                 self.log_scroll_state.scroll_to_top();
             }
-            KeyCode::Char('q') => {
+            Some(action @ (Action::Search | Action::SearchBackward)) => {
+                let backward = action == Action::SearchBackward;
+                self.log_search_prompt = Some(SearchPrompt::new(backward, self.log_search.clone()));
+            }
+            Some(action @ (Action::NextMatch | Action::PrevMatch)) if self.log_search.is_some() => {
+                self.jump_to_match(action == Action::PrevMatch);
+            }
+            Some(Action::GotoLine) => {
+                self.log_goto = Some(String::new());
+                self.log_goto_error = None;
+            }
+            Some(Action::FilterMatches) => {
+                if let Some(search) = self.log_search.as_mut() {
+                    search.filter = !search.filter;
+                    match search.current() {
+                        Some(line) => self.scroll_to_log_line(line),
+                        None => self.log_scroll_state.scroll_to_top(),
+                    }
+                }
+            }
+            Some(Action::MoreContext) => {
+                if let Some(search) = self.log_search.as_mut() {
+                    search.widen_context();
+                }
+            }
+            Some(Action::LessContext) => {
+                if let Some(search) = self.log_search.as_mut() {
+                    search.narrow_context();
+                }
+            }
+            Some(Action::Quit) => {
                 self.should_quit = true;
             }
-            KeyCode::Char('j') => {
+            Some(Action::Help) => {
+                self.show_help = true;
+            }
+            Some(Action::Down) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_down();
             }
-            KeyCode::Char('k') => {
+            Some(Action::Up) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_up();
             }
-            KeyCode::Char('h') => {
+            Some(Action::Left) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_left();
             }
-            KeyCode::Char('l') => {
+            Some(Action::Right) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_right();
             }
-            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::NONE) => {
+            Some(Action::Top) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_to_top();
             }
-            KeyCode::Char('G') => {
+            Some(Action::Bottom) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_to_bottom();
             }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
+            Some(Action::Level) => {
+                let top = self.top_log_line();
+                self.log_level = LogLevel::next_minimum(self.log_level);
+                self.log_level_filter = self.log_level.map(LevelFilter::new);
+                self.update_log_search();
+                if !self.auto_scroll_enabled {
+                    let row = self.log_row_of(top);
+                    self.log_scroll_state.set_row(row);
+                }
+            }
+            Some(Action::PrettyJson) => {
+                self.pretty_json = !self.pretty_json;
+            }
+            Some(Action::Ansi) => {
+                self.ansi_mode = self.ansi_mode.next();
+                // Lines shown in raw mode differ, so search them again
+                if let Some(search) = self.log_search.as_mut() {
+                    search.restart();
+                }
             }
-            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::PageDown) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_page_down();
             }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::PageUp) => {
                 self.auto_scroll_enabled = false; // Disable auto-scroll on manual navigation
                 self.log_scroll_state.scroll_page_up();
             }
-            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                // Toggle auto-scroll mode with Ctrl+F
+            Some(Action::Follow) => {
+                // Toggle auto-scroll mode
                 self.auto_scroll_enabled = !self.auto_scroll_enabled;
                 // If enabling auto-scroll, immediately scroll to bottom
                 if self.auto_scroll_enabled {
@@ -247,27 +764,27 @@ impl TuiApp {
     }
 
     fn handle_process_details_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => {
+        match self.key_map.action(ViewMode::ProcessDetails, key) {
+            Some(Action::Back) => {
                 self.view_mode = ViewMode::TaskList;
                 self.env_scroll_state = ScrollViewState::default();
             }
-            KeyCode::Char('j') => {
+            Some(Action::Down) => {
                 self.env_scroll_state.scroll_down();
             }
-            KeyCode::Char('k') => {
+            Some(Action::Up) => {
                 self.env_scroll_state.scroll_up();
             }
-            KeyCode::Char('q') => {
+            Some(Action::Quit) => {
                 self.should_quit = true;
             }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
+            Some(Action::Help) => {
+                self.show_help = true;
             }
-            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::PageDown) => {
                 self.env_scroll_state.scroll_page_down();
             }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(Action::PageUp) => {
                 self.env_scroll_state.scroll_page_up();
             }
             _ => {}
@@ -276,6 +793,269 @@ impl TuiApp {
         Ok(())
     }
 
+    /// Handle keys while a search pattern is typed after `/` or `?`
+    fn handle_log_search_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.log_search_prompt.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+                return;
+            }
+            KeyCode::Esc => {
+                self.log_search = prompt.previous.take();
+                self.log_search_prompt = None;
+                return;
+            }
+            KeyCode::Enter => {
+                if prompt.error.is_some() {
+                    return;
+                }
+                // An empty pattern repeats the last search in the prompt's direction
+                let search = match (&self.log_search, prompt.input.is_empty()) {
+                    (Some(search), true) => LogSearch::new(search.pattern(), prompt.backward)
+                        .ok()
+                        .map(|new| new.with_view_of(Some(search))),
+                    (search, _) => search.clone(),
+                };
+                self.log_search_prompt = None;
+                self.log_search = search;
+                if self.log_search.is_some() {
+                    let from = self.top_log_line();
+                    self.jump_to_match_from(from);
+                }
+                return;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                prompt.input.push(c);
+            }
+            _ => return,
+        }
+
+        // Highlight matches while the pattern is typed
+        if prompt.input.is_empty() {
+            prompt.error = None;
+            self.log_search = prompt.previous.clone();
+            return;
+        }
+        match LogSearch::new(&prompt.input, prompt.backward) {
+            Ok(search) => {
+                prompt.error = None;
+                self.log_search = Some(search.with_view_of(prompt.previous.as_ref()));
+            }
+            Err(e) => prompt.error = Some(e.to_string()),
+        }
+    }
+
+    /// Handle keys while a line number or percentage is typed after `:`
+    fn handle_log_goto_key(&mut self, key: KeyEvent) {
+        let Some(input) = self.log_goto.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Esc => {
+                self.log_goto = None;
+                self.log_goto_error = None;
+            }
+            KeyCode::Enter => match self.goto_log_position() {
+                Ok(()) => {
+                    self.log_goto = None;
+                    self.log_goto_error = None;
+                }
+                Err(e) => self.log_goto_error = Some(e),
+            },
+            KeyCode::Backspace => {
+                input.pop();
+                self.log_goto_error = None;
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() || c == '%' => {
+                input.push(c);
+                self.log_goto_error = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Scroll to the line number or percentage typed after `:`
+    fn goto_log_position(&mut self) -> std::result::Result<(), String> {
+        let input = self.log_goto.clone().unwrap_or_default();
+        let (number, percent) = match input.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (input.as_str(), false),
+        };
+        let number: usize = number
+            .parse()
+            .map_err(|_| "Enter a line number or a percentage, e.g. 120 or 50%".to_string())?;
+        if percent && number > 100 {
+            return Err("Percentage must be between 0 and 100".to_string());
+        }
+
+        let Some(index) = self.log_index() else {
+            return Ok(());
+        };
+        // The position may lie beyond what has been indexed so far
+        if percent || number > index.line_count() {
+            index.index_all();
+        }
+        let line_count = index.line_count();
+        let line = if percent {
+            line_count * number / 100
+        } else {
+            number.saturating_sub(1)
+        };
+        self.scroll_to_log_line(line.min(line_count.saturating_sub(1)));
+        Ok(())
+    }
+
+    /// The prompt shown in place of the log viewer footer while typing after `:`
+    fn log_goto_prompt(&self) -> Option<String> {
+        let input = self.log_goto.as_ref()?;
+        Some(match &self.log_goto_error {
+            Some(error) => format!(" :{input}█  {error}"),
+            None => format!(" :{input}█  Line number or N%  Enter:Go  Esc:Cancel"),
+        })
+    }
+
+    /// Index of the selected task's log
+    fn log_index(&mut self) -> Option<&mut LogIndex> {
+        let task = self.tasks.get(self.table_scroll.selected()?)?;
+        Some(
+            self.log_indexes
+                .entry(task.log_path.clone())
+                .or_insert_with(|| LogIndex::new(&task.log_path)),
+        )
+    }
+
+    /// Lines indexed so far in the selected task's log
+    fn log_line_count(&self) -> usize {
+        self.tasks
+            .get(self.selected_index())
+            .and_then(|task| self.log_indexes.get(&task.log_path))
+            .map_or(0, LogIndex::line_count)
+    }
+
+    /// Search and filter the next part of the log, if a search or level filter is active
+    fn update_log_search(&mut self) {
+        let Some(task) = self.tasks.get(self.selected_index()) else {
+            return;
+        };
+        let Some(index) = self.log_indexes.get(&task.log_path) else {
+            return;
+        };
+        if let Some(search) = self.log_search.as_mut() {
+            search.update(index, self.ansi_mode);
+        }
+        if let Some(filter) = self.log_level_filter.as_mut() {
+            filter.update(index);
+        }
+    }
+
+    /// Rows of the log viewer when lines are filtered, see `log_level_filter::filtered_rows`
+    fn filtered_log_rows(&self) -> Option<Vec<Option<usize>>> {
+        log_level_filter::filtered_rows(
+            self.log_search.as_ref(),
+            self.log_level_filter.as_ref(),
+            self.log_line_count(),
+        )
+    }
+
+    /// Row of the log viewer showing a line, or the next line shown when it is filtered out
+    fn log_row_of(&self, line: usize) -> usize {
+        match self.filtered_log_rows() {
+            Some(rows) => rows
+                .iter()
+                .position(|row| row.is_some_and(|row| row >= line))
+                .unwrap_or(rows.len()),
+            None => line,
+        }
+    }
+
+    /// The log shown in the log viewer, which should be drawn again when it changes
+    pub fn viewed_log(&self) -> Option<&str> {
+        if self.view_mode != ViewMode::LogView {
+            return None;
+        }
+        self.tasks
+            .get(self.selected_index())
+            .map(|task| task.log_path.as_str())
+    }
+
+    /// Whether the viewer is still indexing or searching the log, so it
+    /// should be drawn again soon
+    pub fn is_loading_log(&self) -> bool {
+        if self.view_mode != ViewMode::LogView {
+            return false;
+        }
+        let indexing = self
+            .tasks
+            .get(self.selected_index())
+            .and_then(|task| self.log_indexes.get(&task.log_path))
+            .is_some_and(|index| !index.is_indexed());
+        let searching = self
+            .log_search
+            .as_ref()
+            .is_some_and(|search| !search.is_complete());
+        let filtering = self
+            .log_level_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.is_complete());
+        indexing || searching || filtering
+    }
+
+    /// Index of the log line at the top of the viewer
+    fn top_log_line(&self) -> usize {
+        let row = self.log_scroll_state.row();
+        match self.filtered_log_rows() {
+            Some(rows) => rows.iter().skip(row).find_map(|line| *line).unwrap_or(0),
+            None => row,
+        }
+    }
+
+    /// Jump to the next search match, or the previous one with `reverse`
+    fn jump_to_match(&mut self, reverse: bool) {
+        let from = match self.log_search.as_ref().and_then(LogSearch::current) {
+            Some(line) => line,
+            None => return self.jump_to_match_from(self.top_log_line()),
+        };
+        self.update_log_search();
+        if let Some(line) = self
+            .log_search
+            .as_mut()
+            .and_then(|search| search.jump(from, reverse))
+        {
+            self.scroll_to_log_line(line);
+        }
+    }
+
+    /// Jump to the first match from `from` in the search direction
+    fn jump_to_match_from(&mut self, from: usize) {
+        self.update_log_search();
+        if let Some(line) = self
+            .log_search
+            .as_mut()
+            .and_then(|search| search.jump_from(from))
+        {
+            self.scroll_to_log_line(line);
+        }
+    }
+
+    /// Scroll the log viewer so that a line is shown near the top
+    fn scroll_to_log_line(&mut self, line: usize) {
+        let row = self.log_row_of(line);
+        self.auto_scroll_enabled = false;
+        self.log_scroll_state
+            .set_row(row.saturating_sub(SEARCH_SCROLL_MARGIN));
+    }
+
     fn initialize_log_view(&mut self) {
         if let Some(selected) = self.table_scroll.selected()
             && selected < self.tasks.len()
@@ -283,16 +1063,18 @@ impl TuiApp {
             let selected_task = &self.tasks[selected];
             let log_path = &selected_task.log_path;
 
-            // Check cache first
-            if let Some(cache) = self.log_cache.get(log_path) {
-                self.log_lines_count = cache.content.len();
-            } else {
-                // If not in cache, we'll load it on first render
-                self.log_lines_count = 0;
-            }
+            // Logs opened before keep their index; others are indexed on first render
+            self.log_lines_count = self
+                .log_indexes
+                .get(log_path)
+                .map_or(0, LogIndex::line_count);
 
             // Reset scroll state to start from the top
             self.log_scroll_state.scroll_to_top();
+            self.log_search = None;
+            self.log_search_prompt = None;
+            self.log_goto = None;
+            self.log_level_filter = self.log_level.map(LevelFilter::new);
         }
     }
 
@@ -304,100 +1086,202 @@ impl TuiApp {
             ViewMode::TaskList => self.render_task_list(frame, area),
             ViewMode::LogView => self.render_log_view(frame, area),
             ViewMode::ProcessDetails => self.render_process_details(frame, area),
+            ViewMode::TemplateLauncher => self.render_template_launcher(frame, area),
+            ViewMode::NewTask => {
+                // The form is drawn over the task list
+                self.render_task_list(frame, area);
+                if let Some(form) = &self.new_task {
+                    new_task::NewTaskModal::new(form)
+                        .with_theme(self.theme)
+                        .render(frame, area);
+                }
+            }
+            ViewMode::Search => self.render_task_list(frame, area),
+            ViewMode::Notifications => {
+                notifications::NotificationsWidget::new(
+                    &self.notifications,
+                    self.notification_index,
+                )
+                .with_key_map(&self.key_map)
+                .with_theme(self.theme)
+                .render(frame, area);
+            }
+        }
+
+        if self.view_mode != ViewMode::Notifications {
+            let toasts = self.notifications.toasts();
+            notifications::render_toasts(frame, area, &toasts, &self.theme);
+        }
+
+        if self.show_help {
+            HelpOverlay::new(&self.key_map, self.view_mode)
+                .with_theme(self.theme)
+                .render(frame, area);
         }
     }
 
+    /// Notifications not sent to the terminal yet
+    pub fn take_unsent_notifications(&mut self) -> Vec<Notification> {
+        self.notifications.take_unsent()
+    }
+
+    /// Render the template launcher
+    fn render_template_launcher(&mut self, frame: &mut Frame, area: Rect) {
+        use super::template_launcher::TemplateLauncherWidget;
+
+        TemplateLauncherWidget::new(&self.templates, self.template_index)
+            .with_input(self.template_input.as_deref())
+            .with_error(self.template_error.as_deref())
+            .with_key_map(&self.key_map)
+            .with_theme(self.theme)
+            .render(frame, area);
+    }
+
     /// Render task list widget
     fn render_task_list(&mut self, frame: &mut Frame, area: Rect) {
         use super::task_list::TaskListWidget;
 
-        let widget = TaskListWidget::new(&self.tasks, &self.filter, &mut self.table_scroll);
+        let prompt = self.search_prompt();
+        let search = (!self.search.is_empty()).then_some(self.search_input.as_str());
+        let level_counts: HashMap<String, LevelCounts> = self
+            .level_counters
+            .iter()
+            .map(|(task_id, counter)| (task_id.clone(), counter.counts()))
+            .collect();
+        let widget = TaskListWidget::new(&self.tasks, &self.filter, &mut self.table_scroll)
+            .with_stats(self.stats.latest())
+            .with_schedules(&self.schedules)
+            .with_log_levels(&level_counts)
+            .with_search(search)
+            .with_prompt(prompt.as_deref())
+            .with_key_map(&self.key_map)
+            .with_theme(self.theme);
         frame.render_widget(widget, area);
     }
 
-    /// Render log view widget
-    fn render_log_view(&mut self, frame: &mut Frame, area: Rect) {
-        if let Some(selected) = self.table_scroll.selected()
-            && selected < self.tasks.len()
-        {
-            let selected_task = &self.tasks[selected];
-            let log_path = &selected_task.log_path;
+    /// The search line shown in place of the footer while searching
+    fn search_prompt(&self) -> Option<String> {
+        if self.view_mode != ViewMode::Search {
+            return None;
+        }
 
-            // Check if we need to reload or incrementally update the file
-            let update_strategy = if let Ok(metadata) = fs::metadata(log_path) {
-                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
-                let file_size = metadata.len();
-
-                if let Some(cache) = self.log_cache.get(log_path) {
-                    if modified > cache.last_modified {
-                        if file_size > cache.file_size {
-                            // File grew, use incremental update
-                            UpdateStrategy::Incremental(cache.file_size)
-                        } else {
-                            // File changed in other ways, full reload
-                            UpdateStrategy::FullReload
-                        }
-                    } else {
-                        // No changes
-                        UpdateStrategy::UseCache
-                    }
-                } else {
-                    // No cache exists, need to load
-                    UpdateStrategy::FullReload
-                }
-            } else {
-                // File doesn't exist or can't read metadata
-                UpdateStrategy::UseCache
-            };
+        let prompt = match &self.preset_name {
+            Some(name) => format!(" Save search as: {name}█  Enter:Save  Esc:Back"),
+            None => {
+                let status = match &self.search_error {
+                    Some(_) => String::new(),
+                    None => format!("{} matching  ", self.tasks.len()),
+                };
+                format!(
+                    " /{}█  {status}↑/↓:Presets  C-s:Save  Enter:Keep  Esc:Clear",
+                    self.search_input
+                )
+            }
+        };
+        Some(match &self.search_error {
+            Some(error) => format!("{prompt}  {error}"),
+            None => prompt,
+        })
+    }
 
-            // Use scrollview widget
-            let mut scrollview_widget = match update_strategy {
-                UpdateStrategy::FullReload => LogViewerScrollWidget::new(selected_task),
-                UpdateStrategy::Incremental(previous_size) => {
-                    let cache = self.log_cache.get(log_path).unwrap();
-                    LogViewerScrollWidget::load_incremental_content(
-                        selected_task,
-                        cache.content.clone(),
-                        previous_size,
-                    )
-                }
-                UpdateStrategy::UseCache => {
-                    let cache = self.log_cache.get(log_path).unwrap();
-                    LogViewerScrollWidget::with_cached_content(selected_task, cache.content.clone())
-                }
-            };
+    /// Render log view widget
+    fn render_log_view(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(selected_task) = self.tasks.get(self.selected_index()) else {
+            return;
+        };
+        let prompt = self
+            .log_search_prompt
+            .as_ref()
+            .map(SearchPrompt::line)
+            .or_else(|| self.log_goto_prompt());
+        let index = self
+            .log_indexes
+            .entry(selected_task.log_path.clone())
+            .or_insert_with(|| LogIndex::new(&selected_task.log_path));
 
-            // Set auto-scroll state from app
-            if self.auto_scroll_enabled {
-                scrollview_widget.enable_auto_scroll();
-            } else {
-                scrollview_widget.disable_auto_scroll();
+        // Pick up new output; a truncated or replaced log is read from the start
+        if index.refresh() {
+            self.log_lines_count = 0;
+            if let Some(search) = self.log_search.as_mut() {
+                search.restart();
             }
-
-            // Update cache if needed
-            if matches!(
-                update_strategy,
-                UpdateStrategy::FullReload | UpdateStrategy::Incremental(_)
-            ) && let Ok(metadata) = fs::metadata(log_path)
-            {
-                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
-                self.log_cache.insert(
-                    log_path.clone(),
-                    LogCache {
-                        content: scrollview_widget.get_lines().to_vec(),
-                        last_modified: modified,
-                        file_size: metadata.len(),
-                    },
-                );
+            if let Some(filter) = self.log_level_filter.as_mut() {
+                filter.restart();
             }
+        }
+        if let Some(search) = self.log_search.as_mut() {
+            search.update(index, self.ansi_mode);
+        }
+        if let Some(filter) = self.log_level_filter.as_mut() {
+            filter.update(index);
+        }
 
-            // Handle auto-scroll update
-            let new_line_count = scrollview_widget.get_lines_count();
-            self.handle_auto_scroll_update(&update_strategy, new_line_count);
+        // If auto-scroll is enabled and we have new content, scroll to bottom
+        let line_count = index.line_count();
+        if self.auto_scroll_enabled && line_count > self.log_lines_count {
+            self.log_scroll_state.scroll_to_bottom();
+        }
+        self.log_lines_count = line_count;
 
-            // Render with scrollview state
-            frame.render_stateful_widget(scrollview_widget, area, &mut self.log_scroll_state);
+        // Read only the rows on screen
+        let height = LogViewerScrollWidget::content_area(area).height as usize;
+        let filtered = log_level_filter::filtered_rows(
+            self.log_search.as_ref(),
+            self.log_level_filter.as_ref(),
+            line_count,
+        );
+        let row_count = filtered.as_ref().map_or(line_count, Vec::len);
+        let line_at = |row: usize| match &filtered {
+            Some(rows) => rows[row],
+            None => Some(row),
+        };
+        if self.pretty_json {
+            let last_row = last_pretty_row(index, row_count, line_at, height);
+            self.log_scroll_state.clamp_to(last_row, height);
+        } else {
+            self.log_scroll_state.clamp(row_count, height);
         }
+        let first = self.log_scroll_state.row();
+        let rows: Vec<(Option<usize>, String)> = match (&filtered, index.error()) {
+            (_, Some(error)) if line_count == 0 => vec![(Some(0), format!("Error: {error}"))],
+            (Some(_), _) => (first..row_count.min(first + height))
+                .map(|row| {
+                    let line = line_at(row);
+                    let text = line.and_then(|line| index.line(line)).unwrap_or_default();
+                    (line, text.to_string())
+                })
+                .collect(),
+            (None, _) => index
+                .lines(first, height)
+                .iter()
+                .enumerate()
+                .map(|(offset, line)| (Some(first + offset), line.clone()))
+                .collect(),
+        };
+        let format = self
+            .level_counters
+            .get(&selected_task.id)
+            .map_or(LogFormat::Plain, LevelCounter::format);
+
+        let mut widget = LogViewerScrollWidget::new(selected_task, rows, line_count)
+            .with_search(self.log_search.clone())
+            .with_prompt(prompt)
+            .with_ansi_mode(self.ansi_mode)
+            .with_indexing((!index.is_indexed()).then(|| index.progress()))
+            .with_level_filter(self.log_level)
+            .with_format(format)
+            .with_pretty(self.pretty_json)
+            .with_key_map(&self.key_map)
+            .with_theme(self.theme);
+
+        // Set auto-scroll state from app
+        if self.auto_scroll_enabled {
+            widget.enable_auto_scroll();
+        } else {
+            widget.disable_auto_scroll();
+        }
+
+        frame.render_stateful_widget(widget, area, &mut self.log_scroll_state);
     }
 
     pub fn should_quit(&self) -> bool {
@@ -438,7 +1322,14 @@ impl TuiApp {
         // Find the selected task
         if let Some(task_id) = &self.selected_task_id {
             if let Some(task) = self.tasks.iter().find(|t| t.id == *task_id) {
-                let widget = ProcessDetailsWidget::new(task);
+                let widget = ProcessDetailsWidget::new(task)
+                    .with_stats(self.stats.get(task_id), self.stats.history(task_id))
+                    .with_process_tree(&self.process_tree)
+                    .with_chain(&self.chain)
+                    .with_events(&self.events)
+                    .with_redact(&self.redact)
+                    .with_key_map(&self.key_map)
+                    .with_theme(self.theme);
                 widget.render(frame, area, &mut self.env_scroll_state);
             } else {
                 // Task not found, go back to task list
@@ -521,23 +1412,161 @@ impl TuiApp {
         Ok(())
     }
 
-    /// Handle auto-scroll update based on new content
-    fn handle_auto_scroll_update(
-        &mut self,
-        update_strategy: &UpdateStrategy,
-        new_line_count: usize,
-    ) {
-        let had_new_content = matches!(
-            update_strategy,
-            UpdateStrategy::FullReload | UpdateStrategy::Incremental(_)
-        ) && new_line_count > self.log_lines_count;
+    /// Open the form for starting a new task
+    /// Start typing a search, keeping the current one for editing
+    pub fn open_search(&mut self) -> Result<()> {
+        self.filter_presets = storage::get_filter_presets(&self.conn)?;
+        self.preset_index = None;
+        self.preset_name = None;
+        self.search_error = None;
+        self.view_mode = ViewMode::Search;
+        Ok(())
+    }
 
-        self.log_lines_count = new_line_count;
+    /// Filter the task list by the typed search, keeping the selected task selected
+    fn apply_search(&mut self) -> Result<()> {
+        match Query::parse(&self.search_input, &self.filter_presets) {
+            Ok(query) => {
+                self.search = query;
+                self.search_error = None;
+            }
+            // Keep showing the last search that parsed
+            Err(e) => {
+                self.search_error = Some(e.to_string());
+                return Ok(());
+            }
+        }
 
-        // If auto-scroll is enabled and we have new content, scroll to bottom
-        if self.auto_scroll_enabled && had_new_content {
-            self.log_scroll_state.scroll_to_bottom();
+        let selected_id = self
+            .table_scroll
+            .selected()
+            .and_then(|index| self.tasks.get(index))
+            .map(|task| task.id.clone());
+        self.refresh_tasks()?;
+        if !self.tasks.is_empty() {
+            let index = selected_id
+                .and_then(|id| self.tasks.iter().position(|task| task.id == id))
+                .unwrap_or(0);
+            self.table_scroll.select(Some(index));
+        }
+        Ok(())
+    }
+
+    pub fn open_new_task(&mut self) -> Result<()> {
+        // Offer earlier commands for recall, most recent first
+        let mut history: Vec<String> = Vec::new();
+        for task in storage::get_tasks(&self.conn, None)? {
+            let Ok(command) = serde_json::from_str::<Vec<String>>(&task.command) else {
+                continue;
+            };
+            let command = new_task::quote_command(&command);
+            if !history.contains(&command) {
+                history.push(command);
+            }
+            if history.len() == new_task::MAX_HISTORY {
+                break;
+            }
+        }
+
+        self.new_task = Some(NewTaskForm::new(history));
+        self.view_mode = ViewMode::NewTask;
+        Ok(())
+    }
+
+    /// Validate the new task form and start the task
+    ///
+    /// Invalid inputs and spawn failures are shown in the form, which stays open.
+    fn start_new_task(&mut self) -> Result<()> {
+        let Some(form) = self.new_task.as_mut() else {
+            return Ok(());
+        };
+        let Some(task) = form.validate() else {
+            return Ok(());
+        };
+
+        let spawned = commands::spawn_and_register_process_with_options(
+            task.command,
+            task.cwd,
+            task.env,
+            &task.options,
+            &self.conn,
+        )
+        .and_then(|(process_info, child)| {
+            if let Some(ref name) = task.name {
+                storage::set_task_name(&self.conn, &process_info.id, name)?;
+            }
+            Ok((process_info, child))
+        });
+
+        match spawned {
+            Ok((process_info, child)) => {
+                self.child_processes.insert(process_info.id, child);
+                self.new_task = None;
+                self.view_mode = ViewMode::TaskList;
+                self.refresh_tasks()?;
+                // Select the new task, which is listed first
+                self.table_scroll.first();
+            }
+            Err(e) => form.set_error(new_task::Field::Command, e.to_string()),
         }
+
+        Ok(())
+    }
+
+    /// Show the saved templates to pick one to start
+    pub fn open_template_launcher(&mut self) -> Result<()> {
+        self.templates = storage::get_templates(&self.conn)?;
+        self.template_index = self
+            .template_index
+            .min(self.templates.len().saturating_sub(1));
+        self.template_input = None;
+        self.template_error = None;
+        self.view_mode = ViewMode::TemplateLauncher;
+        Ok(())
+    }
+
+    /// Start the selected template with the typed parameters
+    ///
+    /// On failure the launcher stays open and shows the error.
+    fn start_selected_template(&mut self) -> Result<()> {
+        let Some(selected) = self.templates.get(self.template_index) else {
+            return Ok(());
+        };
+
+        let input = self.template_input.as_deref().unwrap_or_default();
+        let launch = if input.trim().is_empty() {
+            Ok(Vec::new())
+        } else {
+            helpers::parse_command(input)
+        }
+        .and_then(|params| template::parse_params(&params))
+        .and_then(|mut params| {
+            // Prefilled parameters left empty count as not given
+            params.retain(|(_, value)| !value.is_empty());
+            template::prepare(selected, &params, &[])
+        })
+        .and_then(|launch| {
+            commands::spawn_and_register_process_with_options(
+                launch.command,
+                launch.cwd,
+                launch.env,
+                &launch.options,
+                &self.conn,
+            )
+        });
+
+        match launch {
+            Ok((process_info, child)) => {
+                self.child_processes.insert(process_info.id, child);
+                self.template_input = None;
+                self.template_error = None;
+                self.view_mode = ViewMode::TaskList;
+                self.refresh_tasks()?;
+            }
+            Err(e) => self.template_error = Some(e.to_string()),
+        }
+
+        Ok(())
     }
 
     /// Clean up finished child processes to prevent zombie processes
@@ -551,3 +1580,29 @@ impl TuiApp {
         });
     }
 }
+
+/// The last row the log viewer can scroll to in pretty mode, where JSON
+/// lines take several rows: the one from which the remaining rows fill all
+/// but the last row of the screen
+fn last_pretty_row(
+    index: &mut LogIndex,
+    row_count: usize,
+    line_at: impl Fn(usize) -> Option<usize>,
+    height: usize,
+) -> usize {
+    let mut used = 0;
+    let mut row = row_count;
+    while row > 0 {
+        let taken = line_at(row - 1)
+            .and_then(|line| index.line(line))
+            .and_then(log_level::pretty_json)
+            .map_or(1, |lines| lines.len());
+        // The last line is shown even when it does not fit
+        if used > 0 && used + taken > height.saturating_sub(1) {
+            break;
+        }
+        used += taken;
+        row -= 1;
+    }
+    row
+}
@@ -0,0 +1,93 @@
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use super::ViewMode;
+use super::keymap::KeyMap;
+use super::theme::Theme;
+
+/// Footer hints with the keys in brackets, e.g. "[q] Quit  [Esc] Back to list"
+pub fn bracketed_hints(hints: &[(String, &str)], theme: &Theme) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (index, (keys, label)) in hints.iter().enumerate() {
+        if index > 0 {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(
+            format!("[{keys}]"),
+            Style::default().fg(theme.key),
+        ));
+        spans.push(Span::raw(format!(" {label}")));
+    }
+    Line::from(spans)
+}
+
+/// Lists the keys of a view and what they do, drawn over the view
+pub struct HelpOverlay<'a> {
+    key_map: &'a KeyMap,
+    view: ViewMode,
+    theme: Theme,
+}
+
+impl<'a> HelpOverlay<'a> {
+    pub fn new(key_map: &'a KeyMap, view: ViewMode) -> Self {
+        Self {
+            key_map,
+            view,
+            theme: Theme::default(),
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn render(self, frame: &mut Frame, area: Rect) {
+        let help = self.key_map.help(self.view);
+        let key_width = help
+            .iter()
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let key_style = Style::default()
+            .fg(self.theme.key)
+            .add_modifier(Modifier::BOLD);
+        let mut lines: Vec<Line> = help
+            .into_iter()
+            .map(|(keys, description)| {
+                Line::from(vec![
+                    Span::styled(format!(" {keys:>key_width$}  "), key_style),
+                    Span::raw(description),
+                ])
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            " Press any key to close",
+            Style::default().fg(self.theme.muted),
+        )));
+
+        let content_width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 1;
+        let width = (content_width + 2).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let overlay = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let block = Block::default()
+            .title(" Keys ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.panel_border));
+        frame.render_widget(Clear, overlay);
+        frame.render_widget(Paragraph::new(lines).block(block), overlay);
+    }
+}
@@ -0,0 +1,971 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::ViewMode;
+use crate::app::config::FileConfig;
+
+/// Something a key can be bound to in the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    Back,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    PageDown,
+    PageUp,
+    Select,
+    OpenLog,
+    Rerun,
+    Stop,
+    Kill,
+    CycleFilter,
+    Templates,
+    NewTask,
+    Search,
+    Notifications,
+    Left,
+    Right,
+    Follow,
+    SearchBackward,
+    NextMatch,
+    PrevMatch,
+    GotoLine,
+    FilterMatches,
+    MoreContext,
+    LessContext,
+    Level,
+    PrettyJson,
+    Ansi,
+}
+
+/// The views keys are bound in, by the name of their table in `[tui.keys]`
+const VIEWS: &[(ViewMode, &str)] = &[
+    (ViewMode::TaskList, "list"),
+    (ViewMode::LogView, "log"),
+    (ViewMode::ProcessDetails, "details"),
+    (ViewMode::Notifications, "notifications"),
+    (ViewMode::TemplateLauncher, "templates"),
+];
+
+const ALL_VIEWS: &[ViewMode] = &[
+    ViewMode::TaskList,
+    ViewMode::LogView,
+    ViewMode::ProcessDetails,
+    ViewMode::Notifications,
+    ViewMode::TemplateLauncher,
+];
+const LISTS: &[ViewMode] = &[
+    ViewMode::TaskList,
+    ViewMode::Notifications,
+    ViewMode::TemplateLauncher,
+];
+const PAGED: &[ViewMode] = &[
+    ViewMode::TaskList,
+    ViewMode::LogView,
+    ViewMode::ProcessDetails,
+];
+const LIST_AND_LOG: &[ViewMode] = &[ViewMode::TaskList, ViewMode::LogView];
+const TASK_LIST: &[ViewMode] = &[ViewMode::TaskList];
+const LOG_VIEW: &[ViewMode] = &[ViewMode::LogView];
+
+/// An action as named in the config file, and the views it is done in
+struct ActionInfo {
+    action: Action,
+    name: &'static str,
+    /// Shown in the help overlay
+    description: &'static str,
+    views: &'static [ViewMode],
+}
+
+/// Every action, in the order the help overlay lists them
+const ACTIONS: &[ActionInfo] = &[
+    ActionInfo {
+        action: Action::Down,
+        name: "down",
+        description: "Move down",
+        views: ALL_VIEWS,
+    },
+    ActionInfo {
+        action: Action::Up,
+        name: "up",
+        description: "Move up",
+        views: ALL_VIEWS,
+    },
+    ActionInfo {
+        action: Action::Left,
+        name: "left",
+        description: "Scroll left",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::Right,
+        name: "right",
+        description: "Scroll right",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::Top,
+        name: "top",
+        description: "Go to the top",
+        views: LIST_AND_LOG,
+    },
+    ActionInfo {
+        action: Action::Bottom,
+        name: "bottom",
+        description: "Go to the bottom",
+        views: LIST_AND_LOG,
+    },
+    ActionInfo {
+        action: Action::PageDown,
+        name: "page_down",
+        description: "Page down",
+        views: PAGED,
+    },
+    ActionInfo {
+        action: Action::PageUp,
+        name: "page_up",
+        description: "Page up",
+        views: PAGED,
+    },
+    ActionInfo {
+        action: Action::Select,
+        name: "select",
+        description: "Show the task details, or start the template",
+        views: LISTS,
+    },
+    ActionInfo {
+        action: Action::OpenLog,
+        name: "open_log",
+        description: "Show the log of the task",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::Rerun,
+        name: "rerun",
+        description: "Run the command again",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::Stop,
+        name: "stop",
+        description: "Stop the task",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::Kill,
+        name: "kill",
+        description: "Kill the task (SIGKILL)",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::CycleFilter,
+        name: "cycle_filter",
+        description: "Switch the status filter",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::NewTask,
+        name: "new_task",
+        description: "Start a new task",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::Templates,
+        name: "templates",
+        description: "Open the template launcher",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::Notifications,
+        name: "notifications",
+        description: "Show the notification history",
+        views: TASK_LIST,
+    },
+    ActionInfo {
+        action: Action::Search,
+        name: "search",
+        description: "Search",
+        views: LIST_AND_LOG,
+    },
+    ActionInfo {
+        action: Action::SearchBackward,
+        name: "search_backward",
+        description: "Search backward",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::NextMatch,
+        name: "next_match",
+        description: "Jump to the next match",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::PrevMatch,
+        name: "prev_match",
+        description: "Jump to the previous match",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::FilterMatches,
+        name: "filter_matches",
+        description: "Show only the matching lines",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::MoreContext,
+        name: "more_context",
+        description: "Show more lines around matches",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::LessContext,
+        name: "less_context",
+        description: "Show fewer lines around matches",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::GotoLine,
+        name: "goto_line",
+        description: "Jump to a line number or percentage",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::Follow,
+        name: "follow",
+        description: "Toggle auto-scroll",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::Level,
+        name: "level",
+        description: "Cycle the minimum log level shown",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::PrettyJson,
+        name: "pretty_json",
+        description: "Pretty-print JSON lines",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::Ansi,
+        name: "ansi",
+        description: "Cycle ANSI colors rendered/stripped/raw",
+        views: LOG_VIEW,
+    },
+    ActionInfo {
+        action: Action::Back,
+        name: "back",
+        description: "Go back, or clear the search",
+        views: ALL_VIEWS,
+    },
+    ActionInfo {
+        action: Action::Help,
+        name: "help",
+        description: "Show this help",
+        views: ALL_VIEWS,
+    },
+    ActionInfo {
+        action: Action::Quit,
+        name: "quit",
+        description: "Quit",
+        views: ALL_VIEWS,
+    },
+];
+
+impl Action {
+    fn info(self) -> &'static ActionInfo {
+        ACTIONS
+            .iter()
+            .find(|info| info.action == self)
+            .expect("every action is listed in ACTIONS")
+    }
+
+    /// Name of the action in `[tui.keys]`, e.g. "page_down"
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+
+    pub fn description(self) -> String {
+        let description = self.info().description;
+        match self {
+            // The signal sent is the configured `stop.signal`
+            Action::Stop => format!("{description} ({})", FileConfig::current().stop.signal()),
+            _ => description.to_string(),
+        }
+    }
+
+    /// Whether the action can be done in a view
+    pub fn is_in(self, view: ViewMode) -> bool {
+        self.info().views.contains(&view)
+    }
+}
+
+/// Keys of the default key map, vim style
+const DEFAULT_KEYS: &str = r#"
+quit = ["q", "C-c"]
+help = "?"
+back = "Esc"
+down = "j"
+up = "k"
+top = "g"
+bottom = "G"
+page_down = "C-d"
+page_up = "C-u"
+select = "Enter"
+open_log = "l"
+rerun = "r"
+stop = "s"
+kill = "C-k"
+cycle_filter = "Tab"
+new_task = "n"
+templates = "t"
+notifications = "N"
+search = "/"
+left = "h"
+right = "l"
+follow = "C-f"
+search_backward = "C-r"
+next_match = "n"
+prev_match = "N"
+goto_line = ":"
+filter_matches = "&"
+more_context = "+"
+less_context = "-"
+level = "v"
+pretty_json = "p"
+ansi = "c"
+"#;
+
+/// Keys the emacs key map changes in the default one
+const EMACS_KEYS: &str = r#"
+back = ["Esc", "C-g"]
+down = "C-n"
+up = "C-p"
+top = "M-<"
+bottom = "M->"
+page_down = "C-v"
+page_up = "M-v"
+left = "C-b"
+right = "C-f"
+follow = "F"
+search = "C-s"
+next_match = "M-n"
+prev_match = "M-p"
+goto_line = "M-g"
+"#;
+
+/// Keys the arrows key map changes in the default one
+const ARROWS_KEYS: &str = r#"
+down = "Down"
+up = "Up"
+left = "Left"
+right = "Right"
+top = "Home"
+bottom = "End"
+page_down = "PageDown"
+page_up = "PageUp"
+"#;
+
+/// A built-in key map the keys of the config file are applied over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyPreset {
+    /// vim-style keys
+    #[default]
+    Default,
+    Emacs,
+    /// Arrow keys, Home/End and PageUp/PageDown for moving around
+    Arrows,
+}
+
+impl KeyPreset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyPreset::Default => "default",
+            KeyPreset::Emacs => "emacs",
+            KeyPreset::Arrows => "arrows",
+        }
+    }
+
+    /// The keys the preset changes in the default key map
+    fn keys(&self) -> Option<&'static str> {
+        match self {
+            KeyPreset::Default => None,
+            KeyPreset::Emacs => Some(EMACS_KEYS),
+            KeyPreset::Arrows => Some(ARROWS_KEYS),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for KeyPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(KeyPreset::Default),
+            "emacs" => Ok(KeyPreset::Emacs),
+            "arrows" => Ok(KeyPreset::Arrows),
+            _ => Err(format!(
+                "Invalid key map: {s}. Valid options: default, emacs, arrows"
+            )),
+        }
+    }
+}
+
+/// A key with its modifiers, written like "C-k", "M-v", "Esc" or "F1"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            // Terminals send Shift+Tab as BackTab
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            // Shift is part of the character, e.g. `G`
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+
+    /// Whether a key event is this key
+    pub fn matches(&self, key: KeyEvent) -> bool {
+        *self == Self::new(key.code, key.modifiers)
+    }
+
+    fn without_modifiers(self) -> Self {
+        Self {
+            code: self.code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+}
+
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::BackTab => write!(f, "S-Tab"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+impl std::str::FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+        // A lone `-` is a key, so a prefix needs something after it
+        while key.len() > 2 {
+            let modifier = match key.get(..2) {
+                Some("C-") => KeyModifiers::CONTROL,
+                Some("M-" | "A-") => KeyModifiers::ALT,
+                Some("S-") => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            modifiers |= modifier;
+            key = &key[2..];
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some('↑'), None) => KeyCode::Up,
+            (Some('↓'), None) => KeyCode::Down,
+            (Some('←'), None) => KeyCode::Left,
+            (Some('→'), None) => KeyCode::Right,
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "space" => KeyCode::Char(' '),
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => {
+                        return Err(format!(
+                            "Invalid key: {s}. Use a character or a name like Enter, Esc, Tab, Up, PageDown or F1, optionally after C-, M- or S-"
+                        ));
+                    }
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+/// The keys bound to each action, per view
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap {
+    bindings: HashMap<(ViewMode, Action), Vec<KeyBinding>>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::new(KeyPreset::Default, None).expect("the default key map is valid")
+    }
+}
+
+impl KeyMap {
+    /// The keys of a preset with the `[tui.keys]` of the config file applied
+    /// over them
+    ///
+    /// Fails when a key is bound to two actions of the same view.
+    pub fn new(preset: KeyPreset, keys: Option<&toml::Table>) -> Result<Self, String> {
+        let mut key_map = Self {
+            bindings: HashMap::new(),
+        };
+        key_map.apply(&preset_table(DEFAULT_KEYS))?;
+        if let Some(preset_keys) = preset.keys() {
+            key_map.apply(&preset_table(preset_keys))?;
+        }
+        if let Some(keys) = keys {
+            key_map.apply(keys)?;
+        }
+        key_map.check_conflicts()?;
+        Ok(key_map)
+    }
+
+    /// The default key map, for widgets that are not given the configured one
+    pub fn builtin() -> &'static KeyMap {
+        static BUILTIN: OnceLock<KeyMap> = OnceLock::new();
+        BUILTIN.get_or_init(KeyMap::default)
+    }
+
+    /// Bind the keys of a table like `[tui.keys]`
+    ///
+    /// `action = keys` binds the keys in every view the action is done in;
+    /// `[view]` tables of them bind keys in that view only, and win.
+    pub fn apply(&mut self, table: &toml::Table) -> Result<(), String> {
+        for (name, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+            let action = find_action(name)?;
+            let keys = parse_keys(value)?;
+            for &(view, _) in VIEWS.iter().filter(|(view, _)| action.is_in(*view)) {
+                self.bindings.insert((view, action), keys.clone());
+            }
+        }
+
+        for (name, entries) in table
+            .iter()
+            .filter_map(|(name, value)| value.as_table().map(|entries| (name, entries)))
+        {
+            let Some(&(view, _)) = VIEWS
+                .iter()
+                .find(|(_, view_name)| *view_name == name.as_str())
+            else {
+                let valid: Vec<&str> = VIEWS.iter().map(|(_, name)| *name).collect();
+                return Err(format!(
+                    "unknown view `{name}`; valid views: {}",
+                    valid.join(", ")
+                ));
+            };
+            for (action_name, value) in entries {
+                let action = find_action(action_name)?;
+                if !action.is_in(view) {
+                    return Err(format!(
+                        "`{action_name}` is not an action of the {name} view"
+                    ));
+                }
+                self.bindings.insert((view, action), parse_keys(value)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_conflicts(&self) -> Result<(), String> {
+        for &(view, name) in VIEWS {
+            let mut bound: Vec<(KeyBinding, Action)> = Vec::new();
+            for action in actions_in(view) {
+                for key in self.keys(view, action) {
+                    if let Some((_, other)) = bound
+                        .iter()
+                        .find(|(bound_key, other)| bound_key == key && *other != action)
+                    {
+                        return Err(format!(
+                            "`{key}` is bound to both `{}` and `{}` in the {name} view",
+                            other.name(),
+                            action.name()
+                        ));
+                    }
+                    bound.push((*key, action));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The action a key is bound to in a view
+    pub fn action(&self, view: ViewMode, key: KeyEvent) -> Option<Action> {
+        actions_in(view).find(|action| {
+            self.keys(view, *action)
+                .iter()
+                .any(|binding| binding.matches(key))
+        })
+    }
+
+    /// The keys bound to an action in a view, the one shown in hints first
+    pub fn keys(&self, view: ViewMode, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .get(&(view, action))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The keys of a footer hint, e.g. "j/k" for down and up, or "C-d/u" for
+    /// keys with the same modifiers; None when none of the actions has a key
+    pub fn hint(&self, view: ViewMode, actions: &[Action]) -> Option<String> {
+        let keys: Vec<KeyBinding> = actions
+            .iter()
+            .filter_map(|action| self.keys(view, *action).first().copied())
+            .collect();
+        let first = keys.first()?;
+        let shared = first.modifiers != KeyModifiers::NONE
+            && keys.iter().all(|key| key.modifiers == first.modifiers);
+        let keys: Vec<String> = keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| match index {
+                0 => key.to_string(),
+                _ if shared => key.without_modifiers().to_string(),
+                _ => key.to_string(),
+            })
+            .collect();
+        Some(keys.join("/"))
+    }
+
+    /// Footer hints of a view as keys and labels, e.g. ("j/k", "Move"),
+    /// leaving out the hints whose actions have no keys
+    pub fn hints<'l>(
+        &self,
+        view: ViewMode,
+        hints: &[(&[Action], &'l str)],
+    ) -> Vec<(String, &'l str)> {
+        hints
+            .iter()
+            .filter_map(|(actions, label)| Some((self.hint(view, actions)?, *label)))
+            .collect()
+    }
+
+    /// Footer hints joined like " j/k:Move  l:Log"
+    pub fn footer(&self, view: ViewMode, hints: &[(&[Action], &str)]) -> String {
+        self.footer_within(view, hints, usize::MAX)
+    }
+
+    /// Footer hints that fit in `width` columns, those at the end of `hints`
+    /// left out first
+    pub fn footer_within(
+        &self,
+        view: ViewMode,
+        hints: &[(&[Action], &str)],
+        width: usize,
+    ) -> String {
+        let mut hints: Vec<String> = self
+            .hints(view, hints)
+            .into_iter()
+            .map(|(keys, label)| format!("{keys}:{label}"))
+            .collect();
+        loop {
+            let footer = format!(" {}", hints.join("  "));
+            if footer.chars().count() <= width || hints.len() <= 1 {
+                return footer;
+            }
+            hints.pop();
+        }
+    }
+
+    /// Every action of a view with its keys, for the help overlay
+    pub fn help(&self, view: ViewMode) -> Vec<(String, String)> {
+        actions_in(view)
+            .filter_map(|action| {
+                let keys = self.keys(view, action);
+                (!keys.is_empty()).then(|| {
+                    let keys: Vec<String> = keys.iter().map(KeyBinding::to_string).collect();
+                    (keys.join(", "), action.description())
+                })
+            })
+            .collect()
+    }
+}
+
+/// The actions done in a view, in the order of `ACTIONS`
+fn actions_in(view: ViewMode) -> impl Iterator<Item = Action> {
+    ACTIONS
+        .iter()
+        .filter(move |info| info.views.contains(&view))
+        .map(|info| info.action)
+}
+
+fn find_action(name: &str) -> Result<Action, String> {
+    ACTIONS
+        .iter()
+        .find(|info| info.name == name)
+        .map(|info| info.action)
+        .ok_or_else(|| {
+            let valid: Vec<&str> = ACTIONS.iter().map(|info| info.name).collect();
+            format!(
+                "unknown action `{name}`; valid actions: {}",
+                valid.join(", ")
+            )
+        })
+}
+
+/// Keys given as one string or a list of them; an empty list unbinds the action
+fn parse_keys(value: &toml::Value) -> Result<Vec<KeyBinding>, String> {
+    let expected = || {
+        format!(
+            "expected a key or a list of keys, found {}",
+            value.type_str()
+        )
+    };
+    match value {
+        toml::Value::String(key) => Ok(vec![key.parse()?]),
+        toml::Value::Array(keys) => keys
+            .iter()
+            .map(|key| key.as_str().ok_or_else(expected)?.parse())
+            .collect(),
+        _ => Err(expected()),
+    }
+}
+
+fn preset_table(keys: &str) -> toml::Table {
+    keys.parse().expect("the presets are valid TOML")
+}
+
+/// Check a `[tui.keys]` table on its own; conflicts are only known once the
+/// config files are merged
+pub fn check_keys(value: &toml::Value) -> Result<(), String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| format!("expected a table, found {}", value.type_str()))?;
+    KeyMap::default().apply(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_key_binding_round_trip() {
+        for text in [
+            "q", "C-k", "M-<", "Esc", "Enter", "F1", "S-Tab", "PgDn", "↑", "Space", "-",
+        ] {
+            let binding: KeyBinding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), text);
+        }
+        assert_eq!("C--".parse::<KeyBinding>().unwrap().to_string(), "C--");
+        assert_eq!(
+            "pagedown".parse::<KeyBinding>().unwrap().to_string(),
+            "PgDn"
+        );
+        assert!("C-".parse::<KeyBinding>().is_err());
+        assert!("Hyper".parse::<KeyBinding>().is_err());
+        assert!("F13".parse::<KeyBinding>().is_err());
+
+        let shift_g: KeyBinding = "G".parse().unwrap();
+        assert!(shift_g.matches(key(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert!(shift_g.matches(key(KeyCode::Char('G'), KeyModifiers::NONE)));
+        let back_tab: KeyBinding = "S-Tab".parse().unwrap();
+        assert!(back_tab.matches(key(KeyCode::BackTab, KeyModifiers::SHIFT)));
+    }
+
+    #[test]
+    fn test_presets_have_no_conflicts() {
+        for preset in [KeyPreset::Default, KeyPreset::Emacs, KeyPreset::Arrows] {
+            KeyMap::new(preset, None).unwrap();
+            assert_eq!(preset.as_str().parse::<KeyPreset>(), Ok(preset));
+        }
+
+        let default = KeyMap::default();
+        let list = ViewMode::TaskList;
+        assert_eq!(
+            default.action(list, key(KeyCode::Char('k'), KeyModifiers::NONE)),
+            Some(Action::Up)
+        );
+        assert_eq!(
+            default.action(list, key(KeyCode::Char('k'), KeyModifiers::CONTROL)),
+            Some(Action::Kill)
+        );
+        assert_eq!(
+            default.action(
+                ViewMode::LogView,
+                key(KeyCode::Char('?'), KeyModifiers::NONE)
+            ),
+            Some(Action::Help)
+        );
+        assert_eq!(
+            default.action(
+                ViewMode::LogView,
+                key(KeyCode::Char('r'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::SearchBackward)
+        );
+        assert_eq!(
+            default.hint(list, &[Action::Down, Action::Up]).unwrap(),
+            "j/k"
+        );
+        assert_eq!(
+            default
+                .hint(list, &[Action::PageDown, Action::PageUp])
+                .unwrap(),
+            "C-d/u"
+        );
+        assert_eq!(
+            default.hint(list, &[Action::Stop, Action::Kill]).unwrap(),
+            "s/C-k"
+        );
+        let hints: &[(&[Action], &str)] = &[
+            (&[Action::Quit], "Quit"),
+            (&[Action::Down, Action::Up], "Move"),
+        ];
+        assert_eq!(default.footer(list, hints), " q:Quit  j/k:Move");
+        assert_eq!(default.footer_within(list, hints, 10), " q:Quit");
+
+        let emacs = KeyMap::new(KeyPreset::Emacs, None).unwrap();
+        assert_eq!(
+            emacs.action(list, key(KeyCode::Char('n'), KeyModifiers::CONTROL)),
+            Some(Action::Down)
+        );
+        assert_eq!(
+            emacs.action(
+                list,
+                key(KeyCode::Char('>'), KeyModifiers::ALT | KeyModifiers::SHIFT)
+            ),
+            Some(Action::Bottom)
+        );
+        let arrows = KeyMap::new(KeyPreset::Arrows, None).unwrap();
+        assert_eq!(
+            arrows.hint(list, &[Action::Down, Action::Up]).unwrap(),
+            "↓/↑"
+        );
+    }
+
+    #[test]
+    fn test_config_keys_override_the_preset() {
+        let keys: toml::Table = r#"
+            quit = "Q"
+            rerun = []
+            [details]
+            down = ["j", "Down"]
+        "#
+        .parse()
+        .unwrap();
+        let key_map = KeyMap::new(KeyPreset::Default, Some(&keys)).unwrap();
+        let list = ViewMode::TaskList;
+        assert_eq!(
+            key_map.action(list, key(KeyCode::Char('Q'), KeyModifiers::SHIFT)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            key_map.action(list, key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            key_map.action(list, key(KeyCode::Char('r'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(key_map.hint(list, &[Action::Rerun]), None);
+        assert_eq!(
+            key_map.action(
+                ViewMode::ProcessDetails,
+                key(KeyCode::Down, KeyModifiers::NONE)
+            ),
+            Some(Action::Down)
+        );
+        assert_eq!(
+            key_map.action(list, key(KeyCode::Down, KeyModifiers::NONE)),
+            None
+        );
+        assert!(
+            key_map
+                .help(list)
+                .contains(&("Q".to_string(), "Quit".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_stop_description_names_the_configured_signal() {
+        let signal = FileConfig::current().stop.signal();
+        assert_eq!(
+            Action::Stop.description(),
+            format!("Stop the task ({signal})")
+        );
+        assert_eq!(Action::Kill.description(), "Kill the task (SIGKILL)");
+    }
+
+    #[test]
+    fn test_config_key_errors() {
+        let error = |keys: &str| {
+            let keys: toml::Table = keys.parse().unwrap();
+            KeyMap::new(KeyPreset::Default, Some(&keys)).unwrap_err()
+        };
+
+        let conflict = error("stop = \"r\"");
+        assert_eq!(
+            conflict,
+            "`r` is bound to both `rerun` and `stop` in the list view"
+        );
+        // `l` opens the log in the list, but scrolls right in the log viewer
+        assert!(error("right = \"h\"").contains("`h` is bound to both `left` and `right`"));
+        assert!(error("jump = \"J\"").contains("unknown action `jump`"));
+        assert!(error("[list]\nleft = \"h\"").contains("`left` is not an action of the list view"));
+        assert!(error("[editor]\nquit = \"q\"").contains("unknown view `editor`"));
+        assert!(error("quit = 1").contains("expected a key or a list of keys"));
+        assert!(error("quit = \"Ctrl+Q\"").contains("Invalid key: Ctrl+Q"));
+    }
+}
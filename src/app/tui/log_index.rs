@@ -0,0 +1,302 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+/// Every this many lines the index keeps the offset where a line starts
+const CHECKPOINT_INTERVAL: usize = 256;
+/// Bytes indexed per refresh, so a huge log is indexed over several frames
+const INDEX_BYTES_PER_REFRESH: u64 = 64 * 1024 * 1024;
+/// Lines read around a requested line, so scrolling rarely touches the disk
+const WINDOW_LINES: usize = 512;
+
+/// Line index over a log file that pages lines in from disk
+///
+/// Only the start offset of every `CHECKPOINT_INTERVAL`th line and a window
+/// of lines around the viewport are held in memory, so the whole log can be
+/// viewed however large it grows. Files that shrink or are replaced are
+/// indexed again from the start.
+#[derive(Debug)]
+pub struct LogIndex {
+    path: PathBuf,
+    checkpoints: Vec<u64>,
+    /// Lines ended by a newline
+    complete_lines: usize,
+    /// Offset just past the last newline found
+    lines_end: u64,
+    /// Bytes looked at so far
+    scanned: u64,
+    file_size: u64,
+    inode: Option<u64>,
+    window_start: usize,
+    window: Vec<String>,
+    error: Option<String>,
+}
+
+impl LogIndex {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            checkpoints: vec![0],
+            complete_lines: 0,
+            lines_end: 0,
+            scanned: 0,
+            file_size: 0,
+            inode: None,
+            window_start: 0,
+            window: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Pick up changes to the file and index the next part of it
+    ///
+    /// Returns true when the file was truncated or replaced, so line numbers
+    /// from before no longer apply.
+    pub fn refresh(&mut self) -> bool {
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.error = Some(format!("Could not read log file: {e}"));
+                return false;
+            }
+        };
+        self.error = None;
+
+        let replaced = self.inode.is_some_and(|inode| inode != metadata.ino())
+            || metadata.len() < self.scanned;
+        if replaced {
+            *self = Self::new(std::mem::take(&mut self.path));
+        }
+        self.inode = Some(metadata.ino());
+        self.file_size = metadata.len();
+
+        if self.scanned < self.file_size {
+            let previous_lines = self.complete_lines;
+            if let Err(e) = self.index_next(INDEX_BYTES_PER_REFRESH) {
+                self.error = Some(format!("Could not read log file: {e}"));
+            }
+            // The last line may have been cut off when the window was read
+            if self.window_start + self.window.len() > previous_lines {
+                self.window
+                    .truncate(previous_lines.saturating_sub(self.window_start));
+            }
+        }
+        replaced
+    }
+
+    /// Index the whole file, e.g. before jumping to a percentage of it
+    pub fn index_all(&mut self) {
+        while !self.is_indexed() && self.error.is_none() {
+            if let Err(e) = self.index_next(INDEX_BYTES_PER_REFRESH) {
+                self.error = Some(format!("Could not read log file: {e}"));
+            }
+        }
+    }
+
+    fn index_next(&mut self, max_bytes: u64) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.scanned))?;
+        let mut reader = file.take(max_bytes.min(self.file_size - self.scanned));
+        let mut buffer = vec![0; 256 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            for (position, _) in buffer[..read]
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+            {
+                self.complete_lines += 1;
+                self.lines_end = self.scanned + position as u64 + 1;
+                if self.complete_lines.is_multiple_of(CHECKPOINT_INTERVAL) {
+                    self.checkpoints.push(self.lines_end);
+                }
+            }
+            self.scanned += read as u64;
+        }
+        // The file may have been truncated while it was read
+        self.file_size = self.file_size.max(self.scanned);
+        Ok(())
+    }
+
+    /// Number of lines indexed so far, including a last line without a newline
+    pub fn line_count(&self) -> usize {
+        self.complete_lines + usize::from(self.scanned > self.lines_end)
+    }
+
+    /// Number of lines ended by a newline, which will not change any more
+    pub fn complete_lines(&self) -> usize {
+        self.complete_lines
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.scanned >= self.file_size
+    }
+
+    /// Share of the file indexed so far, in percent
+    pub fn progress(&self) -> u8 {
+        match self.file_size {
+            0 => 100,
+            size => (self.scanned.saturating_mul(100) / size).min(100) as u8,
+        }
+    }
+
+    /// Why the file could not be read, if it could not
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Read lines `start..start + count`, or as many of them as exist
+    pub fn lines(&mut self, start: usize, count: usize) -> &[String] {
+        let end = (start + count).min(self.line_count());
+        if start >= end {
+            return &[];
+        }
+
+        let window_end = self.window_start + self.window.len();
+        if start < self.window_start || end > window_end {
+            let size = count.max(WINDOW_LINES);
+            self.window_start = start.saturating_sub((size - count) / 2);
+            self.window = match self.lines_from(self.window_start) {
+                Ok(lines) => lines.take(size).collect(),
+                Err(_) => Vec::new(),
+            };
+        }
+
+        let from = (start - self.window_start).min(self.window.len());
+        let to = (end - self.window_start).min(self.window.len());
+        &self.window[from..to]
+    }
+
+    /// Read a single line
+    pub fn line(&mut self, index: usize) -> Option<&str> {
+        self.lines(index, 1).first().map(String::as_str)
+    }
+
+    /// Stream the indexed lines from `start` on, without caching them
+    pub fn lines_from(&self, start: usize) -> io::Result<Lines> {
+        let checkpoint = (start / CHECKPOINT_INTERVAL).min(self.checkpoints.len() - 1);
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.checkpoints[checkpoint]))?;
+        let mut lines = Lines {
+            reader: BufReader::new(file),
+            remaining: self
+                .line_count()
+                .saturating_sub(checkpoint * CHECKPOINT_INTERVAL),
+            buffer: Vec::new(),
+        };
+        for _ in checkpoint * CHECKPOINT_INTERVAL..start {
+            if lines.next().is_none() {
+                break;
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// Lines read from a log file, without their line endings
+pub struct Lines {
+    reader: BufReader<File>,
+    remaining: usize,
+    buffer: Vec<u8>,
+}
+
+impl Iterator for Lines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.buffer.clear();
+        match self.reader.read_until(b'\n', &mut self.buffer) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                self.remaining -= 1;
+                let line = self.buffer.strip_suffix(b"\n").unwrap_or(&self.buffer);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                Some(String::from_utf8_lossy(line).into_owned())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn log_file(lines: usize) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        for i in 0..lines {
+            writeln!(file, "Line {i}").unwrap();
+        }
+        file.flush().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_lines_are_read_around_any_position() {
+        let file = log_file(2000);
+        let mut index = LogIndex::new(file.path());
+        index.refresh();
+
+        assert!(index.is_indexed());
+        assert_eq!(index.line_count(), 2000);
+        assert_eq!(index.checkpoints.len(), 2000 / CHECKPOINT_INTERVAL + 1);
+        assert_eq!(index.lines(0, 2), ["Line 0", "Line 1"]);
+        assert_eq!(index.lines(1500, 2), ["Line 1500", "Line 1501"]);
+        assert_eq!(index.lines(1998, 5), ["Line 1998", "Line 1999"]);
+        assert_eq!(index.line(257), Some("Line 257"));
+        assert!(index.window.len() <= WINDOW_LINES);
+        assert_eq!(
+            index.lines_from(700).unwrap().take(2).collect::<Vec<_>>(),
+            ["Line 700", "Line 701"]
+        );
+    }
+
+    #[test]
+    fn test_growing_file_and_partial_last_line() {
+        let mut file = log_file(2);
+        write!(file, "partial").unwrap();
+        file.flush().unwrap();
+        let mut index = LogIndex::new(file.path());
+        index.refresh();
+
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.complete_lines(), 2);
+        assert_eq!(index.line(2), Some("partial"));
+
+        writeln!(file, " line\r").unwrap();
+        writeln!(file, "Line 3").unwrap();
+        file.flush().unwrap();
+        assert!(!index.refresh());
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.lines(2, 2), ["partial line", "Line 3"]);
+    }
+
+    #[test]
+    fn test_truncated_file_is_indexed_again() {
+        let file = log_file(10);
+        let mut index = LogIndex::new(file.path());
+        index.refresh();
+        assert_eq!(index.line_count(), 10);
+
+        std::fs::write(file.path(), "new\n").unwrap();
+        assert!(index.refresh());
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.line(0), Some("new"));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let mut index = LogIndex::new("/non/existent/file.log");
+        index.refresh();
+        assert!(index.error().unwrap().contains("Could not read log file"));
+        assert_eq!(index.line_count(), 0);
+    }
+}
@@ -0,0 +1,167 @@
+use super::log_index::LogIndex;
+use super::log_search::LogSearch;
+use crate::app::log_level::{LevelTracker, LogLevel};
+
+/// Lines looked at per update, so a huge log is filtered over several frames
+const LINES_PER_UPDATE: usize = 100_000;
+
+/// A minimum level filter in the log viewer
+///
+/// Like a search, the log is read from disk a part at a time with `update`.
+/// Lines without a level of their own are shown with the entry above them.
+#[derive(Debug, Clone)]
+pub struct LevelFilter {
+    minimum: LogLevel,
+    /// Lines at the minimum level or above, counted from 0
+    lines: Vec<usize>,
+    /// Lines looked at so far
+    searched: usize,
+    /// Level of the entry the lines looked at so far end in
+    tracker: LevelTracker,
+    complete: bool,
+}
+
+impl LevelFilter {
+    pub fn new(minimum: LogLevel) -> Self {
+        Self {
+            minimum,
+            lines: Vec::new(),
+            searched: 0,
+            tracker: LevelTracker::default(),
+            complete: false,
+        }
+    }
+
+    pub fn minimum(&self) -> LogLevel {
+        self.minimum
+    }
+
+    /// Look at the lines of the log not looked at yet, up to `LINES_PER_UPDATE` of them
+    pub fn update(&mut self, index: &LogIndex) {
+        // A last line without a newline may still grow, so it is looked at again
+        let kept = self.lines.partition_point(|&line| line < self.searched);
+        self.lines.truncate(kept);
+
+        let complete_lines = index.complete_lines();
+        let end = index.line_count().min(self.searched + LINES_PER_UPDATE);
+        if end > self.searched
+            && let Ok(lines) = index.lines_from(self.searched)
+        {
+            for (number, line) in (self.searched..end).zip(lines) {
+                // The level of an unfinished line is not carried on to the next one
+                let keep = if number < complete_lines {
+                    self.tracker.keep(&line, self.minimum)
+                } else {
+                    let mut tracker = self.tracker;
+                    tracker.keep(&line, self.minimum)
+                };
+                if keep {
+                    self.lines.push(number);
+                }
+            }
+            self.searched = end.min(complete_lines);
+        }
+        self.complete = self.searched >= complete_lines;
+    }
+
+    /// Filter the log again from the start, e.g. after it was truncated
+    pub fn restart(&mut self) {
+        *self = Self::new(self.minimum);
+    }
+
+    /// Whether the whole log has been looked at
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Lines shown so far
+    pub fn lines(&self) -> &[usize] {
+        &self.lines
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        self.lines.binary_search(&line).is_ok()
+    }
+}
+
+/// Rows the log viewer shows when lines are filtered, as line numbers with
+/// None for a separator; None when every line is shown
+///
+/// With both filters on, the search filter picks the lines and the level
+/// filter hides those below its level.
+pub fn filtered_rows(
+    search: Option<&LogSearch>,
+    levels: Option<&LevelFilter>,
+    line_count: usize,
+) -> Option<Vec<Option<usize>>> {
+    match (search.filter(|search| search.filter), levels) {
+        (Some(search), levels) => {
+            let mut rows: Vec<Option<usize>> = Vec::new();
+            for row in search.filtered_rows(line_count) {
+                match row {
+                    Some(line) if levels.is_some_and(|levels| !levels.contains(line)) => {}
+                    None if rows.last().is_none_or(Option::is_none) => {}
+                    row => rows.push(row),
+                }
+            }
+            if rows.last() == Some(&None) {
+                rows.pop();
+            }
+            Some(rows)
+        }
+        (None, Some(levels)) => Some(levels.lines().iter().copied().map(Some).collect()),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_update_filters_the_log_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "INFO up\nERROR failed\n  at main\n{{\"level\":\"debug\"}}\nWARN"
+        )
+        .unwrap();
+        file.flush().unwrap();
+        let mut index = LogIndex::new(file.path());
+        index.refresh();
+
+        let mut filter = LevelFilter::new(LogLevel::Warn);
+        filter.update(&index);
+        assert_eq!(filter.lines(), [1, 2, 4]);
+        assert!(filter.is_complete());
+
+        // The last line is looked at again once it is complete
+        writeln!(file, "ING: ok\ncontinued").unwrap();
+        file.flush().unwrap();
+        index.refresh();
+        filter.update(&index);
+        assert_eq!(filter.lines(), [1, 2, 4, 5]);
+
+        let mut search = LogSearch::new("i", false).unwrap();
+        search.search_lines(
+            0,
+            [
+                "INFO up",
+                "ERROR failed",
+                "  at main",
+                "{}",
+                "WARNING: ok",
+                "continued",
+            ]
+            .map(String::from),
+        );
+        search.filter = true;
+        search.context = 0;
+        assert_eq!(
+            filtered_rows(Some(&search), Some(&filter), 6),
+            Some(vec![Some(1), Some(2), None, Some(4), Some(5)])
+        );
+        assert_eq!(filtered_rows(None, None, 6), None);
+    }
+}
@@ -0,0 +1,329 @@
+use regex::{Regex, RegexBuilder};
+
+use super::log_index::LogIndex;
+use crate::app::ansi::{self, AnsiMode};
+use crate::app::error::{GhostError, Result};
+
+/// Context lines shown around matches in filter mode unless changed with +/-
+pub const DEFAULT_CONTEXT_LINES: usize = 2;
+const MAX_CONTEXT_LINES: usize = 9;
+/// Lines searched per update, so a huge log is searched over several frames
+const LINES_PER_UPDATE: usize = 100_000;
+
+/// A regex search in the log viewer
+///
+/// The log is searched from disk a part at a time with `update`; matches
+/// are line numbers in the log, counted from 0.
+#[derive(Debug, Clone)]
+pub struct LogSearch {
+    pattern: String,
+    regex: Regex,
+    /// Started with `?`, so `n` searches upwards
+    backward: bool,
+    /// Line of the match last jumped to
+    current: Option<usize>,
+    /// Show only matching lines and their context
+    pub filter: bool,
+    pub context: usize,
+    matches: Vec<usize>,
+    /// Lines searched so far
+    searched: usize,
+    complete: bool,
+}
+
+impl LogSearch {
+    /// Compile a search; patterns without capitals ignore case
+    pub fn new(pattern: &str, backward: bool) -> Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!pattern.chars().any(char::is_uppercase))
+            .build()
+            .map_err(|e| GhostError::InvalidArgument {
+                message: format!(
+                    "Invalid pattern: {}",
+                    e.to_string().lines().last().unwrap_or_default()
+                ),
+            })?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+            backward,
+            current: None,
+            filter: false,
+            context: DEFAULT_CONTEXT_LINES,
+            matches: Vec::new(),
+            searched: 0,
+            complete: false,
+        })
+    }
+
+    /// Keep the filter settings of an earlier search
+    pub fn with_view_of(mut self, previous: Option<&LogSearch>) -> Self {
+        if let Some(previous) = previous {
+            self.filter = previous.filter;
+            self.context = previous.context;
+        }
+        self
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    pub fn is_backward(&self) -> bool {
+        self.backward
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Search the lines of the log not searched yet, up to `LINES_PER_UPDATE`
+    /// of them, as shown in the given mode
+    pub fn update(&mut self, index: &LogIndex, mode: AnsiMode) {
+        // A last line without a newline may still grow, so it is searched again
+        let kept = self.matches.partition_point(|&line| line < self.searched);
+        self.matches.truncate(kept);
+
+        let end = index.line_count().min(self.searched + LINES_PER_UPDATE);
+        if end > self.searched
+            && let Ok(lines) = index.lines_from(self.searched)
+        {
+            let texts = lines
+                .take(end - self.searched)
+                .map(|line| ansi::display_text(&line, mode));
+            self.search_lines(self.searched, texts);
+        }
+        self.searched = self.searched.min(index.complete_lines());
+        self.complete = self.searched >= index.complete_lines();
+    }
+
+    /// Search lines that follow the lines searched so far, starting at line `first`
+    pub fn search_lines(&mut self, first: usize, lines: impl IntoIterator<Item = String>) {
+        let mut next = first;
+        for line in lines {
+            if self.regex.is_match(&line) {
+                self.matches.push(next);
+            }
+            next += 1;
+        }
+        self.searched = next;
+    }
+
+    /// Search the log again from the start, e.g. after it was truncated
+    pub fn restart(&mut self) {
+        self.matches.clear();
+        self.searched = 0;
+        self.current = None;
+        self.complete = false;
+    }
+
+    /// Whether the whole log has been searched
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Lines with a match found so far
+    pub fn matching_lines(&self) -> &[usize] {
+        &self.matches
+    }
+
+    /// Jump to the next match after line `from` in the search direction, or
+    /// the other way when `reverse` is set; wraps around at either end
+    pub fn jump(&mut self, from: usize, reverse: bool) -> Option<usize> {
+        let matches = &self.matches;
+        let next = if self.backward != reverse {
+            matches
+                .iter()
+                .rev()
+                .find(|&&line| line < from)
+                .or(matches.last())
+        } else {
+            matches
+                .iter()
+                .find(|&&line| line > from)
+                .or(matches.first())
+        };
+        self.current = next.copied();
+        self.current
+    }
+
+    /// Jump to the first match at or after `from` (or at or before, for `?`)
+    pub fn jump_from(&mut self, from: usize) -> Option<usize> {
+        // Start one line outside so a match on `from` itself is found
+        if self.backward {
+            self.jump(from + 1, false)
+        } else {
+            match from.checked_sub(1) {
+                Some(before) => self.jump(before, false),
+                None => {
+                    self.current = self.matches.first().copied();
+                    self.current
+                }
+            }
+        }
+    }
+
+    /// Position of the match last jumped to among all matches, counted from 1
+    pub fn current_position(&self) -> Option<usize> {
+        let current = self.current?;
+        self.matches
+            .binary_search(&current)
+            .ok()
+            .map(|index| index + 1)
+    }
+
+    pub fn widen_context(&mut self) {
+        self.context = (self.context + 1).min(MAX_CONTEXT_LINES);
+    }
+
+    pub fn narrow_context(&mut self) {
+        self.context = self.context.saturating_sub(1);
+    }
+
+    /// Rows shown in filter mode for a log of `line_count` lines: matching
+    /// lines with their context, and None for the separator between groups
+    /// that are not adjacent
+    pub fn filtered_rows(&self, line_count: usize) -> Vec<Option<usize>> {
+        let mut rows = Vec::new();
+        let mut shown_until = 0; // One past the last line shown
+        for &line in &self.matches {
+            let start = line.saturating_sub(self.context).max(shown_until);
+            let end = (line + self.context + 1).min(line_count);
+            if start >= end {
+                continue;
+            }
+            if start > shown_until && !rows.is_empty() {
+                rows.push(None);
+            }
+            rows.extend((start..end).map(Some));
+            shown_until = end;
+        }
+        rows
+    }
+}
+
+/// A search pattern being typed after `/` or `?`
+#[derive(Debug, Clone)]
+pub struct SearchPrompt {
+    pub input: String,
+    pub backward: bool,
+    /// Search to restore when the prompt is cancelled
+    pub previous: Option<LogSearch>,
+    pub error: Option<String>,
+}
+
+impl SearchPrompt {
+    pub fn new(backward: bool, previous: Option<LogSearch>) -> Self {
+        Self {
+            input: String::new(),
+            backward,
+            previous,
+            error: None,
+        }
+    }
+
+    /// The prompt as shown in place of the footer
+    pub fn line(&self) -> String {
+        let prefix = if self.backward { '?' } else { '/' };
+        match &self.error {
+            Some(error) => format!(" {prefix}{}█  {error}", self.input),
+            None => format!(" {prefix}{}█", self.input),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_log(pattern: &str, backward: bool, texts: &[&str]) -> LogSearch {
+        let mut search = LogSearch::new(pattern, backward).unwrap();
+        search.search_lines(0, texts.iter().map(|text| text.to_string()));
+        search
+    }
+
+    #[test]
+    fn test_smart_case_and_invalid_patterns() {
+        let log = ["Error: one", "error: two", "ok"];
+        assert_eq!(search_log("error", false, &log).matching_lines(), [0, 1]);
+        assert_eq!(search_log("Error", false, &log).matching_lines(), [0]);
+        let error = LogSearch::new("(unclosed", false).unwrap_err().to_string();
+        assert!(error.contains("Invalid pattern"), "{error}");
+    }
+
+    #[test]
+    fn test_jump_wraps_in_both_directions() {
+        let log = ["a", "match", "b", "match", "c"];
+        let mut search = search_log("match", false, &log);
+
+        assert_eq!(search.jump_from(0), Some(1));
+        assert_eq!(search.jump(1, false), Some(3));
+        assert_eq!(search.jump(3, false), Some(1));
+        assert_eq!(search.jump(1, true), Some(3));
+        assert_eq!(search.jump_from(3), Some(3));
+
+        let mut backward = search_log("match", true, &log);
+        assert_eq!(backward.jump_from(2), Some(1));
+        assert_eq!(backward.jump(1, false), Some(3));
+        assert_eq!(backward.current_position(), Some(2));
+    }
+
+    #[test]
+    fn test_update_searches_the_log_file() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "\x1b[31merror\x1b[0m: one\nok\nerr").unwrap();
+        file.flush().unwrap();
+        let mut index = LogIndex::new(file.path());
+        index.refresh();
+
+        let mut search = LogSearch::new("error:|or$", false).unwrap();
+        search.update(&index, AnsiMode::Rendered);
+        assert_eq!(search.matching_lines(), [0]);
+        assert!(search.is_complete());
+
+        // The last line is searched again once it is complete
+        writeln!(file, "or").unwrap();
+        file.flush().unwrap();
+        index.refresh();
+        search.update(&index, AnsiMode::Rendered);
+        assert_eq!(search.matching_lines(), [0, 2]);
+
+        // Raw mode searches the escape sequences as shown
+        search.restart();
+        search.update(&index, AnsiMode::Raw);
+        assert_eq!(search.matching_lines(), [2]);
+    }
+
+    #[test]
+    fn test_filtered_rows_with_context() {
+        let log = ["0", "1 hit", "2", "3", "4", "5", "6 hit", "7 hit", "8"];
+        let mut search = search_log("hit", false, &log);
+        search.context = 1;
+
+        assert_eq!(
+            search.filtered_rows(log.len()),
+            vec![
+                Some(0),
+                Some(1),
+                Some(2),
+                None,
+                Some(5),
+                Some(6),
+                Some(7),
+                Some(8)
+            ]
+        );
+
+        search.context = 0;
+        assert_eq!(
+            search.filtered_rows(log.len()),
+            vec![Some(1), None, Some(6), Some(7)]
+        );
+    }
+}
@@ -1,110 +1,227 @@
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Layout, Rect, Size},
+    layout::{Constraint, Layout, Rect},
     style::{Color, Style},
     symbols,
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
 };
 use serde_json;
-use tui_scrollview::{ScrollView, ScrollViewState};
 
+use super::ViewMode;
+use super::keymap::{Action, KeyMap};
+use super::log_search::LogSearch;
+use super::theme::Theme;
+use crate::app::ansi::{self, AnsiMode};
+use crate::app::log_level::{self, LogFormat, LogLevel};
 use crate::app::storage::task::Task;
 
-const MAX_LINES_IN_MEMORY: usize = 10_000;
+/// Scroll position of the log viewer
+///
+/// Rows are what the viewer shows: every line of the log, or the matching
+/// lines and separators in filter mode. Unlike `ScrollViewState` positions
+/// are not limited to `u16`, so logs of any length can be scrolled through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogScrollState {
+    row: usize,
+    column: usize,
+    /// Rows shown at once in the last render
+    page_height: usize,
+}
+
+impl LogScrollState {
+    /// Row shown at the top
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    /// Column of the log lines shown first
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn set_row(&mut self, row: usize) {
+        self.row = row;
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.row = self.row.saturating_add(1);
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.row = self.row.saturating_sub(1);
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.column = self.column.saturating_sub(1);
+    }
+
+    pub fn scroll_right(&mut self) {
+        self.column = self.column.saturating_add(1);
+    }
+
+    /// Move down a page, keeping one row of overlap
+    pub fn scroll_page_down(&mut self) {
+        self.row = self
+            .row
+            .saturating_add(self.page_height.max(1))
+            .saturating_sub(1);
+    }
+
+    /// Move up a page, keeping one row of overlap
+    pub fn scroll_page_up(&mut self) {
+        self.row = self
+            .row
+            .saturating_add(1)
+            .saturating_sub(self.page_height.max(1));
+    }
 
-/// A log viewer widget using tui-scrollview for efficient scrolling
+    pub fn scroll_to_top(&mut self) {
+        self.row = 0;
+        self.column = 0;
+    }
+
+    /// Move to the end; the position is adjusted to the rows shown on render
+    pub fn scroll_to_bottom(&mut self) {
+        self.row = usize::MAX;
+    }
+
+    /// Keep the position within `rows` rows shown `height` at a time
+    ///
+    /// The last row can be scrolled up to one row above the bottom edge.
+    pub fn clamp(&mut self, rows: usize, height: usize) {
+        self.page_height = height;
+        self.row = if rows < height {
+            0
+        } else {
+            self.row.min(rows - height.saturating_sub(1))
+        };
+    }
+
+    /// Keep the position at or above `last_row`, for rows that take more
+    /// than one line on screen, such as expanded JSON
+    pub fn clamp_to(&mut self, last_row: usize, height: usize) {
+        self.page_height = height;
+        self.row = self.row.min(last_row);
+    }
+}
+
+/// A log viewer widget that draws the rows around the scroll position
+///
+/// The rows are read by the caller, usually through a `LogIndex`, so only
+/// what is on screen has to be held in memory.
 #[derive(Clone)]
-pub struct LogViewerScrollWidget {
-    lines: Vec<String>,
+pub struct LogViewerScrollWidget<'a> {
+    /// Line number and text of the rows shown; None for a filter mode separator
+    rows: Vec<(Option<usize>, String)>,
+    /// Lines in the whole log, which sets the width of the line numbers
+    line_count: usize,
     task_id: String,
     command: String,
     auto_scroll_enabled: bool,
+    search: Option<LogSearch>,
+    /// Search pattern being typed, shown in place of the footer
+    prompt: Option<String>,
+    ansi_mode: AnsiMode,
+    /// Share of the log indexed, while it is being indexed
+    indexing: Option<u8>,
+    /// Lines below this level are hidden
+    level_filter: Option<LogLevel>,
+    format: LogFormat,
+    /// Expand JSON lines over several rows
+    pretty: bool,
+    key_map: &'a KeyMap,
+    theme: Theme,
 }
 
-impl LogViewerScrollWidget {
-    /// Create a new log viewer with loaded content
-    pub fn new(task: &Task) -> Self {
-        let lines = Self::load_log_file(&task.log_path);
+impl<'a> LogViewerScrollWidget<'a> {
+    /// Create a log viewer showing `rows` of a log with `line_count` lines
+    pub fn new(task: &Task, rows: Vec<(Option<usize>, String)>, line_count: usize) -> Self {
         Self {
-            lines,
+            rows,
+            line_count,
             task_id: task.id.clone(),
             command: Self::parse_command(&task.command),
             auto_scroll_enabled: true, // Auto-scroll is enabled by default
+            search: None,
+            prompt: None,
+            ansi_mode: AnsiMode::default(),
+            indexing: None,
+            level_filter: None,
+            format: LogFormat::default(),
+            pretty: false,
+            key_map: KeyMap::builtin(),
+            theme: Theme::default(),
         }
     }
 
-    /// Create with cached content
-    pub fn with_cached_content(task: &Task, cached_lines: Vec<String>) -> Self {
-        Self {
-            lines: cached_lines,
-            task_id: task.id.clone(),
-            command: Self::parse_command(&task.command),
-            auto_scroll_enabled: true, // Auto-scroll is enabled by default
+    /// Area the rows are drawn in when the viewer is rendered in `area`
+    pub fn content_area(area: Rect) -> Rect {
+        let chunks = Self::layout(area);
+        Rect {
+            y: chunks[0].y + 1,
+            height: chunks[0].height.saturating_sub(1),
+            x: chunks[0].x + 1,
+            width: chunks[0].width.saturating_sub(2),
         }
     }
 
-    /// Load incremental content from a file
-    pub fn load_incremental_content(
-        task: &Task,
-        mut existing_lines: Vec<String>,
-        previous_size: u64,
-    ) -> Self {
-        // Try to read only the new content
-        if let Ok(mut file) = std::fs::File::open(&task.log_path) {
-            use std::io::{Read, Seek, SeekFrom};
-
-            // Seek to the previous end position
-            if file.seek(SeekFrom::Start(previous_size)).is_ok() {
-                let mut new_content = String::new();
-                if file.read_to_string(&mut new_content).is_ok() {
-                    // Append new lines to existing lines
-                    for line in new_content.lines() {
-                        existing_lines.push(line.to_string());
-                    }
+    fn layout(area: Rect) -> [Rect; 2] {
+        // Layout: content + footer (3)
+        Layout::vertical([Constraint::Min(5), Constraint::Length(3)]).areas(area)
+    }
 
-                    // Apply memory limit if needed
-                    if existing_lines.len() > MAX_LINES_IN_MEMORY {
-                        let skip_count = existing_lines.len() - MAX_LINES_IN_MEMORY;
-                        existing_lines = existing_lines.into_iter().skip(skip_count).collect();
-                    }
-                }
-            }
-        }
+    /// Highlight the matches of a search and show its match counter
+    pub fn with_search(mut self, search: Option<LogSearch>) -> Self {
+        self.search = search;
+        self
+    }
 
-        Self {
-            lines: existing_lines,
-            task_id: task.id.clone(),
-            command: Self::parse_command(&task.command),
-            auto_scroll_enabled: true, // Auto-scroll is enabled by default
-        }
+    /// Show the search pattern being typed in place of the footer
+    pub fn with_prompt(mut self, prompt: Option<String>) -> Self {
+        self.prompt = prompt;
+        self
     }
 
-    /// Load log file with memory limit
-    fn load_log_file(path: &str) -> Vec<String> {
-        match std::fs::read_to_string(path) {
-            Ok(content) => {
-                let lines: Vec<String> = content.lines().map(String::from).collect();
-                if lines.len() > MAX_LINES_IN_MEMORY {
-                    // Take last MAX_LINES_IN_MEMORY lines
-                    let skip_count = lines.len() - MAX_LINES_IN_MEMORY;
-                    lines.into_iter().skip(skip_count).collect()
-                } else {
-                    lines
-                }
-            }
-            Err(_) => vec!["Error: Could not read log file".to_string()],
-        }
+    /// Choose whether escape sequences are rendered, stripped or shown as text
+    pub fn with_ansi_mode(mut self, ansi_mode: AnsiMode) -> Self {
+        self.ansi_mode = ansi_mode;
+        self
     }
 
-    /// Get the lines for external use (caching)
-    pub fn get_lines(&self) -> &[String] {
-        &self.lines
+    /// Show how much of the log has been indexed while it is being indexed
+    pub fn with_indexing(mut self, indexing: Option<u8>) -> Self {
+        self.indexing = indexing;
+        self
     }
 
-    /// Get the total line count
-    pub fn get_lines_count(&self) -> usize {
-        self.lines.len()
+    /// Show the minimum level lines are filtered by in the title
+    pub fn with_level_filter(mut self, level_filter: Option<LogLevel>) -> Self {
+        self.level_filter = level_filter;
+        self
+    }
+
+    /// Show the format the log was detected to be in
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Expand JSON lines into indented rows
+    pub fn with_pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Generate the key hints of the footer from a key map
+    pub fn with_key_map(mut self, key_map: &'a KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
     }
 
     /// Check if auto-scroll is enabled
@@ -138,62 +255,209 @@ impl LogViewerScrollWidget {
         }
     }
 
-    /// Create footer widget
-    fn create_footer(&'_ self) -> Paragraph<'_> {
+    /// Create footer widget, its hints fitted to `width` columns
+    fn create_footer(&'_ self, width: u16) -> Paragraph<'_> {
         let auto_scroll_status = if self.auto_scroll_enabled {
             "ON"
         } else {
             "OFF"
         };
-        let keybinds = format!(
-            " j/k:Scroll  h/l:H-Scroll  g/G:Top/Bot  C-d/u:Page  C-f:Auto({auto_scroll_status})  Esc:Back  q "
-        );
+        // Inside the borders
+        let width = usize::from(width.saturating_sub(2));
+        // Hints are left out from the end when the terminal is narrow, so the
+        // ways out of the view and to the help come first
+        let keybinds = match (&self.prompt, &self.search) {
+            (Some(prompt), _) => prompt.clone(),
+            (None, Some(search)) => {
+                let filter = format!("Filter({})", if search.filter { "ON" } else { "OFF" });
+                let context = format!("Context({})", search.context);
+                self.key_map.footer_within(
+                    ViewMode::LogView,
+                    &[
+                        (&[Action::Quit], "Quit"),
+                        (&[Action::Help], "Help"),
+                        (&[Action::Back], "Clear"),
+                        (&[Action::NextMatch, Action::PrevMatch], "Next/Prev"),
+                        (&[Action::FilterMatches], &filter),
+                        (&[Action::MoreContext, Action::LessContext], &context),
+                        (&[Action::PageDown, Action::PageUp], "Page"),
+                        (&[Action::Search], "Search"),
+                    ],
+                    width,
+                )
+            }
+            (None, None) => {
+                let auto_scroll = format!("Auto({auto_scroll_status})");
+                self.key_map.footer_within(
+                    ViewMode::LogView,
+                    &[
+                        (&[Action::Quit], "Quit"),
+                        (&[Action::Help], "Help"),
+                        (&[Action::Back], "Back"),
+                        (
+                            &[Action::Down, Action::Up, Action::Left, Action::Right],
+                            "Scroll",
+                        ),
+                        (&[Action::PageDown, Action::PageUp], "Page"),
+                        (&[Action::Top, Action::Bottom], "Ends"),
+                        (&[Action::Follow], &auto_scroll),
+                    ],
+                    width,
+                )
+            }
+        };
 
         Paragraph::new(keybinds).block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::LightMagenta)),
+                .border_style(Style::default().fg(self.theme.log_border)),
         )
     }
+
+    /// Match counter shown in the title, e.g. "[/error/ 2 of 5]"
+    fn search_summary(&self, search: &LogSearch) -> String {
+        let total = search.matching_lines().len();
+        let prefix = if search.is_backward() { '?' } else { '/' };
+        let mut count = match (total, search.current_position()) {
+            (0, _) => "no matches".to_string(),
+            (total, Some(position)) => format!("{position} of {total}"),
+            (1, None) => "1 match".to_string(),
+            (total, None) => format!("{total} matches"),
+        };
+        if !search.is_complete() {
+            count.push_str(", searching");
+        }
+        format!("[{prefix}{}{prefix} {count}] ", search.pattern())
+    }
+
+    /// Split a line into spans by style, with the search matches highlighted,
+    /// leaving out the first `column` characters
+    ///
+    /// Text the log did not color itself is colored by the level of its line.
+    fn styled_line(
+        &self,
+        line: &str,
+        level: Option<LogLevel>,
+        current: bool,
+        column: usize,
+    ) -> Line<'static> {
+        let mut cells = ansi::display_line(line, self.ansi_mode);
+        if let Some(color) = level.and_then(|level| self.level_color(level)) {
+            for cell in cells.iter_mut().filter(|cell| cell.style.fg.is_none()) {
+                cell.style = cell.style.fg(color);
+            }
+        }
+        if let Some(ref search) = self.search {
+            let text: String = cells.iter().map(|cell| cell.ch).collect();
+            let match_style = if current {
+                Style::default()
+                    .fg(self.theme.match_fg)
+                    .bg(self.theme.current_match_bg)
+            } else {
+                Style::default()
+                    .fg(self.theme.match_fg)
+                    .bg(self.theme.match_bg)
+            };
+            for found in search.regex().find_iter(&text) {
+                let start = text[..found.start()].chars().count();
+                let len = found.as_str().chars().count();
+                for cell in cells.iter_mut().skip(start).take(len) {
+                    cell.style = cell.style.patch(match_style);
+                }
+            }
+        }
+
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut run = String::new();
+        let mut run_style = Style::default();
+        for cell in cells.into_iter().skip(column) {
+            if cell.style != run_style && !run.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut run), run_style));
+            }
+            run_style = cell.style;
+            run.push(cell.ch);
+        }
+        if !run.is_empty() {
+            spans.push(Span::styled(run, run_style));
+        }
+        Line::from(spans)
+    }
+
+    /// Color of text in lines of a level, if they stand out
+    fn level_color(&self, level: LogLevel) -> Option<Color> {
+        match level {
+            LogLevel::Error => Some(self.theme.error),
+            LogLevel::Warn => Some(self.theme.warning),
+            LogLevel::Info => None,
+            LogLevel::Debug | LogLevel::Trace => Some(self.theme.muted),
+        }
+    }
+
+    /// The rows a line is drawn in; JSON lines expand to several in pretty mode
+    fn display_rows(&self, number: Option<usize>, line: &str) -> Vec<DisplayRow> {
+        let level = number.and_then(|_| log_level::detect(line));
+        let expanded = self
+            .pretty
+            .then(|| number.and_then(|_| log_level::pretty_json(line)))
+            .flatten();
+        match expanded {
+            Some(texts) => texts
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| DisplayRow {
+                    number,
+                    first: index == 0,
+                    text,
+                    level,
+                })
+                .collect(),
+            None => vec![DisplayRow {
+                number,
+                first: true,
+                text: line.to_string(),
+                level,
+            }],
+        }
+    }
 }
 
-impl StatefulWidget for LogViewerScrollWidget {
-    type State = ScrollViewState;
+impl StatefulWidget for LogViewerScrollWidget<'_> {
+    type State = LogScrollState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        // Layout: content + footer (3)
-        let chunks = Layout::vertical([Constraint::Min(5), Constraint::Length(3)]).split(area);
+        let chunks = Self::layout(area);
 
         // Render footer
-        self.create_footer().render(chunks[1], buf);
+        self.create_footer(chunks[1].width).render(chunks[1], buf);
 
         // Calculate line number width based on total lines
-        let line_count = self.lines.len();
-        let line_number_width = line_count.to_string().len().max(1) + 1; // +1 for space
-        let line_number_area_width = line_number_width as u16;
-
-        // Calculate content size (lines count, max line width)
-        let content_width = self
-            .lines
-            .iter()
-            .map(|line| line.len() + line_number_width)
-            .max()
-            .unwrap_or(80) as u16;
-
-        let content_height = self.lines.len() as u16;
-        let content_size = Size::new(content_width, content_height);
+        let line_number_width = self.line_count.to_string().len().max(1) + 1; // +1 for space
 
         // Create a block for the content area with borders and title
-        let title = format!(" {} - {} ", self.task_id, self.command);
+        let mut title = format!(" {} - {} ", self.task_id, self.command);
+        if let Some(ref search) = self.search {
+            title.push_str(&self.search_summary(search));
+        }
+        if self.ansi_mode != AnsiMode::Rendered {
+            title.push_str(&format!("[{}] ", self.ansi_mode.label()));
+        }
+        if let Some(level) = self.level_filter {
+            title.push_str(&format!("[level: {level}+] "));
+        }
+        match (self.format, self.pretty) {
+            (LogFormat::Json, true) => title.push_str("[json, pretty] "),
+            (LogFormat::Json, false) => title.push_str("[json] "),
+            (_, true) => title.push_str("[pretty] "),
+            _ => {}
+        }
+        if let Some(progress) = self.indexing {
+            title.push_str(&format!("[indexing {progress}%] "));
+        }
         let content_block = Block::default()
             .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
-            .border_style(Style::default().fg(Color::LightMagenta))
+            .border_style(Style::default().fg(self.theme.log_border))
             .title(title);
-
-        // Get the inner area for the scroll view
         let content_inner = content_block.inner(chunks[0]);
-
-        // Render the content block
         content_block.render(chunks[0], buf);
 
         // Draw the separator line between content and footer
@@ -212,60 +476,76 @@ impl StatefulWidget for LogViewerScrollWidget {
                 .set_symbol(symbols::line::VERTICAL_LEFT);
         }
 
-        // Create scroll view with content size and hide scrollbars
-        let mut scroll_view = ScrollView::new(content_size)
-            .scrollbars_visibility(tui_scrollview::ScrollbarVisibility::Never);
-
-        // Create line numbers paragraph with dynamic width
-        let line_numbers: Vec<Line> = self
-            .lines
+        // Rows as drawn: expanded JSON lines take several, numbered on the first
+        let rows: Vec<DisplayRow> = self
+            .rows
             .iter()
-            .enumerate()
-            .map(|(idx, _)| {
-                let line_number = format!("{:>width$} ", idx + 1, width = line_number_width - 1);
-                Line::from(Span::styled(
-                    line_number,
-                    Style::default().fg(Color::DarkGray),
-                ))
-            })
+            .flat_map(|(number, line)| self.display_rows(*number, line))
             .collect();
-        let line_numbers_paragraph = Paragraph::new(line_numbers);
 
-        // Create content paragraph
-        let content_lines: Vec<Line> = self
-            .lines
+        // Keep horizontal scrolling within the widest row shown
+        let text_width = (content_inner.width as usize).saturating_sub(line_number_width);
+        let widest = rows
             .iter()
-            .map(|line| Line::from(line.as_str()))
-            .collect();
-        let content_paragraph = Paragraph::new(content_lines);
-
-        // Render line numbers and content inside scroll view
-        scroll_view.render_widget(
-            line_numbers_paragraph,
-            Rect::new(0, 0, line_number_area_width, content_height),
-        );
-        scroll_view.render_widget(
-            content_paragraph,
-            Rect::new(
-                line_number_area_width,
-                0,
-                content_width.saturating_sub(line_number_area_width),
-                content_height,
-            ),
-        );
-
-        // Render the scroll view in the inner content area
-        scroll_view.render(content_inner, buf, state);
+            .map(|row| {
+                ansi::display_text(&row.text, self.ansi_mode)
+                    .chars()
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        state.column = state
+            .column
+            .min(widest.saturating_sub(text_width.saturating_sub(1)));
+
+        let [numbers_area, text_area] = Layout::horizontal([
+            Constraint::Length(line_number_width as u16),
+            Constraint::Min(0),
+        ])
+        .areas(content_inner);
+
+        let current = self.search.as_ref().and_then(LogSearch::current);
+        let (line_numbers, content_lines): (Vec<Line>, Vec<Line>) = rows
+            .iter()
+            .map(|row| {
+                let label = match (row.number, row.first) {
+                    (Some(idx), true) => (idx + 1).to_string(),
+                    (Some(_), false) => String::new(),
+                    (None, _) => "--".to_string(),
+                };
+                let line_number = Line::from(Span::styled(
+                    format!("{label:>width$} ", width = line_number_width - 1),
+                    Style::default().fg(self.theme.muted),
+                ));
+                let content = match row.number {
+                    Some(idx) => {
+                        self.styled_line(&row.text, row.level, current == Some(idx), state.column)
+                    }
+                    None => Line::from(""),
+                };
+                (line_number, content)
+            })
+            .unzip();
+        Paragraph::new(line_numbers).render(numbers_area, buf);
+        Paragraph::new(content_lines).render(text_area, buf);
     }
 }
 
+/// A row as drawn by the log viewer
+struct DisplayRow {
+    /// Line of the log the row shows part of; None for a separator
+    number: Option<usize>,
+    /// Whether this is the first row of its line
+    first: bool,
+    text: String,
+    level: Option<LogLevel>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ratatui::Terminal;
     use ratatui::backend::TestBackend;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
     fn create_test_task(log_path: String) -> Task {
         Task {
@@ -280,23 +560,45 @@ mod tests {
             started_at: 1704109200,
             finished_at: None,
             log_path,
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         }
     }
 
+    fn rows(lines: &[&str]) -> Vec<(Option<usize>, String)> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (Some(index), line.to_string()))
+            .collect()
+    }
+
+    fn render(widget: LogViewerScrollWidget, state: &mut LogScrollState) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal
+            .draw(|f| widget.render(f.area(), f.buffer_mut(), state))
+            .unwrap();
+        terminal.backend().buffer().clone()
+    }
+
     #[test]
     fn test_basic_rendering() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Line 1").unwrap();
-        writeln!(temp_file, "Line 2").unwrap();
-        writeln!(temp_file, "Line 3").unwrap();
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let widget = LogViewerScrollWidget::new(&task);
+        let task = create_test_task("/dummy/path.log".to_string());
+        let widget = LogViewerScrollWidget::new(&task, rows(&["Line 1", "Line 2", "Line 3"]), 3);
 
         let backend = TestBackend::new(80, 20);
         let mut terminal = Terminal::new(backend).unwrap();
-        let mut scroll_state = ScrollViewState::default();
+        let mut scroll_state = LogScrollState::default();
 
         terminal
             .draw(|f| {
@@ -312,9 +614,13 @@ mod tests {
         assert!(content.contains("echo test"));
 
         // Check footer
-        assert!(content.contains("j/k:Scroll"));
-        assert!(content.contains("h/l:H-Scroll"));
-        assert!(content.contains("g/G:Top/Bot"));
+        assert!(content.contains("q:Quit"));
+        assert!(content.contains("?:Help"));
+        assert!(content.contains("Esc:Back"));
+        assert!(content.contains("C-d/u:Page"));
+        assert!(content.contains("j/k/h/l:Scroll"));
+        assert!(content.contains("g/G:Ends"));
+        assert!(content.contains("C-f:Auto(ON)"));
 
         // Check content with line numbers (dynamic width)
         assert!(content.contains("1 Line 1"));
@@ -323,78 +629,134 @@ mod tests {
     }
 
     #[test]
-    fn test_memory_limit() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        // Write more than MAX_LINES_IN_MEMORY lines
-        for i in 0..15000 {
-            writeln!(temp_file, "Line {i}").unwrap();
-        }
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let widget = LogViewerScrollWidget::new(&task);
-
-        // Should only load MAX_LINES_IN_MEMORY lines
-        assert_eq!(widget.get_lines_count(), MAX_LINES_IN_MEMORY);
+    fn test_line_numbers_fit_the_whole_log() {
+        let task = create_test_task("/dummy/path.log".to_string());
+        let shown = vec![(Some(1_233_999), "far down".to_string())];
+        let widget = LogViewerScrollWidget::new(&task, shown, 2_000_000).with_indexing(Some(40));
 
-        // Should contain the last lines
-        let lines = widget.get_lines();
-        assert!(lines[0].contains("Line 5000")); // 15000 - 10000 = 5000
-        assert!(lines[9999].contains("Line 14999"));
+        let content = buffer_to_string(&render(widget, &mut LogScrollState::default()));
+        assert!(content.contains("│1234000 far down"), "{content}");
+        assert!(content.contains("[indexing 40%]"));
     }
 
     #[test]
-    fn test_error_handling() {
-        let task = create_test_task("/non/existent/file.log".to_string());
-        let widget = LogViewerScrollWidget::new(&task);
-
-        // Should have error message
-        assert_eq!(widget.get_lines_count(), 1);
-        assert_eq!(widget.get_lines()[0], "Error: Could not read log file");
+    fn test_scroll_state_clamps_to_rows() {
+        let mut state = LogScrollState::default();
+        state.scroll_to_bottom();
+        state.clamp(100, 11);
+        assert_eq!(state.row(), 90);
+
+        state.scroll_page_up();
+        assert_eq!(state.row(), 80);
+        state.scroll_page_down();
+        assert_eq!(state.row(), 90);
+
+        // Everything fits, so there is nothing to scroll
+        state.clamp(5, 11);
+        assert_eq!(state.row(), 0);
+
+        // Rows beyond u16 can be reached
+        state.set_row(5_000_000);
+        state.clamp(10_000_000, 11);
+        assert_eq!(state.row(), 5_000_000);
     }
 
     #[test]
-    fn test_cached_content() {
+    fn test_horizontal_scroll_stops_at_widest_row() {
         let task = create_test_task("/dummy/path.log".to_string());
-        let cached_lines = vec![
-            "Cached line 1".to_string(),
-            "Cached line 2".to_string(),
-            "Cached line 3".to_string(),
-        ];
-
-        let widget = LogViewerScrollWidget::with_cached_content(&task, cached_lines.clone());
+        let long = format!("start {}end", "x".repeat(100));
+        let mut state = LogScrollState::default();
+        for _ in 0..200 {
+            state.scroll_right();
+        }
 
-        assert_eq!(widget.get_lines_count(), 3);
-        assert_eq!(widget.get_lines(), &cached_lines);
+        let widget = LogViewerScrollWidget::new(&task, rows(&[&long]), 1);
+        let content = buffer_to_string(&render(widget, &mut state));
+        assert!(content.contains("xxend"), "{content}");
+        assert!(content.contains("│1 x"));
+        assert_eq!(state.column(), 109 - 75);
     }
 
     #[test]
-    fn test_incremental_loading() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Initial line 1").unwrap();
-        writeln!(temp_file, "Initial line 2").unwrap();
-        temp_file.flush().unwrap();
-
-        let initial_size = temp_file.as_file().metadata().unwrap().len();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let existing_lines = vec!["Initial line 1".to_string(), "Initial line 2".to_string()];
-
-        // Add more lines
-        writeln!(temp_file, "New line 3").unwrap();
-        writeln!(temp_file, "New line 4").unwrap();
-        temp_file.flush().unwrap();
+    fn test_search_counter_highlight_and_filter_mode() {
+        let task = create_test_task("/dummy/path.log".to_string());
+        let lines = ["boot", "error: disk", "ok", "ok", "ok", "error: net"];
+        let mut search = LogSearch::new("error", false).unwrap();
+        search.search_lines(0, lines.iter().map(|line| line.to_string()));
+        search.jump_from(2);
+
+        let buffer = render(
+            LogViewerScrollWidget::new(&task, rows(&lines), 6).with_search(Some(search.clone())),
+            &mut LogScrollState::default(),
+        );
+        let content = buffer_to_string(&buffer);
+        assert!(content.contains("[/error/ 2 of 2, searching]"), "{content}");
+        assert!(content.contains("n/N:Next/Prev"));
+        assert!(content.contains("3 ok"));
+        // Line 6 holds the current match, line 2 another one
+        let row = |number: &str| {
+            (0..buffer.area.height)
+                .find(|&y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                        .contains(&format!("{number} error"))
+                })
+                .unwrap()
+        };
+        assert_eq!(buffer[(3, row("2"))].bg, Color::Yellow);
+        assert_eq!(buffer[(3, row("6"))].bg, Color::LightRed);
+
+        search.filter = true;
+        search.context = 0;
+        let filtered = search
+            .filtered_rows(lines.len())
+            .into_iter()
+            .map(|row| (row, row.map_or(String::new(), |idx| lines[idx].to_string())))
+            .collect();
+        let content = buffer_to_string(&render(
+            LogViewerScrollWidget::new(&task, filtered, 6).with_search(Some(search)),
+            &mut LogScrollState::default(),
+        ));
+        assert!(content.contains("2 error: disk"));
+        assert!(content.contains("--"));
+        assert!(content.contains("6 error: net"));
+        assert!(!content.contains("3 ok"));
+    }
 
-        // Load incrementally
-        let widget =
-            LogViewerScrollWidget::load_incremental_content(&task, existing_lines, initial_size);
+    #[test]
+    fn test_ansi_colors_rendered_stripped_and_raw() {
+        let task = create_test_task("/dummy/path.log".to_string());
+        let lines = ["\x1b[31mfailed\x1b[0m: disk", "50%\r100%"];
+        let render = |ansi_mode: AnsiMode, search: Option<LogSearch>| {
+            let widget = LogViewerScrollWidget::new(&task, rows(&lines), 2)
+                .with_ansi_mode(ansi_mode)
+                .with_search(search);
+            render(widget, &mut LogScrollState::default())
+        };
 
-        assert_eq!(widget.get_lines_count(), 4);
-        let lines = widget.get_lines();
-        assert_eq!(lines[0], "Initial line 1");
-        assert_eq!(lines[1], "Initial line 2");
-        assert_eq!(lines[2], "New line 3");
-        assert_eq!(lines[3], "New line 4");
+        let buffer = render(AnsiMode::Rendered, None);
+        let content = buffer_to_string(&buffer);
+        assert!(content.contains("1 failed: disk"), "{content}");
+        assert!(content.contains("2 100%"));
+        assert!(!content.contains("50%"));
+        assert_eq!(buffer[(3, 1)].fg, Color::Red);
+        assert_eq!(buffer[(10, 1)].fg, Color::Reset);
+
+        // Matches are highlighted in the text as shown, across color changes
+        let search = LogSearch::new("failed: d", false).unwrap();
+        let buffer = render(AnsiMode::Rendered, Some(search));
+        assert_eq!(buffer[(3, 1)].bg, Color::Yellow);
+        assert_eq!(buffer[(11, 1)].bg, Color::Yellow);
+
+        let buffer = render(AnsiMode::Stripped, None);
+        assert!(buffer_to_string(&buffer).contains("[stripped]"));
+        assert_eq!(buffer[(3, 1)].fg, Color::Reset);
+
+        let content = buffer_to_string(&render(AnsiMode::Raw, None));
+        assert!(content.contains("[raw]"));
+        assert!(content.contains("1 ^[[31mfailed^[[0m: disk"), "{content}");
+        assert!(content.contains("2 50%^M100%"));
     }
 
     fn buffer_to_string(buffer: &ratatui::buffer::Buffer) -> String {
@@ -413,12 +775,8 @@ mod tests {
 
     #[test]
     fn test_auto_scroll_disabled_by_default() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Line 1").unwrap();
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let widget = LogViewerScrollWidget::new(&task);
+        let task = create_test_task("/dummy/path.log".to_string());
+        let widget = LogViewerScrollWidget::new(&task, rows(&["Line 1"]), 1);
 
         // Auto-scroll should be enabled by default
         assert!(widget.is_auto_scroll_enabled());
@@ -426,12 +784,8 @@ mod tests {
 
     #[test]
     fn test_auto_scroll_toggle() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Line 1").unwrap();
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let mut widget = LogViewerScrollWidget::new(&task);
+        let task = create_test_task("/dummy/path.log".to_string());
+        let mut widget = LogViewerScrollWidget::new(&task, rows(&["Line 1"]), 1);
 
         // Initially enabled
         assert!(widget.is_auto_scroll_enabled());
@@ -447,12 +801,8 @@ mod tests {
 
     #[test]
     fn test_auto_scroll_disable() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Line 1").unwrap();
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let mut widget = LogViewerScrollWidget::new(&task);
+        let task = create_test_task("/dummy/path.log".to_string());
+        let mut widget = LogViewerScrollWidget::new(&task, rows(&["Line 1"]), 1);
 
         // Initially enabled
         assert!(widget.is_auto_scroll_enabled());
@@ -464,12 +814,8 @@ mod tests {
 
     #[test]
     fn test_auto_scroll_enable() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Line 1").unwrap();
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let mut widget = LogViewerScrollWidget::new(&task);
+        let task = create_test_task("/dummy/path.log".to_string());
+        let mut widget = LogViewerScrollWidget::new(&task, rows(&["Line 1"]), 1);
 
         // Disable first
         widget.disable_auto_scroll();
@@ -482,16 +828,12 @@ mod tests {
 
     #[test]
     fn test_footer_displays_auto_scroll_status() {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Line 1").unwrap();
-        temp_file.flush().unwrap();
-
-        let task = create_test_task(temp_file.path().to_string_lossy().to_string());
-        let mut widget = LogViewerScrollWidget::new(&task);
+        let task = create_test_task("/dummy/path.log".to_string());
+        let mut widget = LogViewerScrollWidget::new(&task, rows(&["Line 1"]), 1);
 
         let backend = TestBackend::new(80, 20);
         let mut terminal = Terminal::new(backend).unwrap();
-        let mut scroll_state = ScrollViewState::default();
+        let mut scroll_state = LogScrollState::default();
 
         // Test with auto-scroll enabled (default)
         terminal
@@ -1,10 +1,23 @@
 pub mod app;
+pub mod help;
+pub mod keymap;
+pub mod log_index;
+pub mod log_level_filter;
+pub mod log_search;
 pub mod log_viewer_scrollview;
+pub mod new_task;
+pub mod notifications;
 pub mod process_details;
 pub mod table_state_scroll;
 pub mod task_list;
+pub mod template_launcher;
+pub mod theme;
+
+use std::collections::HashMap;
 
 use self::table_state_scroll::TableScroll;
+use crate::app::stats::TaskStats;
+use crate::app::storage::Schedule;
 use crate::app::storage::task::Task;
 
 pub struct App {
@@ -12,6 +25,8 @@ pub struct App {
     pub selected_index: usize,
     pub filter: TaskFilter,
     pub table_scroll: TableScroll,
+    pub stats: HashMap<String, TaskStats>,
+    pub schedules: Vec<Schedule>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,11 +37,15 @@ pub enum TaskFilter {
     Killed,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ViewMode {
     TaskList,
     LogView,
     ProcessDetails,
+    TemplateLauncher,
+    NewTask,
+    Search,
+    Notifications,
 }
 
 impl Default for App {
@@ -42,6 +61,8 @@ impl App {
             selected_index: 0,
             filter: TaskFilter::All,
             table_scroll: TableScroll::new(),
+            stats: HashMap::new(),
+            schedules: Vec::new(),
         }
     }
 
@@ -52,6 +73,8 @@ impl App {
             selected_index: 0,
             filter: TaskFilter::All,
             table_scroll,
+            stats: HashMap::new(),
+            schedules: Vec::new(),
         }
     }
 
@@ -65,6 +88,8 @@ impl App {
             selected_index: 0,
             filter: TaskFilter::All,
             table_scroll,
+            stats: HashMap::new(),
+            schedules: Vec::new(),
         }
     }
 }
@@ -0,0 +1,567 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use super::theme::Theme;
+use crate::app::config;
+use crate::app::error::Result;
+use crate::app::helpers;
+use crate::app::run_options::{RunOptionArgs, RunOptions};
+
+/// How many earlier commands can be recalled with the arrow keys
+pub const MAX_HISTORY: usize = 50;
+
+/// Inputs of the new task form, in the order they are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Command,
+    Cwd,
+    Env,
+    Name,
+    Options,
+}
+
+const FIELDS: [Field; 5] = [
+    Field::Command,
+    Field::Cwd,
+    Field::Env,
+    Field::Name,
+    Field::Options,
+];
+
+impl Field {
+    fn label(&self) -> &'static str {
+        match self {
+            Field::Command => "Command",
+            Field::Cwd => "Directory",
+            Field::Env => "Env",
+            Field::Name => "Name",
+            Field::Options => "Options",
+        }
+    }
+
+    /// Shown in an empty, unfocused input
+    fn hint(&self) -> &'static str {
+        match self {
+            Field::Command => "e.g. npm run dev",
+            Field::Cwd => "current directory",
+            Field::Env => "KEY=VALUE ...",
+            Field::Name => "optional",
+            Field::Options => "ghost run options, e.g. --idle-timeout 10m --limit-mem 512M",
+        }
+    }
+
+    fn index(&self) -> usize {
+        FIELDS.iter().position(|field| field == self).unwrap_or(0)
+    }
+}
+
+/// What the form wants done after a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormAction {
+    Continue,
+    Cancel,
+    Submit,
+}
+
+/// A validated new task, ready to be spawned
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewTask {
+    pub command: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub name: Option<String>,
+    pub options: RunOptions,
+}
+
+/// State of the "new task" modal
+#[derive(Debug, Clone)]
+pub struct NewTaskForm {
+    values: [String; 5],
+    errors: [Option<String>; 5],
+    focus: Field,
+    /// Earlier commands, most recent first
+    history: Vec<String>,
+    history_index: Option<usize>,
+    /// The command typed before browsing the history
+    draft: String,
+}
+
+impl NewTaskForm {
+    pub fn new(history: Vec<String>) -> Self {
+        Self {
+            values: Default::default(),
+            errors: Default::default(),
+            focus: Field::Command,
+            history,
+            history_index: None,
+            draft: String::new(),
+        }
+    }
+
+    pub fn value(&self, field: Field) -> &str {
+        &self.values[field.index()]
+    }
+
+    pub fn error(&self, field: Field) -> Option<&str> {
+        self.errors[field.index()].as_deref()
+    }
+
+    pub fn focus(&self) -> Field {
+        self.focus
+    }
+
+    /// Show an error below a field, e.g. when spawning the task failed
+    pub fn set_error(&mut self, field: Field, message: String) {
+        self.errors[field.index()] = Some(message);
+        self.focus = field;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormAction {
+        match key.code {
+            KeyCode::Esc => return FormAction::Cancel,
+            KeyCode::Enter => return FormAction::Submit,
+            KeyCode::Tab => {
+                // Complete a directory first; move on once there is nothing to complete
+                if self.focus == Field::Cwd
+                    && let Some(completed) = complete_path(self.value(Field::Cwd))
+                    && completed != self.value(Field::Cwd)
+                {
+                    self.values[Field::Cwd.index()] = completed;
+                } else {
+                    self.move_focus(1);
+                }
+            }
+            KeyCode::BackTab => self.move_focus(-1),
+            KeyCode::Up if self.focus == Field::Command => self.recall(1),
+            KeyCode::Down if self.focus == Field::Command => self.recall(-1),
+            KeyCode::Up => self.move_focus(-1),
+            KeyCode::Down => self.move_focus(1),
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.edit(String::clear);
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.edit(|value| value.push(c));
+            }
+            KeyCode::Backspace => {
+                self.edit(|value| {
+                    value.pop();
+                });
+            }
+            _ => {}
+        }
+
+        FormAction::Continue
+    }
+
+    fn edit(&mut self, f: impl FnOnce(&mut String)) {
+        let index = self.focus.index();
+        f(&mut self.values[index]);
+        self.errors[index] = None;
+        if self.focus == Field::Command {
+            self.history_index = None;
+        }
+    }
+
+    fn move_focus(&mut self, step: isize) {
+        let index = self.focus.index() as isize + step;
+        self.focus = FIELDS[index.rem_euclid(FIELDS.len() as isize) as usize];
+    }
+
+    /// Step back (1) or forward (-1) through earlier commands
+    fn recall(&mut self, step: isize) {
+        let command = Field::Command.index();
+        let next = match self.history_index {
+            None if step > 0 && !self.history.is_empty() => {
+                self.draft = self.values[command].clone();
+                Some(0)
+            }
+            None => return,
+            Some(index) => {
+                let index = index as isize + step;
+                if index < 0 {
+                    None
+                } else {
+                    Some((index as usize).min(self.history.len() - 1))
+                }
+            }
+        };
+
+        self.history_index = next;
+        self.values[command] = match next {
+            Some(index) => self.history[index].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.errors[command] = None;
+    }
+
+    /// Check every input, marking the fields that are invalid
+    pub fn validate(&mut self) -> Option<NewTask> {
+        self.errors = Default::default();
+
+        let command = match self.value(Field::Command).trim() {
+            "" => Err("Enter a command to run".to_string()),
+            command => helpers::parse_command(command).map_err(|e| e.to_string()),
+        };
+        let cwd = validate_cwd(self.value(Field::Cwd));
+        let env = split_words(self.value(Field::Env))
+            .and_then(|words| config::env::parse_env_vars(&words))
+            .map_err(|e| e.to_string());
+        let options = split_words(self.value(Field::Options))
+            .and_then(|words| RunOptionArgs::parse_flags(&words))
+            .map_err(|e| e.to_string());
+        let name = match self.value(Field::Name).trim() {
+            "" => None,
+            name => Some(name.to_string()),
+        };
+
+        let mut first_error = None;
+        for (field, error) in [
+            (Field::Command, command.as_ref().err()),
+            (Field::Cwd, cwd.as_ref().err()),
+            (Field::Env, env.as_ref().err()),
+            (Field::Options, options.as_ref().err()),
+        ] {
+            if let Some(error) = error {
+                self.errors[field.index()] = Some(error.clone());
+                first_error.get_or_insert(field);
+            }
+        }
+        if let Some(field) = first_error {
+            self.focus = field;
+            return None;
+        }
+
+        Some(NewTask {
+            command: command.ok()?,
+            cwd: cwd.ok()?,
+            env: env.ok()?,
+            name,
+            options: options.ok()?,
+        })
+    }
+}
+
+/// Split an input into words, honouring quotes like the command does
+fn split_words(input: &str) -> Result<Vec<String>> {
+    if input.trim().is_empty() {
+        Ok(Vec::new())
+    } else {
+        helpers::parse_command(input)
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+fn validate_cwd(input: &str) -> std::result::Result<Option<PathBuf>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let path = expand_home(input);
+    if path.is_dir() {
+        Ok(Some(std::path::absolute(&path).unwrap_or(path)))
+    } else {
+        Err(format!("Not a directory: {input}"))
+    }
+}
+
+/// Complete the last component of a directory path
+///
+/// Returns the input extended by the longest prefix shared by all matching
+/// directories, with a trailing slash once only one directory matches, or
+/// None if there is nothing to complete.
+pub fn complete_path(input: &str) -> Option<String> {
+    let (parent, prefix) = match input.rfind('/') {
+        Some(index) => input.split_at(index + 1),
+        None => ("", input),
+    };
+    // Listing a whole directory is not worth it; Tab then moves to the next field
+    if prefix.is_empty() {
+        return None;
+    }
+    let dir = if parent.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_home(parent)
+    };
+
+    let mut matches: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        // Hidden directories only when asked for
+        .filter(|name| {
+            name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'))
+        })
+        .collect();
+    matches.sort();
+
+    let completion = match matches.as_slice() {
+        [] => return None,
+        [only] => format!("{only}/"),
+        [first, rest @ ..] => {
+            let mut common = first.clone();
+            for name in rest {
+                let shared = common
+                    .chars()
+                    .zip(name.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                common = common.chars().take(shared).collect();
+            }
+            common
+        }
+    };
+    Some(format!("{parent}{completion}"))
+}
+
+/// Quote a stored command so `helpers::parse_command` splits it the same way
+///
+/// The parser has no escapes, so an argument containing both kinds of quotes
+/// cannot be represented exactly.
+pub fn quote_command(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|arg| {
+            if !arg.is_empty()
+                && !arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+            {
+                arg.clone()
+            } else if !arg.contains('\'') {
+                format!("'{arg}'")
+            } else {
+                format!("\"{arg}\"")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Modal drawn over the task list to start a new task
+pub struct NewTaskModal<'a> {
+    form: &'a NewTaskForm,
+    theme: Theme,
+}
+
+impl<'a> NewTaskModal<'a> {
+    pub fn new(form: &'a NewTaskForm) -> Self {
+        Self {
+            form,
+            theme: Theme::default(),
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn render(self, frame: &mut Frame, area: Rect) {
+        let label_style = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+
+        for field in FIELDS {
+            let focused = self.form.focus == field;
+            let value = self.form.value(field);
+            let mut spans = vec![Span::styled(
+                format!(" {:>9}: ", field.label()),
+                if focused {
+                    label_style.fg(self.theme.key)
+                } else {
+                    label_style
+                },
+            )];
+            if focused {
+                spans.push(Span::raw(format!("{value}█")));
+            } else if value.is_empty() {
+                spans.push(Span::styled(
+                    field.hint(),
+                    Style::default().fg(self.theme.muted),
+                ));
+            } else {
+                spans.push(Span::raw(value.to_string()));
+            }
+            lines.push(Line::from(spans));
+
+            if let Some(error) = self.form.error(field) {
+                lines.push(Line::from(Span::styled(
+                    format!("            {error}"),
+                    Style::default().fg(self.theme.error),
+                )));
+            }
+        }
+
+        lines.push(Line::from(""));
+        let mut keybinds = vec![
+            Span::styled("[Enter]", Style::default().fg(self.theme.key)),
+            Span::raw(" Start  "),
+            Span::styled("[Tab]", Style::default().fg(self.theme.key)),
+            Span::raw(" Next field  "),
+        ];
+        if self.form.focus == Field::Command {
+            keybinds.push(Span::styled("[↑/↓]", Style::default().fg(self.theme.key)));
+            keybinds.push(Span::raw(" History  "));
+        }
+        keybinds.push(Span::styled("[Esc]", Style::default().fg(self.theme.key)));
+        keybinds.push(Span::raw(" Cancel"));
+        lines.push(Line::from(keybinds));
+
+        // Center the modal, leaving some of the task list visible around it
+        let width = area.width.saturating_sub(4).min(100);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let modal = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let block = Block::default()
+            .title(" New Task ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.panel_border));
+        frame.render_widget(Clear, modal);
+        frame.render_widget(Paragraph::new(lines).block(block), modal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(form: &mut NewTaskForm, code: KeyCode) -> FormAction {
+        form.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(form: &mut NewTaskForm, text: &str) {
+        for c in text.chars() {
+            press(form, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_errors_per_field() {
+        let mut form = NewTaskForm::new(vec![]);
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "/does/not/exist");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "NOEQUALS");
+        press(&mut form, KeyCode::Tab);
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "--idle-action stop");
+
+        assert_eq!(form.validate(), None);
+        assert_eq!(form.error(Field::Command), Some("Enter a command to run"));
+        assert!(form.error(Field::Cwd).unwrap().contains("Not a directory"));
+        assert!(form.error(Field::Env).unwrap().contains("KEY=VALUE"));
+        assert!(
+            form.error(Field::Options)
+                .unwrap()
+                .contains("--idle-timeout")
+        );
+        assert_eq!(form.focus(), Field::Command);
+
+        // Editing a field clears its error
+        type_text(&mut form, "x");
+        assert_eq!(form.error(Field::Command), None);
+    }
+
+    #[test]
+    fn test_validate_builds_new_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut form = NewTaskForm::new(vec![]);
+        type_text(&mut form, "echo 'hello world'");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, &format!("{}/", dir.path().display()));
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "A=1 B='two words'");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, " greeter ");
+        press(&mut form, KeyCode::Tab);
+        type_text(&mut form, "--nice 5");
+
+        let task = form.validate().unwrap();
+        assert_eq!(task.command, vec!["echo", "hello world"]);
+        assert_eq!(task.cwd.as_deref(), Some(dir.path()));
+        assert_eq!(
+            task.env,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "two words".to_string())
+            ]
+        );
+        assert_eq!(task.name.as_deref(), Some("greeter"));
+        assert_eq!(task.options.limits.nice, Some(5));
+    }
+
+    #[test]
+    fn test_history_recall_keeps_draft() {
+        let history = vec!["cargo test".to_string(), "npm run dev".to_string()];
+        let mut form = NewTaskForm::new(history);
+        type_text(&mut form, "ls");
+
+        press(&mut form, KeyCode::Up);
+        assert_eq!(form.value(Field::Command), "cargo test");
+        press(&mut form, KeyCode::Up);
+        press(&mut form, KeyCode::Up);
+        assert_eq!(form.value(Field::Command), "npm run dev");
+        press(&mut form, KeyCode::Down);
+        assert_eq!(form.value(Field::Command), "cargo test");
+        press(&mut form, KeyCode::Down);
+        assert_eq!(form.value(Field::Command), "ls");
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["project-api", "project-web", "other", ".hidden"] {
+            std::fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        std::fs::write(dir.path().join("otherfile"), "").unwrap();
+        let base = format!("{}/", dir.path().display());
+
+        assert_eq!(
+            complete_path(&format!("{base}pro")),
+            Some(format!("{base}project-"))
+        );
+        assert_eq!(
+            complete_path(&format!("{base}project-a")),
+            Some(format!("{base}project-api/"))
+        );
+        assert_eq!(
+            complete_path(&format!("{base}oth")),
+            Some(format!("{base}other/"))
+        );
+        assert_eq!(
+            complete_path(&format!("{base}.h")),
+            Some(format!("{base}.hidden/"))
+        );
+        assert_eq!(complete_path(&format!("{base}missing")), None);
+    }
+
+    #[test]
+    fn test_quote_command_round_trips() {
+        let command: Vec<String> = ["sh", "-c", "echo 'hi'", "say \"there\"", "plain"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let quoted = quote_command(&command);
+        assert_eq!(helpers::parse_command(&quoted).unwrap(), command);
+    }
+}
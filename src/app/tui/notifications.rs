@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use super::ViewMode;
+use super::help::bracketed_hints;
+use super::keymap::{Action, KeyMap};
+use super::theme::Theme;
+use crate::app::notify::{Notification, NotificationKind};
+use crate::app::storage::TaskStatus;
+use crate::app::storage::task::Task;
+
+/// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// Toasts shown at once; older ones are only in the history
+const MAX_TOASTS: usize = 3;
+const TOAST_WIDTH: u16 = 50;
+/// Notifications kept in the history pane
+const MAX_HISTORY: usize = 100;
+
+/// Key hints in the footer of the history pane
+const FOOTER_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::Quit], "Quit"),
+    (&[Action::Back], "Back to list"),
+    (&[Action::Down, Action::Up], "Move"),
+    (&[Action::Select], "Task details"),
+];
+
+/// Notices tasks that ended or restarted between refreshes of the task list
+#[derive(Debug, Default)]
+pub struct Notifications {
+    /// Status and restart count of every task at the last update, None before the first
+    known: Option<HashMap<String, (TaskStatus, u32)>>,
+    /// Newest last
+    history: Vec<Notification>,
+    toasts: Vec<(Notification, Instant)>,
+    /// Notifications not yet sent to the terminal
+    unsent: Vec<Notification>,
+}
+
+impl Notifications {
+    /// Compare the tasks with those of the last update and notify of the changes
+    ///
+    /// Tasks already finished when the TUI opened are not notified of.
+    pub fn update(&mut self, tasks: &[Task]) {
+        let current: HashMap<String, (TaskStatus, u32)> = tasks
+            .iter()
+            .map(|task| (task.id.clone(), (task.status, task.restart_count)))
+            .collect();
+        let Some(known) = self.known.replace(current) else {
+            return;
+        };
+
+        let now = Instant::now();
+        for task in tasks.iter().rev() {
+            let Some(&(status, restart_count)) = known.get(&task.id) else {
+                continue;
+            };
+            let notification = if task.restart_count > restart_count {
+                Notification::for_restart(task)
+            } else if status == TaskStatus::Running
+                && matches!(
+                    task.status,
+                    TaskStatus::Exited | TaskStatus::Killed | TaskStatus::Unknown
+                )
+            {
+                Notification::for_task_end(task)
+            } else {
+                continue;
+            };
+            self.toasts.push((notification.clone(), now));
+            self.unsent.push(notification.clone());
+            self.history.push(notification);
+        }
+
+        let excess = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..excess);
+    }
+
+    /// Notifications to show as toasts, newest first
+    pub fn toasts(&mut self) -> Vec<&Notification> {
+        self.toasts
+            .retain(|(_, shown_at)| shown_at.elapsed() < TOAST_DURATION);
+        self.toasts
+            .iter()
+            .rev()
+            .take(MAX_TOASTS)
+            .map(|(notification, _)| notification)
+            .collect()
+    }
+
+    /// Hide the toasts; they stay in the history
+    pub fn dismiss_toasts(&mut self) {
+        self.toasts.clear();
+    }
+
+    /// Notifications so far, newest first
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter().rev()
+    }
+
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// Notifications not handed out yet, e.g. to be sent to the terminal
+    pub fn take_unsent(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.unsent)
+    }
+}
+
+fn kind_color(kind: NotificationKind, theme: &Theme) -> Color {
+    match kind {
+        NotificationKind::Exited => theme.success,
+        NotificationKind::Failed => theme.error,
+        NotificationKind::Restarted => theme.warning,
+    }
+}
+
+/// Draw toasts stacked in the top right corner, over whatever is shown
+pub fn render_toasts(frame: &mut Frame, area: Rect, toasts: &[&Notification], theme: &Theme) {
+    let width = TOAST_WIDTH.min(area.width.saturating_sub(2));
+    if width < 10 {
+        return;
+    }
+    let x = area.x + area.width - width - 1;
+    let mut y = area.y + 1;
+    for notification in toasts {
+        if y + 3 > area.y + area.height {
+            break;
+        }
+        let rect = Rect::new(x, y, width, 3);
+        let color = kind_color(notification.kind, theme);
+        let block = Block::default()
+            .title(format!(" {} ", notification.title))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color));
+        frame.render_widget(Clear, rect);
+        frame.render_widget(
+            Paragraph::new(format!(" {}", notification.message)).block(block),
+            rect,
+        );
+        y += 3;
+    }
+}
+
+/// Lists the notifications since the TUI was opened
+pub struct NotificationsWidget<'a> {
+    notifications: &'a Notifications,
+    selected: usize,
+    key_map: &'a KeyMap,
+    theme: Theme,
+}
+
+impl<'a> NotificationsWidget<'a> {
+    pub fn new(notifications: &'a Notifications, selected: usize) -> Self {
+        Self {
+            notifications,
+            selected,
+            key_map: KeyMap::builtin(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Generate the key hints of the footer from a key map
+    pub fn with_key_map(mut self, key_map: &'a KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn render(self, frame: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(2)])
+            .split(area);
+
+        let block = Block::default()
+            .title(format!(" Notifications ({}) ", self.notifications.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.border));
+
+        if self.notifications.is_empty() {
+            let hint = Paragraph::new(
+                " No notifications yet. Tasks that end or restart while the TUI is open show up here.",
+            )
+            .style(Style::default().fg(self.theme.muted))
+            .block(block);
+            frame.render_widget(hint, chunks[0]);
+        } else {
+            let items: Vec<ListItem> = self
+                .notifications
+                .history()
+                .map(|notification| {
+                    ListItem::new(Line::from(vec![
+                        Span::raw(format!(" {}  ", format_time(notification.created_at))),
+                        Span::styled(
+                            format!("{:<9}", notification.kind.as_str()),
+                            Style::default().fg(kind_color(notification.kind, &self.theme)),
+                        ),
+                        Span::raw(format!(" {}", notification.message)),
+                    ]))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default().with_selected(Some(self.selected));
+            frame.render_stateful_widget(list, chunks[0], &mut state);
+        }
+
+        let hints = self.key_map.hints(ViewMode::Notifications, FOOTER_HINTS);
+        let footer = Paragraph::new(bracketed_hints(&hints, &self.theme))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM)
+                    .border_style(Style::default().fg(self.theme.panel_border)),
+            );
+        frame.render_widget(footer, chunks[1]);
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, status: TaskStatus, restart_count: u32) -> Task {
+        Task {
+            id: id.to_string(),
+            pid: 1,
+            pgid: None,
+            command: r#"["make"]"#.to_string(),
+            env: None,
+            cwd: None,
+            status,
+            exit_code: (status == TaskStatus::Exited).then_some(2),
+            started_at: 0,
+            finished_at: None,
+            log_path: format!("/tmp/{id}.log"),
+            options: None,
+            restart_count,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_update_notices_exits_and_restarts() {
+        let mut notifications = Notifications::default();
+        notifications.update(&[
+            task("a", TaskStatus::Running, 0),
+            task("b", TaskStatus::Running, 0),
+            task("c", TaskStatus::Exited, 0),
+        ]);
+        assert!(notifications.is_empty());
+
+        notifications.update(&[
+            task("a", TaskStatus::Exited, 0),
+            task("b", TaskStatus::Running, 1),
+            task("c", TaskStatus::Exited, 0),
+            task("d", TaskStatus::Exited, 0),
+        ]);
+        let kinds: Vec<NotificationKind> = notifications
+            .history()
+            .map(|notification| notification.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [NotificationKind::Failed, NotificationKind::Restarted]
+        );
+        assert_eq!(notifications.toasts().len(), 2);
+        assert_eq!(notifications.take_unsent().len(), 2);
+        assert!(notifications.take_unsent().is_empty());
+
+        notifications.dismiss_toasts();
+        assert!(notifications.toasts().is_empty());
+        assert_eq!(notifications.len(), 2);
+    }
+}
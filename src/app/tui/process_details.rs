@@ -1,25 +1,117 @@
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect, Size},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     symbols,
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Paragraph, Sparkline, Wrap},
 };
 use tui_scrollview::{ScrollView, ScrollViewState, ScrollbarVisibility};
 
+use super::ViewMode;
+use super::help::bracketed_hints;
+use super::keymap::{Action, KeyMap};
+use super::theme::Theme;
+use crate::app::config::{EnvConfig, REDACTED};
+use crate::app::display::{limits_description, tree_indent};
+use crate::app::helpers::{format_bytes, format_duration_short};
 use crate::app::port_detector::detect_listening_ports;
+use crate::app::process_state;
+use crate::app::process_tree::ProcessNode;
+use crate::app::stats::TaskStats;
 use crate::app::storage::task::Task;
 use crate::app::storage::task_status::TaskStatus;
+use crate::app::storage::{self, EventKind, TaskEvent};
 use chrono::{TimeZone, Utc};
 
+/// Maximum number of processes listed in the process tree section
+const MAX_TREE_ROWS: usize = 6;
+
+/// Maximum number of steps listed in the chain section
+const MAX_CHAIN_ROWS: usize = 6;
+
+/// Maximum number of events listed in the events section, the latest ones
+const MAX_EVENT_ROWS: usize = 5;
+
+/// Key hints in the footer, left out when their actions have no keys
+const FOOTER_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::Quit], "Quit"),
+    (&[Action::Back], "Back to list"),
+    (&[Action::Down, Action::Up], "Scroll"),
+    (&[Action::PageDown, Action::PageUp], "Page"),
+    (&[Action::Help], "Help"),
+];
+
 pub struct ProcessDetailsWidget<'a> {
     task: &'a Task,
+    stats: Option<&'a TaskStats>,
+    cpu_history: Vec<u64>,
+    process_tree: &'a [ProcessNode],
+    chain: &'a [(usize, Task)],
+    events: &'a [TaskEvent],
+    /// Hides the values of secret environment variables
+    redact: Option<&'a EnvConfig>,
+    key_map: &'a KeyMap,
+    theme: Theme,
 }
 
 impl<'a> ProcessDetailsWidget<'a> {
     pub fn new(task: &'a Task) -> Self {
-        Self { task }
+        Self {
+            task,
+            stats: None,
+            cpu_history: Vec::new(),
+            process_tree: &[],
+            chain: &[],
+            events: &[],
+            redact: None,
+            key_map: KeyMap::builtin(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Show a resources section with the latest stats and a CPU sparkline
+    pub fn with_stats(mut self, stats: Option<&'a TaskStats>, history: Vec<&TaskStats>) -> Self {
+        self.stats = stats;
+        self.cpu_history = history
+            .iter()
+            .map(|stats| stats.cpu_percent.unwrap_or(0.0).round() as u64)
+            .collect();
+        self
+    }
+
+    /// Show a process tree section listing the task's descendants
+    pub fn with_process_tree(mut self, process_tree: &'a [ProcessNode]) -> Self {
+        self.process_tree = process_tree;
+        self
+    }
+
+    /// Show a chain section listing the steps of the chain the task belongs to
+    pub fn with_chain(mut self, chain: &'a [(usize, Task)]) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Show an events section with the latest entries of the task's event history
+    pub fn with_events(mut self, events: &'a [TaskEvent]) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_redact(mut self, redact: &'a EnvConfig) -> Self {
+        self.redact = Some(redact);
+        self
+    }
+
+    /// Generate the key hints of the footer from a key map
+    pub fn with_key_map(mut self, key_map: &'a KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
     }
 
     fn format_command(&self) -> String {
@@ -33,35 +125,92 @@ impl<'a> ProcessDetailsWidget<'a> {
     }
 
     pub fn render(self, frame: &mut Frame, area: Rect, env_scroll_state: &mut ScrollViewState) {
+        let info_lines = self.basic_info_lines();
+
+        // The resources section is only shown once the task has been sampled
+        let resources_height = if self.stats.is_some() { 6 } else { 0 };
+        // Show up to 6 processes of the tree; the section is hidden when there are none
+        let tree_height = if self.process_tree.is_empty() {
+            0
+        } else {
+            self.process_tree.len().min(MAX_TREE_ROWS) as u16 + 2
+        };
+
+        // The chain section is only shown for tasks that run before or after other tasks
+        let chain_height = if self.chain.len() > 1 {
+            self.chain.len().min(MAX_CHAIN_ROWS) as u16 + 2
+        } else {
+            0
+        };
+
+        // The events section is only shown once something has happened to the task
+        let events_height = if self.events.is_empty() {
+            0
+        } else {
+            self.events.len().min(MAX_EVENT_ROWS) as u16 + 2
+        };
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(8), // Basic info section (6 lines + 2 borders)
-                Constraint::Length(5), // Listening ports section
-                Constraint::Min(5),    // Environment variables section
-                Constraint::Length(2), // Footer
+                Constraint::Length(info_lines.len() as u16 + 2), // Basic info section + 2 borders
+                Constraint::Length(chain_height),                // Chain section
+                Constraint::Length(resources_height),            // Resources section
+                Constraint::Length(tree_height),                 // Process tree section
+                Constraint::Length(events_height),               // Events section
+                Constraint::Length(5),                           // Listening ports section
+                Constraint::Min(5),                              // Environment variables section
+                Constraint::Length(2),                           // Footer
             ])
             .split(area);
 
         // Render basic info section
-        self.render_basic_info(frame, chunks[0]);
+        self.render_basic_info(frame, chunks[0], info_lines);
+
+        // Render chain section
+        if self.chain.len() > 1 {
+            self.render_chain(frame, chunks[1]);
+        }
+
+        // Render resources section
+        if let Some(stats) = self.stats {
+            self.render_resources(frame, chunks[2], stats);
+        }
+
+        // Render process tree section
+        if !self.process_tree.is_empty() {
+            self.render_process_tree(frame, chunks[3]);
+        }
+
+        // Render events section
+        if !self.events.is_empty() {
+            self.render_events(frame, chunks[4]);
+        }
 
         // Render listening ports section
-        self.render_listening_ports(frame, chunks[1]);
+        self.render_listening_ports(frame, chunks[5]);
 
         // Render environment variables section
-        self.render_environment_variables(frame, chunks[2], env_scroll_state);
+        self.render_environment_variables(frame, chunks[6], env_scroll_state);
 
         // Render footer
-        self.render_footer(frame, chunks[3]);
+        self.render_footer(frame, chunks[7]);
     }
 
-    fn render_basic_info(&self, frame: &mut Frame, area: Rect) {
+    fn render_basic_info(&self, frame: &mut Frame, area: Rect, info_lines: Vec<Line<'a>>) {
         let block = Block::default()
             .title(" Process Details ")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan));
+            .border_style(Style::default().fg(self.theme.panel_border));
+
+        let paragraph = Paragraph::new(info_lines)
+            .block(block)
+            .wrap(Wrap { trim: true });
 
+        frame.render_widget(paragraph, area);
+    }
+
+    fn basic_info_lines(&self) -> Vec<Line<'a>> {
         // Calculate runtime
         let runtime = {
             let started = Utc.timestamp_opt(self.task.started_at, 0).single().unwrap();
@@ -89,17 +238,24 @@ impl<'a> ProcessDetailsWidget<'a> {
         };
 
         // Format status with color
-        let status_style = match self.task.status {
-            TaskStatus::Running => Style::default().fg(Color::Green),
-            TaskStatus::Exited => Style::default().fg(Color::Yellow),
-            TaskStatus::Killed => Style::default().fg(Color::Red),
-            TaskStatus::Unknown => Style::default().fg(Color::Gray),
-        };
+        let status_style = Style::default().fg(self.theme.status(self.task.status));
 
-        let status_text = format!("{} ({})", self.task.status.as_str(), runtime);
+        let mut status_text = format!("{} ({})", self.task.status.as_str(), runtime);
+        if let Some(idle) = process_state::idle_seconds(self.task) {
+            status_text.push_str(&format!(" | idle {}", format_duration_short(idle)));
+        }
+        if self.task.restart_count > 0 {
+            status_text.push_str(&format!(" | restarts: {}", self.task.restart_count));
+        }
+        if let Some(ref exit_reason) = self.task.exit_reason {
+            status_text.push_str(&format!(" | {exit_reason}"));
+        }
+        if self.task.status == TaskStatus::Running && storage::ready_since(self.events).is_some() {
+            status_text.push_str(" | ready");
+        }
 
         // Build info lines
-        let info_lines = vec![
+        let mut info_lines = vec![
             Line::from(vec![
                 Span::styled("Task ID: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(&self.task.id),
@@ -129,18 +285,249 @@ impl<'a> ProcessDetailsWidget<'a> {
             ]),
         ];
 
-        let paragraph = Paragraph::new(info_lines)
-            .block(block)
-            .wrap(Wrap { trim: true });
+        if let Some(ref name) = self.task.name {
+            info_lines.insert(
+                1,
+                Line::from(vec![
+                    Span::styled("Name: ", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(name.clone()),
+                ]),
+            );
+        }
 
-        frame.render_widget(paragraph, area);
+        if !self.task.run_options().limits.is_empty() {
+            info_lines.push(Line::from(vec![
+                Span::styled("Limits: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(limits_description(self.task)),
+            ]));
+        }
+
+        if let Some(watch) = self.task.run_options().watch {
+            let mut watching = watch.describe();
+            if let Some(ref trigger) = self.task.watch_trigger {
+                watching.push_str(&format!(" | last trigger: {trigger}"));
+            }
+            info_lines.push(Line::from(vec![
+                Span::styled("Watching: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(watching),
+            ]));
+        }
+
+        let on_output = self.task.run_options().on_output;
+        if !on_output.is_empty() {
+            let triggers: Vec<String> = on_output.iter().map(|t| t.describe()).collect();
+            info_lines.push(Line::from(vec![
+                Span::styled("Triggers: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(triggers.join(", ")),
+            ]));
+        }
+
+        let hooks = self.task.run_options().hooks;
+        if !hooks.is_empty() {
+            info_lines.push(Line::from(vec![
+                Span::styled("Hooks: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(hooks.describe()),
+            ]));
+        }
+
+        info_lines
+    }
+
+    fn render_chain(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .title(" Chain ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.panel_border));
+
+        let mut lines: Vec<Line> = self
+            .chain
+            .iter()
+            .take(MAX_CHAIN_ROWS)
+            .map(|(depth, step)| {
+                let status = match step.exit_code {
+                    Some(code) => format!("{} ({code})", step.status),
+                    None => step.status.to_string(),
+                };
+                let command = serde_json::from_str::<Vec<String>>(&step.command)
+                    .map(|command| command.join(" "))
+                    .unwrap_or_else(|_| step.command.clone());
+                let id_style = if step.id == self.task.id {
+                    Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+                } else {
+                    Style::default().fg(self.theme.accent)
+                };
+                Line::from(vec![
+                    Span::styled(step.id.chars().take(8).collect::<String>(), id_style),
+                    Span::raw(" "),
+                    Span::raw(tree_indent(*depth)),
+                    Span::styled(
+                        format!("{status:<14}"),
+                        Style::default().fg(self.theme.status(step.status)),
+                    ),
+                    Span::raw(command),
+                ])
+            })
+            .collect();
+
+        let hidden = self.chain.len().saturating_sub(MAX_CHAIN_ROWS);
+        if hidden > 0 {
+            // Replace the last row so the count of hidden steps stays visible
+            lines.pop();
+            lines.push(Line::from(Span::styled(
+                format!("... {} more (see `ghost status`)", hidden + 1),
+                Style::default().fg(self.theme.muted),
+            )));
+        }
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn render_resources(&self, frame: &mut Frame, area: Rect, stats: &TaskStats) {
+        let block = Block::default()
+            .title(" Resources ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.panel_border));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(1)])
+            .split(inner);
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let cpu = stats
+            .cpu_percent
+            .map_or("-".to_string(), |cpu| format!("{cpu:.1}%"));
+        let threads = stats.threads.map_or("-".to_string(), |t| t.to_string());
+        let io = match (stats.read_bytes, stats.write_bytes) {
+            (Some(read), Some(write)) => {
+                format!("read {}, write {}", format_bytes(read), format_bytes(write))
+            }
+            _ => "N/A".to_string(),
+        };
+
+        let lines = vec![
+            Line::from(vec![
+                Span::styled("CPU: ", bold),
+                Span::raw(cpu),
+                Span::raw(" | "),
+                Span::styled("Memory: ", bold),
+                Span::raw(format_bytes(stats.rss_bytes)),
+                Span::raw(" | "),
+                Span::styled("Threads: ", bold),
+                Span::raw(threads),
+                Span::raw(" | "),
+                Span::styled("Processes: ", bold),
+                Span::raw(stats.processes.to_string()),
+            ]),
+            Line::from(vec![Span::styled("I/O: ", bold), Span::raw(io)]),
+        ];
+        frame.render_widget(Paragraph::new(lines), chunks[0]);
+
+        let sparkline = Sparkline::default()
+            .data(&self.cpu_history)
+            .style(Style::default().fg(self.theme.success));
+        frame.render_widget(sparkline, chunks[1]);
+    }
+
+    fn render_process_tree(&self, frame: &mut Frame, area: Rect) {
+        let escaped = self.process_tree.iter().filter(|node| node.escaped).count();
+        let title = if escaped > 0 {
+            format!(" Process Tree ({escaped} escaped) ")
+        } else {
+            " Process Tree ".to_string()
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.panel_border));
+
+        let mut lines: Vec<Line> = self
+            .process_tree
+            .iter()
+            .take(MAX_TREE_ROWS)
+            .map(|node| {
+                let mut spans = vec![
+                    Span::styled(
+                        format!("{:<8}", node.pid),
+                        Style::default().fg(self.theme.accent),
+                    ),
+                    Span::raw(format!("{:<2}", node.state)),
+                    Span::raw(tree_indent(node.depth)),
+                    Span::raw(node.command.clone()),
+                ];
+                for port in &node.ports {
+                    spans.push(Span::styled(
+                        format!("  {} {}", port.protocol, port.local_addr),
+                        Style::default().fg(self.theme.success),
+                    ));
+                }
+                if node.escaped {
+                    spans.push(Span::styled(
+                        format!("  escaped (pgid {})", node.pgid),
+                        Style::default().fg(self.theme.error),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect();
+
+        let hidden = self.process_tree.len().saturating_sub(MAX_TREE_ROWS);
+        if hidden > 0 {
+            // Replace the last row so the count of hidden processes stays visible
+            lines.pop();
+            lines.push(Line::from(Span::styled(
+                format!("... {} more (see `ghost tree`)", hidden + 1),
+                Style::default().fg(self.theme.muted),
+            )));
+        }
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn render_events(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .title(format!(" Events ({}) ", self.events.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.panel_border));
+
+        let start = self.events.len().saturating_sub(MAX_EVENT_ROWS);
+        let lines: Vec<Line> = self.events[start..]
+            .iter()
+            .map(|event| {
+                let time = Utc
+                    .timestamp_opt(event.created_at, 0)
+                    .single()
+                    .map(|at| {
+                        at.with_timezone(&chrono::Local)
+                            .format("%H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                let kind_style = match event.kind {
+                    EventKind::Ready => Style::default().fg(self.theme.success),
+                    EventKind::Trigger => Style::default().fg(self.theme.warning),
+                    EventKind::Restart => Style::default().fg(self.theme.error),
+                    EventKind::Hook => Style::default().fg(self.theme.accent),
+                };
+                Line::from(vec![
+                    Span::styled(time, Style::default().fg(self.theme.muted)),
+                    Span::raw(" "),
+                    Span::styled(format!("{:<8}", event.kind.as_str()), kind_style),
+                    Span::raw(event.message.clone()),
+                ])
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
     }
 
     fn render_listening_ports(&self, frame: &mut Frame, area: Rect) {
         let block = Block::default()
             .title(" Listening Ports ")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan));
+            .border_style(Style::default().fg(self.theme.panel_border));
 
         let port_lines = if self.task.status == TaskStatus::Running {
             // Get actual listening ports for running processes
@@ -149,7 +536,7 @@ impl<'a> ProcessDetailsWidget<'a> {
                     if ports.is_empty() {
                         vec![Line::from(Span::styled(
                             "Not listening on any ports",
-                            Style::default().fg(Color::DarkGray),
+                            Style::default().fg(self.theme.muted),
                         ))]
                     } else {
                         ports
@@ -158,12 +545,12 @@ impl<'a> ProcessDetailsWidget<'a> {
                                 Line::from(vec![
                                     Span::styled(
                                         format!("{:<6}", port.protocol),
-                                        Style::default().fg(Color::Blue),
+                                        Style::default().fg(self.theme.accent),
                                     ),
                                     Span::raw(format!("{:<20}", port.local_addr)),
                                     Span::styled(
                                         port.state.clone(),
-                                        Style::default().fg(Color::Green),
+                                        Style::default().fg(self.theme.success),
                                     ),
                                 ])
                             })
@@ -176,17 +563,17 @@ impl<'a> ProcessDetailsWidget<'a> {
                         vec![
                             Line::from(Span::styled(
                                 format!("{command} command not found"),
-                                Style::default().fg(Color::Yellow),
+                                Style::default().fg(self.theme.warning),
                             )),
                             Line::from(Span::styled(
                                 format!("Please install {command} to enable port detection"),
-                                Style::default().fg(Color::DarkGray),
+                                Style::default().fg(self.theme.muted),
                             )),
                         ]
                     } else {
                         vec![Line::from(Span::styled(
                             format!("Failed to detect ports: {e:?}"),
-                            Style::default().fg(Color::Red),
+                            Style::default().fg(self.theme.error),
                         ))]
                     }
                 }
@@ -194,7 +581,7 @@ impl<'a> ProcessDetailsWidget<'a> {
         } else {
             vec![Line::from(Span::styled(
                 "Process not running",
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(self.theme.muted),
             ))]
         };
 
@@ -220,7 +607,7 @@ impl<'a> ProcessDetailsWidget<'a> {
         let block = Block::default()
             .title(" Environment Variables ")
             .borders(Borders::LEFT | Borders::RIGHT | Borders::TOP)
-            .border_style(Style::default().fg(Color::Cyan));
+            .border_style(Style::default().fg(self.theme.panel_border));
 
         let inner = block.inner(chunks[0]);
 
@@ -230,7 +617,10 @@ impl<'a> ProcessDetailsWidget<'a> {
             if let Ok(env_map) = serde_json::from_str::<Vec<(String, String)>>(env_json) {
                 env_map
                     .iter()
-                    .map(|(key, value)| format!("{key}={value}"))
+                    .map(|(key, value)| match self.redact {
+                        Some(redact) if redact.is_redacted(key) => format!("{key}={REDACTED}"),
+                        _ => format!("{key}={value}"),
+                    })
                     .collect()
             } else {
                 vec!["Failed to parse environment variables".to_string()]
@@ -270,42 +660,31 @@ impl<'a> ProcessDetailsWidget<'a> {
             // Left connection: ├
             buf[(chunks[0].x, chunks[1].y)]
                 .set_symbol(symbols::line::VERTICAL_RIGHT)
-                .set_style(Style::default().fg(Color::Cyan));
+                .set_style(Style::default().fg(self.theme.panel_border));
 
             // Horizontal line
             for x in chunks[0].x + 1..chunks[0].x + chunks[0].width - 1 {
                 buf[(x, chunks[1].y)]
                     .set_symbol(symbols::line::HORIZONTAL)
-                    .set_style(Style::default().fg(Color::Cyan));
+                    .set_style(Style::default().fg(self.theme.panel_border));
             }
 
             // Right connection: ┤
             buf[(chunks[0].x + chunks[0].width - 1, chunks[1].y)]
                 .set_symbol(symbols::line::VERTICAL_LEFT)
-                .set_style(Style::default().fg(Color::Cyan));
+                .set_style(Style::default().fg(self.theme.panel_border));
         }
     }
 
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
-        // Render keybinds
-        let keybinds = vec![
-            Span::styled("[q]", Style::default().fg(Color::Yellow)),
-            Span::raw(" Quit  "),
-            Span::styled("[Esc]", Style::default().fg(Color::Yellow)),
-            Span::raw(" Back to list  "),
-            Span::styled("[j/k]", Style::default().fg(Color::Yellow)),
-            Span::raw(" Scroll  "),
-            Span::styled("[C-d/C-u]", Style::default().fg(Color::Yellow)),
-            Span::raw(" Page"),
-        ];
-
-        let keybind_paragraph = Paragraph::new(Line::from(keybinds))
+        let hints = self.key_map.hints(ViewMode::ProcessDetails, FOOTER_HINTS);
+        let keybind_paragraph = Paragraph::new(bracketed_hints(&hints, &self.theme))
             .style(Style::default())
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(self.theme.panel_border)),
             );
 
         frame.render_widget(keybind_paragraph, area);
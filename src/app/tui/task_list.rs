@@ -1,17 +1,42 @@
 use ratatui::{
     Frame,
     layout::{Constraint, Rect},
-    style::{Color, Style},
-    widgets::{Block, Borders, Cell, Row, StatefulWidget, Table, TableState, Widget},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
 };
 
 // Layout constants
 const ID_COLUMN_WIDTH: u16 = 38; // Full UUID (36 chars) + 2 for padding
 const PID_COLUMN_WIDTH: u16 = 8;
 const STATUS_COLUMN_WIDTH: u16 = 9;
+const CPU_COLUMN_WIDTH: u16 = 7;
+const MEM_COLUMN_WIDTH: u16 = 8;
 const STARTED_COLUMN_WIDTH: u16 = 16;
 const COMMAND_COLUMN_MIN_WIDTH: u16 = 20;
 const DIRECTORY_COLUMN_MIN_WIDTH: u16 = 20;
+const MAX_SCHEDULE_ROWS: usize = 3;
+
+// Key hints in the footer, left out when their actions have no keys
+const FOOTER_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::Down, Action::Up], "Move"),
+    (&[Action::OpenLog], "Log"),
+    (&[Action::Rerun], "Rerun"),
+    (&[Action::Stop, Action::Kill], "Stop"),
+    (&[Action::Quit], "Quit"),
+    (&[Action::Top, Action::Bottom], "Top/Bot"),
+    (&[Action::PageDown, Action::PageUp], "Page"),
+];
+
+// Minimum table width at which the CPU and memory columns are shown
+const USAGE_COLUMNS_MIN_WIDTH: u16 = ID_COLUMN_WIDTH
+    + PID_COLUMN_WIDTH
+    + STATUS_COLUMN_WIDTH
+    + CPU_COLUMN_WIDTH
+    + MEM_COLUMN_WIDTH
+    + STARTED_COLUMN_WIDTH
+    + COMMAND_COLUMN_MIN_WIDTH
+    + DIRECTORY_COLUMN_MIN_WIDTH;
 
 // Column constraints for the table
 const COLUMN_CONSTRAINTS: [Constraint; 6] = [
@@ -23,14 +48,37 @@ const COLUMN_CONSTRAINTS: [Constraint; 6] = [
     Constraint::Min(DIRECTORY_COLUMN_MIN_WIDTH),
 ];
 
-use super::{App, TaskFilter, table_state_scroll::TableScroll};
+// Column constraints for wide tables, with CPU and memory after the status
+const WIDE_COLUMN_CONSTRAINTS: [Constraint; 8] = [
+    Constraint::Length(ID_COLUMN_WIDTH),
+    Constraint::Length(PID_COLUMN_WIDTH),
+    Constraint::Length(STATUS_COLUMN_WIDTH),
+    Constraint::Length(CPU_COLUMN_WIDTH),
+    Constraint::Length(MEM_COLUMN_WIDTH),
+    Constraint::Length(STARTED_COLUMN_WIDTH),
+    Constraint::Min(COMMAND_COLUMN_MIN_WIDTH),
+    Constraint::Min(DIRECTORY_COLUMN_MIN_WIDTH),
+];
+
+use std::collections::HashMap;
+
+use super::keymap::{Action, KeyMap};
+use super::theme::Theme;
+use super::{App, TaskFilter, ViewMode, table_state_scroll::TableScroll};
+use crate::app::helpers::format_bytes;
+use crate::app::log_level::LevelCounts;
+use crate::app::process_state;
+use crate::app::stats::TaskStats;
+use crate::app::storage::Schedule;
 use crate::app::storage::task::Task;
 use crate::app::storage::task_status::TaskStatus;
 
 impl App {
     pub fn render_task_list(&mut self, frame: &mut Frame, area: Rect) {
         let task_list_widget =
-            TaskListWidget::new(&self.tasks, &self.filter, &mut self.table_scroll);
+            TaskListWidget::new(&self.tasks, &self.filter, &mut self.table_scroll)
+                .with_stats(&self.stats)
+                .with_schedules(&self.schedules);
         frame.render_widget(task_list_widget, area);
     }
 }
@@ -39,6 +87,13 @@ pub struct TaskListWidget<'a> {
     tasks: &'a [Task],
     filter: &'a TaskFilter,
     table_scroll: &'a mut TableScroll,
+    stats: Option<&'a HashMap<String, TaskStats>>,
+    schedules: &'a [Schedule],
+    log_levels: Option<&'a HashMap<String, LevelCounts>>,
+    search: Option<&'a str>,
+    prompt: Option<&'a str>,
+    key_map: &'a KeyMap,
+    theme: Theme,
 }
 
 impl<'a> TaskListWidget<'a> {
@@ -51,6 +106,87 @@ impl<'a> TaskListWidget<'a> {
             tasks,
             filter,
             table_scroll,
+            stats: None,
+            schedules: &[],
+            log_levels: None,
+            search: None,
+            prompt: None,
+            key_map: KeyMap::builtin(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Fill the CPU and memory columns from sampled stats, keyed by task ID
+    pub fn with_stats(mut self, stats: &'a HashMap<String, TaskStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// List upcoming schedules below the tasks
+    pub fn with_schedules(mut self, schedules: &'a [Schedule]) -> Self {
+        self.schedules = schedules;
+        self
+    }
+
+    /// Mark tasks whose logs have errors or warnings, keyed by task ID
+    pub fn with_log_levels(mut self, log_levels: &'a HashMap<String, LevelCounts>) -> Self {
+        self.log_levels = Some(log_levels);
+        self
+    }
+
+    /// Error and warning counts put before a task's command, e.g. "✗3 ⚠12 "
+    fn level_spans(&self, task_id: &str) -> Vec<Span<'static>> {
+        let Some(counts) = self.log_levels.and_then(|levels| levels.get(task_id)) else {
+            return Vec::new();
+        };
+        let mut spans = Vec::new();
+        if counts.errors > 0 {
+            spans.push(Span::styled(
+                format!("✗{} ", counts.errors),
+                Style::default().fg(self.theme.error),
+            ));
+        }
+        if counts.warnings > 0 {
+            spans.push(Span::styled(
+                format!("⚠{} ", counts.warnings),
+                Style::default().fg(self.theme.warning),
+            ));
+        }
+        spans
+    }
+
+    /// Show the search the tasks were filtered by in the title
+    pub fn with_search(mut self, search: Option<&'a str>) -> Self {
+        self.search = search;
+        self
+    }
+
+    /// Show a prompt, e.g. the search being typed, in place of the key hints
+    pub fn with_prompt(mut self, prompt: Option<&'a str>) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Generate the key hints of the footer from a key map
+    pub fn with_key_map(mut self, key_map: &'a KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    fn format_usage(&self, task_id: &str) -> (String, String) {
+        match self.stats.and_then(|stats| stats.get(task_id)) {
+            Some(stats) => (
+                stats
+                    .cpu_percent
+                    .map_or("-".to_string(), |cpu| format!("{cpu:.1}")),
+                format_bytes(stats.rss_bytes),
+            ),
+            None => ("-".to_string(), "-".to_string()),
         }
     }
 
@@ -64,12 +200,7 @@ impl<'a> TaskListWidget<'a> {
     }
 
     fn status_style(&self, status: &TaskStatus) -> Style {
-        match status {
-            TaskStatus::Running => Style::default().fg(Color::Green),
-            TaskStatus::Exited => Style::default().fg(Color::Blue),
-            TaskStatus::Killed => Style::default().fg(Color::Red),
-            TaskStatus::Unknown => Style::default().fg(Color::Gray),
-        }
+        Style::default().fg(self.theme.status(*status))
     }
 
     fn parse_command(&self, command_json: &str) -> String {
@@ -85,32 +216,45 @@ impl<'a> TaskListWidget<'a> {
         dt.format("%Y-%m-%d %H:%M").to_string()
     }
 
-    fn create_header_row(&self) -> Row<'_> {
-        Row::new(vec![
-            Cell::from(" ID"),
-            Cell::from(" PID"),
-            Cell::from(" Status"),
+    fn create_header_row(&self, show_usage: bool) -> Row<'_> {
+        let mut cells = vec![Cell::from(" ID"), Cell::from(" PID"), Cell::from(" Status")];
+        if show_usage {
+            cells.push(Cell::from(" CPU%"));
+            cells.push(Cell::from(" MEM"));
+        }
+        cells.extend([
             Cell::from(" Started"),
             Cell::from(" Command"),
             Cell::from(" Directory"),
-        ])
-        .style(Style::default())
+        ]);
+        Row::new(cells).style(Style::default())
+    }
+
+    fn column_constraints(show_usage: bool) -> &'static [Constraint] {
+        if show_usage {
+            &WIDE_COLUMN_CONSTRAINTS
+        } else {
+            &COLUMN_CONSTRAINTS
+        }
     }
 }
 
 impl<'a> Widget for TaskListWidget<'a> {
     fn render(self, area: Rect, buf: &mut ratatui::buffer::Buffer) {
         let filter_name = self.filter_name();
-        let title = format!(
+        let mut title = format!(
             " Ghost v{} [Filter: {filter_name}] ",
             env!("CARGO_PKG_VERSION")
         );
+        if let Some(search) = self.search {
+            title.push_str(&format!("[Search: {search}] "));
+        }
 
         // Create main block
         let block = Block::default()
             .borders(Borders::ALL)
             .title(title)
-            .border_style(Style::default().fg(Color::Green));
+            .border_style(Style::default().fg(self.theme.border));
 
         // Get inner area for content
         let inner_area = block.inner(area);
@@ -127,17 +271,38 @@ impl<'a> Widget for TaskListWidget<'a> {
             inner_area.height.saturating_sub(2)
         };
 
+        // Schedules take a title line and a few rows from the bottom of the content
+        let schedule_height = if self.schedules.is_empty() {
+            0
+        } else {
+            (self.schedules.len().min(MAX_SCHEDULE_ROWS) as u16 + 1)
+                .min(content_height.saturating_sub(2))
+        };
+        let table_height = content_height - schedule_height;
+
         // Render table content
         self.render_table_content(
             Rect {
                 x: inner_area.x,
                 y: inner_area.y,
                 width: inner_area.width,
-                height: content_height,
+                height: table_height,
             },
             buf,
         );
 
+        if schedule_height > 0 {
+            self.render_schedules(
+                Rect {
+                    x: inner_area.x,
+                    y: inner_area.y + table_height,
+                    width: inner_area.width,
+                    height: schedule_height,
+                },
+                buf,
+            );
+        }
+
         // Only render footer if there's enough space
         if inner_area.height >= 2 {
             // Render footer separator (right before the footer text)
@@ -155,9 +320,12 @@ impl<'a> Widget for TaskListWidget<'a> {
 
 impl<'a> TaskListWidget<'a> {
     fn render_table_content(&self, area: Rect, buf: &mut ratatui::buffer::Buffer) {
+        let show_usage = area.width >= USAGE_COLUMNS_MIN_WIDTH;
+        let constraints = Self::column_constraints(show_usage);
+
         if self.tasks.is_empty() {
             let rows: Vec<Row<'_>> = vec![];
-            let table = Table::new(rows, COLUMN_CONSTRAINTS).header(self.create_header_row());
+            let table = Table::new(rows, constraints).header(self.create_header_row(show_usage));
 
             ratatui::widgets::Widget::render(table, area, buf);
         } else {
@@ -166,29 +334,58 @@ impl<'a> TaskListWidget<'a> {
                 .tasks
                 .iter()
                 .map(|task| {
-                    let status_style = self.status_style(&task.status);
+                    let idle = process_state::idle_seconds(task).is_some();
+                    let status_style = if idle {
+                        Style::default().fg(self.theme.idle)
+                    } else {
+                        self.status_style(&task.status)
+                    };
 
                     let task_id = &task.id;
                     let pid = task.pid;
-                    let status = task.status.as_str();
+                    let status = process_state::status_label(task);
+                    let (cpu, mem) = self.format_usage(&task.id);
                     let timestamp = self.format_timestamp(task.started_at);
-                    let command = self.parse_command(&task.command);
+                    let mut command = self.parse_command(&task.command);
+                    if task.after_task.is_some() {
+                        // Chain steps point back at the step they run after
+                        command = format!("↳ {command}");
+                    }
+                    if let Some(ref name) = task.name {
+                        command = format!("[{name}] {command}");
+                    }
+                    if task.run_options().watch.is_some() {
+                        command = format!("⟳ {command}");
+                    }
                     let directory = task.cwd.as_deref().unwrap_or("-");
 
-                    Row::new(vec![
+                    let mut cells = vec![
                         Cell::from(format!(" {task_id}")), // Show full ID
                         Cell::from(format!(" {pid}")),
                         Cell::from(format!(" {status}")).style(status_style),
+                    ];
+                    if show_usage {
+                        cells.push(Cell::from(format!(" {cpu}")));
+                        cells.push(Cell::from(format!(" {mem}")));
+                    }
+                    cells.extend([
                         Cell::from(format!(" {timestamp}")),
-                        Cell::from(format!(" {command}")),
+                        Cell::from(Line::from(
+                            [Span::raw(" ")]
+                                .into_iter()
+                                .chain(self.level_spans(task_id))
+                                .chain([Span::raw(command)])
+                                .collect::<Vec<_>>(),
+                        )),
                         Cell::from(format!(" {directory}")),
-                    ])
+                    ]);
+                    Row::new(cells)
                 })
                 .collect();
 
-            let table = Table::new(rows, COLUMN_CONSTRAINTS)
-                .header(self.create_header_row())
-                .row_highlight_style(Style::default().bg(Color::DarkGray));
+            let table = Table::new(rows, constraints)
+                .header(self.create_header_row(show_usage))
+                .row_highlight_style(self.theme.selection());
 
             // Use a temporary table state and apply the selection
             let mut table_state = TableState::default();
@@ -197,6 +394,29 @@ impl<'a> TaskListWidget<'a> {
         }
     }
 
+    fn render_schedules(&self, area: Rect, buf: &mut ratatui::buffer::Buffer) {
+        let rows = area.height.saturating_sub(1) as usize;
+        let mut lines = vec![Line::styled(
+            format!(" Scheduled ({})", self.schedules.len()),
+            Style::default().fg(self.theme.queued),
+        )];
+        for (index, schedule) in self.schedules.iter().take(rows).enumerate() {
+            let hidden = self.schedules.len() - index - 1;
+            if index + 1 == rows && hidden > 0 {
+                lines.push(Line::from(format!(" … and {} more", hidden + 1)));
+                break;
+            }
+            lines.push(Line::from(format!(
+                " {}  {:<16} {}",
+                format_local_timestamp(schedule.next_run),
+                schedule.cron.as_deref().unwrap_or("once"),
+                self.parse_command(&schedule.command)
+            )));
+        }
+
+        Paragraph::new(lines).render(area, buf);
+    }
+
     fn render_footer_separator(
         &self,
         x: u16,
@@ -210,14 +430,16 @@ impl<'a> TaskListWidget<'a> {
         for i in 0..width {
             buf[(x + i, y)]
                 .set_symbol("─")
-                .set_style(Style::default().fg(Color::Green));
+                .set_style(Style::default().fg(self.theme.border));
         }
         buf[(x + width, y)].set_symbol("┤");
     }
 
     fn render_footer_text(&self, x: u16, y: u16, width: u16, buf: &mut ratatui::buffer::Buffer) {
-        let keybinds_text =
-            " j/k:Move  l:Log  r:Rerun  s/C-k:Stop  q:Quit  g/G:Top/Bot  C-d/u:Page";
+        let keybinds_text = self.prompt.map_or_else(
+            || self.key_map.footer(ViewMode::TaskList, FOOTER_HINTS),
+            str::to_string,
+        );
 
         // Draw the text
         for (i, ch) in keybinds_text.chars().enumerate() {
@@ -234,3 +456,14 @@ impl<'a> TaskListWidget<'a> {
         }
     }
 }
+
+/// Format a timestamp in local time, the time zone schedules are given in
+fn format_local_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
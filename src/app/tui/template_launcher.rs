@@ -0,0 +1,209 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+};
+
+use super::ViewMode;
+use super::help::bracketed_hints;
+use super::keymap::{Action, KeyMap};
+use super::theme::Theme;
+use crate::app::storage::Template;
+use crate::app::template;
+
+const NAME_COLUMN_WIDTH: u16 = 18;
+const PARAMETERS_COLUMN_WIDTH: u16 = 26;
+const COMMAND_COLUMN_MIN_WIDTH: u16 = 20;
+const DIRECTORY_COLUMN_MIN_WIDTH: u16 = 20;
+
+/// Key hints in the footer while a template is picked
+const FOOTER_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::Quit], "Quit"),
+    (&[Action::Back], "Back to list"),
+    (&[Action::Down, Action::Up], "Move"),
+    (&[Action::Select], "Start"),
+];
+
+/// Lists saved templates and asks for parameter values before starting one
+pub struct TemplateLauncherWidget<'a> {
+    templates: &'a [Template],
+    selected: usize,
+    /// Parameter values being typed for the selected template, as "name=value ..."
+    input: Option<&'a str>,
+    /// Why the last start failed
+    error: Option<&'a str>,
+    key_map: &'a KeyMap,
+    theme: Theme,
+}
+
+impl<'a> TemplateLauncherWidget<'a> {
+    pub fn new(templates: &'a [Template], selected: usize) -> Self {
+        Self {
+            templates,
+            selected,
+            input: None,
+            error: None,
+            key_map: KeyMap::builtin(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Show the parameter input line with the text typed so far
+    pub fn with_input(mut self, input: Option<&'a str>) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Show an error from the last attempt to start a template
+    pub fn with_error(mut self, error: Option<&'a str>) -> Self {
+        self.error = error;
+        self
+    }
+
+    /// Generate the key hints of the footer from a key map
+    pub fn with_key_map(mut self, key_map: &'a KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    fn format_parameters(template: &Template) -> String {
+        template::parameters(template)
+            .into_iter()
+            .map(|parameter| match parameter.default {
+                Some(default) => format!("{}={default}", parameter.name),
+                None => parameter.name,
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn format_command(command_json: &str) -> String {
+        match serde_json::from_str::<Vec<String>>(command_json) {
+            Ok(command) => command.join(" "),
+            Err(_) => command_json.to_string(),
+        }
+    }
+
+    pub fn render(self, frame: &mut Frame, area: Rect) {
+        // The input and error lines only take space when they are shown
+        let input_height = if self.input.is_some() { 3 } else { 0 };
+        let error_height = if self.error.is_some() { 1 } else { 0 };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),               // Template list
+                Constraint::Length(input_height), // Parameter input
+                Constraint::Length(error_height), // Error message
+                Constraint::Length(2),            // Footer
+            ])
+            .split(area);
+
+        self.render_templates(frame, chunks[0]);
+        if let Some(input) = self.input {
+            self.render_input(frame, chunks[1], input);
+        }
+        if let Some(error) = self.error {
+            frame.render_widget(
+                Paragraph::new(format!(" {error}")).style(Style::default().fg(self.theme.error)),
+                chunks[2],
+            );
+        }
+        self.render_footer(frame, chunks[3]);
+    }
+
+    fn render_templates(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .title(format!(" Templates ({}) ", self.templates.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.border));
+
+        if self.templates.is_empty() {
+            let hint = Paragraph::new(
+                " No templates yet. Save one with: ghost template save <name> -- <command>",
+            )
+            .style(Style::default().fg(self.theme.muted))
+            .block(block);
+            frame.render_widget(hint, area);
+            return;
+        }
+
+        let header = Row::new(vec![
+            Cell::from(" Name"),
+            Cell::from(" Parameters"),
+            Cell::from(" Command"),
+            Cell::from(" Directory"),
+        ]);
+        let rows: Vec<Row> = self
+            .templates
+            .iter()
+            .map(|template| {
+                Row::new(vec![
+                    Cell::from(format!(" {}", template.name)),
+                    Cell::from(format!(" {}", Self::format_parameters(template))),
+                    Cell::from(format!(" {}", Self::format_command(&template.command))),
+                    Cell::from(format!(" {}", template.cwd.as_deref().unwrap_or("-"))),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(NAME_COLUMN_WIDTH),
+                Constraint::Length(PARAMETERS_COLUMN_WIDTH),
+                Constraint::Min(COMMAND_COLUMN_MIN_WIDTH),
+                Constraint::Min(DIRECTORY_COLUMN_MIN_WIDTH),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .row_highlight_style(self.theme.selection());
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(self.selected.min(self.templates.len() - 1)));
+        frame.render_stateful_widget(table, area, &mut table_state);
+    }
+
+    fn render_input(&self, frame: &mut Frame, area: Rect, input: &str) {
+        let name = self
+            .templates
+            .get(self.selected)
+            .map_or("", |template| template.name.as_str());
+        let block = Block::default()
+            .title(format!(" Parameters for {name} "))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.key));
+
+        frame.render_widget(Paragraph::new(format!(" {input}█")).block(block), area);
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        // The parameter input line takes fixed keys, like any text input
+        let hints = if self.input.is_some() {
+            vec![
+                ("Enter".to_string(), "Start"),
+                ("Esc".to_string(), "Cancel"),
+            ]
+        } else {
+            self.key_map.hints(ViewMode::TemplateLauncher, FOOTER_HINTS)
+        };
+
+        let keybind_paragraph = Paragraph::new(bracketed_hints(&hints, &self.theme))
+            .style(Style::default())
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM)
+                    .border_style(Style::default().fg(self.theme.panel_border)),
+            );
+
+        frame.render_widget(keybind_paragraph, area);
+    }
+}
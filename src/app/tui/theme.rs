@@ -0,0 +1,316 @@
+use std::str::FromStr;
+
+use ratatui::style::{Color, Style};
+
+use crate::app::storage::task_status::TaskStatus;
+
+/// Colors of the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub queued: Color,
+    pub running: Color,
+    pub exited: Color,
+    pub killed: Color,
+    pub cancelled: Color,
+    pub unknown: Color,
+    /// Running tasks that produced no output for their idle timeout
+    pub idle: Color,
+    /// Frame of the task list, notifications and template launcher
+    pub border: Color,
+    /// Frames of the details sections, footers and popups
+    pub panel_border: Color,
+    pub log_border: Color,
+    /// Selected row; a `reset` foreground keeps the colors of the row
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    /// Search matches in the log viewer; the current one stands out
+    pub match_fg: Color,
+    pub match_bg: Color,
+    pub current_match_bg: Color,
+    /// Keys in footers and the help overlay
+    pub key: Color,
+    /// Hints, line numbers and other secondary text
+    pub muted: Color,
+    /// PIDs and protocols
+    pub accent: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        queued: Color::Cyan,
+        running: Color::Green,
+        exited: Color::Blue,
+        killed: Color::Red,
+        cancelled: Color::DarkGray,
+        unknown: Color::Gray,
+        idle: Color::Yellow,
+        border: Color::Green,
+        panel_border: Color::Cyan,
+        log_border: Color::LightMagenta,
+        selection_fg: Color::Reset,
+        selection_bg: Color::DarkGray,
+        match_fg: Color::Black,
+        match_bg: Color::Yellow,
+        current_match_bg: Color::LightRed,
+        key: Color::Yellow,
+        muted: Color::DarkGray,
+        accent: Color::Blue,
+        success: Color::Green,
+        warning: Color::Yellow,
+        error: Color::Red,
+    };
+
+    /// For terminals with a light background, avoiding yellow and light text
+    pub const LIGHT: Theme = Theme {
+        queued: Color::Magenta,
+        running: Color::Green,
+        exited: Color::Blue,
+        killed: Color::Red,
+        cancelled: Color::DarkGray,
+        unknown: Color::DarkGray,
+        idle: Color::Magenta,
+        border: Color::Blue,
+        panel_border: Color::Blue,
+        log_border: Color::Magenta,
+        selection_fg: Color::Black,
+        selection_bg: Color::Gray,
+        match_fg: Color::Black,
+        match_bg: Color::Yellow,
+        current_match_bg: Color::LightRed,
+        key: Color::Magenta,
+        muted: Color::DarkGray,
+        accent: Color::Blue,
+        success: Color::Green,
+        warning: Color::Magenta,
+        error: Color::Red,
+    };
+
+    /// Bright colors only, and black on white for the selection
+    pub const HIGH_CONTRAST: Theme = Theme {
+        queued: Color::LightCyan,
+        running: Color::LightGreen,
+        exited: Color::LightBlue,
+        killed: Color::LightRed,
+        cancelled: Color::White,
+        unknown: Color::White,
+        idle: Color::LightYellow,
+        border: Color::White,
+        panel_border: Color::White,
+        log_border: Color::White,
+        selection_fg: Color::Black,
+        selection_bg: Color::White,
+        match_fg: Color::Black,
+        match_bg: Color::LightYellow,
+        current_match_bg: Color::LightMagenta,
+        key: Color::LightYellow,
+        muted: Color::Gray,
+        accent: Color::LightCyan,
+        success: Color::LightGreen,
+        warning: Color::LightYellow,
+        error: Color::LightRed,
+    };
+
+    /// The colors of a preset with the `[tui.colors]` of the config file
+    /// applied over them
+    pub fn new(preset: ThemePreset, colors: Option<&toml::Table>) -> Result<Self, String> {
+        let mut theme = preset.theme();
+        for (name, value) in colors.into_iter().flatten() {
+            let color = value.as_str().ok_or_else(|| {
+                format!("expected a color for `{name}`, found {}", value.type_str())
+            })?;
+            *theme.color_mut(name)? = parse_color(color)?;
+        }
+        Ok(theme)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Result<&mut Color, String> {
+        Ok(match name {
+            "queued" => &mut self.queued,
+            "running" => &mut self.running,
+            "exited" => &mut self.exited,
+            "killed" => &mut self.killed,
+            "cancelled" => &mut self.cancelled,
+            "unknown" => &mut self.unknown,
+            "idle" => &mut self.idle,
+            "border" => &mut self.border,
+            "panel_border" => &mut self.panel_border,
+            "log_border" => &mut self.log_border,
+            "selection_fg" => &mut self.selection_fg,
+            "selection_bg" => &mut self.selection_bg,
+            "match_fg" => &mut self.match_fg,
+            "match_bg" => &mut self.match_bg,
+            "current_match_bg" => &mut self.current_match_bg,
+            "key" => &mut self.key,
+            "muted" => &mut self.muted,
+            "accent" => &mut self.accent,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            _ => {
+                return Err(format!(
+                    "unknown color `{name}`; valid colors: {}",
+                    COLOR_NAMES.join(", ")
+                ));
+            }
+        })
+    }
+
+    /// Color of a task status
+    pub fn status(&self, status: TaskStatus) -> Color {
+        match status {
+            TaskStatus::Queued | TaskStatus::Waiting | TaskStatus::Starting => self.queued,
+            TaskStatus::Running => self.running,
+            TaskStatus::Exited => self.exited,
+            TaskStatus::Killed => self.killed,
+            TaskStatus::Cancelled => self.cancelled,
+            TaskStatus::Unknown => self.unknown,
+        }
+    }
+
+    /// Style of the selected row of a table
+    pub fn selection(&self) -> Style {
+        let style = Style::default().bg(self.selection_bg);
+        match self.selection_fg {
+            Color::Reset => style,
+            fg => style.fg(fg),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+/// Names of the colors in `[tui.colors]`
+const COLOR_NAMES: &[&str] = &[
+    "queued",
+    "running",
+    "exited",
+    "killed",
+    "cancelled",
+    "unknown",
+    "idle",
+    "border",
+    "panel_border",
+    "log_border",
+    "selection_fg",
+    "selection_bg",
+    "match_fg",
+    "match_bg",
+    "current_match_bg",
+    "key",
+    "muted",
+    "accent",
+    "success",
+    "warning",
+    "error",
+];
+
+/// A color by name ("red", "light-blue", "dark-gray", "reset"), as "#rrggbb",
+/// or as an index into the 256-color palette
+fn parse_color(color: &str) -> Result<Color, String> {
+    Color::from_str(color).map_err(|_| {
+        format!(
+            "Invalid color: {color}. Use a name like red or light-blue, #rrggbb, or a number up to 255"
+        )
+    })
+}
+
+/// A built-in theme the colors of the config file are applied over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThemePreset::Dark => "dark",
+            ThemePreset::Light => "light",
+            ThemePreset::HighContrast => "high-contrast",
+        }
+    }
+
+    fn theme(&self) -> Theme {
+        match self {
+            ThemePreset::Dark => Theme::DARK,
+            ThemePreset::Light => Theme::LIGHT,
+            ThemePreset::HighContrast => Theme::HIGH_CONTRAST,
+        }
+    }
+}
+
+impl std::fmt::Display for ThemePreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for ThemePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(ThemePreset::Dark),
+            "light" => Ok(ThemePreset::Light),
+            "high-contrast" => Ok(ThemePreset::HighContrast),
+            _ => Err(format!(
+                "Invalid theme: {s}. Valid options: dark, light, high-contrast"
+            )),
+        }
+    }
+}
+
+/// Check a `[tui.colors]` table
+pub fn check_colors(value: &toml::Value) -> Result<(), String> {
+    let colors = value
+        .as_table()
+        .ok_or_else(|| format!("expected a table, found {}", value.type_str()))?;
+    Theme::new(ThemePreset::default(), Some(colors)).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_override_the_preset() {
+        let colors: toml::Table = r##"
+            running = "light-green"
+            border = "#ff8800"
+            selection_bg = "236"
+        "##
+        .parse()
+        .unwrap();
+        let theme = Theme::new(ThemePreset::Light, Some(&colors)).unwrap();
+        assert_eq!(theme.running, Color::LightGreen);
+        assert_eq!(theme.border, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.selection_bg, Color::Indexed(236));
+        assert_eq!(theme.killed, Theme::LIGHT.killed);
+        assert_eq!(theme.status(TaskStatus::Waiting), theme.queued);
+
+        for preset in ["dark", "light", "high-contrast"] {
+            assert_eq!(preset.parse::<ThemePreset>().unwrap().as_str(), preset);
+        }
+        assert_eq!(Theme::default(), Theme::DARK);
+    }
+
+    #[test]
+    fn test_color_errors() {
+        let error = |colors: &str| {
+            let colors: toml::Table = colors.parse().unwrap();
+            Theme::new(ThemePreset::Dark, Some(&colors)).unwrap_err()
+        };
+        assert!(error("background = \"black\"").contains("unknown color `background`"));
+        assert!(error("running = \"greenish\"").contains("Invalid color: greenish"));
+        assert!(error("running = 2").contains("expected a color for `running`"));
+    }
+}
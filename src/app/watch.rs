@@ -0,0 +1,270 @@
+//! Restarting tasks when watched files change
+//!
+//! A task started with `--watch` runs under a supervisor, which watches the
+//! given paths and restarts the task once changes have settled for the
+//! debounce interval. The supervisor keeps watching after the task has exited
+//! on its own, so the next change starts it again, until `ghost stop`.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::app::config;
+use crate::app::error::{GhostError, Result};
+use crate::app::process;
+use crate::app::storage::{self, Task};
+
+/// Debounce interval used when `--watch-debounce` is not given
+pub const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+/// Directories never worth restarting for
+const ALWAYS_IGNORED: [&str; 1] = [".git"];
+
+/// What a task watches, stored with its run options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchOptions {
+    /// Files or directories to watch; relative paths are relative to the task's directory
+    pub paths: Vec<PathBuf>,
+    /// Patterns of paths whose changes are ignored, e.g. "target/" or "*.log"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// How long changes have to settle before the task is restarted
+    pub debounce_ms: u64,
+}
+
+impl WatchOptions {
+    /// Whether a change to `path` should be ignored
+    ///
+    /// A pattern containing a slash matches paths below it, relative to `base`;
+    /// any other pattern matches a single path component, with `*` and `?`
+    /// wildcards.
+    pub fn is_ignored(&self, path: &Path, base: &Path) -> bool {
+        let relative = path.strip_prefix(base).unwrap_or(path);
+        let components: Vec<&str> = relative
+            .components()
+            .filter_map(|component| component.as_os_str().to_str())
+            .collect();
+
+        ALWAYS_IGNORED
+            .iter()
+            .copied()
+            .chain(self.ignore.iter().map(String::as_str))
+            .any(|pattern| {
+                let trimmed = pattern.trim_end_matches('/');
+                if trimmed.is_empty() {
+                    return false;
+                }
+                if trimmed.contains('/') {
+                    let prefix = Path::new(trimmed.trim_start_matches("./"));
+                    relative.starts_with(prefix) || path.starts_with(prefix)
+                } else {
+                    components
+                        .iter()
+                        .any(|component| wildcard_match(trimmed, component))
+                }
+            })
+    }
+
+    /// Short description for `ghost status`, the TUI and `ghost watch list`
+    pub fn describe(&self) -> String {
+        let paths: Vec<String> = self
+            .paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        let mut description = paths.join(", ");
+        if !self.ignore.is_empty() {
+            description.push_str(&format!(" (ignoring {})", self.ignore.join(", ")));
+        }
+        description
+    }
+}
+
+/// Match a name against a pattern with `*` (any run of characters) and `?` (one character)
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Watches a task's paths and reports when a debounced change is due
+pub struct FileWatcher {
+    options: WatchOptions,
+    base: PathBuf,
+    log_dir: PathBuf,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// The most recent relevant change, and when it happened
+    pending: Option<(PathBuf, Instant)>,
+    // Dropping the watcher stops the notifications
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    /// Start watching; relative paths are resolved against `cwd`
+    pub fn new(options: &WatchOptions, cwd: Option<&Path>) -> Result<Self> {
+        let base = match cwd {
+            Some(cwd) => cwd.to_path_buf(),
+            None => std::env::current_dir()?,
+        };
+
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|e| GhostError::FileWatch {
+            message: format!("Failed to create file watcher: {e}"),
+        })?;
+
+        for path in &options.paths {
+            let path = base.join(path);
+            watcher
+                .watch(&path, RecursiveMode::Recursive)
+                .map_err(|e| GhostError::FileWatch {
+                    message: format!("Failed to watch {}: {e}", path.display()),
+                })?;
+        }
+
+        Ok(Self {
+            options: options.clone(),
+            base,
+            log_dir: config::get_log_dir(),
+            events,
+            pending: None,
+            _watcher: watcher,
+        })
+    }
+
+    /// The changed path that should trigger a restart now, if changes have settled
+    pub fn poll(&mut self) -> Option<PathBuf> {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else { continue };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                // The task's own log must not restart it
+                if path.starts_with(&self.log_dir) || self.options.is_ignored(&path, &self.base) {
+                    continue;
+                }
+                self.pending = Some((path, Instant::now()));
+            }
+        }
+
+        let debounce = Duration::from_millis(self.options.debounce_ms);
+        match &self.pending {
+            Some((_, changed_at)) if changed_at.elapsed() >= debounce => {
+                self.pending.take().map(|(path, _)| path)
+            }
+            _ => None,
+        }
+    }
+
+    /// Forget changes seen so far, e.g. those made while the task was restarting
+    pub fn clear(&mut self) {
+        while self.events.try_recv().is_ok() {}
+        self.pending = None;
+    }
+}
+
+/// Tasks whose supervisor is still watching their files
+pub fn active_watchers(conn: &Connection) -> Result<Vec<Task>> {
+    Ok(storage::get_tasks(conn, None)?
+        .into_iter()
+        .filter(is_watching)
+        .collect())
+}
+
+/// Whether a task is in watch mode and its supervisor is still alive
+pub fn is_watching(task: &Task) -> bool {
+    task.run_options().watch.is_some() && task.supervisor_pid.is_some_and(process::exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(ignore: &[&str]) -> WatchOptions {
+        WatchOptions {
+            paths: vec![PathBuf::from("src")],
+            ignore: ignore.iter().map(|pattern| pattern.to_string()).collect(),
+            debounce_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let base = Path::new("/project");
+        let options = options(&["target/", "*.log", "build/generated"]);
+
+        assert!(options.is_ignored(Path::new("/project/target/debug/app"), base));
+        assert!(options.is_ignored(Path::new("/project/src/server.log"), base));
+        assert!(options.is_ignored(Path::new("/project/build/generated/x.rs"), base));
+        assert!(options.is_ignored(Path::new("/project/.git/index"), base));
+
+        assert!(!options.is_ignored(Path::new("/project/src/main.rs"), base));
+        assert!(!options.is_ignored(Path::new("/project/build/main.rs"), base));
+        assert!(!options.is_ignored(Path::new("/project/src/logger.rs"), base));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.rs", "main.rs"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("file?.txt", "file1.txt"));
+        assert!(wildcard_match("*~", "main.rs~"));
+        assert!(!wildcard_match("*.rs", "main.rs.bak"));
+        assert!(!wildcard_match("file?.txt", "file10.txt"));
+    }
+
+    #[test]
+    fn test_watcher_reports_settled_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(dir.join("src")).unwrap();
+        std::fs::create_dir(dir.join("target")).unwrap();
+        let mut watcher = FileWatcher::new(
+            &WatchOptions {
+                paths: vec![PathBuf::from("src"), PathBuf::from("target")],
+                ignore: vec!["target/".to_string()],
+                debounce_ms: 100,
+            },
+            Some(&dir),
+        )
+        .unwrap();
+
+        let wait_for_trigger = |watcher: &mut FileWatcher| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if let Some(path) = watcher.poll() {
+                    return Some(path);
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            None
+        };
+
+        std::fs::write(dir.join("target/out.o"), "ignored").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        let trigger = wait_for_trigger(&mut watcher).unwrap();
+        assert_eq!(trigger, dir.join("src/main.rs"));
+    }
+}
@@ -2,7 +2,19 @@ use clap::{Parser, Subcommand};
 use rusqlite::Connection;
 use std::path::PathBuf;
 
-use ghost::app::{commands, config, error::Result, logging, storage};
+use ghost::app::{
+    commands, config,
+    error::{GhostError, Result},
+    helpers,
+    log_follow::FollowTargets,
+    log_grep::{GrepMode, LogFilterArgs},
+    log_reader::{self, LogQueryArgs},
+    logging, queue,
+    run_options::{RunOptionArgs, RunOptions},
+    scheduler,
+    storage::{self, AfterCondition},
+    supervisor,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "ghost")]
@@ -35,23 +47,163 @@ enum Commands {
         /// Environment variables (KEY=VALUE format)
         #[arg(short, long)]
         env: Vec<String>,
+
+        /// Add the command(s) to this queue instead of starting them right away
+        #[arg(short, long, value_name = "NAME")]
+        queue: Option<String>,
+
+        /// How many tasks of the queue may run at once (kept for later additions)
+        #[arg(long, value_name = "N", requires = "queue", value_parser = clap::value_parser!(u32).range(1..))]
+        parallel: Option<u32>,
+
+        /// Start only once this task has exited successfully
+        #[arg(long, value_name = "TASK_ID", conflicts_with_all = ["queue", "after_failure"])]
+        after_success: Option<String>,
+
+        /// Start only once this task has failed or was killed
+        #[arg(long, value_name = "TASK_ID", conflicts_with = "queue")]
+        after_failure: Option<String>,
+
+        /// Start at this local time instead of now (HH:MM or YYYY-MM-DD HH:MM)
+        #[arg(long, value_name = "TIME", conflicts_with_all = ["queue", "after_success", "after_failure", "in_"])]
+        at: Option<String>,
+
+        /// Start after this delay instead of now (e.g. 30s, 10m, 2h)
+        #[arg(long = "in", value_name = "DURATION", conflicts_with_all = ["queue", "after_success", "after_failure"])]
+        in_: Option<String>,
+
+        /// Name shown for the task(s), which `ghost log` also accepts in place of an ID
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["queue", "after_success", "after_failure", "at", "in_"])]
+        name: Option<String>,
+
+        #[command(flatten)]
+        options: RunOptionArgs,
+    },
+
+    /// Run commands one after another; a failing step cancels the rest
+    ///
+    /// Example: ghost chain "make migrate" "make seed" "make serve"
+    Chain {
+        /// Commands to run in order; quote each command
+        #[arg(required = true)]
+        commands: Vec<String>,
+
+        /// Working directory for the commands
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+
+        /// Environment variables (KEY=VALUE format)
+        #[arg(short, long)]
+        env: Vec<String>,
+
+        #[command(flatten)]
+        options: RunOptionArgs,
+    },
+
+    /// Manage queues of tasks that run a limited number at a time
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommands,
+    },
+
+    /// Manage commands that run on a cron schedule
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+
+    /// Manage saved command templates
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+
+    /// Manage search presets for the TUI task list
+    Filter {
+        #[command(subcommand)]
+        command: FilterCommands,
+    },
+
+    /// Start a saved template, appending any extra arguments to its command
+    ///
+    /// Example: ghost start api --param port=8080 --release
+    Start {
+        /// Name of the template
+        template: String,
+
+        /// Value for a {{placeholder}} in the template (NAME=VALUE, repeatable)
+        #[arg(short, long, value_name = "NAME=VALUE")]
+        param: Vec<String>,
+
+        /// Extra arguments appended to the template's command; options of
+        /// `ghost start` must come before them
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Inspect tasks started with --watch
+    Watch {
+        #[command(subcommand)]
+        command: WatchCommands,
+    },
+
+    /// Run due schedules until interrupted; started automatically when needed
+    Scheduler {
+        /// Exit once no schedules are left
+        #[arg(long)]
+        exit_when_idle: bool,
     },
 
     /// List all background processes
     List {
-        /// Filter by status (running, exited, killed)
+        /// Filter by status (queued, waiting, starting, running, exited, killed, cancelled)
         #[arg(short, long)]
         status: Option<String>,
     },
 
+    /// Search the logs of all tasks, printing task:line: text for each match
+    ///
+    /// Example: ghost grep EADDRINUSE --since 1d
+    Grep {
+        /// Regular expression to search for
+        pattern: String,
+
+        /// Match upper and lower case alike
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Print the number of matching lines per task instead of the lines
+        #[arg(short, long, conflicts_with = "files_with_matches")]
+        count: bool,
+
+        /// Print only the log files that match
+        #[arg(short = 'l', long)]
+        files_with_matches: bool,
+
+        #[command(flatten)]
+        filter: LogFilterArgs,
+    },
+
     /// Show logs for a process
     Log {
-        /// Task ID to show logs for
-        task_id: String,
+        /// Task IDs or names to show logs for; several are merged with [name] prefixes
+        #[arg(value_name = "TASK", required_unless_present = "all_running")]
+        task_ids: Vec<String>,
 
-        /// Follow log output (like tail -f)
+        /// Follow log output (like tail -f) until the tasks have finished
         #[arg(short, long)]
         follow: bool,
+
+        /// Follow every running task, including ones started while following
+        #[arg(long, requires = "follow", conflicts_with = "task_ids")]
+        all_running: bool,
+
+        /// Strip ANSI colors and collapse progress bar redraws
+        #[arg(long)]
+        no_color: bool,
+
+        #[command(flatten)]
+        query: LogQueryArgs,
     },
 
     /// Stop a background process
@@ -70,13 +222,32 @@ enum Commands {
         task_id: String,
     },
 
+    /// Show CPU, memory, thread and I/O usage of a running task
+    Stats {
+        /// Task ID to sample
+        task_id: String,
+    },
+
+    /// Show the processes spawned by a running task
+    Tree {
+        /// Task ID to inspect
+        task_id: String,
+    },
+
+    /// Continuously show resource usage of all running tasks
+    Top {
+        /// Refresh interval (e.g. 500ms, 2s)
+        #[arg(short, long, default_value = "1s")]
+        interval: String,
+    },
+
     /// Clean up old finished tasks
     Cleanup {
-        /// Delete tasks older than this many days (default: 30)
-        #[arg(short, long, default_value = "30")]
-        days: u64,
+        /// Delete tasks older than this many days (default: cleanup.days of the config, 30)
+        #[arg(short, long)]
+        days: Option<u64>,
 
-        /// Filter by status (exited, killed, all). Default: exited,killed
+        /// Filter by status (exited, killed, cancelled, all). Default: exited,killed,cancelled
         #[arg(short, long)]
         status: Option<String>,
 
@@ -89,8 +260,240 @@ enum Commands {
         all: bool,
     },
 
+    /// Check for problems left behind by past tasks
+    Doctor {
+        /// Find processes left behind by finished or deleted tasks (default when no check is given)
+        #[arg(long)]
+        orphans: bool,
+
+        /// Kill the orphaned processes that were found
+        #[arg(long)]
+        kill: bool,
+    },
+
+    /// Show and change settings of the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
     /// Run MCP server for ghost operations
     Mcp,
+
+    /// Supervise a task on behalf of `ghost run` (internal)
+    #[command(hide = true)]
+    Supervise,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum QueueCommands {
+    /// Add a command to the end of a queue
+    Add {
+        /// Command to run
+        #[arg(required = true)]
+        command: Vec<String>,
+
+        /// Queue to add the command to
+        #[arg(short, long, value_name = "NAME", default_value = storage::DEFAULT_QUEUE)]
+        queue: String,
+
+        /// How many tasks of the queue may run at once (kept for later additions)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        parallel: Option<u32>,
+
+        /// Working directory for the command
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+
+        /// Environment variables (KEY=VALUE format)
+        #[arg(short, long)]
+        env: Vec<String>,
+
+        #[command(flatten)]
+        options: RunOptionArgs,
+    },
+
+    /// List queues and their waiting tasks
+    List {
+        /// Only show this queue
+        queue: Option<String>,
+    },
+
+    /// Stop starting tasks from a queue; running tasks keep running
+    Pause {
+        /// Queue to pause
+        #[arg(default_value = storage::DEFAULT_QUEUE)]
+        queue: String,
+    },
+
+    /// Resume a paused queue
+    Resume {
+        /// Queue to resume
+        #[arg(default_value = storage::DEFAULT_QUEUE)]
+        queue: String,
+    },
+
+    /// Remove all waiting tasks from a queue
+    Clear {
+        /// Queue to clear
+        #[arg(default_value = storage::DEFAULT_QUEUE)]
+        queue: String,
+    },
+
+    /// Move a waiting task to another position in its queue
+    Move {
+        /// Task ID to move
+        task_id: String,
+
+        /// New position, starting at 1 for the next task to run
+        position: usize,
+    },
+
+    /// Set how many tasks of a queue may run at once
+    Parallel {
+        /// Queue to configure
+        queue: String,
+
+        /// Maximum number of running tasks
+        #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+        parallel: u32,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum TemplateCommands {
+    /// Save a command under a name; {{name}} or {{name:default}} mark parameters
+    ///
+    /// Example: ghost template save api -c ~/work/api -e RUST_LOG=debug -- cargo run --bin api
+    Save {
+        /// Name of the template; an existing template with this name is replaced
+        name: String,
+
+        /// Command to run
+        #[arg(required = true, last = true)]
+        command: Vec<String>,
+
+        /// Working directory for the command
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+
+        /// Environment variables (KEY=VALUE format)
+        #[arg(short, long)]
+        env: Vec<String>,
+
+        #[command(flatten)]
+        options: RunOptionArgs,
+    },
+
+    /// List saved templates and their parameters
+    List,
+
+    /// Remove a saved template
+    #[command(alias = "remove")]
+    Rm {
+        /// Name of the template to remove
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum FilterCommands {
+    /// Save a search under a name, used as @name in the TUI search
+    ///
+    /// Example: ghost filter save api status:running cwd:~/work/api
+    Save {
+        /// Name of the preset; an existing preset with this name is replaced
+        name: String,
+
+        /// Search terms, e.g. status:running cwd:~/api port:8080 cargo
+        #[arg(required = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+
+    /// List saved search presets
+    List,
+
+    /// Remove a saved search preset
+    #[command(alias = "remove")]
+    Rm {
+        /// Name of the preset to remove
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// Print the value of a key, e.g. stop.signal
+    Get {
+        /// Key as section.name
+        key: String,
+    },
+
+    /// Set a key in the user config file
+    ///
+    /// Example: ghost config set stop.timeout 10
+    Set {
+        /// Key as section.name
+        key: String,
+
+        /// Value as TOML, e.g. 10, true or ["*TOKEN*"]; other text is taken as a string
+        value: String,
+
+        /// Set it in the project's .ghost.toml instead
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// List every key with its value and where it comes from
+    List,
+
+    /// Print the path of the user config file and of the project one, if any
+    Path,
+}
+
+#[derive(Subcommand, Debug)]
+enum WatchCommands {
+    /// List tasks whose files are being watched
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum ScheduleCommands {
+    /// Run a command every time a cron expression matches
+    ///
+    /// Example: ghost schedule add "0 * * * *" -- ./backup.sh
+    Add {
+        /// Cron expression in local time (minute hour day month weekday) or @hourly, @daily, ...
+        cron: String,
+
+        /// Command to run
+        #[arg(required = true)]
+        command: Vec<String>,
+
+        /// Working directory for the command
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+
+        /// Environment variables (KEY=VALUE format)
+        #[arg(short, long)]
+        env: Vec<String>,
+
+        #[command(flatten)]
+        options: RunOptionArgs,
+    },
+
+    /// List schedules and delayed starts with their next run time
+    List,
+
+    /// Remove a schedule; tasks it already started keep running
+    #[command(alias = "remove")]
+    Rm {
+        /// Schedule ID to remove
+        schedule_id: String,
+    },
 }
 
 #[tokio::main]
@@ -98,45 +501,197 @@ async fn main() {
     let cli = Cli::parse();
 
     let result = match cli.command {
+        // Works with a broken config file too, so it can be fixed
+        Some(Commands::Config { command }) => run_config_command(command),
         Some(cmd) => {
+            // A broken config file is reported before anything runs; the supervisor
+            // of a task that is already running carries on with the defaults
+            let checked = if matches!(cmd, Commands::Supervise) {
+                Ok(())
+            } else {
+                config::FileConfig::load().map(drop)
+            };
+
             // Initialize database connection once for all commands (except TUI)
-            match storage::init_database() {
-                Ok(conn) => match cmd {
-                    Commands::Run { commands, cwd, env } => run_commands(&conn, commands, cwd, env),
-                    Commands::List { status } => commands::list(&conn, status, true).map(|_| ()),
-                    Commands::Log { task_id, follow } => {
-                        commands::log(&conn, &task_id, follow, true)
-                            .await
-                            .map(|_| ())
-                    }
-                    Commands::Stop { task_id, force } => {
-                        commands::stop(&conn, &task_id, force, true)
+            match checked.and_then(|()| storage::init_database()) {
+                Ok(conn) => {
+                    // Without a daemon, every invocation gives due schedules and waiting
+                    // queued tasks a chance to start
+                    if !matches!(cmd, Commands::Supervise) {
+                        if let Err(e) = scheduler::fire_due(&conn) {
+                            eprintln!("Warning: failed to run due schedules: {e}");
+                        }
+                        if let Err(e) = queue::promote(&conn) {
+                            eprintln!("Warning: failed to start queued tasks: {e}");
+                        }
                     }
-                    Commands::Status { task_id } => {
-                        commands::status(&conn, &task_id, true).map(|_| ())
-                    }
-                    Commands::Cleanup {
-                        days,
-                        status,
-                        dry_run,
-                        all,
-                    } => commands::cleanup(&conn, days, status, dry_run, all),
-                    Commands::Mcp => {
-                        // Initialize file logger for MCP server
-                        let log_dir = config::get_log_dir();
-                        let _guard = logging::init_file_logger(&log_dir);
-
-                        ghost::mcp::run_stdio_server(conn).await.map_err(|e| {
-                            ghost::app::error::GhostError::Config {
-                                message: e.to_string(),
+
+                    match cmd {
+                        Commands::Run {
+                            commands,
+                            cwd,
+                            env,
+                            queue,
+                            parallel,
+                            after_success,
+                            after_failure,
+                            at,
+                            in_,
+                            name,
+                            options,
+                        } => options.into_options().and_then(|options| {
+                            if let Some(start_at) = start_time(at.as_deref(), in_.as_deref())? {
+                                return run_later(&conn, start_at, commands, cwd, env, &options);
                             }
-                        })
+                            let after =
+                                after_success
+                                    .map(|task_id| (task_id, AfterCondition::Success))
+                                    .or(after_failure
+                                        .map(|task_id| (task_id, AfterCondition::Failure)));
+                            match (queue, after) {
+                                (Some(queue), _) => queue_commands(
+                                    &conn, &queue, parallel, commands, cwd, env, &options,
+                                ),
+                                (None, Some(after)) => {
+                                    run_after(&conn, after, commands, cwd, env, &options)
+                                }
+                                (None, None) => run_commands(
+                                    &conn,
+                                    commands,
+                                    cwd,
+                                    env,
+                                    &options,
+                                    name.as_deref(),
+                                ),
+                            }
+                        }),
+                        Commands::Chain {
+                            commands,
+                            cwd,
+                            env,
+                            options,
+                        } => options.into_options().and_then(|options| {
+                            commands::chain(&conn, commands, cwd, env, &options, true).map(|_| ())
+                        }),
+                        Commands::Queue { command } => run_queue_command(&conn, command),
+                        Commands::Schedule { command } => run_schedule_command(&conn, command),
+                        Commands::Template { command } => run_template_command(&conn, command),
+                        Commands::Filter { command } => run_filter_command(&conn, command),
+                        Commands::Start {
+                            template,
+                            param,
+                            args,
+                        } => commands::start_template(&conn, &template, &param, args, true)
+                            .map(|_| ()),
+                        Commands::Watch {
+                            command: WatchCommands::List,
+                        } => commands::watch_list(&conn, true).map(|_| ()),
+                        Commands::Scheduler { exit_when_idle } => {
+                            // An automatically started scheduler has no terminal
+                            let log_dir = config::get_log_dir();
+                            let _guard = logging::init_file_logger(&log_dir);
+
+                            scheduler::run(&conn, exit_when_idle)
+                        }
+                        Commands::List { status } => {
+                            commands::list(&conn, status, true).map(|_| ())
+                        }
+                        Commands::Grep {
+                            pattern,
+                            ignore_case,
+                            count,
+                            files_with_matches,
+                            filter,
+                        } => run_grep(
+                            &conn,
+                            &pattern,
+                            ignore_case,
+                            count,
+                            files_with_matches,
+                            filter,
+                        ),
+                        Commands::Log {
+                            task_ids,
+                            follow,
+                            all_running,
+                            no_color,
+                            query,
+                        } => match query.into_query(no_color) {
+                            Ok(query) if follow || task_ids.len() != 1 => {
+                                let targets = if all_running {
+                                    FollowTargets::AllRunning
+                                } else {
+                                    FollowTargets::Tasks(task_ids)
+                                };
+                                commands::log_follow(&conn, &targets, &query, follow, true).await
+                            }
+                            Ok(query) => {
+                                commands::log(&conn, &task_ids[0], &query, true).map(|_| ())
+                            }
+                            Err(e) => Err(e),
+                        },
+                        Commands::Stop { task_id, force } => {
+                            commands::stop(&conn, &task_id, force, true).map(|_| ())
+                        }
+                        Commands::Status { task_id } => {
+                            commands::status(&conn, &task_id, true).map(|_| ())
+                        }
+                        Commands::Stats { task_id } => {
+                            commands::stats(&conn, &task_id, true).map(|_| ())
+                        }
+                        Commands::Tree { task_id } => {
+                            commands::tree(&conn, &task_id, true).map(|_| ())
+                        }
+                        Commands::Top { interval } => match helpers::parse_duration(&interval) {
+                            Ok(interval) => commands::top(&conn, interval).await,
+                            Err(e) => Err(e),
+                        },
+                        Commands::Cleanup {
+                            days,
+                            status,
+                            dry_run,
+                            all,
+                        } => commands::cleanup(
+                            &conn,
+                            days.unwrap_or_else(|| config::FileConfig::current().cleanup.days()),
+                            status,
+                            dry_run,
+                            all,
+                        ),
+                        // Orphan detection is currently the only check
+                        Commands::Doctor { orphans: _, kill } => {
+                            commands::doctor_orphans(&conn, kill, true).map(|_| ())
+                        }
+                        Commands::Config { .. } => {
+                            unreachable!("`ghost config` runs before the config files are checked")
+                        }
+                        Commands::Mcp => {
+                            // Initialize file logger for MCP server
+                            let log_dir = config::get_log_dir();
+                            let _guard = logging::init_file_logger(&log_dir);
+
+                            ghost::mcp::run_stdio_server(conn).await.map_err(|e| {
+                                GhostError::Config {
+                                    message: e.to_string(),
+                                }
+                            })
+                        }
+                        Commands::Supervise => {
+                            // The supervisor has no terminal, so it logs to a file
+                            let log_dir = config::get_log_dir();
+                            let _guard = logging::init_file_logger(&log_dir);
+
+                            supervisor::run(&conn)
+                        }
                     }
-                },
+                }
                 Err(e) => Err(e),
             }
         }
-        None => commands::tui().await,
+        None => match config::FileConfig::load() {
+            Ok(_) => commands::tui().await,
+            Err(e) => Err(e),
+        },
     };
 
     if let Err(e) = result {
@@ -154,9 +709,11 @@ fn run_commands(
     args: Vec<String>,
     cwd: Option<PathBuf>,
     env: Vec<String>,
+    options: &RunOptions,
+    name: Option<&str>,
 ) -> Result<()> {
     if args.is_empty() {
-        return Err(ghost::app::error::GhostError::InvalidArgument {
+        return Err(GhostError::InvalidArgument {
             message: "No command specified".to_string(),
         });
     }
@@ -169,10 +726,258 @@ fn run_commands(
     if is_multi_command {
         // Multi-command mode: each argument is a complete command string
         // Note: Error messages are printed by spawn_multi, so we don't need to handle failures here
-        let _ = commands::spawn_multi(conn, args, cwd, env, true);
+        let results = commands::spawn_multi_with_options(conn, args, cwd, env, options, true);
+        if let Some(name) = name {
+            for info in results
+                .iter()
+                .filter_map(|spawned| spawned.result.as_ref().ok())
+            {
+                storage::set_task_name(conn, &info.id, name)?;
+            }
+        }
         Ok(())
     } else {
         // Single-command mode: all arguments form one command (backward compatible)
-        commands::spawn(conn, args, cwd, env, true).map(|_| ())
+        let info = commands::spawn_with_options(conn, args, cwd, env, options, true)?;
+        if let Some(name) = name {
+            storage::set_task_name(conn, &info.id, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run one or more commands once another task has finished, using the same formats as `ghost run`
+fn run_after(
+    conn: &Connection,
+    (after_task, condition): (String, AfterCondition),
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+) -> Result<()> {
+    for command in split_commands(args)? {
+        commands::spawn_after(
+            conn,
+            command,
+            cwd.clone(),
+            env.clone(),
+            options,
+            &after_task,
+            condition,
+            true,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Search task logs, exiting with status 1 when nothing matches like grep does
+fn run_grep(
+    conn: &Connection,
+    pattern: &str,
+    ignore_case: bool,
+    count: bool,
+    files_with_matches: bool,
+    filter: LogFilterArgs,
+) -> Result<()> {
+    let pattern = if ignore_case {
+        log_reader::compile_pattern(&format!("(?i){pattern}"))?
+    } else {
+        log_reader::compile_pattern(pattern)?
+    };
+    let mode = match (count, files_with_matches) {
+        (true, _) => GrepMode::Count,
+        (_, true) => GrepMode::FilesWithMatches,
+        _ => GrepMode::Lines,
+    };
+    if commands::grep(conn, &pattern, &filter.into_filter()?, mode, true)? == 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Work out when `ghost run --at/--in` should start, or None to start now
+fn start_time(
+    at: Option<&str>,
+    delay: Option<&str>,
+) -> Result<Option<chrono::DateTime<chrono::Local>>> {
+    let now = chrono::Local::now();
+    match (at, delay) {
+        (Some(at), _) => scheduler::parse_at(at, now).map(Some),
+        (None, Some(delay)) => {
+            let delay =
+                chrono::Duration::from_std(helpers::parse_duration(delay)?).map_err(|_| {
+                    GhostError::InvalidArgument {
+                        message: format!("Delay is too long: {delay}"),
+                    }
+                })?;
+            Ok(Some(now + delay))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Run one or more commands once at a later time, using the same formats as `ghost run`
+fn run_later(
+    conn: &Connection,
+    start_at: chrono::DateTime<chrono::Local>,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+) -> Result<()> {
+    for command in split_commands(args)? {
+        commands::run_at(
+            conn,
+            command,
+            cwd.clone(),
+            env.clone(),
+            options,
+            start_at,
+            true,
+        )?;
+    }
+
+    start_scheduler();
+    Ok(())
+}
+
+/// Make sure a scheduler is running to start what was just scheduled
+fn start_scheduler() {
+    if let Err(e) = scheduler::ensure_running() {
+        eprintln!("Warning: {e}; run `ghost scheduler` to start scheduled tasks");
+    }
+}
+
+/// Split `ghost run` arguments into commands, like `run_commands` does
+fn split_commands(args: Vec<String>) -> Result<Vec<Vec<String>>> {
+    let is_multi_command = args.first().map(|s| s.contains(' ')).unwrap_or(false);
+    if is_multi_command {
+        args.iter()
+            .map(|command_str| helpers::parse_command(command_str))
+            .collect()
+    } else {
+        Ok(vec![args])
+    }
+}
+
+/// Add one or more commands to a queue, using the same formats as `ghost run`
+fn queue_commands(
+    conn: &Connection,
+    queue: &str,
+    parallel: Option<u32>,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<String>,
+    options: &RunOptions,
+) -> Result<()> {
+    if let Some(parallel) = parallel {
+        commands::queue_parallel(conn, queue, parallel, false)?;
+    }
+
+    for command in split_commands(args)? {
+        commands::queue_add(
+            conn,
+            queue,
+            command,
+            cwd.clone(),
+            env.clone(),
+            options,
+            true,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Dispatch a `ghost queue` subcommand
+fn run_queue_command(conn: &Connection, command: QueueCommands) -> Result<()> {
+    match command {
+        QueueCommands::Add {
+            command,
+            queue,
+            parallel,
+            cwd,
+            env,
+            options,
+        } => {
+            let options = options.into_options()?;
+            queue_commands(conn, &queue, parallel, command, cwd, env, &options)
+        }
+        QueueCommands::List { queue } => {
+            commands::queue_list(conn, queue.as_deref(), true).map(|_| ())
+        }
+        QueueCommands::Pause { queue } => commands::queue_pause(conn, &queue, true),
+        QueueCommands::Resume { queue } => commands::queue_resume(conn, &queue, true).map(|_| ()),
+        QueueCommands::Clear { queue } => commands::queue_clear(conn, &queue, true).map(|_| ()),
+        QueueCommands::Move { task_id, position } => {
+            commands::queue_move(conn, &task_id, position, true).map(|_| ())
+        }
+        QueueCommands::Parallel { queue, parallel } => {
+            commands::queue_parallel(conn, &queue, parallel, true)
+        }
+    }
+}
+
+/// Dispatch a `ghost schedule` subcommand
+fn run_schedule_command(conn: &Connection, command: ScheduleCommands) -> Result<()> {
+    match command {
+        ScheduleCommands::Add {
+            cron,
+            command,
+            cwd,
+            env,
+            options,
+        } => {
+            let options = options.into_options()?;
+            commands::schedule_add(conn, &cron, command, cwd, env, &options, true)?;
+            start_scheduler();
+            Ok(())
+        }
+        ScheduleCommands::List => commands::schedule_list(conn, true).map(|_| ()),
+        ScheduleCommands::Rm { schedule_id } => commands::schedule_remove(conn, &schedule_id, true),
+    }
+}
+
+/// Dispatch a `ghost filter` subcommand
+fn run_filter_command(conn: &Connection, command: FilterCommands) -> Result<()> {
+    match command {
+        FilterCommands::Save { name, query } => {
+            commands::filter_save(conn, &name, &query.join(" "), true).map(|_| ())
+        }
+        FilterCommands::List => commands::filter_list(conn, true).map(|_| ()),
+        FilterCommands::Rm { name } => commands::filter_remove(conn, &name, true),
+    }
+}
+
+/// Dispatch a `ghost config` subcommand; it needs neither a valid config file nor the database
+fn run_config_command(command: ConfigCommands) -> Result<()> {
+    match command {
+        ConfigCommands::Get { key } => commands::config_get(&key),
+        ConfigCommands::Set {
+            key,
+            value,
+            project,
+        } => commands::config_set(&key, &value, project),
+        ConfigCommands::List => commands::config_list(),
+        ConfigCommands::Path => commands::config_path(),
+    }
+}
+
+/// Dispatch a `ghost template` subcommand
+fn run_template_command(conn: &Connection, command: TemplateCommands) -> Result<()> {
+    match command {
+        TemplateCommands::Save {
+            name,
+            command,
+            cwd,
+            env,
+            options,
+        } => {
+            let options = options.into_options()?;
+            commands::template_save(conn, &name, command, cwd, env, &options, true).map(|_| ())
+        }
+        TemplateCommands::List => commands::template_list(conn, true).map(|_| ()),
+        TemplateCommands::Rm { name } => commands::template_remove(conn, &name, true),
     }
 }
@@ -9,12 +9,20 @@ use rust_mcp_sdk::schema::{
 use rust_mcp_sdk::{McpServer, tool_box};
 use rust_mcp_transport::{StdioTransport, TransportOptions};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tracing::{error, info};
 
 use crate::app::commands;
+use crate::app::config::FileConfig;
+use crate::app::log_grep::{self, GrepEvent, GrepMode, LogFilter};
+use crate::app::log_level::LevelCounter;
+use crate::app::log_reader::{self, LogQuery};
+use crate::app::stats::{self, GroupSample, TaskStats};
+use crate::app::storage;
+use crate::app::storage::task::Task;
 use crate::app::storage::task_repository;
+use crate::app::template;
 use rusqlite::Connection;
 
 #[mcp_tool(
@@ -26,9 +34,11 @@ pub struct RunTool {
     /// Commands to run. Each string is a complete command with arguments.
     /// Example: ["sleep 10", "echo hello"]
     pub commands: Vec<String>,
-    /// Working directory (defaults to current directory)
+    /// Working directory (defaults to current directory; refused when the
+    /// server only allows some commands)
     pub cwd: Option<String>,
-    /// Environment variables (KEY=VALUE format)
+    /// Environment variables (KEY=VALUE format; refused when the server only
+    /// allows some commands)
     pub env: Option<Vec<String>>,
 }
 
@@ -51,16 +61,31 @@ pub struct StopTool {
     pub force: Option<bool>,
 }
 
-#[mcp_tool(name = "ghost_log", description = "Get logs for a specific process")]
+#[mcp_tool(
+    name = "ghost_log",
+    description = "Get logs for a specific process. Returns the log lines, then JSON with total_size, next_offset, eof and lines; pass next_offset as offset to read on"
+)]
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct LogTool {
     /// Process ID to get logs for
     pub id: String,
+    /// Strip ANSI colors and collapse progress bar redraws (default: true)
+    pub strip_ansi: Option<bool>,
+    /// Return only the last N lines
+    pub tail: Option<u64>,
+    /// Byte offset to start reading at, e.g. next_offset of an earlier call
+    pub offset: Option<u64>,
+    /// Maximum number of lines to return (default: logs.limit of the config, 1000)
+    pub limit: Option<u64>,
+    /// Return only lines matching this regular expression
+    pub grep: Option<String>,
+    /// Return only entries at this level or above (trace, debug, info, warn, error)
+    pub level: Option<String>,
 }
 
 #[mcp_tool(
     name = "ghost_status",
-    description = "Check status of a specific process"
+    description = "Check status of a specific process, including the number of errors and warnings in its log and its event history, such as fired output triggers"
 )]
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct StatusTool {
@@ -68,20 +93,106 @@ pub struct StatusTool {
     pub id: String,
 }
 
+#[mcp_tool(
+    name = "ghost_templates",
+    description = "List saved command templates and the parameters they take"
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct TemplatesTool {}
+
+#[mcp_tool(
+    name = "ghost_start_template",
+    description = "Start a saved command template as a background process"
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct StartTemplateTool {
+    /// Name of the template
+    pub name: String,
+    /// Values for the template's placeholders (NAME=VALUE format)
+    pub params: Option<Vec<String>>,
+    /// Extra arguments appended to the template's command
+    pub args: Option<Vec<String>>,
+}
+
+#[mcp_tool(
+    name = "ghost_search_logs",
+    description = "Search the logs of all tasks for a regular expression, e.g. to find which task printed an error. Returns JSON with the matching lines and whether more were left out"
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct SearchLogsTool {
+    /// Regular expression to search for; prefix with (?i) to ignore case
+    pub pattern: String,
+    /// Only tasks with this status (queued, waiting, starting, running, exited, killed, cancelled)
+    pub status: Option<String>,
+    /// Only tasks still running at or after this time (e.g. 1d, 14:30, 2026-05-01 14:30)
+    pub since: Option<String>,
+    /// Only tasks run in this directory or below it
+    pub cwd: Option<String>,
+    /// Only tasks with this name
+    pub name: Option<String>,
+    /// Maximum number of matching lines to return (default: logs.search_limit of the config, 100)
+    pub limit: Option<u64>,
+}
+
 tool_box!(
     GhostTools,
-    [RunTool, ListTool, StopTool, LogTool, StatusTool]
+    [
+        RunTool,
+        ListTool,
+        StopTool,
+        LogTool,
+        StatusTool,
+        TemplatesTool,
+        StartTemplateTool,
+        SearchLogsTool
+    ]
 );
 
+/// How long ghost_status measures CPU usage of a running task
+const STATUS_STATS_WINDOW: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Sample a running task's resource usage without blocking the server
+async fn sample_stats(task: &Task) -> Option<TaskStats> {
+    let pgid = stats::running_pgid(task)?;
+    let first = GroupSample::take(pgid)?;
+    tokio::time::sleep(STATUS_STATS_WINDOW).await;
+    let second = GroupSample::take(pgid)?;
+    Some(TaskStats::from_samples(Some(&first), &second))
+}
+
 pub struct GhostServerHandler {
     conn: Arc<Mutex<Connection>>,
+    config: FileConfig,
 }
 
 impl GhostServerHandler {
     pub fn new(conn: Connection) -> Self {
         Self {
             conn: Arc::new(Mutex::new(conn)),
+            config: FileConfig::current().clone(),
+        }
+    }
+
+    /// Use these settings instead of those of the config files
+    pub fn with_config(mut self, config: FileConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Refuse a tool that starts or stops tasks when the server is read-only
+    fn check_writable(&self, tool: &str) -> Result<(), CallToolError> {
+        if self.config.mcp.read_only() {
+            return Err(CallToolError::from_message(format!(
+                "{tool} is not available: the server is read-only (mcp.read_only)"
+            )));
         }
+        Ok(())
+    }
+
+    /// Hide the values of secret environment variables
+    fn redact(&self, mut task: Task) -> Task {
+        self.config.env.redact_task(&mut task);
+        task
     }
 }
 
@@ -109,14 +220,28 @@ impl ServerHandler for GhostServerHandler {
 
         match tool {
             GhostTools::RunTool(t) => {
+                self.check_writable("ghost_run")?;
                 if t.commands.is_empty() {
                     return Err(CallToolError::from_message(
                         "No commands specified".to_string(),
                     ));
                 }
+                if let Some(command) = t
+                    .commands
+                    .iter()
+                    .find(|command| !self.config.mcp.allows_command(command))
+                {
+                    return Err(CallToolError::from_message(format!(
+                        "Command '{command}' is not allowed by mcp.allowed_commands"
+                    )));
+                }
 
-                let cwd = t.cwd.map(PathBuf::from);
                 let env = t.env.unwrap_or_default();
+                self.config
+                    .mcp
+                    .check_run_overrides(t.cwd.as_deref(), &env)
+                    .map_err(CallToolError::from_message)?;
+                let cwd = t.cwd.map(PathBuf::from);
 
                 let conn = self.conn.lock().unwrap();
                 let results = commands::spawn_multi(&conn, t.commands, cwd, env, false);
@@ -128,7 +253,7 @@ impl ServerHandler for GhostServerHandler {
                 for spawn_result in results {
                     match spawn_result.result {
                         Ok(info) => match task_repository::get_task(&conn, &info.id) {
-                            Ok(task) => tasks.push(task),
+                            Ok(task) => tasks.push(self.redact(task)),
                             Err(e) => errors.push(format!(
                                 "Failed to get task for '{}': {e}",
                                 spawn_result.command_str
@@ -160,9 +285,11 @@ impl ServerHandler for GhostServerHandler {
                 let conn = self.conn.lock().unwrap();
 
                 // Prepare status filter
-                let tasks = commands::list(&conn, t.status, false).map_err(|e| {
-                    CallToolError::from_message(format!("Failed to list tasks: {e}"))
-                })?;
+                let tasks: Vec<Task> = commands::list(&conn, t.status, false)
+                    .map_err(|e| CallToolError::from_message(format!("Failed to list tasks: {e}")))?
+                    .into_iter()
+                    .map(|task| self.redact(task))
+                    .collect();
 
                 let result = serde_json::to_string_pretty(&tasks)
                     .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
@@ -172,14 +299,23 @@ impl ServerHandler for GhostServerHandler {
                 )]))
             }
             GhostTools::StopTool(t) => {
+                self.check_writable("ghost_stop")?;
                 let conn = self.conn.lock().unwrap();
-                commands::stop(&conn, &t.id, t.force.unwrap_or(false), false)
+                let survivors = commands::stop(&conn, &t.id, t.force.unwrap_or(false), false)
                     .map_err(|e| CallToolError::from_message(format!("Failed to stop: {e}")))?;
 
+                let mut message = format!("Process {} stopped successfully", t.id);
+                if !survivors.is_empty() {
+                    let pids: Vec<String> = survivors.iter().map(|p| p.pid.to_string()).collect();
+                    message.push_str(&format!(
+                        "; {} process(es) are still running: {}",
+                        survivors.len(),
+                        pids.join(", ")
+                    ));
+                }
+
                 Ok(CallToolResult::text_content(vec![TextContent::new(
-                    format!("Process {} stopped successfully", t.id),
-                    None,
-                    None,
+                    message, None, None,
                 )]))
             }
             GhostTools::LogTool(t) => {
@@ -187,24 +323,162 @@ impl ServerHandler for GhostServerHandler {
                 let task = task_repository::get_task(&conn, &t.id)
                     .map_err(|e| CallToolError::from_message(format!("Failed to get task: {e}")))?;
 
-                let log_content = std::fs::read_to_string(&task.log_path)
-                    .map_err(|e| CallToolError::from_message(format!("Failed to read log: {e}")))?;
+                let query = LogQuery {
+                    offset: t.offset.unwrap_or(0),
+                    tail: t.tail.map(|tail| tail as usize),
+                    limit: Some(t.limit.unwrap_or(self.config.logs.limit()) as usize),
+                    grep: t
+                        .grep
+                        .as_deref()
+                        .map(log_reader::compile_pattern)
+                        .transpose()
+                        .map_err(|e| CallToolError::from_message(e.to_string()))?,
+                    level: t
+                        .level
+                        .as_deref()
+                        .map(log_reader::parse_level)
+                        .transpose()
+                        .map_err(|e| CallToolError::from_message(e.to_string()))?,
+                    strip_ansi: t.strip_ansi.unwrap_or(true),
+                    ..Default::default()
+                };
+                let mut log_content = String::new();
+                let summary = log_reader::read_log(Path::new(&task.log_path), &query, |line| {
+                    log_content.push_str(line)
+                })
+                .map_err(|e| CallToolError::from_message(format!("Failed to read log: {e}")))?;
+                let summary = serde_json::to_string(&summary)
+                    .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+
+                Ok(CallToolResult::text_content(vec![
+                    TextContent::new(log_content, None, None),
+                    TextContent::new(summary, None, None),
+                ]))
+            }
+            GhostTools::StatusTool(t) => {
+                let (task, events) = {
+                    let conn = self.conn.lock().unwrap();
+                    let task = commands::status(&conn, &t.id, false).map_err(|e| {
+                        CallToolError::from_message(format!("Failed to get status: {e}"))
+                    })?;
+                    let events = storage::get_task_events(&conn, &task.id).map_err(|e| {
+                        CallToolError::from_message(format!("Failed to get events: {e}"))
+                    })?;
+                    (task, events)
+                };
+
+                // Running tasks also report their resource usage
+                let mut value = serde_json::to_value(self.redact(task.clone()))
+                    .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+                if let Some(task_stats) = sample_stats(&task).await {
+                    value["stats"] = serde_json::to_value(task_stats)
+                        .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+                }
+                let mut levels = LevelCounter::new(&task.log_path);
+                if levels.update(u64::MAX).is_ok() {
+                    value["log_levels"] = serde_json::json!({
+                        "errors": levels.counts().errors,
+                        "warnings": levels.counts().warnings,
+                        "format": levels.format(),
+                    });
+                }
+                if !events.is_empty() {
+                    value["ready"] = storage::ready_since(&events).is_some().into();
+                    value["events"] = serde_json::to_value(&events)
+                        .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+                }
+
+                let result = serde_json::to_string_pretty(&value)
+                    .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
 
                 Ok(CallToolResult::text_content(vec![TextContent::new(
-                    log_content,
-                    None,
-                    None,
+                    result, None, None,
                 )]))
             }
-            GhostTools::StatusTool(t) => {
+            GhostTools::TemplatesTool(_) => {
+                let conn = self.conn.lock().unwrap();
+                let templates = commands::template_list(&conn, false).map_err(|e| {
+                    CallToolError::from_message(format!("Failed to list templates: {e}"))
+                })?;
+
+                // Include the parameters so callers know what to pass
+                let mut values = Vec::new();
+                for saved in &templates {
+                    let mut value = serde_json::to_value(saved)
+                        .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+                    value["parameters"] = serde_json::to_value(template::parameters(saved))
+                        .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+                    values.push(value);
+                }
+
+                let result = serde_json::to_string_pretty(&values)
+                    .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+
+                Ok(CallToolResult::text_content(vec![TextContent::new(
+                    result, None, None,
+                )]))
+            }
+            GhostTools::StartTemplateTool(t) => {
+                self.check_writable("ghost_start_template")?;
                 let conn = self.conn.lock().unwrap();
-                let task = commands::status(&conn, &t.id, false).map_err(|e| {
-                    CallToolError::from_message(format!("Failed to get status: {e}"))
+                let info = commands::start_template(
+                    &conn,
+                    &t.name,
+                    &t.params.unwrap_or_default(),
+                    t.args.unwrap_or_default(),
+                    false,
+                )
+                .map_err(|e| {
+                    CallToolError::from_message(format!("Failed to start template: {e}"))
                 })?;
+                let task = task_repository::get_task(&conn, &info.id)
+                    .map_err(|e| CallToolError::from_message(format!("Failed to get task: {e}")))?;
 
-                let result = serde_json::to_string_pretty(&task)
+                let result = serde_json::to_string_pretty(&self.redact(task))
                     .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
 
+                Ok(CallToolResult::text_content(vec![TextContent::new(
+                    result, None, None,
+                )]))
+            }
+            GhostTools::SearchLogsTool(t) => {
+                let invalid =
+                    |e: crate::app::error::GhostError| CallToolError::from_message(e.to_string());
+                let pattern = log_reader::compile_pattern(&t.pattern).map_err(invalid)?;
+                let filter = LogFilter::parse(
+                    t.status.as_deref(),
+                    t.since.as_deref(),
+                    t.cwd.map(PathBuf::from),
+                    t.name,
+                )
+                .map_err(invalid)?;
+                let tasks = {
+                    let conn = self.conn.lock().unwrap();
+                    filter.tasks(&conn).map_err(|e| {
+                        CallToolError::from_message(format!("Failed to list tasks: {e}"))
+                    })?
+                };
+
+                let limit = t.limit.unwrap_or(self.config.logs.search_limit()) as usize;
+                let mut matches = Vec::new();
+                let mut truncated = false;
+                log_grep::search_logs(&tasks, &pattern, GrepMode::Lines, |event| {
+                    if let GrepEvent::Match(found) = event {
+                        if matches.len() == limit {
+                            truncated = true;
+                            return false;
+                        }
+                        matches.push(found);
+                    }
+                    true
+                });
+
+                let result = serde_json::to_string_pretty(&serde_json::json!({
+                    "matches": matches,
+                    "truncated": truncated,
+                }))
+                .map_err(|e| CallToolError::from_message(format!("JSON error: {e}")))?;
+
                 Ok(CallToolResult::text_content(vec![TextContent::new(
                     result, None, None,
                 )]))
@@ -2,7 +2,7 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use async_trait::async_trait;
-use ghost::app::config::Config;
+use ghost::app::config::{Config, FileConfig, McpConfig};
 use ghost::app::storage::{self, Task, TaskStatus};
 use ghost::mcp::GhostServerHandler;
 use rusqlite::Connection;
@@ -258,6 +258,133 @@ async fn ghost_log_returns_task_log_contents() {
     assert_eq!(payload, log_body);
 }
 
+#[tokio::test]
+async fn ghost_log_strips_ansi_unless_asked_for_raw_output() {
+    let ctx = McpTestContext::new();
+    let conn = ctx.connection();
+
+    let log_body = "\x1b[32mok\x1b[0m\n10%\r100%\n";
+    insert_task_with_log(&ctx, &conn, "task-color", log_body);
+
+    let handler = GhostServerHandler::new(conn);
+    let response = call_tool(&handler, "ghost_log", json!({ "id": "task-color" })).await;
+    assert_eq!(text_content(&response), "ok\n100%\n");
+
+    let response = call_tool(
+        &handler,
+        "ghost_log",
+        json!({ "id": "task-color", "strip_ansi": false }),
+    )
+    .await;
+    assert_eq!(text_content(&response), log_body);
+}
+
+#[tokio::test]
+async fn ghost_log_pages_with_tail_limit_offset_and_grep() {
+    let ctx = McpTestContext::new();
+    let conn = ctx.connection();
+
+    let log_body: String = (1..=10).map(|i| format!("line {i}\n")).collect();
+    insert_task_with_log(&ctx, &conn, "task-page", &log_body);
+
+    let handler = GhostServerHandler::new(conn);
+    let summary = |response: &CallToolResult| -> Value {
+        match response.content.get(1) {
+            Some(ContentBlock::TextContent(text)) => {
+                serde_json::from_str(&text.text).expect("valid summary JSON")
+            }
+            other => panic!("unexpected summary block: {other:?}"),
+        }
+    };
+
+    let response = call_tool(
+        &handler,
+        "ghost_log",
+        json!({ "id": "task-page", "tail": 2 }),
+    )
+    .await;
+    assert_eq!(text_content(&response), "line 9\nline 10\n");
+    assert_eq!(summary(&response)["eof"], true);
+    assert_eq!(summary(&response)["total_size"], log_body.len());
+
+    let response = call_tool(
+        &handler,
+        "ghost_log",
+        json!({ "id": "task-page", "limit": 3 }),
+    )
+    .await;
+    assert_eq!(text_content(&response), "line 1\nline 2\nline 3\n");
+    let first_page = summary(&response);
+    assert_eq!(first_page["eof"], false);
+    assert_eq!(first_page["lines"], 3);
+
+    let response = call_tool(
+        &handler,
+        "ghost_log",
+        json!({ "id": "task-page", "offset": first_page["next_offset"], "limit": 1 }),
+    )
+    .await;
+    assert_eq!(text_content(&response), "line 4\n");
+
+    let response = call_tool(
+        &handler,
+        "ghost_log",
+        json!({ "id": "task-page", "grep": "line 1\\d?$" }),
+    )
+    .await;
+    assert_eq!(text_content(&response), "line 1\nline 10\n");
+}
+
+#[tokio::test]
+async fn ghost_search_logs_finds_lines_across_tasks() {
+    let ctx = McpTestContext::new();
+    let conn = ctx.connection();
+
+    insert_task_with_log(&ctx, &conn, "task-api", "ok\nError: EADDRINUSE\n");
+    insert_task_with_log(&ctx, &conn, "task-web", "nothing here\n");
+    insert_task_with_log(&ctx, &conn, "task-db", "EADDRINUSE\nEADDRINUSE again\n");
+
+    let handler = GhostServerHandler::new(conn);
+    let response = call_tool(
+        &handler,
+        "ghost_search_logs",
+        json!({ "pattern": "EADDRINUSE" }),
+    )
+    .await;
+    let result: Value = serde_json::from_str(&text_content(&response)).expect("valid JSON");
+    let mut hits: Vec<(String, u64)> = result["matches"]
+        .as_array()
+        .expect("matches array")
+        .iter()
+        .map(|m| {
+            (
+                m["task_id"].as_str().unwrap().to_string(),
+                m["line"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    hits.sort();
+    assert_eq!(
+        hits,
+        [
+            ("task-api".to_string(), 2),
+            ("task-db".to_string(), 1),
+            ("task-db".to_string(), 2),
+        ]
+    );
+    assert_eq!(result["truncated"], false);
+
+    let response = call_tool(
+        &handler,
+        "ghost_search_logs",
+        json!({ "pattern": "EADDRINUSE", "limit": 1 }),
+    )
+    .await;
+    let result: Value = serde_json::from_str(&text_content(&response)).expect("valid JSON");
+    assert_eq!(result["matches"].as_array().unwrap().len(), 1);
+    assert_eq!(result["truncated"], true);
+}
+
 #[tokio::test]
 async fn ghost_run_multiple_commands() {
     let ctx = McpTestContext::new();
@@ -345,6 +472,11 @@ async fn ghost_run_and_stop_lifecycle() {
 
     tokio::time::sleep(Duration::from_millis(100)).await;
 
+    let running_result = call_tool(&handler, "ghost_status", json!({ "id": task_id })).await;
+    let running_payload: Value =
+        serde_json::from_str(&text_content(&running_result)).expect("valid status JSON");
+    assert!(running_payload["stats"]["processes"].as_u64().unwrap_or(0) >= 1);
+
     let stop_id = task_id.clone();
 
     let stop_result = call_tool(
@@ -367,4 +499,159 @@ async fn ghost_run_and_stop_lifecycle() {
     let status_payload = text_content(&status_result);
     let updated_task: Task = serde_json::from_str(&status_payload).expect("valid status task JSON");
     assert_eq!(updated_task.status, TaskStatus::Killed);
+    let updated_payload: Value = serde_json::from_str(&status_payload).expect("valid status JSON");
+    assert!(updated_payload.get("stats").is_none());
+}
+
+#[tokio::test]
+async fn ghost_templates_and_start_template() {
+    let ctx = McpTestContext::new();
+    let conn = ctx.connection();
+    ghost::app::commands::template_save(
+        &conn,
+        "greet",
+        vec!["echo".to_string(), "hello {{who}}".to_string()],
+        None,
+        vec![],
+        &Default::default(),
+        false,
+    )
+    .expect("failed to save template");
+    let handler = GhostServerHandler::new(conn);
+
+    let list_result = call_tool(&handler, "ghost_templates", json!({})).await;
+    let templates: Value =
+        serde_json::from_str(&text_content(&list_result)).expect("valid template list JSON");
+    assert_eq!(templates[0]["name"], "greet");
+    assert_eq!(templates[0]["parameters"][0]["name"], "who");
+
+    let missing = handler
+        .handle_call_tool_request(
+            make_call_request("ghost_start_template", json!({ "name": "greet" })),
+            Arc::new(DummyRuntime::default()),
+        )
+        .await;
+    assert!(missing.is_err(), "a missing parameter should return error");
+
+    let start_result = call_tool(
+        &handler,
+        "ghost_start_template",
+        json!({ "name": "greet", "params": ["who=ghost"], "args": ["!"] }),
+    )
+    .await;
+    let task: Task = serde_json::from_str(&text_content(&start_result)).expect("valid task JSON");
+    assert_eq!(task.command, r#"["echo","hello ghost","!"]"#);
+
+    for _ in 0..50 {
+        if std::fs::read_to_string(&task.log_path).is_ok_and(|log| log.contains("hello ghost !")) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("template output not found in {}", task.log_path);
+}
+
+#[tokio::test]
+async fn read_only_server_refuses_to_start_or_stop_tasks_and_hides_secrets() {
+    let ctx = McpTestContext::new();
+    let conn = ctx.connection();
+
+    let log_path = ctx.log_path("task-env.log");
+    std::fs::write(&log_path, "").expect("failed to write log file");
+    let env = vec![
+        ("API_TOKEN".to_string(), "s3cr3t".to_string()),
+        ("PORT".to_string(), "8080".to_string()),
+    ];
+    storage::insert_task(
+        &conn,
+        "task-env",
+        12345,
+        Some(12345),
+        &["serve".to_string()],
+        Some(&env),
+        None,
+        &log_path,
+    )
+    .expect("failed to insert task");
+
+    let config = FileConfig {
+        mcp: McpConfig {
+            read_only: Some(true),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let handler = GhostServerHandler::new(conn).with_config(config);
+
+    for (name, args) in [
+        ("ghost_run", json!({ "commands": ["echo hi"] })),
+        ("ghost_stop", json!({ "id": "task-env" })),
+        ("ghost_start_template", json!({ "name": "greet" })),
+    ] {
+        let refused = handler
+            .handle_call_tool_request(
+                make_call_request(name, args),
+                Arc::new(DummyRuntime::default()),
+            )
+            .await;
+        assert!(refused.is_err(), "{name} should be refused");
+    }
+
+    let list_result = call_tool(&handler, "ghost_list", json!({})).await;
+    let tasks: Vec<Task> = serde_json::from_str(&text_content(&list_result)).unwrap();
+    let env = tasks[0].env.as_deref().unwrap();
+    assert!(env.contains(r#"["API_TOKEN","***"]"#), "{env}");
+    assert!(env.contains(r#"["PORT","8080"]"#), "{env}");
+}
+
+#[tokio::test]
+async fn ghost_run_only_starts_allowed_commands() {
+    let ctx = McpTestContext::new();
+    let conn = ctx.connection();
+
+    let config = FileConfig {
+        mcp: McpConfig {
+            allowed_commands: Some(vec!["cargo *".to_string()]),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let handler = GhostServerHandler::new(conn).with_config(config);
+
+    let refused = handler
+        .handle_call_tool_request(
+            make_call_request("ghost_run", json!({ "commands": ["rm -rf target"] })),
+            Arc::new(DummyRuntime::default()),
+        )
+        .await;
+    assert!(refused.is_err(), "a command not allowed should be refused");
+
+    // An allowed command must not be turned into another one by its environment
+    let refused = handler
+        .handle_call_tool_request(
+            make_call_request(
+                "ghost_run",
+                json!({ "commands": ["cargo build"], "env": ["RUSTC_WRAPPER=/tmp/evil"] }),
+            ),
+            Arc::new(DummyRuntime::default()),
+        )
+        .await;
+    assert!(
+        refused.is_err(),
+        "env should be refused with allowed_commands"
+    );
+
+    let refused = handler
+        .handle_call_tool_request(
+            make_call_request(
+                "ghost_run",
+                json!({ "commands": ["cargo build"], "cwd": "/tmp" }),
+            ),
+            Arc::new(DummyRuntime::default()),
+        )
+        .await;
+    assert!(
+        refused.is_err(),
+        "cwd should be refused with allowed_commands"
+    );
 }
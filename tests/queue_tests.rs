@@ -0,0 +1,161 @@
+//! End-to-end tests of queues, chains and schedules: promotion happens in the
+//! supervisors of finishing tasks and schedules are run by a background
+//! scheduler, so these drive the real `ghost` binary.
+
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+use ghost::app::config::Config;
+use ghost::app::storage::{self, TaskStatus};
+use tempfile::TempDir;
+
+fn ghost(data_dir: &Path, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_ghost"))
+        .args(args)
+        .env("GHOST_DATA_DIR", data_dir)
+        .output()
+        .expect("failed to run ghost");
+    assert!(
+        output.status.success(),
+        "ghost {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn test_queue_starts_waiting_tasks_as_slots_free_up() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path();
+
+    ghost(
+        data_dir,
+        &[
+            "run",
+            "--queue",
+            "build",
+            "--parallel",
+            "1",
+            "--",
+            "sleep",
+            "1",
+        ],
+    );
+    ghost(data_dir, &["queue", "add", "-q", "build", "--", "true"]);
+    ghost(data_dir, &["queue", "add", "-q", "build", "--", "true"]);
+
+    let conn =
+        storage::init_database_with_config(Some(Config::with_data_dir(data_dir.to_path_buf())))
+            .unwrap();
+
+    let waiting = storage::get_queued_tasks(&conn, Some("build")).unwrap();
+    assert_eq!(waiting.len(), 2);
+    assert_eq!(storage::count_running_in_queue(&conn, "build").unwrap(), 1);
+
+    // The first task's supervisor starts the next one, which starts the last one
+    let all_finished = wait_until(Duration::from_secs(10), || {
+        storage::get_tasks(&conn, None)
+            .unwrap()
+            .iter()
+            .all(|task| task.status == TaskStatus::Exited)
+    });
+    assert!(all_finished, "queued tasks did not all run");
+
+    let tasks = storage::get_tasks(&conn, None).unwrap();
+    assert_eq!(tasks.len(), 3);
+    assert!(tasks.iter().all(|task| task.exit_code == Some(0)));
+    assert!(tasks.iter().all(|task| task.pid != 0));
+}
+
+#[test]
+fn test_paused_queue_keeps_tasks_waiting_until_resumed() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path();
+
+    ghost(data_dir, &["queue", "pause"]);
+    ghost(data_dir, &["queue", "add", "--", "true"]);
+
+    let conn =
+        storage::init_database_with_config(Some(Config::with_data_dir(data_dir.to_path_buf())))
+            .unwrap();
+    assert_eq!(storage::get_queued_tasks(&conn, None).unwrap().len(), 1);
+
+    ghost(data_dir, &["queue", "resume"]);
+
+    let finished = wait_until(Duration::from_secs(10), || {
+        storage::get_tasks(&conn, Some("exited")).unwrap().len() == 1
+    });
+    assert!(finished, "resumed queue did not start its task");
+}
+
+#[test]
+fn test_chain_halts_at_the_first_failing_step() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path();
+
+    ghost(data_dir, &["chain", "true", "sh -c 'exit 3'", "echo never"]);
+
+    let conn =
+        storage::init_database_with_config(Some(Config::with_data_dir(data_dir.to_path_buf())))
+            .unwrap();
+
+    let settled = wait_until(Duration::from_secs(10), || {
+        storage::get_tasks(&conn, Some("cancelled")).unwrap().len() == 1
+    });
+    assert!(settled, "the step after the failing one was not cancelled");
+
+    let exited = storage::get_tasks(&conn, Some("exited")).unwrap();
+    let mut exit_codes: Vec<Option<i32>> = exited.iter().map(|task| task.exit_code).collect();
+    exit_codes.sort();
+    assert_eq!(exit_codes, vec![Some(0), Some(3)]);
+}
+
+#[test]
+fn test_delayed_start_is_run_by_the_autostarted_scheduler() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path();
+
+    ghost(data_dir, &["run", "--in", "1s", "--", "true"]);
+
+    let conn =
+        storage::init_database_with_config(Some(Config::with_data_dir(data_dir.to_path_buf())))
+            .unwrap();
+    let schedules = storage::get_schedules(&conn).unwrap();
+    assert_eq!(schedules.len(), 1);
+    assert!(storage::get_tasks(&conn, None).unwrap().is_empty());
+
+    let ran = wait_until(Duration::from_secs(10), || {
+        storage::get_tasks(&conn, None)
+            .unwrap()
+            .iter()
+            .any(|task| task.status == TaskStatus::Exited)
+    });
+    assert!(ran, "delayed task did not run");
+
+    let tasks = storage::get_tasks(&conn, None).unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].exit_code, Some(0));
+    assert_eq!(
+        tasks[0].schedule_id.as_deref(),
+        Some(schedules[0].id.as_str())
+    );
+    assert!(storage::get_schedules(&conn).unwrap().is_empty());
+
+    // With nothing left to run, the scheduler exits
+    let exited = wait_until(Duration::from_secs(5), || {
+        !data_dir.join("scheduler.pid").exists()
+    });
+    assert!(exited, "scheduler kept running");
+}
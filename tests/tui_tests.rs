@@ -1,4 +1,6 @@
 use ghost::app::config::Config;
+use ghost::app::stats::TaskStats;
+use ghost::app::storage::Schedule;
 use ghost::app::storage::task::Task;
 use ghost::app::storage::task_status::TaskStatus;
 use ghost::app::tui::{App, TaskFilter, ViewMode};
@@ -70,6 +72,18 @@ fn create_test_tasks() -> Vec<Task> {
             started_at: 1704109200, // 2024-01-01 10:00 UTC
             finished_at: None,
             log_path: "/tmp/test.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
         Task {
             id: "def67890-1234-5678-9abc-def123456789".to_string(),
@@ -83,6 +97,18 @@ fn create_test_tasks() -> Vec<Task> {
             started_at: 1704107400,        // 2024-01-01 09:30 UTC
             finished_at: Some(1704107460), // 2024-01-01 09:31 UTC
             log_path: "/tmp/test2.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
         Task {
             id: "ghi11111-5678-9abc-def1-23456789abcd".to_string(),
@@ -96,6 +122,18 @@ fn create_test_tasks() -> Vec<Task> {
             started_at: 1704105600,        // 2024-01-01 09:00 UTC
             finished_at: Some(1704105660), // 2024-01-01 09:01 UTC
             log_path: "/tmp/test3.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
     ]
 }
@@ -236,6 +274,99 @@ fn test_task_filter_display() {
     assert!(buffer_output.contains("[Filter: Running]"));
 }
 
+#[test]
+fn test_task_list_usage_columns_on_wide_terminal() {
+    let tasks = create_test_tasks();
+    let mut app = App::with_tasks(tasks);
+    app.stats.insert(
+        "abc12345-6789-1234-5678-123456789abc".to_string(),
+        TaskStats {
+            cpu_percent: Some(12.5),
+            rss_bytes: 64 * 1024 * 1024,
+            threads: Some(4),
+            read_bytes: None,
+            write_bytes: None,
+            processes: 2,
+        },
+    );
+
+    // Narrow terminals keep the original columns
+    let mut terminal = Terminal::new(TestBackend::new(75, 8)).unwrap();
+    terminal
+        .draw(|f| {
+            app.render_task_list(f, f.area());
+        })
+        .unwrap();
+    let narrow_output = buffer_to_string(terminal.backend().buffer());
+    assert!(!narrow_output.contains("CPU%"));
+
+    let mut terminal = Terminal::new(TestBackend::new(140, 8)).unwrap();
+    terminal
+        .draw(|f| {
+            app.render_task_list(f, f.area());
+        })
+        .unwrap();
+    let wide_output = buffer_to_string(terminal.backend().buffer());
+    assert!(wide_output.contains("CPU%"));
+    assert!(wide_output.contains("MEM"));
+    assert!(wide_output.contains("12.5"));
+    assert!(wide_output.contains("64M"));
+}
+
+#[test]
+fn test_task_list_with_schedules() {
+    let schedule = |id: &str, cron: Option<&str>, command: &str, next_run: i64| Schedule {
+        id: id.to_string(),
+        command: command.to_string(),
+        env: None,
+        cwd: None,
+        options: None,
+        cron: cron.map(str::to_string),
+        next_run,
+        last_run: None,
+        last_task_id: None,
+        created_at: 0,
+    };
+    let mut app = App::with_tasks(create_test_tasks());
+    app.schedules = vec![
+        schedule("once", None, r#"["echo","delayed"]"#, 1704110400),
+        schedule(
+            "hourly",
+            Some("0 * * * *"),
+            r#"["./backup.sh"]"#,
+            1704114000,
+        ),
+    ];
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 14)).unwrap();
+    terminal
+        .draw(|f| {
+            app.render_task_list(f, f.area());
+        })
+        .unwrap();
+    let output = buffer_to_string(terminal.backend().buffer());
+
+    let next_run = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap()
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+    assert!(output.contains("Scheduled (2)"));
+    assert!(output.contains(&format!(
+        "{}  once             echo delayed",
+        next_run(1704110400)
+    )));
+    assert!(output.contains(&format!(
+        "{}  0 * * * *        ./backup.sh",
+        next_run(1704114000)
+    )));
+    // The tasks are still listed above the schedules
+    assert!(output.contains("echo hello"));
+    assert!(output.contains("j/k:Move"));
+}
+
 #[test]
 fn test_footer_keybinds_display() {
     let backend = TestBackend::new(75, 12);
@@ -370,6 +501,18 @@ fn test_table_scroll_functionality() {
             started_at: 1704109200 + i as i64,
             finished_at: None,
             log_path: format!("/tmp/test_{i}.log"),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         });
     }
     app.tasks = tasks;
@@ -424,6 +567,18 @@ fn test_table_scroll_display() {
             started_at: 1704109200 + i as i64,
             finished_at: None,
             log_path: format!("/tmp/test_{i}.log"),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         });
     }
 
@@ -467,6 +622,18 @@ fn test_task_termination_keys() {
         started_at: 1704109200,
         finished_at: None,
         log_path: "/tmp/test.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -506,6 +673,18 @@ fn test_task_filter_cycling_with_tab() {
             started_at: 1704109200,
             finished_at: None,
             log_path: "/tmp/running.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
         Task {
             id: "exited_task".to_string(),
@@ -519,6 +698,18 @@ fn test_task_filter_cycling_with_tab() {
             started_at: 1704109200,
             finished_at: Some(1704109260),
             log_path: "/tmp/exited.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
         Task {
             id: "killed_task".to_string(),
@@ -532,6 +723,18 @@ fn test_task_filter_cycling_with_tab() {
             started_at: 1704109200,
             finished_at: Some(1704109260),
             log_path: "/tmp/killed.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
     ];
     app.tasks = tasks;
@@ -579,6 +782,18 @@ fn test_process_details_navigation() {
         started_at: 1000000000,
         finished_at: None,
         log_path: "/tmp/ghost/logs/12345678.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -611,6 +826,53 @@ fn test_process_details_navigation() {
     assert!(app.should_quit());
 }
 
+#[test]
+fn test_emacs_key_map_and_help_overlay() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::tui::app::TuiApp;
+    use ghost::app::tui::keymap::{KeyMap, KeyPreset};
+
+    let env = TestEnvironment::new();
+    let key_map = KeyMap::new(KeyPreset::Emacs, None).unwrap();
+    let mut app = TuiApp::new_with_config(env.config.clone())
+        .unwrap()
+        .with_key_map(key_map);
+    app.tasks = create_test_tasks();
+    app.table_scroll.set_total_items(app.tasks.len());
+    app.set_selected_index(0);
+
+    // C-n moves down, and `j` is no longer bound
+    let key_ctrl_n = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+    app.handle_key(key_ctrl_n).unwrap();
+    assert_eq!(app.selected_index(), 1);
+    let key_j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+    app.handle_key(key_j).unwrap();
+    assert_eq!(app.selected_index(), 1);
+
+    // The footer and the help overlay show the keys of the key map
+    let key_help = KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE);
+    app.handle_key(key_help).unwrap();
+    assert!(app.is_showing_help());
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|f| app.render(f)).unwrap();
+    let output = buffer_to_string(terminal.backend().buffer());
+    assert!(output.contains("C-n/p:Move"));
+    assert!(output.contains(" Keys "));
+    assert!(output.contains("C-n  Move down"));
+    assert!(output.contains("C-k  Kill the task (SIGKILL)"));
+
+    // Any key closes the help without acting on it
+    app.handle_key(key_ctrl_n).unwrap();
+    assert!(!app.is_showing_help());
+    assert_eq!(app.selected_index(), 1);
+
+    // Quitting still quits while the help is shown
+    app.handle_key(key_help).unwrap();
+    let key_q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+    app.handle_key(key_q).unwrap();
+    assert!(app.should_quit());
+}
+
 #[test]
 fn test_repeat_command_with_r_key() {
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -660,6 +922,18 @@ fn test_process_details_display() {
         started_at: 1000000000,
         finished_at: Some(1000001000),
         log_path: "/tmp/ghost/logs/test.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -708,6 +982,18 @@ fn test_log_viewer_navigation() {
         started_at: 1000000000,
         finished_at: Some(1000001000),
         log_path: "/tmp/ghost/logs/test.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -773,6 +1059,18 @@ fn test_integrated_navigation_flow() {
             started_at: 1000000000,
             finished_at: None,
             log_path: "/tmp/ghost/logs/task-1.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
         Task {
             id: "task-2".to_string(),
@@ -786,6 +1084,18 @@ fn test_integrated_navigation_flow() {
             started_at: 1000000100,
             finished_at: Some(1000000200),
             log_path: "/tmp/ghost/logs/task-2.log".to_string(),
+            options: None,
+            restart_count: 0,
+            supervisor_pid: None,
+            cgroup: None,
+            exit_reason: None,
+            queue: None,
+            queue_position: None,
+            after_task: None,
+            after_condition: None,
+            schedule_id: None,
+            watch_trigger: None,
+            name: None,
         },
     ];
     app.tasks = tasks;
@@ -869,6 +1179,18 @@ fn test_log_viewer_display() {
         started_at: 1000000000,
         finished_at: Some(1000001000),
         log_path: log_path.to_string_lossy().to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -911,6 +1233,18 @@ fn test_process_details_with_many_env_vars() {
         started_at: 1704109200, // 2024-01-01 10:00 UTC  
         finished_at: None,
         log_path: "/tmp/ghost/logs/test.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -969,6 +1303,18 @@ fn test_log_viewer_with_many_lines() {
         started_at: 1000000000,
         finished_at: None,
         log_path: log_path.to_string_lossy().to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -1018,6 +1364,18 @@ fn test_process_details_with_listening_ports() {
         started_at: 1000000000,
         finished_at: None,
         log_path: log_path.to_str().unwrap().to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
 
     app.tasks = tasks;
@@ -1049,6 +1407,143 @@ fn test_process_details_with_listening_ports() {
     assert!(normalized_output.contains("node server.js"));
 }
 
+#[test]
+fn test_process_details_with_process_tree() {
+    use ghost::app::port_detector::ListeningPort;
+    use ghost::app::process_tree::ProcessNode;
+    use ghost::app::tui::process_details::ProcessDetailsWidget;
+    use tui_scrollview::ScrollViewState;
+
+    let mut task = create_test_tasks().remove(0);
+    task.pid = 100;
+    task.pgid = Some(100);
+
+    let node = |pid: u32, pgid: i32, depth: usize, command: &str| ProcessNode {
+        pid,
+        ppid: 100,
+        pgid,
+        state: "S".to_string(),
+        command: command.to_string(),
+        depth,
+        escaped: pgid != 100,
+        ports: Vec::new(),
+    };
+    let mut server = node(101, 100, 1, "node server.js");
+    server.ports.push(ListeningPort {
+        protocol: "tcp".to_string(),
+        local_addr: "*:3000".to_string(),
+        state: "LISTEN".to_string(),
+    });
+    let tree = vec![
+        node(100, 100, 0, "npm run dev"),
+        server,
+        node(102, 102, 1, "daemonized-worker"),
+    ];
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal
+        .draw(|f| {
+            ProcessDetailsWidget::new(&task)
+                .with_process_tree(&tree)
+                .render(f, f.area(), &mut ScrollViewState::default());
+        })
+        .unwrap();
+
+    let output = buffer_to_string(terminal.backend().buffer());
+    assert!(output.contains("Process Tree (1 escaped)"));
+    assert!(output.contains("└─ node server.js  tcp *:3000"));
+    assert!(output.contains("└─ daemonized-worker  escaped (pgid 102)"));
+}
+
+#[test]
+fn test_process_details_with_chain() {
+    use ghost::app::storage::AfterCondition;
+    use ghost::app::tui::process_details::ProcessDetailsWidget;
+    use tui_scrollview::ScrollViewState;
+
+    let mut tasks = create_test_tasks();
+    let mut migrate = tasks.remove(0);
+    migrate.id = "aaaaaaaa-migrate".to_string();
+    migrate.command = r#"["make", "migrate"]"#.to_string();
+    migrate.status = TaskStatus::Exited;
+    migrate.exit_code = Some(0);
+    let mut seed = migrate.clone();
+    seed.id = "bbbbbbbb-seed".to_string();
+    seed.command = r#"["make", "seed"]"#.to_string();
+    seed.status = TaskStatus::Running;
+    seed.exit_code = None;
+    seed.after_task = Some(migrate.id.clone());
+    seed.after_condition = Some(AfterCondition::Success);
+    let mut serve = seed.clone();
+    serve.id = "cccccccc-serve".to_string();
+    serve.command = r#"["make", "serve"]"#.to_string();
+    serve.status = TaskStatus::Waiting;
+    serve.after_task = Some(seed.id.clone());
+    let chain = vec![(0, migrate), (1, seed.clone()), (2, serve)];
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal
+        .draw(|f| {
+            ProcessDetailsWidget::new(&seed).with_chain(&chain).render(
+                f,
+                f.area(),
+                &mut ScrollViewState::default(),
+            );
+        })
+        .unwrap();
+
+    let output = buffer_to_string(terminal.backend().buffer());
+    assert!(output.contains(" Chain "));
+    assert!(output.contains("aaaaaaaa exited (0)    make migrate"));
+    assert!(output.contains("bbbbbbbb └─ running       make seed"));
+    assert!(output.contains("cccccccc    └─ waiting       make serve"));
+}
+
+#[test]
+fn test_process_details_with_events() {
+    use ghost::app::output_trigger::{OutputTrigger, TriggerAction};
+    use ghost::app::run_options::RunOptions;
+    use ghost::app::storage::{EventKind, TaskEvent};
+    use ghost::app::tui::process_details::ProcessDetailsWidget;
+    use tui_scrollview::ScrollViewState;
+
+    let mut task = create_test_tasks().remove(0);
+    task.status = TaskStatus::Running;
+    let options = RunOptions {
+        on_output: vec![
+            OutputTrigger::new("Listening on".to_string(), TriggerAction::Ready).unwrap(),
+        ],
+        ..Default::default()
+    };
+    task.options = Some(serde_json::to_string(&options).unwrap());
+    let event = |kind: EventKind, message: &str| TaskEvent {
+        task_id: task.id.clone(),
+        created_at: task.started_at,
+        kind,
+        message: message.to_string(),
+    };
+    let events = vec![
+        event(EventKind::Restart, "src/main.rs changed"),
+        event(EventKind::Ready, "/Listening on/ ready: Listening on :8080"),
+    ];
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal
+        .draw(|f| {
+            ProcessDetailsWidget::new(&task)
+                .with_events(&events)
+                .render(f, f.area(), &mut ScrollViewState::default());
+        })
+        .unwrap();
+
+    let output = buffer_to_string(terminal.backend().buffer());
+    assert!(output.contains(" | ready"));
+    assert!(output.contains("Triggers: /Listening on/ ready"));
+    assert!(output.contains(" Events (2) "));
+    assert!(output.contains("restart src/main.rs changed"));
+    assert!(output.contains("ready   /Listening on/ ready: Listening on :8080"));
+}
+
 #[test]
 fn test_auto_scroll_ctrl_f_toggle() {
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -1070,6 +1565,18 @@ fn test_auto_scroll_ctrl_f_toggle() {
         started_at: 1000000000,
         finished_at: None,
         log_path: "/tmp/test.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -1109,6 +1616,18 @@ fn test_manual_scrolling_disables_auto_scroll() {
         started_at: 1000000000,
         finished_at: None,
         log_path: "/tmp/test.log".to_string(),
+        options: None,
+        restart_count: 0,
+        supervisor_pid: None,
+        cgroup: None,
+        exit_reason: None,
+        queue: None,
+        queue_position: None,
+        after_task: None,
+        after_condition: None,
+        schedule_id: None,
+        watch_trigger: None,
+        name: None,
     }];
     app.tasks = tasks;
     app.table_scroll.set_total_items(1);
@@ -1143,3 +1662,551 @@ fn test_manual_scrolling_disables_auto_scroll() {
         );
     }
 }
+
+#[test]
+fn test_template_launcher_starts_template_with_parameters() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::tui::app::TuiApp;
+
+    let env = TestEnvironment::new();
+    let conn = ghost::app::storage::init_database_with_config(Some(env.config.clone())).unwrap();
+    ghost::app::template::save(
+        &conn,
+        "greet",
+        vec!["echo".to_string(), "hello {{who}}".to_string()],
+        None,
+        vec![],
+        &Default::default(),
+    )
+    .unwrap();
+
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    let press = |app: &mut TuiApp, code: KeyCode| {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap()
+    };
+    let render = |app: &mut TuiApp| {
+        let mut terminal = Terminal::new(TestBackend::new(100, 15)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        buffer_to_string(terminal.backend().buffer())
+    };
+
+    press(&mut app, KeyCode::Char('t'));
+    assert_eq!(app.view_mode, ViewMode::TemplateLauncher);
+    let output = render(&mut app);
+    assert!(output.contains("Templates (1)"), "{output}");
+    assert!(output.contains("echo hello {{who}}"), "{output}");
+
+    // The template has a parameter, so Enter asks for its value first
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("Parameters for greet"));
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("needs a value for: who"));
+    for c in "tui".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(app.view_mode, ViewMode::TaskList);
+    assert_eq!(app.tasks.len(), 1);
+    assert_eq!(app.tasks[0].command, r#"["echo","hello tui"]"#);
+}
+
+#[test]
+fn test_new_task_form_starts_named_task() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::tui::app::TuiApp;
+
+    let env = TestEnvironment::new();
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    app.refresh_tasks().unwrap();
+    let press = |app: &mut TuiApp, code: KeyCode| {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap()
+    };
+    let type_text = |app: &mut TuiApp, text: &str| {
+        for c in text.chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .unwrap();
+        }
+    };
+    let render = |app: &mut TuiApp| {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        buffer_to_string(terminal.backend().buffer())
+    };
+
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.view_mode, ViewMode::NewTask);
+    assert!(render(&mut app).contains("New Task"));
+
+    // Submitting an empty form keeps it open and shows the error inline
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.view_mode, ViewMode::NewTask);
+    assert!(render(&mut app).contains("Enter a command to run"));
+
+    type_text(&mut app, "echo 'from the form'");
+    for _ in 0..3 {
+        press(&mut app, KeyCode::Tab);
+    }
+    type_text(&mut app, "greeter");
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(app.view_mode, ViewMode::TaskList);
+    assert_eq!(app.tasks.len(), 1);
+    assert_eq!(app.tasks[0].command, r#"["echo","from the form"]"#);
+    assert_eq!(app.tasks[0].name.as_deref(), Some("greeter"));
+    assert!(render(&mut app).contains("[greeter] echo from"));
+
+    // The command can be recalled the next time
+    press(&mut app, KeyCode::Char('n'));
+    press(&mut app, KeyCode::Up);
+    assert!(render(&mut app).contains("echo 'from the form'"));
+}
+
+#[test]
+fn test_search_filters_task_list_and_saves_presets() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::storage;
+    use ghost::app::tui::app::TuiApp;
+    use std::path::Path;
+
+    let env = TestEnvironment::new();
+    let conn = storage::init_database_with_config(Some(env.config.clone())).unwrap();
+    for (id, command, cwd, status) in [
+        (
+            "api-task",
+            ["cargo", "run"],
+            "/work/api",
+            TaskStatus::Exited,
+        ),
+        (
+            "web-task",
+            ["npm", "start"],
+            "/work/web",
+            TaskStatus::Killed,
+        ),
+        (
+            "test-task",
+            ["cargo", "test"],
+            "/work/api",
+            TaskStatus::Killed,
+        ),
+    ] {
+        let command: Vec<String> = command.iter().map(|arg| arg.to_string()).collect();
+        storage::insert_task(
+            &conn,
+            id,
+            1,
+            None,
+            &command,
+            None,
+            Some(Path::new(cwd)),
+            Path::new("/tmp/search.log"),
+        )
+        .unwrap();
+        storage::update_task_status(&conn, id, status, Some(0)).unwrap();
+    }
+
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    app.refresh_tasks().unwrap();
+    let press = |app: &mut TuiApp, code: KeyCode, modifiers: KeyModifiers| {
+        app.handle_key(KeyEvent::new(code, modifiers)).unwrap()
+    };
+    let type_text = |app: &mut TuiApp, text: &str| {
+        for c in text.chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .unwrap();
+        }
+    };
+    let render = |app: &mut TuiApp| {
+        let mut terminal = Terminal::new(TestBackend::new(120, 12)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        buffer_to_string(terminal.backend().buffer())
+    };
+    let ids = |app: &TuiApp| -> Vec<String> { app.tasks.iter().map(|t| t.id.clone()).collect() };
+    assert_eq!(app.tasks.len(), 3);
+
+    // Select the test task, then narrow the list; the selection follows it
+    let test_index = app.tasks.iter().position(|t| t.id == "test-task").unwrap();
+    app.set_selected_index(test_index);
+    press(&mut app, KeyCode::Char('/'), KeyModifiers::NONE);
+    assert_eq!(app.view_mode, ViewMode::Search);
+    type_text(&mut app, "cwd:/work/api");
+    assert_eq!(app.tasks.len(), 2);
+    assert_eq!(app.tasks[app.selected_index()].id, "test-task");
+    assert!(render(&mut app).contains("/cwd:/work/api█  2 matching"));
+
+    // Compound and fuzzy terms
+    type_text(&mut app, " crun");
+    assert_eq!(ids(&app), vec!["api-task"]);
+
+    // Save it as a preset and keep it
+    press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL);
+    type_text(&mut app, "api-run");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.view_mode, ViewMode::TaskList);
+    assert!(render(&mut app).contains("[Search: cwd:/work/api crun]"));
+
+    // Esc in the task list clears the search
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.tasks.len(), 3);
+
+    // The preset can be picked with the arrow keys
+    press(&mut app, KeyCode::Char('/'), KeyModifiers::NONE);
+    press(&mut app, KeyCode::Down, KeyModifiers::NONE);
+    assert_eq!(ids(&app), vec!["api-task"]);
+    assert!(render(&mut app).contains("/@api-run█"));
+
+    // Errors are shown while the last valid search stays applied
+    press(&mut app, KeyCode::Char('u'), KeyModifiers::CONTROL);
+    type_text(&mut app, "status:killed port:http");
+    assert_eq!(app.tasks.len(), 2);
+    assert!(render(&mut app).contains("Invalid port: http"));
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.view_mode, ViewMode::Search);
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.view_mode, ViewMode::TaskList);
+    assert_eq!(app.tasks.len(), 3);
+}
+
+#[test]
+fn test_log_viewer_search_jumps_between_matches() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::tui::app::TuiApp;
+    use std::io::Write;
+
+    let env = TestEnvironment::new();
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    let log_path = env._temp_dir.path().join("search.log");
+    let mut log = String::new();
+    for i in 1..=40 {
+        if i % 10 == 0 {
+            log.push_str(&format!("ERROR at step {i}\n"));
+        } else {
+            log.push_str(&format!("step {i} ok\n"));
+        }
+    }
+    fs::write(&log_path, log).unwrap();
+    app.tasks = vec![Task {
+        log_path: log_path.to_string_lossy().to_string(),
+        ..create_test_tasks().remove(1)
+    }];
+    app.table_scroll.set_total_items(1);
+    app.view_mode = ViewMode::LogView;
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    let mut render = |app: &mut TuiApp| {
+        terminal.draw(|f| app.render(f)).unwrap();
+        buffer_to_string(terminal.backend().buffer())
+    };
+    let press = |app: &mut TuiApp, code: KeyCode| {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap()
+    };
+    render(&mut app);
+    press(&mut app, KeyCode::Char('g'));
+
+    // Matches are highlighted while typing; Enter jumps to the first one below
+    press(&mut app, KeyCode::Char('/'));
+    for c in "error".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    assert!(render(&mut app).contains(" /error█"));
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("[/error/ 1 of 4]"), "{screen}");
+    assert!(screen.contains("10 ERROR at step 10"));
+
+    press(&mut app, KeyCode::Char('n'));
+    press(&mut app, KeyCode::Char('n'));
+    let screen = render(&mut app);
+    assert!(screen.contains("[/error/ 3 of 4]"), "{screen}");
+    assert!(screen.contains("30 ERROR at step 30"));
+    assert!(!screen.contains("10 ERROR"));
+    press(&mut app, KeyCode::Char('N'));
+    assert!(render(&mut app).contains("[/error/ 2 of 4]"));
+
+    // Filter mode shows only matches and their context
+    press(&mut app, KeyCode::Char('&'));
+    press(&mut app, KeyCode::Char('-'));
+    press(&mut app, KeyCode::Char('-'));
+    press(&mut app, KeyCode::Char('g'));
+    let screen = render(&mut app);
+    assert!(screen.contains("&:Filter(ON)  +/-:Context(0)"), "{screen}");
+    assert!(screen.contains("10 ERROR at step 10"));
+    assert!(screen.contains("20 ERROR at step 20"));
+    assert!(!screen.contains("step 11 ok"));
+
+    // New output is searched as it arrives
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let mut file = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+    writeln!(file, "error: late failure").unwrap();
+    drop(file);
+    assert!(render(&mut app).contains("[/error/ 2 of 5]"));
+
+    // Esc clears the search first, then leaves the viewer
+    press(&mut app, KeyCode::Esc);
+    assert!(render(&mut app).contains("j/k/h/l:Scroll"));
+    assert_eq!(app.view_mode, ViewMode::LogView);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.view_mode, ViewMode::TaskList);
+}
+
+#[test]
+fn test_log_viewer_cycles_ansi_display_modes() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::tui::app::TuiApp;
+
+    let env = TestEnvironment::new();
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    let log_path = env._temp_dir.path().join("color.log");
+    fs::write(
+        &log_path,
+        "\x1b[1;31mFAILED\x1b[0m test\ncompiling 3/10\rcompiling 10/10\n",
+    )
+    .unwrap();
+    app.tasks = vec![Task {
+        log_path: log_path.to_string_lossy().to_string(),
+        ..create_test_tasks().remove(1)
+    }];
+    app.table_scroll.set_total_items(1);
+    app.view_mode = ViewMode::LogView;
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    let mut render = |app: &mut TuiApp| {
+        terminal.draw(|f| app.render(f)).unwrap();
+        terminal.backend().buffer().clone()
+    };
+    let press = |app: &mut TuiApp| {
+        app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE))
+            .unwrap()
+    };
+
+    let buffer = render(&mut app);
+    let screen = buffer_to_string(&buffer);
+    assert!(screen.contains("1 FAILED test"), "{screen}");
+    assert!(screen.contains("2 compiling 10/10"));
+    assert_eq!(buffer[(3, 1)].fg, ratatui::style::Color::Red);
+
+    press(&mut app);
+    let buffer = render(&mut app);
+    assert!(buffer_to_string(&buffer).contains("[stripped]"));
+    assert_eq!(buffer[(3, 1)].fg, ratatui::style::Color::Reset);
+
+    press(&mut app);
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(screen.contains("[raw]"));
+    assert!(screen.contains("1 ^[[1;31mFAILED^[[0m test"), "{screen}");
+
+    press(&mut app);
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(!screen.contains("[raw]") && screen.contains("1 FAILED test"));
+}
+
+#[test]
+fn test_log_viewer_pages_through_large_logs_and_jumps() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::tui::app::TuiApp;
+
+    let env = TestEnvironment::new();
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    let log_path = env._temp_dir.path().join("large.log");
+    let log: String = (1..=100_000).map(|i| format!("entry {i}\n")).collect();
+    fs::write(&log_path, log).unwrap();
+    app.tasks = vec![Task {
+        log_path: log_path.to_string_lossy().to_string(),
+        ..create_test_tasks().remove(1)
+    }];
+    app.table_scroll.set_total_items(1);
+    app.view_mode = ViewMode::LogView;
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    let mut render = |app: &mut TuiApp| {
+        terminal.draw(|f| app.render(f)).unwrap();
+        buffer_to_string(terminal.backend().buffer())
+    };
+    let press = |app: &mut TuiApp, code: KeyCode| {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap()
+    };
+    let goto = |app: &mut TuiApp, target: &str| {
+        press(app, KeyCode::Char(':'));
+        for c in target.chars() {
+            press(app, KeyCode::Char(c));
+        }
+        press(app, KeyCode::Enter);
+    };
+
+    // Every line is reachable, not just the last 10k
+    let screen = render(&mut app);
+    assert!(screen.contains("100000 entry 100000"), "{screen}");
+    press(&mut app, KeyCode::Char('g'));
+    assert!(render(&mut app).contains("     1 entry 1 "));
+
+    goto(&mut app, "50%");
+    let screen = render(&mut app);
+    assert!(screen.contains(" 50001 entry 50001"), "{screen}");
+    assert!(!app.auto_scroll_enabled);
+
+    goto(&mut app, "12345");
+    assert!(render(&mut app).contains(" 12345 entry 12345"));
+
+    // Invalid input keeps the prompt open with an error
+    press(&mut app, KeyCode::Char(':'));
+    press(&mut app, KeyCode::Char('%'));
+    press(&mut app, KeyCode::Enter);
+    assert!(render(&mut app).contains("Enter a line number or a percentage"));
+    press(&mut app, KeyCode::Esc);
+    assert!(render(&mut app).contains("j/k/h/l:Scroll"));
+    assert_eq!(app.view_mode, ViewMode::LogView);
+
+    // Searches cover the whole log
+    press(&mut app, KeyCode::Char('/'));
+    for c in "entry 9999[0-9]$".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    let screen = render(&mut app);
+    assert!(screen.contains("/ 1 of 1"), "{screen}");
+    assert!(screen.contains("99990 entry 99990"));
+    press(&mut app, KeyCode::Char('N'));
+    let screen = render(&mut app);
+    assert!(screen.contains("99999 entry 99999"), "{screen}");
+}
+
+#[test]
+fn test_log_levels_counted_filtered_and_pretty_printed() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::storage;
+    use ghost::app::tui::app::TuiApp;
+
+    let env = TestEnvironment::new();
+    let conn = storage::init_database_with_config(Some(env.config.clone())).unwrap();
+    let log_path = env._temp_dir.path().join("json.log");
+    fs::write(
+        &log_path,
+        concat!(
+            "{\"level\":\"info\",\"msg\":\"up\"}\n",
+            "{\"level\":\"error\",\"msg\":\"db down\",\"ctx\":{\"retry\":3}}\n",
+            "{\"level\":\"warn\",\"msg\":\"slow\"}\n",
+        ),
+    )
+    .unwrap();
+    let command = vec!["node".to_string(), "server.js".to_string()];
+    storage::insert_task(&conn, "json-task", 1, None, &command, None, None, &log_path).unwrap();
+    storage::update_task_status(&conn, "json-task", TaskStatus::Exited, Some(0)).unwrap();
+
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    app.refresh_tasks().unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+    let mut render = |app: &mut TuiApp| {
+        terminal.draw(|f| app.render(f)).unwrap();
+        terminal.backend().buffer().clone()
+    };
+    let press = |app: &mut TuiApp, c: char| {
+        app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+            .unwrap()
+    };
+
+    // The task list counts the errors and warnings of each log
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(screen.contains("✗1 ⚠1 node server"), "{screen}");
+
+    press(&mut app, 'l');
+    let buffer = render(&mut app);
+    let screen = buffer_to_string(&buffer);
+    assert!(screen.contains("[json]"), "{screen}");
+    assert!(screen.contains("1 {\"level\":\"info\""));
+    // Error lines are red, warnings yellow
+    assert_eq!(buffer[(3, 2)].fg, ratatui::style::Color::Red);
+    assert_eq!(buffer[(3, 3)].fg, ratatui::style::Color::Yellow);
+
+    // v raises the minimum level: debug, info, warn
+    for _ in 0..3 {
+        press(&mut app, 'v');
+    }
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(screen.contains("[level: warn+]"), "{screen}");
+    assert!(!screen.contains("\"up\""));
+    assert!(screen.contains("2 {\"level\":\"error\""));
+    assert!(screen.contains("3 {\"level\":\"warn\""));
+
+    press(&mut app, 'p');
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(screen.contains("[json, pretty]"), "{screen}");
+    assert!(screen.contains("2 {"));
+    assert!(screen.contains("│    \"msg\": \"db down\","));
+    assert!(screen.contains("│      \"retry\": 3"), "{screen}");
+    assert!(screen.contains("3 {"));
+
+    // Past error, the filter is off again
+    press(&mut app, 'v');
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(screen.contains("[level: error+]"), "{screen}");
+    assert!(!screen.contains("3 {"));
+    press(&mut app, 'v');
+    press(&mut app, 'p');
+    let screen = buffer_to_string(&render(&mut app));
+    assert!(!screen.contains("[level:"));
+    assert!(screen.contains("\"up\""));
+}
+
+#[test]
+fn test_finished_task_shows_toast_and_notification_history() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ghost::app::storage;
+    use ghost::app::tui::app::TuiApp;
+
+    let env = TestEnvironment::new();
+    let conn = storage::init_database_with_config(Some(env.config.clone())).unwrap();
+    let log_path = env._temp_dir.path().join("build.log");
+    fs::write(&log_path, "").unwrap();
+    let command = vec!["make".to_string(), "release".to_string()];
+    // The test process stands in for the task's process, so it counts as running
+    storage::insert_task(
+        &conn,
+        "build-task",
+        std::process::id(),
+        None,
+        &command,
+        None,
+        None,
+        &log_path,
+    )
+    .unwrap();
+
+    let mut app = TuiApp::new_with_config(env.config.clone()).unwrap();
+    app.refresh_tasks().unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+    let mut render = |app: &mut TuiApp| {
+        terminal.draw(|f| app.render(f)).unwrap();
+        buffer_to_string(terminal.backend().buffer())
+    };
+    assert!(!render(&mut app).contains("Task failed"));
+
+    storage::record_task_exit(&conn, "build-task", Some(2), TaskStatus::Exited, None).unwrap();
+    app.refresh_tasks().unwrap();
+    let screen = render(&mut app);
+    assert!(screen.contains("Task failed"), "{screen}");
+    assert!(
+        screen.contains("make release exited with code 2"),
+        "{screen}"
+    );
+    assert_eq!(app.take_unsent_notifications().len(), 1);
+
+    app.handle_key(KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT))
+        .unwrap();
+    assert_eq!(app.view_mode, ViewMode::Notifications);
+    let screen = render(&mut app);
+    assert!(screen.contains("Notifications (1)"), "{screen}");
+    assert!(screen.contains("failed"));
+    assert!(!screen.contains("Task failed"));
+
+    // Enter shows the task the notification is about
+    app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert_eq!(app.view_mode, ViewMode::ProcessDetails);
+    assert_eq!(app.selected_task_id.as_deref(), Some("build-task"));
+}